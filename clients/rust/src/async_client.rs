@@ -0,0 +1,548 @@
+//! Async HTTP client for the OSGeo Library REST API.
+//!
+//! The same endpoints as [`OsgeoClient`](crate::OsgeoClient), for running
+//! requests concurrently or from async services. Requests need a tokio
+//! runtime; the client is cheap to clone and can be shared between tasks.
+
+use crate::client::{
+    decode_page, ChatEvent, RequestPolicy, LISTING_PAGE_SIZE, PAGE_ACCEPT, PAGE_METADATA, UPLOAD_TIMEOUT_SECS,
+};
+use crate::types::*;
+use crate::{digest, failover, retry};
+use anyhow::{Context, Result};
+use reqwest::{Client, Response};
+use serde::de::DeserializeOwned;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Clone)]
+pub struct AsyncOsgeoClient {
+    client: Client,
+    /// Server and mirrors, in the order they are tried
+    servers: Vec<String>,
+    /// Index in `servers` of the one requests go to, shared by clones
+    current: Arc<AtomicUsize>,
+    policy: RequestPolicy,
+    /// Whether the last request reached the server
+    reachable: Arc<AtomicBool>,
+}
+
+impl AsyncOsgeoClient {
+    /// Client for the server at `base_url` (e.g. `DEFAULT_SERVER_URL`).
+    pub fn new(base_url: &str) -> Result<Self> {
+        Self::with_policy(base_url, RequestPolicy::default())
+    }
+
+    /// Client for the server at `base_url`, with the given timeouts and retries.
+    pub fn with_policy(base_url: &str, policy: RequestPolicy) -> Result<Self> {
+        let client = Client::builder()
+            .user_agent(crate::client::user_agent())
+            .connect_timeout(policy.connect_timeout)
+            .timeout(policy.timeout)
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self {
+            client,
+            servers: failover::servers(base_url, &[]),
+            current: Arc::new(AtomicUsize::new(0)),
+            policy,
+            reachable: Arc::new(AtomicBool::new(true)),
+        })
+    }
+
+    /// The same client, falling back on `mirrors` (server URLs) when the
+    /// server can't be reached (see `failover`).
+    pub fn with_mirrors(mut self, mirrors: &[String]) -> Self {
+        self.servers = failover::servers(&self.servers[0], mirrors);
+        self
+    }
+
+    /// URL of the server requests go to, without a trailing slash: the one
+    /// given, or the mirror that answered when it couldn't be reached.
+    pub fn base_url(&self) -> &str {
+        &self.servers[self.current.load(Ordering::Relaxed)]
+    }
+
+    /// The servers to fall back on from the current one, in order.
+    pub fn mirrors(&self) -> Vec<String> {
+        failover::after(self.current.load(Ordering::Relaxed), self.servers.len())
+            .map(|i| self.servers[i].clone())
+            .collect()
+    }
+
+    /// URL of an element image, from a result's slug and image path
+    /// (see `SearchResult::best_image_path`).
+    pub fn image_url(&self, slug: &str, path: &str) -> String {
+        format!("{}/image/{}/{}", self.base_url(), slug, path)
+    }
+
+    /// Timeouts and retries of the client's requests.
+    pub fn policy(&self) -> RequestPolicy {
+        self.policy
+    }
+
+    /// Whether the last request (of any clone) reached the server.
+    pub fn is_reachable(&self) -> bool {
+        self.reachable.load(Ordering::Relaxed)
+    }
+
+    /// Send a request, remembering whether the server could be reached.
+    /// GET and HEAD requests are retried after transient failures (`retry`).
+    async fn send(&self, request: reqwest::RequestBuilder) -> reqwest::Result<Response> {
+        let request = request.build()?;
+        let repeatable = matches!(*request.method(), reqwest::Method::GET | reqwest::Method::HEAD);
+        self.send_request(request, repeatable).await
+    }
+
+    /// Send a request that only reads, retrying it after transient failures
+    /// whatever its method.
+    async fn send_repeatable(&self, request: reqwest::RequestBuilder) -> reqwest::Result<Response> {
+        self.send_request(request.build()?, true).await
+    }
+
+    /// Send a request once, to a mirror if the server can't be reached.
+    async fn execute(&self, mut request: reqwest::Request) -> reqwest::Result<Response> {
+        if let Some(url) = failover::moved(request.url(), &self.servers, self.current.load(Ordering::Relaxed)) {
+            *request.url_mut() = url;
+        }
+        let spare = (self.servers.len() > 1).then(|| request.try_clone()).flatten();
+        let result = self.execute_once(request).await;
+        match (result, spare) {
+            (Err(error), Some(spare)) if error.is_connect() => self.fail_over(spare, error).await,
+            (result, _) => result,
+        }
+    }
+
+    /// Send `request`, which couldn't connect with `error`, to each mirror in
+    /// turn until one answers, and make that one the server.
+    async fn fail_over(&self, request: reqwest::Request, error: reqwest::Error) -> reqwest::Result<Response> {
+        let Some((from, _)) = failover::locate(request.url().as_str(), &self.servers) else {
+            return Err(error);
+        };
+        let mut last = Err(error);
+        for to in failover::after(from, self.servers.len()) {
+            let (Some(mut attempt), Some(mirror_url)) =
+                (request.try_clone(), failover::moved(request.url(), &self.servers, to))
+            else {
+                break;
+            };
+            *attempt.url_mut() = mirror_url;
+            last = self.execute_once(attempt).await;
+            if !matches!(&last, Err(e) if e.is_connect()) {
+                failover::report(&self.servers[from], &self.servers[to]);
+                self.current.store(to, Ordering::Relaxed);
+                break;
+            }
+        }
+        last
+    }
+
+    /// Send a request once, remembering whether the server could be reached.
+    async fn execute_once(&self, request: reqwest::Request) -> reqwest::Result<Response> {
+        let (method, url) = (request.method().to_string(), request.url().to_string());
+        let started = std::time::Instant::now();
+        let result = self.client.execute(request).await;
+        self.reachable.store(result.is_ok(), Ordering::Relaxed);
+        let outcome = result.as_ref().map(|r| r.status().as_u16()).map_err(|e| e.to_string());
+        crate::httplog::record(&method, &url, outcome, started.elapsed());
+        result
+    }
+
+    async fn send_request(&self, request: reqwest::Request, repeatable: bool) -> reqwest::Result<Response> {
+        let (method, url) = (request.method().to_string(), request.url().to_string());
+        let retries = if repeatable { self.policy.retries } else { 0 };
+        let mut request = Some(request);
+        let mut retry = 0;
+        loop {
+            // A request with a streamed body can't be copied, and is sent once
+            let current = request.take().expect("request");
+            let next = (retry < retries).then(|| current.try_clone()).flatten();
+            let result = self.execute(current).await;
+
+            let problem = match &result {
+                Ok(response) if retry::transient_status(response.status()) => response.status().to_string(),
+                Err(error) if retry::transient_error(error) => error.to_string(),
+                _ => return result,
+            };
+            let Some(next) = next else {
+                return result;
+            };
+            retry += 1;
+            let wait = retry::delay(retry);
+            retry::report(&method, &url, &problem, retry, retries, wait);
+            tokio::time::sleep(wait).await;
+            request = Some(next);
+        }
+    }
+
+    pub async fn health(&self) -> Result<HealthResponse> {
+        let url = format!("{}/health", self.base_url());
+        let response = self
+            .send(self.client.get(&url))
+            .await
+            .context("Failed to connect to server")?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Server returned error: {}", response.status());
+        }
+
+        response.json().await.context("Failed to parse health response")
+    }
+
+    /// Optional features of the server; `None` for servers older than the
+    /// endpoint.
+    pub async fn capabilities(&self) -> Result<Option<CapabilitiesResponse>> {
+        let url = format!("{}/capabilities", self.base_url());
+        let response = self
+            .send(self.client.get(&url))
+            .await
+            .context("Failed to fetch server capabilities")?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        read_json(response, "Capabilities failed", "capabilities").await.map(Some)
+    }
+
+    pub async fn search(&self, req: SearchRequest) -> Result<SearchResponse> {
+        let url = format!("{}/search", self.base_url());
+        let response = self
+            .send_repeatable(self.client.post(&url).json(&req))
+            .await
+            .context("Failed to send search request")?;
+        read_json(response, "Search failed", "search").await
+    }
+
+    pub async fn chat(&self, req: ChatRequest) -> Result<ChatResponse> {
+        let url = format!("{}/chat", self.base_url());
+        let response = self
+            .send(self.client.post(&url).json(&req).timeout(self.policy.answer_timeout))
+            .await
+            .context("Failed to send chat request")?;
+        read_json(response, "Chat failed", "chat").await
+    }
+
+    /// Like `chat`, but calls `on_text` with each piece of the answer as it
+    /// is generated. Returns `None` if the server has no streaming endpoint.
+    pub async fn chat_stream(
+        &self,
+        req: &ChatRequest,
+        mut on_text: impl FnMut(&str) -> Result<()>,
+    ) -> Result<Option<ChatResponse>> {
+        let url = format!("{}/chat/stream", self.base_url());
+        let mut response = self
+            .send(self.client.post(&url).json(req).timeout(self.policy.answer_timeout))
+            .await
+            .context("Failed to send chat request")?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::METHOD_NOT_ALLOWED {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Chat failed ({}): {}", status, body);
+        }
+
+        let mut meta = None;
+        let mut answer = String::new();
+        // Events are JSON lines; a chunk may end in the middle of one
+        let mut pending = Vec::new();
+        'stream: while let Some(chunk) = response.chunk().await.context("Chat stream interrupted")? {
+            pending.extend_from_slice(&chunk);
+            while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str(&line).context("Failed to parse chat stream")? {
+                    ChatEvent::Meta(response) => meta = Some(response),
+                    ChatEvent::Token { text } => {
+                        answer.push_str(&text);
+                        on_text(&text)?;
+                    }
+                    ChatEvent::Done => break 'stream,
+                }
+            }
+        }
+
+        let mut response: ChatResponse = meta.context("Chat stream ended before sending sources")?;
+        response.answer = answer;
+        Ok(Some(response))
+    }
+
+    pub async fn synthesize(&self, req: SynthesisRequest) -> Result<SynthesisResponse> {
+        let url = format!("{}/synthesize", self.base_url());
+        let response = self
+            .send(self.client.post(&url).json(&req).timeout(self.policy.answer_timeout))
+            .await
+            .context("Failed to send synthesis request")?;
+        read_json(response, "Synthesis failed", "synthesis").await
+    }
+
+    pub async fn verify(&self, req: VerifyRequest) -> Result<VerifyResponse> {
+        let url = format!("{}/verify", self.base_url());
+        let response = self
+            .send(self.client.post(&url).json(&req).timeout(self.policy.answer_timeout))
+            .await
+            .context("Failed to send verify request")?;
+        read_json(response, "Verify failed", "verify").await
+    }
+
+    pub async fn acronym(&self, acronym: &str, document: Option<&str>, max_sources: i32) -> Result<AcronymResponse> {
+        let mut url = format!(
+            "{}/acronym/{}?max_sources={}",
+            self.base_url(), acronym, max_sources
+        );
+        if let Some(slug) = document {
+            url.push_str(&format!("&document_slug={}", slug));
+        }
+        let response = self
+            .send(self.client.get(&url))
+            .await
+            .context("Failed to look up acronym")?;
+        read_json(response, "Acronym lookup failed", "acronym").await
+    }
+
+    pub async fn list_documents(&self, page: i32, page_size: i32, sort_by: &str) -> Result<DocumentListResponse> {
+        let url = format!(
+            "{}/documents?page={}&page_size={}&sort_by={}",
+            self.base_url(), page, page_size, sort_by
+        );
+        let response = self
+            .send(self.client.get(&url))
+            .await
+            .context("Failed to fetch documents")?;
+        read_json(response, "Failed to list documents", "documents").await
+    }
+
+    /// Hand every document in the library, sorted by `sort_by`, to `each`,
+    /// a page at a time like `OsgeoClient::documents`: only one page is held
+    /// at once, and the next is asked for once `each` has had the last.
+    /// Returns how many documents there were.
+    pub async fn for_each_document(&self, sort_by: &str, mut each: impl FnMut(DocumentListItem)) -> Result<usize> {
+        let mut count = 0;
+        let mut page = 1;
+        loop {
+            let response = self.list_documents(page, LISTING_PAGE_SIZE, sort_by).await?;
+            count += response.documents.len();
+            response.documents.into_iter().for_each(&mut each);
+            if page >= response.total_pages {
+                return Ok(count);
+            }
+            page += 1;
+        }
+    }
+
+    /// Like `list_documents` (by title), with each document's
+    /// `content_checksum`: the same for the same PDF ingested twice under
+    /// different names. Slower, as the server reads every page's text.
+    pub async fn list_documents_with_checksums(&self, page: i32, page_size: i32) -> Result<DocumentListResponse> {
+        let url = format!(
+            "{}/documents?page={}&page_size={}&checksums=true",
+            self.base_url(), page, page_size
+        );
+        let response = self
+            .send(self.client.get(&url))
+            .await
+            .context("Failed to fetch documents")?;
+        read_json(response, "Failed to list documents", "documents").await
+    }
+
+    /// Delete a document with its pages, chunks and elements. Servers refuse
+    /// (403) unless deleting is enabled in their config.
+    pub async fn delete_document(&self, slug: &str) -> Result<DocumentDeleteResponse> {
+        let url = format!("{}/documents/{}", self.base_url(), slug);
+        let response = self
+            .send(self.client.delete(&url))
+            .await
+            .context("Failed to send delete request")?;
+        read_json(response, "Failed to delete document", "delete").await
+    }
+
+    /// Upload a PDF to be extracted, enriched and ingested in the background
+    /// (see `get_ingest_job`). Servers refuse (409) files already in the
+    /// library unless `force`, and (403) uploads unless enabled in their config.
+    pub async fn ingest_document(&self, file_name: &str, pdf: Vec<u8>, force: bool) -> Result<IngestJob> {
+        let url = format!("{}/ingest", self.base_url());
+        let request = self
+            .client
+            .post(&url)
+            .query(&[("filename", file_name), ("force", if force { "true" } else { "false" })])
+            .header(reqwest::header::CONTENT_TYPE, "application/pdf")
+            .timeout(Duration::from_secs(UPLOAD_TIMEOUT_SECS))
+            .body(pdf);
+        let response = self.send(request).await.context("Failed to upload document")?;
+        read_json(response, "Upload refused", "ingest").await
+    }
+
+    /// Progress of an upload started with `ingest_document`.
+    pub async fn get_ingest_job(&self, job_id: &str) -> Result<IngestJob> {
+        let url = format!("{}/ingest/{}", self.base_url(), job_id);
+        let response = self
+            .send(self.client.get(&url))
+            .await
+            .context("Failed to fetch ingest progress")?;
+        read_json(response, "Failed to get ingest progress", "ingest progress").await
+    }
+
+    pub async fn get_document(&self, slug: &str) -> Result<DocumentDetailResponse> {
+        let url = format!("{}/documents/{}", self.base_url(), slug);
+        let response = self
+            .send(self.client.get(&url))
+            .await
+            .context("Failed to fetch document")?;
+        read_json(response, "Failed to get document", "document").await
+    }
+
+    pub async fn list_elements(
+        &self,
+        slug: &str,
+        element_type: Option<&str>,
+        page: Option<i32>,
+        limit: i32,
+        offset: i32,
+    ) -> Result<ElementListResponse> {
+        let mut url = format!(
+            "{}/documents/{}/elements?limit={}&offset={}",
+            self.base_url(), slug, limit, offset
+        );
+        if let Some(t) = element_type {
+            url.push_str(&format!("&element_type={}", t));
+        }
+        if let Some(p) = page {
+            url.push_str(&format!("&page={}", p));
+        }
+        let response = self
+            .send(self.client.get(&url))
+            .await
+            .context("Failed to fetch elements")?;
+        read_json(response, "Failed to list elements", "elements").await
+    }
+
+    pub async fn list_chunks(&self, slug: &str, limit: i32, offset: i32) -> Result<ChunkListResponse> {
+        let url = format!(
+            "{}/documents/{}/chunks?limit={}&offset={}",
+            self.base_url(), slug, limit, offset
+        );
+        let response = self
+            .send(self.client.get(&url))
+            .await
+            .context("Failed to fetch chunks")?;
+        read_json(response, "Failed to list chunks", "chunks").await
+    }
+
+    /// Text chunks of one page, in reading order.
+    pub async fn list_page_chunks(&self, slug: &str, page: i32) -> Result<ChunkListResponse> {
+        let url = format!("{}/documents/{}/chunks?page={}&limit=500", self.base_url(), slug, page);
+        let response = self
+            .send(self.client.get(&url))
+            .await
+            .context("Failed to fetch chunks")?;
+        read_json(response, "Failed to list chunks", "chunks").await
+    }
+
+    pub async fn list_page_summaries(&self, slug: &str, limit: i32, offset: i32) -> Result<PageSummaryListResponse> {
+        let url = format!(
+            "{}/documents/{}/pages?limit={}&offset={}",
+            self.base_url(), slug, limit, offset
+        );
+        let response = self
+            .send(self.client.get(&url))
+            .await
+            .context("Failed to fetch page summaries")?;
+        read_json(response, "Failed to list pages", "pages").await
+    }
+
+    pub async fn list_chapters(&self, slug: &str) -> Result<ChapterListResponse> {
+        let url = format!("{}/documents/{}/chapters", self.base_url(), slug);
+        let response = self
+            .send(self.client.get(&url))
+            .await
+            .context("Failed to fetch chapters")?;
+        read_json(response, "Failed to list chapters", "chapters").await
+    }
+
+    /// Chapters and numbered sections ("4.2", "4.2.1"), in page order.
+    pub async fn list_sections(&self, slug: &str) -> Result<SectionListResponse> {
+        let url = format!("{}/documents/{}/sections", self.base_url(), slug);
+        let response = self
+            .send(self.client.get(&url))
+            .await
+            .context("Failed to fetch sections")?;
+        read_json(response, "Failed to list sections", "sections").await
+    }
+
+    /// Fetch a page image with metadata, scaled down to `width` pixels when
+    /// given. Servers without scaling ignore the parameter and send the page
+    /// at full resolution. The image is asked for as raw bytes; older servers
+    /// send it base64-encoded in JSON.
+    pub async fn get_page(&self, slug: &str, page_number: i32, width: Option<u32>) -> Result<PageResponse> {
+        let url = format!("{}/page/{}/{}", self.base_url(), slug, page_number);
+        let mut request = self.client.get(&url).header(reqwest::header::ACCEPT, PAGE_ACCEPT);
+        if let Some(width) = width {
+            request = request.query(&[("width", width)]);
+        }
+        for _ in 0..digest::ATTEMPTS {
+            let response = self
+                .send(request.try_clone().context("Failed to fetch page")?)
+                .await
+                .context("Failed to fetch page")?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                anyhow::bail!("Failed to get page ({}): {}", status, body);
+            }
+
+            let metadata = response.headers().get(PAGE_METADATA).cloned();
+            let expected = digest::expected(response.headers());
+            let body = response.bytes().await.context("Failed to read page response")?;
+            if digest::matches(expected.as_deref(), &body) {
+                return decode_page(metadata.as_ref(), &body);
+            }
+        }
+        Err(digest::damaged("Page image"))
+    }
+
+    /// Fetch image bytes from the server, again when they don't match the
+    /// server's checksum.
+    pub async fn fetch_image(&self, url: &str) -> Result<Vec<u8>> {
+        for _ in 0..digest::ATTEMPTS {
+            let response = self
+                .send(self.client.get(url))
+                .await
+                .context("Failed to fetch image")?;
+
+            if !response.status().is_success() {
+                anyhow::bail!("Image not found ({})", response.status());
+            }
+
+            let expected = digest::expected(response.headers());
+            let bytes = response.bytes().await.context("Failed to read image bytes")?;
+            if digest::matches(expected.as_deref(), &bytes) {
+                return Ok(bytes.to_vec());
+            }
+        }
+        Err(digest::damaged("Image"))
+    }
+}
+
+/// Parse a successful response, or fail with `failure` and the server's
+/// status and message. The body is read whole, so listings of the whole
+/// library go a page at a time (`for_each_document`).
+async fn read_json<T: DeserializeOwned>(response: Response, failure: &str, what: &str) -> Result<T> {
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        anyhow::bail!("{} ({}): {}", failure, status, body);
+    }
+
+    response
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse {} response", what))
+}
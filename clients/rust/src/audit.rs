@@ -0,0 +1,125 @@
+//! Suspected duplicate documents (`audit duplicates`).
+//!
+//! The same PDF can end up in the library twice: ingested under another
+//! name, or before ingest checked source files. Documents are grouped when
+//! they share a source file name, have the same text (the server's content
+//! checksum), or have nearly the same title; overlapping groups are merged,
+//! so three copies found by different signs form one group.
+
+use crate::types::DocumentListItem;
+use std::collections::HashSet;
+
+/// Share of title words two titles must have in common (of all their
+/// words) to count as the same title.
+const TITLE_SIMILARITY: f64 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    SourceFile,
+    Checksum,
+    Title,
+}
+
+impl Reason {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Self::SourceFile => "same source file",
+            Self::Checksum => "same text",
+            Self::Title => "similar title",
+        }
+    }
+}
+
+/// Documents (indices into the audited list, in list order) that look like
+/// copies of each other, and why.
+pub struct Group {
+    pub members: Vec<usize>,
+    pub reasons: Vec<Reason>,
+}
+
+/// Groups of suspected duplicates among `documents`, in list order.
+pub fn find(documents: &[DocumentListItem]) -> Vec<Group> {
+    let mut parent: Vec<usize> = (0..documents.len()).collect();
+    let mut links: Vec<(usize, Reason)> = Vec::new();
+    let titles: Vec<HashSet<String>> = documents.iter().map(|d| title_words(&d.title)).collect();
+
+    for i in 0..documents.len() {
+        for j in i + 1..documents.len() {
+            let (a, b) = (&documents[i], &documents[j]);
+            let mut reasons = Vec::new();
+            if file_name(a).is_some() && file_name(a) == file_name(b) {
+                reasons.push(Reason::SourceFile);
+            }
+            if a.content_checksum.is_some() && a.content_checksum == b.content_checksum {
+                reasons.push(Reason::Checksum);
+            }
+            if similar(&titles[i], &titles[j]) {
+                reasons.push(Reason::Title);
+            }
+            if !reasons.is_empty() {
+                let (ri, rj) = (root(&mut parent, i), root(&mut parent, j));
+                parent[rj.max(ri)] = ri.min(rj);
+                links.extend(reasons.into_iter().map(|r| (i, r)));
+            }
+        }
+    }
+
+    // Groups keyed by their root, in order of their first member
+    let roots: Vec<usize> = (0..documents.len()).map(|i| root(&mut parent, i)).collect();
+    let mut groups: Vec<(usize, Group)> = Vec::new();
+    for (i, &r) in roots.iter().enumerate() {
+        if roots.iter().filter(|&&other| other == r).count() < 2 {
+            continue;
+        }
+        match groups.iter_mut().find(|(key, _)| *key == r) {
+            Some((_, group)) => group.members.push(i),
+            None => groups.push((
+                r,
+                Group {
+                    members: vec![i],
+                    reasons: Vec::new(),
+                },
+            )),
+        }
+    }
+    for (i, reason) in links {
+        if let Some((_, group)) = groups.iter_mut().find(|(key, _)| *key == roots[i]) {
+            if !group.reasons.contains(&reason) {
+                group.reasons.push(reason);
+            }
+        }
+    }
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+fn root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Source file name without its directory, lowercased.
+fn file_name(document: &DocumentListItem) -> Option<String> {
+    let path = document.source_file.as_deref()?.trim();
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    (!name.is_empty()).then(|| name.to_lowercase())
+}
+
+fn title_words(title: &str) -> HashSet<String> {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn similar(a: &HashSet<String>, b: &HashSet<String>) -> bool {
+    if a.is_empty() || b.is_empty() {
+        return false;
+    }
+    let shared = a.intersection(b).count() as f64;
+    let all = a.union(b).count() as f64;
+    shared / all >= TITLE_SIMILARITY
+}
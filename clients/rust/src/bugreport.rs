@@ -0,0 +1,176 @@
+//! Context for bug reports (`report-bug`).
+//!
+//! Most issues arrive without the client version, the server it talked to
+//! or the terminal it ran in. This gathers them into a Markdown snippet to
+//! paste into a GitHub issue: versions, server health, the config with
+//! anything private taken out, the requests logged with `--verbose` (see
+//! `httplog`) and what the terminal says it can do.
+
+use crate::httplog;
+use crate::types::HealthResponse;
+use anyhow::Result;
+use std::io::IsTerminal;
+use std::process::Command;
+
+/// What the report is made of, gathered by the caller.
+pub struct Inputs<'a> {
+    pub server_url: &'a str,
+    pub health: Result<HealthResponse>,
+    pub config_path: Option<std::path::PathBuf>,
+    pub profile: Option<&'a str>,
+    pub settings: Result<Vec<(String, toml::Value)>>,
+    pub requests: usize,
+}
+
+/// The report as Markdown.
+pub fn markdown(inputs: &Inputs) -> String {
+    let mut out = String::from("### Environment\n\n");
+    out.push_str(&format!(
+        "- Client: osgeo-library {} ({} {})\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    ));
+    out.push_str(&format!("- Server: {}\n", httplog::redact_url(inputs.server_url)));
+    match &inputs.health {
+        Ok(health) => {
+            let check = |ok: bool| if ok { "ok" } else { "FAILED" };
+            out.push_str(&format!(
+                "- Server status: {}, version {} (embedding {}, LLM {}, database {})\n",
+                health.status,
+                health.version,
+                check(health.embedding_server),
+                check(health.llm_server),
+                check(health.database)
+            ));
+        }
+        Err(e) => out.push_str(&format!("- Server status: unreachable ({:#})\n", e)),
+    }
+
+    out.push_str("\n### Terminal\n\n");
+    for (name, value) in terminal() {
+        out.push_str(&format!("- {}: {}\n", name, value));
+    }
+
+    out.push_str("\n### Config\n\n");
+    match &inputs.config_path {
+        Some(path) if path.exists() => out.push_str(&format!("File: `{}`\n", home_relative(path))),
+        _ => out.push_str("No config file (built-in defaults)\n"),
+    }
+    if let Some(profile) = inputs.profile {
+        out.push_str(&format!("Profile: `{}`\n", profile));
+    }
+    match &inputs.settings {
+        Ok(settings) => {
+            out.push_str("\n```toml\n");
+            for (key, value) in settings {
+                out.push_str(&format!("{} = {}\n", key, redact(key, value)));
+            }
+            out.push_str("```\n");
+        }
+        Err(e) => out.push_str(&format!("\nThe config could not be loaded: {:#}\n", e)),
+    }
+
+    if let Some(crash) = last_crash() {
+        out.push_str(&format!("\n### Last crash\n\n```\n{}\n```\n", crash));
+    }
+
+    out.push_str("\n### Recent requests\n\n");
+    let requests = httplog::recent(inputs.requests);
+    if requests.is_empty() {
+        out.push_str("None logged. Run the command that fails with --verbose, then report-bug again.\n");
+    } else {
+        out.push_str("```\n");
+        for line in requests {
+            out.push_str(&format!("{}\n", line));
+        }
+        out.push_str("```\n");
+    }
+    out
+}
+
+/// The last panic in the crash log, without its backtrace.
+fn last_crash() -> Option<String> {
+    let log = std::fs::read_to_string(crate::crash::path()?).ok()?;
+    let last = log.rfind("--- ").map(|i| &log[i..])?;
+    // Header, message and location; the backtrace follows
+    let lines: Vec<&str> = last.lines().take_while(|l| !l.trim_start().starts_with("0:")).collect();
+    Some(lines.join("\n").trim_end().to_string())
+}
+
+/// What the terminal reports about itself, as (name, value) lines.
+fn terminal() -> Vec<(&'static str, String)> {
+    let var = |name: &str| std::env::var(name).unwrap_or_else(|_| "-".to_string());
+    let env = crate::term::env();
+    let yes_no = |b: bool| if b { "yes" } else { "no" }.to_string();
+    let mut lines = vec![
+        ("TERM", var("TERM")),
+        ("COLORTERM", var("COLORTERM")),
+        ("TERM_PROGRAM", var("TERM_PROGRAM")),
+        ("TERM_PROGRAM_VERSION", var("TERM_PROGRAM_VERSION")),
+        ("NO_COLOR", yes_no(std::env::var_os("NO_COLOR").is_some())),
+        ("stdout is a terminal", yes_no(std::io::stdout().is_terminal())),
+        ("plain output", yes_no(env.plain)),
+        ("SSH session", yes_no(env.ssh)),
+        ("graphical display", yes_no(env.gui)),
+    ];
+    lines.push((
+        "size",
+        match crossterm::terminal::window_size() {
+            Ok(size) => format!("{}x{} cells, {}x{} px", size.columns, size.rows, size.width, size.height),
+            Err(_) => "unknown".to_string(),
+        },
+    ));
+    lines.push((
+        "background",
+        match crate::term::background() {
+            Some([r, g, b]) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+            None => "unknown".to_string(),
+        },
+    ));
+    let graphics = crate::term::graphics();
+    lines.push((
+        "graphics support",
+        format!("kitty {}, sixel {}", yes_no(graphics.kitty), yes_no(graphics.sixel)),
+    ));
+    let backend = crate::preview::backend();
+    let resolved = crate::graphics::resolve(backend);
+    lines.push((
+        "image backend",
+        if resolved == backend {
+            format!("{:?}", backend).to_lowercase()
+        } else {
+            format!("{:?} ({:?})", backend, resolved).to_lowercase()
+        },
+    ));
+    let chafa = Command::new("chafa")
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .and_then(|v| v.lines().next().map(str::to_string));
+    lines.push(("chafa", chafa.unwrap_or_else(|| "not found".to_string())));
+    lines
+}
+
+/// A setting's value for the report, with hook commands, credentials in
+/// the server URL and anything that looks like a secret left out.
+fn redact(key: &str, value: &toml::Value) -> String {
+    let name = key.rsplit('.').next().unwrap_or(key);
+    let secret = ["token", "password", "secret"].iter().any(|s| name.contains(s)) || name.ends_with("_key");
+    if secret || key.starts_with("hooks.") {
+        return "\"<redacted>\"".to_string();
+    }
+    match value {
+        toml::Value::String(s) if s.contains("://") => toml::Value::String(httplog::redact_url(s)).to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// `path` with the home directory shown as `~`, so the user name stays out.
+fn home_relative(path: &std::path::Path) -> String {
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(|p| p.to_path_buf())) {
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
@@ -0,0 +1,248 @@
+//! Building a search step by step (`search --interactive`).
+//!
+//! For occasional users who don't remember the search flags: the query,
+//! document, result type, pages and minimum score are asked for in turn,
+//! each with a default (from the flags given) that Enter keeps and `-`
+//! clears. The query is filled in, ready to edit. After each answer the
+//! matching results among the top `PREVIEW_LIMIT` are counted, so a filter
+//! that leaves nothing shows at once. The finished search is printed as the
+//! equivalent command line, to run again or to learn the flags from.
+
+use crate::types::SearchRequest;
+use crate::{notes, ranking, OsgeoClient};
+use anyhow::Result;
+use colored::Colorize;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+/// Element types `search --type` takes
+const ELEMENT_TYPES: [&str; 5] = ["figure", "table", "equation", "chart", "diagram"];
+
+/// Results counted for a preview (the server's cap)
+const PREVIEW_LIMIT: i32 = 50;
+
+/// The search filters the steps fill in.
+#[derive(Debug, Clone, Default)]
+pub struct Filters {
+    pub query: String,
+    pub document: Option<String>,
+    pub elements_only: bool,
+    pub chunks_only: bool,
+    pub element_type: Option<String>,
+    pub pages: Option<(i32, i32)>,
+    pub min_score: Option<f64>,
+}
+
+impl Filters {
+    /// The type step's answer: "all", "text", "elements" or an element type.
+    fn kind(&self) -> &str {
+        match (&self.element_type, self.elements_only, self.chunks_only) {
+            (Some(element_type), _, _) => element_type,
+            (None, true, _) => "elements",
+            (None, false, true) => "text",
+            (None, false, false) => "all",
+        }
+    }
+
+    fn set_kind(&mut self, kind: &str) -> Result<(), String> {
+        let kind = kind.to_lowercase();
+        self.elements_only = kind == "elements";
+        self.chunks_only = kind == "text";
+        self.element_type = None;
+        match kind.as_str() {
+            "" | "all" | "text" | "elements" => Ok(()),
+            t if ELEMENT_TYPES.contains(&t) => {
+                self.element_type = Some(kind);
+                Ok(())
+            }
+            _ => Err(format!("'{}' is not one of: {}", kind, kinds())),
+        }
+    }
+
+    fn request(&self, limit: i32) -> SearchRequest {
+        let elements_only = self.elements_only || self.element_type.is_some();
+        SearchRequest {
+            query: self.query.clone(),
+            limit,
+            document_slug: self.document.clone(),
+            include_chunks: !elements_only,
+            include_elements: !self.chunks_only,
+            element_type: self.element_type.clone(),
+            min_score: self.min_score,
+            ..Default::default()
+        }
+    }
+
+    /// The same search as a command line.
+    pub fn command_line(&self) -> String {
+        let mut line = format!("osgeo-library search {}", quote(&self.query));
+        if let Some(document) = &self.document {
+            line.push_str(&format!(" -d {}", quote(document)));
+        }
+        match self.kind() {
+            "all" => {}
+            "text" => line.push_str(" --chunks-only"),
+            "elements" => line.push_str(" --elements-only"),
+            element_type => line.push_str(&format!(" -t {}", element_type)),
+        }
+        if let Some((from, to)) = self.pages {
+            line.push_str(&format!(" --pages {}-{}", from, to));
+        }
+        if let Some(min_score) = self.min_score {
+            line.push_str(&format!(" --min-score {}", min_score));
+        }
+        line
+    }
+}
+
+/// Ask for each filter in turn, starting from `filters`. `None` when the
+/// user gives up with Ctrl-C or Ctrl-D.
+pub fn run(client: &OsgeoClient, mut filters: Filters) -> Result<Option<Filters>> {
+    let mut rl = DefaultEditor::new()?;
+    println!(
+        "{}",
+        "Build a search step by step. Enter keeps [the default], - clears it, Ctrl-C cancels.\n".dimmed()
+    );
+
+    let query = filters.query.clone();
+    if !step(&mut rl, "Query", "what to look for", &query, "", |answer| {
+        if answer.is_empty() {
+            return Err("The query can't be empty".to_string());
+        }
+        filters.query = answer.to_string();
+        Ok(())
+    })? {
+        return Ok(None);
+    }
+    preview(client, &filters);
+
+    let document = filters.document.clone().unwrap_or_default();
+    if !step(&mut rl, "Document", "slug, empty for the whole library", "", &document, |answer| {
+        if answer.is_empty() {
+            filters.document = None;
+            return Ok(());
+        }
+        client
+            .get_document(answer)
+            .map_err(|e| format!("Can't search '{}': {}", answer, e.root_cause()))?;
+        filters.document = Some(answer.to_string());
+        Ok(())
+    })? {
+        return Ok(None);
+    }
+    preview(client, &filters);
+
+    let kind = filters.kind().to_string();
+    if !step(&mut rl, "Type", &kinds(), "", &kind, |answer| filters.set_kind(answer))? {
+        return Ok(None);
+    }
+    preview(client, &filters);
+
+    let pages = filters.pages.map(|(from, to)| format!("{}-{}", from, to)).unwrap_or_default();
+    if !step(&mut rl, "Pages", "e.g. 45-78, empty for all", "", &pages, |answer| {
+        filters.pages = match answer {
+            "" => None,
+            range => Some(notes::parse_page_range(range)?),
+        };
+        Ok(())
+    })? {
+        return Ok(None);
+    }
+    preview(client, &filters);
+
+    let min_score = filters.min_score.map(|s| s.to_string()).unwrap_or_default();
+    if !step(&mut rl, "Minimum score", "0-100, empty for none", "", &min_score, |answer| {
+        filters.min_score = match answer {
+            "" => None,
+            score => Some(ranking::parse_min_score(score)?),
+        };
+        Ok(())
+    })? {
+        return Ok(None);
+    }
+
+    println!("\n{} {}", "Search:".dimmed(), filters.command_line());
+    Ok(Some(filters))
+}
+
+/// Read one answer, pre-filled with `initial`, until `accept` takes it. An
+/// empty answer stands for `default`, and `-` for none. False on Ctrl-C or
+/// Ctrl-D.
+fn step(
+    rl: &mut DefaultEditor,
+    name: &str,
+    hint: &str,
+    initial: &str,
+    default: &str,
+    mut accept: impl FnMut(&str) -> Result<(), String>,
+) -> Result<bool> {
+    let mut prompt = name.bold().to_string();
+    if !default.is_empty() {
+        prompt.push_str(&format!(" [{}]", default));
+    }
+    prompt.push_str(&format!(" {}: ", format!("({})", hint).dimmed()));
+    loop {
+        let answer = match rl.readline_with_initial(&prompt, (initial, "")) {
+            Ok(answer) => answer,
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        let answer = match answer.trim() {
+            "" => default,
+            "-" => "",
+            answer => answer,
+        };
+        match accept(answer) {
+            Ok(()) => return Ok(true),
+            Err(problem) => println!("{}", problem.yellow()),
+        }
+    }
+}
+
+/// Print how many of the top results the filters so far keep.
+fn preview(client: &OsgeoClient, filters: &Filters) {
+    let line = match count(client, filters) {
+        Ok((text, elements)) => {
+            let total = text + elements;
+            format!(
+                "  {}{} result{} ({} text, {} elements)",
+                total,
+                if total == PREVIEW_LIMIT as usize { "+" } else { "" },
+                if total == 1 { "" } else { "s" },
+                text,
+                elements
+            )
+        }
+        Err(e) => format!("  Can't count results: {}", e.root_cause()),
+    };
+    println!("{}", line.dimmed());
+}
+
+/// Text passages and elements among the top results matching `filters`.
+fn count(client: &OsgeoClient, filters: &Filters) -> Result<(usize, usize)> {
+    let mut response = client.search(filters.request(PREVIEW_LIMIT))?;
+    response.drop_below(filters.min_score);
+    let kept = response
+        .results
+        .iter()
+        .filter(|r| filters.pages.is_none_or(|(from, to)| (from..=to).contains(&r.page_number)));
+    let (elements, text): (Vec<_>, Vec<_>) = kept.partition(|r| r.source_type == "element");
+    Ok((text.len(), elements.len()))
+}
+
+fn kinds() -> String {
+    format!("all, text, elements, {}", ELEMENT_TYPES.join(", "))
+}
+
+/// `value` as a single shell word.
+fn quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=,".contains(c));
+    if plain {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
@@ -0,0 +1,98 @@
+//! Ctrl-C while an answer is being generated.
+//!
+//! Answers can take a minute or two, and Ctrl-C normally ends the program,
+//! which in chat throws away the whole session. While a request is `arm`ed,
+//! Ctrl-C only raises a flag, so the request can be abandoned and chat
+//! carries on. At other times it ends the program as before, after putting
+//! the terminal back (`tty::restore`). At the chat prompt rustyline reads
+//! Ctrl-C as a key, not a signal.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+struct Flags {
+    /// No request armed: Ctrl-C exits
+    idle: Arc<AtomicBool>,
+    interrupted: Arc<AtomicBool>,
+}
+
+static FLAGS: OnceLock<Flags> = OnceLock::new();
+
+/// Exit status of a program ended by Ctrl-C (128 + SIGINT)
+const INTERRUPTED_STATUS: i32 = 130;
+
+/// Take over Ctrl-C. Without this, `arm` has no effect.
+pub fn install() {
+    use signal_hook::{consts::SIGINT, flag, iterator::Signals};
+
+    let flags = Flags {
+        idle: Arc::new(AtomicBool::new(true)),
+        interrupted: Arc::new(AtomicBool::new(false)),
+    };
+    // Exiting is left to a thread rather than done in the signal handler,
+    // where the terminal can't safely be restored
+    let registered = flag::register(SIGINT, Arc::clone(&flags.interrupted)).and_then(|_| Signals::new([SIGINT]));
+    match registered {
+        Ok(mut signals) => {
+            let idle = Arc::clone(&flags.idle);
+            let watcher = std::thread::Builder::new().name("ctrl-c".to_string()).spawn(move || {
+                for _ in signals.forever() {
+                    if idle.load(Ordering::SeqCst) {
+                        exit();
+                    }
+                }
+            });
+            if let Err(e) = watcher {
+                crate::term::verbose_line(&format!("Ctrl-C will not cancel answers: {}", e));
+                return;
+            }
+            FLAGS.set(flags).ok();
+        }
+        Err(e) => crate::term::verbose_line(&format!("Ctrl-C will not cancel answers: {}", e)),
+    }
+}
+
+/// Make Ctrl-C interrupt instead of exiting, until the guard is dropped.
+pub fn arm() -> Armed {
+    if let Some(flags) = FLAGS.get() {
+        flags.interrupted.store(false, Ordering::SeqCst);
+        flags.idle.store(false, Ordering::SeqCst);
+    }
+    Armed(())
+}
+
+pub struct Armed(());
+
+impl Armed {
+    /// Whether Ctrl-C was pressed since `arm`.
+    pub fn interrupted(&self) -> bool {
+        FLAGS.get().is_some_and(|flags| flags.interrupted.load(Ordering::SeqCst))
+    }
+}
+
+impl Drop for Armed {
+    fn drop(&mut self) {
+        if let Some(flags) = FLAGS.get() {
+            flags.idle.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Error of a request abandoned with Ctrl-C.
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Exit the way Ctrl-C would have, restoring the terminal first.
+pub fn exit() -> ! {
+    crate::tty::restore();
+    std::process::exit(INTERRUPTED_STATUS)
+}
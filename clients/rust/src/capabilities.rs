@@ -0,0 +1,203 @@
+//! What each server can do (`GET /capabilities`).
+//!
+//! Servers differ: older ones lack endpoints that newer ones have, uploads
+//! and deletes are off unless enabled in a server's config, and a federated
+//! search (`federation`) may reach several versions at once. Rather than
+//! fail at request time, commands look up the server's optional features
+//! first: chat answers without streaming where the server can't stream,
+//! `ingest`, `verify` and the like stop with a clear message before doing
+//! any work, and a federated search by label leaves out libraries that
+//! can't do one. Features are asked for once per server and cached in the
+//! user's cache directory for `MAX_AGE_SECS`; `health` asks again. Servers
+//! without the endpoint are taken to have every feature, and fail at
+//! request time as they always did.
+
+use crate::OsgeoClient;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// How long a server's features are trusted before asking again
+const MAX_AGE_SECS: u64 = 3600;
+
+/// An optional feature commands check for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// Answers streamed as they are generated (`/chat/stream`)
+    ChatStream,
+    Verify,
+    /// Merging per-document answers (`ask --top-docs`)
+    Synthesize,
+    Acronym,
+    /// Literal label matching (`search --in labels`)
+    LabelsSearch,
+    /// The user's own text as context (`ask --context-file`)
+    UserContext,
+    /// Nearest neighbors of a result (`similar`)
+    Similar,
+    /// Filtering the document list (`docs --keyword`, `--license`, `--search`)
+    DocsFilters,
+    /// PDF uploads (`ingest`)
+    Ingest,
+    /// Deleting documents (`audit duplicates --delete`)
+    Delete,
+    /// Original PDFs (`download`)
+    Download,
+}
+
+impl Feature {
+    /// Name in the server's list
+    fn name(self) -> &'static str {
+        match self {
+            Feature::ChatStream => "chat_stream",
+            Feature::Verify => "verify",
+            Feature::Synthesize => "synthesize",
+            Feature::Acronym => "acronym",
+            Feature::LabelsSearch => "labels_search",
+            Feature::UserContext => "user_context",
+            Feature::Similar => "similar",
+            Feature::DocsFilters => "docs_filters",
+            Feature::Ingest => "ingest",
+            Feature::Delete => "delete",
+            Feature::Download => "download",
+        }
+    }
+
+    /// What a server without the feature can't do, and why
+    pub fn missing(self) -> &'static str {
+        match self {
+            Feature::ChatStream => "stream answers",
+            Feature::Verify => "verify statements (the server is too old)",
+            Feature::Synthesize => "merge answers from several documents (the server is too old)",
+            Feature::Acronym => "look up acronyms (the server is too old)",
+            Feature::LabelsSearch => "search by label (the server is too old)",
+            Feature::UserContext => "take your own text as context (the server is too old)",
+            Feature::Similar => "find similar content (the server is too old)",
+            Feature::DocsFilters => "filter the document list (the server is too old)",
+            Feature::Ingest => "accept uploads (allow_ingest is off in its [server] config)",
+            Feature::Delete => "delete documents (allow_delete is off in its [server] config)",
+            Feature::Download => {
+                "send original PDFs (allow_download is off in its [server] config, or the server is too old)"
+            }
+        }
+    }
+}
+
+/// A server's version and optional features.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// As reported by the server, when known
+    pub version: Option<String>,
+    /// `None` for servers that don't list them, taken to have them all
+    pub features: Option<Vec<String>>,
+}
+
+impl Capabilities {
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.features
+            .as_ref()
+            .is_none_or(|features| features.iter().any(|f| f == feature.name()))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Cached {
+    /// Unix time the server was asked
+    fetched: u64,
+    #[serde(flatten)]
+    capabilities: Capabilities,
+}
+
+/// Features of the client's server, from the cache when fresh. A server that
+/// can't be asked is taken to have every feature, so requests go ahead and
+/// fail as they would have.
+pub fn of(client: &OsgeoClient) -> Capabilities {
+    let server = client.base_url();
+    let cached = read_cache().remove(server);
+    if let Some(cached) = cached.filter(|c| now().saturating_sub(c.fetched) < MAX_AGE_SECS) {
+        return cached.capabilities;
+    }
+    refresh(client).unwrap_or_else(|e| {
+        crate::term::verbose_line(&format!("Capabilities of {} unknown: {:#}", server, e));
+        Capabilities::default()
+    })
+}
+
+/// Ask the client's server for its features, and cache them.
+pub fn refresh(client: &OsgeoClient) -> Result<Capabilities> {
+    let capabilities = match client.capabilities()? {
+        Some(response) => Capabilities {
+            version: Some(response.version),
+            features: Some(response.features),
+        },
+        // Older servers: the version from /health, and every feature
+        None => Capabilities {
+            version: client.health().ok().map(|h| h.version),
+            features: None,
+        },
+    };
+    crate::term::verbose_line(&format!(
+        "Capabilities of {}: {}",
+        client.base_url(),
+        match &capabilities.features {
+            Some(features) => features.join(", "),
+            None => "not listed (older server)".to_string(),
+        }
+    ));
+    write_cache(client.base_url(), &capabilities);
+    Ok(capabilities)
+}
+
+/// Stop unless the client's server has `feature`.
+pub fn require(client: &OsgeoClient, feature: Feature) -> Result<()> {
+    let capabilities = of(client);
+    if capabilities.supports(feature) {
+        return Ok(());
+    }
+    anyhow::bail!(
+        "{}{} can't {}",
+        client.base_url(),
+        capabilities.version.map(|v| format!(" (version {})", v)).unwrap_or_default(),
+        feature.missing()
+    )
+}
+
+fn path() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("osgeo-library").join("capabilities.json"))
+}
+
+/// Cached features by server URL; empty when there is no usable cache.
+fn read_cache() -> BTreeMap<String, Cached> {
+    path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn write_cache(server: &str, capabilities: &Capabilities) {
+    if !crate::shared::enabled() {
+        return;
+    }
+    let Some(path) = path() else {
+        return;
+    };
+    let cached = Cached {
+        fetched: now(),
+        capabilities: capabilities.clone(),
+    };
+    // A cache that can't be written only costs a request next time
+    crate::shared::update(&path, |text| {
+        let mut servers: BTreeMap<String, Cached> = serde_json::from_str(&text).unwrap_or_default();
+        servers.insert(server.to_string(), cached);
+        Ok(serde_json::to_string_pretty(&servers)?)
+    })
+    .ok();
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
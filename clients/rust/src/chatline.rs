@@ -0,0 +1,284 @@
+//! Line editing at the chat prompt: Tab completion, hints and history.
+//!
+//! Tab completes command names, then what each command takes: document
+//! slugs after `doc`, `page`, `read`, `sections`, `exclude doc` and `again -d`,
+//! `all` after `figures`, `tables` and `equations`, the settings of `set`
+//! and file names after `save`. Slugs are listed from the server at the
+//! first Tab that needs them, once per session. While a command is typed,
+//! what it takes is hinted in grey after the cursor (Right arrow completes
+//! the command's name); otherwise the last line from history starting the
+//! same way is. History is kept across sessions, up to `HISTORY_SIZE`
+//! lines, in `~/.local/share/osgeo-library/history` (the platform's data
+//! directory elsewhere). Each line is added to the file as it is entered,
+//! so sessions side by side keep theirs; piped input and sessions run with
+//! `--no-shared-state` add nothing.
+
+use crate::{completion, shared, OsgeoClient};
+use colored::Colorize;
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::{Hinter, HistoryHinter};
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+use rustyline::{CompletionType, Context, Editor};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::path::PathBuf;
+
+/// Lines of history kept
+const HISTORY_SIZE: usize = 1000;
+
+/// Chat commands and what they take, as listed by `help`
+const COMMANDS: &[(&str, &str)] = &[
+    ("docs", "[page N] [-n N] [-s title|recent|pages] [-k KEYWORD] [-l LICENSE] [--search TEXT]"),
+    ("doc", "<N|slug>"),
+    ("page", "[slug] <N>, or find <query>"),
+    ("sections", "[slug]"),
+    ("section", "<N>"),
+    ("read", "[slug] [--start N]"),
+    ("next", ""),
+    ("prev", ""),
+    ("recent", "[N]"),
+    ("figures", "[all]"),
+    ("tables", "[all]"),
+    ("equations", "[all]"),
+    ("show", "<N> [--as-text]"),
+    ("open", "<N>, or page <N>"),
+    ("detail", "<N>"),
+    ("xref", "<ref>"),
+    ("verify-element", "<N>"),
+    ("copy", "<N>"),
+    ("pick", "[open]"),
+    ("pipe", "<N> <cmd>"),
+    ("search", "[--min-score <pct>] <query>"),
+    ("sources", ""),
+    ("set", "sources <off|compact|full>"),
+    ("why", "<N>"),
+    ("similar", "<N>"),
+    ("pin", "<N>"),
+    ("pins", ""),
+    ("unpin", "<N|all>"),
+    ("exclude", "<N>, doc <slug> or clear"),
+    ("verify", "<claim>"),
+    (":paste", ""),
+    (":edit", ""),
+    ("again", "[-n N] [-d SLUG|all] [-m MODEL]"),
+    ("health", ""),
+    ("version", ""),
+    ("save", "FILE.md, or --pdf FILE"),
+    ("clear", ""),
+    ("help", ""),
+    ("quit", ""),
+    ("exit", ""),
+];
+
+pub type LineEditor<'a> = Editor<ChatHelper<'a>, FileHistory>;
+
+/// The chat prompt's editor, with the history of earlier sessions unless
+/// the input is `piped`.
+pub fn editor(client: &OsgeoClient, plain: bool, piped: bool) -> rustyline::Result<LineEditor<'_>> {
+    let config = rustyline::Config::builder()
+        .max_history_size(HISTORY_SIZE)?
+        .completion_type(CompletionType::List)
+        .build();
+    let mut rl = Editor::with_config(config)?;
+    let history = (!piped).then(history_path).flatten();
+    if let Some(path) = &history {
+        // No history yet, or one that can't be read: start afresh
+        rl.load_history(path).ok();
+    }
+    rl.set_helper(Some(ChatHelper {
+        client,
+        plain,
+        history: history.filter(|_| shared::enabled()),
+        plugin_commands: Vec::new(),
+        slugs: RefCell::new(None),
+        files: FilenameCompleter::new(),
+        hinter: HistoryHinter {},
+    }));
+    Ok(rl)
+}
+
+/// Add `line` to the history, and to the history file.
+pub fn remember(rl: &mut LineEditor, line: &str) -> rustyline::Result<()> {
+    rl.add_history_entry(line)?;
+    let Some(path) = rl.helper().and_then(|h| h.history.clone()) else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).ok();
+    }
+    // A history that can't be written is only missing next session
+    rl.append_history(&path).ok();
+    Ok(())
+}
+
+/// Complete plugin commands too.
+pub fn add_commands(rl: &mut LineEditor, names: impl IntoIterator<Item = String>) {
+    if let Some(helper) = rl.helper_mut() {
+        helper.plugin_commands.extend(names);
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("osgeo-library").join("history"))
+}
+
+pub struct ChatHelper<'a> {
+    client: &'a OsgeoClient,
+    plain: bool,
+    /// File lines are added to, when they are kept
+    history: Option<PathBuf>,
+    plugin_commands: Vec<String>,
+    /// The server's slugs, once listed
+    slugs: RefCell<Option<Vec<String>>>,
+    files: FilenameCompleter,
+    hinter: HistoryHinter,
+}
+
+impl ChatHelper<'_> {
+    /// Every document's slug; none when the server can't list them.
+    fn slugs(&self) -> Vec<String> {
+        self.slugs
+            .borrow_mut()
+            .get_or_insert_with(|| {
+                completion::list(self.client)
+                    .map(|documents| documents.into_iter().map(|(slug, _)| slug).collect())
+                    .unwrap_or_default()
+            })
+            .clone()
+    }
+}
+
+impl Completer for ChatHelper<'_> {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        let start = before.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let word = &before[start..];
+        let words: Vec<String> = before[..start].split_whitespace().map(str::to_lowercase).collect();
+        let Some((command, args)) = words.split_first() else {
+            let names = COMMANDS
+                .iter()
+                .map(|(name, _)| name.to_string())
+                .chain(self.plugin_commands.iter().cloned());
+            return Ok((start, matching(word, names)));
+        };
+        let fixed = |choices: &[&str]| choices.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        let choices = match (command.as_str(), args) {
+            ("save", _) if !word.starts_with('-') => return self.files.complete(line, pos, ctx),
+            ("save", []) => fixed(&["--pdf", "--md"]),
+            ("doc" | "sections" | "read", []) => self.slugs(),
+            ("page", []) => fixed(&["find"]).into_iter().chain(self.slugs()).collect(),
+            ("exclude", []) => fixed(&["doc", "clear"]),
+            ("exclude", [doc]) if doc == "doc" => self.slugs(),
+            ("again", [.., flag]) if flag == "-d" || flag == "--document" => {
+                fixed(&["all"]).into_iter().chain(self.slugs()).collect()
+            }
+            ("figures" | "tables" | "equations", []) => fixed(&["all"]),
+            ("set", []) => fixed(&["sources"]),
+            ("set", [setting]) if setting == "sources" => fixed(&["off", "compact", "full"]),
+            ("open", []) => fixed(&["page"]),
+            ("pick", []) => fixed(&["open"]),
+            ("unpin", []) => fixed(&["all"]),
+            ("search", []) => fixed(&["--min-score"]),
+            ("show", [_]) => fixed(&["--as-text"]),
+            _ => Vec::new(),
+        };
+        Ok((start, matching(word, choices)))
+    }
+}
+
+/// `choices` starting with `word`, ignoring case.
+fn matching(word: &str, choices: impl IntoIterator<Item = String>) -> Vec<Pair> {
+    let word = word.to_lowercase();
+    choices
+        .into_iter()
+        .filter(|choice| choice.to_lowercase().starts_with(&word))
+        .map(|choice| Pair {
+            display: choice.clone(),
+            replacement: choice,
+        })
+        .collect()
+}
+
+/// Text shown after the cursor, and what Right arrow inserts of it.
+pub struct Hint {
+    display: String,
+    completion: Option<String>,
+}
+
+impl rustyline::hint::Hint for Hint {
+    fn display(&self) -> &str {
+        &self.display
+    }
+
+    fn completion(&self) -> Option<&str> {
+        self.completion.as_deref()
+    }
+}
+
+impl Hinter for ChatHelper<'_> {
+    type Hint = Hint;
+
+    fn hint(&self, line: &str, pos: usize, ctx: &Context<'_>) -> Option<Hint> {
+        if pos < line.len() {
+            return None;
+        }
+        usage_hint(line).or_else(|| {
+            self.hinter.hint(line, pos, ctx).map(|rest| Hint {
+                display: rest.clone(),
+                completion: Some(rest),
+            })
+        })
+    }
+}
+
+/// While a command's name is typed, the rest of it and what it takes: "doc"
+/// hints " <N|slug>", "sec" hints "tions [slug]".
+fn usage_hint(line: &str) -> Option<Hint> {
+    let typed = line.trim_start().to_lowercase();
+    let name = typed.strip_suffix(' ').unwrap_or(&typed);
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
+    let exact = COMMANDS.iter().find(|(command, _)| *command == name);
+    if let Some((_, usage)) = exact {
+        if usage.is_empty() {
+            return None;
+        }
+        let space = if typed.ends_with(' ') { "" } else { " " };
+        return Some(Hint {
+            display: format!("{}{}", space, usage),
+            completion: None,
+        });
+    }
+    if typed.ends_with(' ') {
+        return None;
+    }
+    let mut candidates = COMMANDS.iter().filter(|(command, _)| command.starts_with(name));
+    let (command, usage) = candidates.next()?;
+    if candidates.next().is_some() {
+        return None;
+    }
+    let rest = &command[name.len()..];
+    Some(Hint {
+        display: format!("{} {}", rest, usage).trim_end().to_string(),
+        completion: Some(rest.to_string()),
+    })
+}
+
+impl Highlighter for ChatHelper<'_> {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        if self.plain {
+            Cow::Borrowed(hint)
+        } else {
+            Cow::Owned(hint.dimmed().to_string())
+        }
+    }
+}
+
+impl Validator for ChatHelper<'_> {}
+
+impl rustyline::Helper for ChatHelper<'_> {}
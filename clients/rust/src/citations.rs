@@ -0,0 +1,58 @@
+//! Citation extraction from LLM answers.
+//!
+//! Answers cite their context with numeric tags matching the order of the
+//! returned sources: `[1]`, `[2]`, and sometimes grouped forms like
+//! `[1, 3]` or `[2-4]`. This finds the cited numbers so they can be mapped
+//! back to sources (`ask --citations-out`).
+
+/// 1-based source numbers cited in `answer`, in order of first appearance.
+pub fn cited_numbers(answer: &str) -> Vec<usize> {
+    let mut numbers = Vec::new();
+    let mut rest = answer;
+    while let Some(open) = rest.find('[') {
+        rest = &rest[open + 1..];
+        let Some(close) = rest.find(']') else {
+            break;
+        };
+        if let Some(group) = parse_group(&rest[..close]) {
+            for n in group {
+                if !numbers.contains(&n) {
+                    numbers.push(n);
+                }
+            }
+        }
+        rest = &rest[close + 1..];
+    }
+    numbers
+}
+
+/// Parse the inside of a bracket: "1", "1, 3", "2-4". Anything else
+/// (links, LaTeX, prose in brackets) is not a citation.
+fn parse_group(inner: &str) -> Option<Vec<usize>> {
+    let inner = inner.trim();
+    if inner.is_empty() || inner.len() > 30 {
+        return None;
+    }
+    let mut numbers = Vec::new();
+    for part in inner.split(',') {
+        let part = part.trim();
+        match part.split_once(['-', '–']) {
+            Some((from, to)) => {
+                let from: usize = from.trim().parse().ok()?;
+                let to: usize = to.trim().parse().ok()?;
+                if from == 0 || to < from || to - from > 20 {
+                    return None;
+                }
+                numbers.extend(from..=to);
+            }
+            None => {
+                let n: usize = part.parse().ok()?;
+                if n == 0 {
+                    return None;
+                }
+                numbers.push(n);
+            }
+        }
+    }
+    Some(numbers)
+}
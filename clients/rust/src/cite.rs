@@ -0,0 +1,126 @@
+//! Citation entries for library documents (`cite`, `doc --cite`).
+//!
+//! Built from what the library knows about a document: its title, source
+//! file, extraction date, license, keywords, page count and summary. There
+//! are no authors or publication year in the metadata, so entries are
+//! `@misc` (BibTeX) and `GEN` (RIS) records to complete by hand where a
+//! style needs them. The extraction date is given as the access date.
+
+use crate::types::DocumentDetailResponse;
+
+/// Citation format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Bibtex,
+    Ris,
+}
+
+impl Style {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().as_str() {
+            "bibtex" | "bib" => Ok(Self::Bibtex),
+            "ris" => Ok(Self::Ris),
+            _ => Err(format!("Unknown citation format '{}'. Use: bibtex, ris", value)),
+        }
+    }
+}
+
+/// Citation entry for `doc` in `style`, ending with a blank line so entries
+/// can be concatenated.
+pub fn entry(doc: &DocumentDetailResponse, style: Style) -> String {
+    match style {
+        Style::Bibtex => bibtex(doc),
+        Style::Ris => ris(doc),
+    }
+}
+
+fn bibtex(doc: &DocumentDetailResponse) -> String {
+    let key: String = doc
+        .slug
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | ':') { c } else { '_' })
+        .collect();
+    // Double braces keep the title's capitalization
+    let mut fields = vec![("title", format!("{{{}}}", bibtex_escape(&doc.title)))];
+    if let Some(date) = date(doc) {
+        fields.push(("urldate", date.to_string()));
+    }
+    if let Some(file) = &doc.source_file {
+        fields.push(("file", bibtex_escape(file)));
+    }
+    if doc.total_pages > 0 {
+        fields.push(("pagetotal", doc.total_pages.to_string()));
+    }
+    if let Some(keywords) = doc.keywords.as_ref().filter(|k| !k.is_empty()) {
+        fields.push(("keywords", bibtex_escape(&keywords.join(", "))));
+    }
+    if let Some(license) = &doc.license {
+        fields.push(("note", format!("License: {}", bibtex_escape(license))));
+    }
+    if let Some(summary) = &doc.summary {
+        fields.push(("abstract", bibtex_escape(&one_line(summary))));
+    }
+
+    let mut out = format!("@misc{{{},\n", key);
+    for (name, value) in fields {
+        out.push_str(&format!("  {:<9} = {{{}}},\n", name, value));
+    }
+    out.push_str("}\n\n");
+    out
+}
+
+fn ris(doc: &DocumentDetailResponse) -> String {
+    let mut out = String::from("TY  - GEN\n");
+    out.push_str(&format!("ID  - {}\n", doc.slug));
+    out.push_str(&format!("TI  - {}\n", one_line(&doc.title)));
+    if let Some(date) = date(doc) {
+        // RIS dates are YYYY/MM/DD
+        out.push_str(&format!("Y2  - {}\n", date.replace('-', "/")));
+    }
+    if let Some(file) = &doc.source_file {
+        out.push_str(&format!("L1  - {}\n", file));
+    }
+    for keyword in doc.keywords.iter().flatten() {
+        out.push_str(&format!("KW  - {}\n", one_line(keyword)));
+    }
+    if let Some(license) = &doc.license {
+        out.push_str(&format!("N1  - License: {}\n", one_line(license)));
+    }
+    if let Some(summary) = &doc.summary {
+        out.push_str(&format!("AB  - {}\n", one_line(summary)));
+    }
+    out.push_str("ER  - \n\n");
+    out
+}
+
+/// Extraction date as YYYY-MM-DD (the server sends an ISO timestamp).
+fn date(doc: &DocumentDetailResponse) -> Option<&str> {
+    let date = doc.extraction_date.as_deref()?.get(..10)?;
+    let digits = date.bytes().enumerate().all(|(i, b)| match i {
+        4 | 7 => b == b'-',
+        _ => b.is_ascii_digit(),
+    });
+    digits.then_some(date)
+}
+
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `text` with LaTeX's special characters escaped.
+fn bibtex_escape(text: &str) -> String {
+    let mut out = String::new();
+    for c in one_line(text).chars() {
+        match c {
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '~' => out.push_str("\\textasciitilde{}"),
+            '^' => out.push_str("\\textasciicircum{}"),
+            '\\' => out.push_str("\\textbackslash{}"),
+            c => out.push(c),
+        }
+    }
+    out
+}
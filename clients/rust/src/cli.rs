@@ -0,0 +1,7883 @@
+//! The command-line client: argument parsing, one-shot commands and the
+//! interactive chat. Everything here is private; the binary calls `main`.
+
+use crate::async_client::AsyncOsgeoClient;
+use crate::capabilities::Feature;
+use crate::client::{OsgeoClient, DEFAULT_SERVER_URL};
+use crate::format::*;
+use crate::types::*;
+use crate::{
+    audit, bugreport, builder, cancel, capabilities, chatline, cite, citations, clipboard, columns, completion, config, crash, digest, docdiff, editor, epub, evidence, federation, graphics, grep, hooks, live, lookup, mathtext, notes,
+    paths, picker, plan, plugins, prefetch, preview, project, prompt, quantity, ranking, reader, recent, records, redact, report, resume, shared, split, table,
+    templates, term, textcache, transcript, tty, watch, xref,
+};
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::engine::ArgValueCompleter;
+use colored::*;
+use rustyline::error::ReadlineError;
+use rustyline::{Cmd, KeyCode, KeyEvent, Modifiers};
+use serde::Serialize;
+use std::io::IsTerminal;
+use std::process::Command;
+
+// -----------------------------------------------------------------------------
+// CLI Definition
+// -----------------------------------------------------------------------------
+
+#[derive(Parser)]
+#[command(name = "osgeo-library")]
+#[command(about = "Search and chat with the OSGeo Library")]
+#[command(version)]
+#[command(after_help = "EXAMPLES:
+    osgeo-library                              Start interactive chat
+    osgeo-library docs                         List all documents
+    osgeo-library doc usgs_snyder              Show document details
+    osgeo-library doc usgs_snyder --cite bibtex   BibTeX entry for a document
+    osgeo-library cite --all -f ris -o library.ris   Cite the whole library
+    osgeo-library doc-diff snyder_1987 snyder_1987_v2   Compare two documents
+    osgeo-library search \"mercator projection\" Search all content
+    osgeo-library search \"area\" -t equation    Search only equations
+    osgeo-library search --in labels \"tissot\"  Find figures/tables by label
+    osgeo-library search --has-number \"sea level rise\"  Passages with quantities
+    osgeo-library search \"utm\" --explain-ranking  Show why results ranked as they did
+    osgeo-library search \"habitat\" -t table --show   Search tables, display image
+    osgeo-library ask \"What is SAM?\"           One-shot question
+    osgeo-library grep usgs_snyder \"EPSG:\\d+\"     Regex over a document's text
+    osgeo-library verify \"UTM zones are 6 degrees wide\"  Fact-check a statement
+    osgeo-library acronym SAM                  Expansions of an acronym, with sources
+    osgeo-library export notes usgs_snyder --chapter 3   Chapter as Markdown notes
+    osgeo-library export doc usgs_snyder --epub snyder.epub  Document as an e-book
+    osgeo-library --out-dir ~/exports export doc usgs_snyder   Named usgs_snyder_doc.epub
+    osgeo-library export doc usgs_snyder --resume   Finish an interrupted export
+    osgeo-library audit duplicates             Documents ingested more than once
+    osgeo-library ingest *.pdf --dry-run       What would be uploaded, with sizes
+    osgeo-library chat --split                 Chat with images pinned above
+    osgeo-library config set defaults.server http://myserver:8095   Remember a setting
+    osgeo-library --profile work search \"datum\"   Use the [profiles.work] settings
+
+ELEMENT TYPES (-t):
+    figure, table, equation, chart, diagram")]
+struct Cli {
+    /// Server URL (default: http://127.0.0.1:8095, see config). Given more
+    /// than once, 'search' searches all of them and the first serves the rest
+    #[arg(short, long, env = "OSGEO_SERVER_URL")]
+    server: Vec<String>,
+
+    /// Server with the same library to switch to when the server can't be
+    /// reached; repeat for more, tried in order [default: none, see config]
+    #[arg(long = "mirror", global = true, env = "OSGEO_MIRRORS", value_name = "URL", value_delimiter = ',')]
+    mirrors: Vec<String>,
+
+    /// Apply a named profile from the config file ([profiles.NAME])
+    #[arg(long, global = true, env = "OSGEO_PROFILE", value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Plain output: no colors, images or progress messages
+    /// (automatic when output is redirected or TERM=dumb)
+    #[arg(long, global = true)]
+    plain: bool,
+
+    /// Show equation LaTeX as-is instead of converting it to Unicode
+    #[arg(long, global = true)]
+    no_mathtext: bool,
+
+    /// Report extra detail on stderr (e.g. ranking adjustments)
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// Leave out hints (commands to try next, results left out); warnings
+    /// and hints go to stderr either way, keeping stdout for results
+    #[arg(long, global = true)]
+    no_hints: bool,
+
+    /// Report on stderr how long each step of startup took
+    #[arg(long, global = true)]
+    debug: bool,
+
+    /// Enlarge small images (under 800 px wide) 2x before terminal preview,
+    /// so short equation and table crops stay legible
+    #[arg(long, global = true)]
+    upscale: bool,
+
+    /// How images are drawn: auto, halfblocks, kitty, iterm, chafa, symbols,
+    /// sixels or none [default: auto, see config]
+    #[arg(long, global = true, env = "OSGEO_IMAGE_BACKEND", value_name = "BACKEND", value_parser = config::ImageBackend::parse)]
+    image_backend: Option<config::ImageBackend>,
+
+    /// Directory exports are written to, and relative output paths are
+    /// taken in [default: current directory, see config]
+    #[arg(long, global = true, env = "OSGEO_OUT_DIR", value_name = "DIR")]
+    out_dir: Option<std::path::PathBuf>,
+
+    /// Don't record requests, crashes, recent views or chat history in the
+    /// files shared with other running clients (for scripts)
+    #[arg(long, global = true)]
+    no_shared_state: bool,
+
+    /// Take server URLs, local paths and user names out of exported files,
+    /// transcripts and --json output, for sharing them
+    #[arg(long, global = true)]
+    redact: bool,
+
+    /// Record searches, questions and the sources they cited in this
+    /// project's log, for 'project report'
+    #[arg(long, global = true, env = "OSGEO_PROJECT", value_name = "NAME", value_parser = project::parse_name)]
+    project: Option<String>,
+
+    /// Seconds a request may take, answers to questions included
+    /// [default: 120, 300 for answers, see config]
+    #[arg(long, global = true, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: Option<u64>,
+
+    /// Seconds allowed for connecting to the server [default: 10, see config]
+    #[arg(long, global = true, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    connect_timeout: Option<u64>,
+
+    /// Times a search or download is retried after a dropped connection,
+    /// timeout or 502/503/504, waiting longer each time (0 for none)
+    /// [default: 3, see config]
+    #[arg(long, global = true, value_name = "N")]
+    retries: Option<u32>,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Search documents (text chunks and elements)
+    Search {
+        /// Search query
+        #[arg(required_unless_present = "interactive")]
+        query: Option<String>,
+
+        /// Build the search step by step (query, document, type, pages,
+        /// minimum score), with a count of matching results after each
+        #[arg(short, long, conflicts_with = "json")]
+        interactive: bool,
+
+        /// Maximum number of results [default: 10, see config]
+        #[arg(short = 'n', long)]
+        limit: Option<i32>,
+
+        /// Filter by document slug
+        #[arg(short, long, add = ArgValueCompleter::new(completion::document_slugs))]
+        document: Option<String>,
+
+        /// Show only elements (figures, tables, equations)
+        #[arg(long)]
+        elements_only: bool,
+
+        /// Show only text chunks
+        #[arg(long)]
+        chunks_only: bool,
+
+        /// Filter by element type: figure, table, equation, chart, diagram
+        #[arg(short, long, value_name = "TYPE")]
+        r#type: Option<String>,
+
+        /// Match literally in one field instead of semantic search:
+        /// "labels" finds figures/tables by their label (e.g. "tissot")
+        #[arg(long = "in", value_name = "FIELD", value_parser = ["labels"], conflicts_with = "chunks_only")]
+        search_in: Option<String>,
+
+        /// Keep only text passages stating a number with a unit (mm, °C, km², %)
+        #[arg(long, conflicts_with_all = ["elements_only", "type", "search_in"])]
+        has_number: bool,
+
+        /// Leave out results scoring below this percentage (e.g. 60)
+        #[arg(long, value_name = "PCT", value_parser = ranking::parse_min_score)]
+        min_score: Option<f64>,
+
+        /// Keep only results on these pages, e.g. 45-78 (best with --document)
+        #[arg(long, value_name = "FROM-TO", value_parser = notes::parse_page_range)]
+        pages: Option<(i32, i32)>,
+
+        /// Show why each result ranked where it did: score per retrieval
+        /// method and any ranking boosts
+        #[arg(long)]
+        explain_ranking: bool,
+
+        /// Display images in terminal: --show (first), --show 1, --show 1,3,5
+        #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "1")]
+        show: Option<String>,
+
+        /// Copy result N to the clipboard: the LaTeX of an equation, the
+        /// text of anything else
+        #[arg(long, value_name = "N")]
+        copy: Option<usize>,
+
+        /// With --show, print tables as text tables of their extracted rows
+        /// instead of images
+        #[arg(long, requires = "show")]
+        as_text: bool,
+
+        /// Open images in GUI viewer: --open (first), --open 1, --open 1,3,5
+        /// Requires X11 forwarding for remote access (ssh -X)
+        #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "1")]
+        open: Option<String>,
+
+        /// Keep only the top result; with --show or --open, the top figure,
+        /// table or equation: search "..." --first --open
+        #[arg(long, conflicts_with = "has_number")]
+        first: bool,
+
+        /// Choose the result to show from a list filtered as you type (with
+        /// --open, to open); text passages are printed whole
+        #[arg(long, conflicts_with_all = ["show", "first", "json"])]
+        pick: bool,
+
+        /// Leave out a document (repeatable, or comma-separated)
+        #[arg(long = "exclude-doc", value_name = "SLUG", value_delimiter = ',', add = ArgValueCompleter::new(completion::document_slugs))]
+        exclude_doc: Vec<String>,
+
+        /// Boost a document in the ranking: --boost-doc usgs_snyder=10 (repeatable)
+        #[arg(long, value_name = "SLUG=POINTS", value_parser = ranking::parse_boost)]
+        boost_doc: Vec<(String, f64)>,
+
+        /// Boost an element type ("text" for chunks): --boost-type equation=5 (repeatable)
+        #[arg(long, value_name = "TYPE=POINTS", value_parser = ranking::parse_boost)]
+        boost_type: Vec<(String, f64)>,
+
+        /// Print the results as JSON (see the schema command)
+        #[arg(long, conflicts_with_all = ["explain_ranking", "show", "open"])]
+        json: bool,
+
+        /// Also write the results (id, score, document, page, element type,
+        /// label, snippet) to a CSV file, or JSON for a .json name
+        #[arg(long, value_name = "FILE")]
+        export: Option<std::path::PathBuf>,
+
+        #[command(flatten)]
+        manifest: ManifestArgs,
+    },
+
+    /// Find passages and elements close in meaning to a result or a page,
+    /// in other documents or elsewhere in the same one
+    Similar {
+        /// What to start from: a result's id ("element:12", "chunk:34",
+        /// shown by 'detail' in chat and in --json output) or a page
+        /// ("usgs_snyder:45")
+        source: String,
+
+        /// Maximum number of results [default: 10, see config]
+        #[arg(short = 'n', long)]
+        limit: Option<i32>,
+
+        /// Look only in this document
+        #[arg(short, long, add = ArgValueCompleter::new(completion::document_slugs))]
+        document: Option<String>,
+
+        /// Show only elements (figures, tables, equations)
+        #[arg(long, conflicts_with = "chunks_only")]
+        elements_only: bool,
+
+        /// Show only text chunks
+        #[arg(long)]
+        chunks_only: bool,
+
+        /// Print the results as JSON (see the schema command)
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Find a regex (or literal) pattern in a document's text, with page references
+    Grep {
+        /// Document slug
+        #[arg(add = ArgValueCompleter::new(completion::document_slugs))]
+        slug: String,
+
+        /// Regular expression, e.g. "EPSG:\d+"
+        pattern: String,
+
+        /// Case-insensitive matching
+        #[arg(short, long)]
+        ignore_case: bool,
+
+        /// Treat the pattern as a literal string
+        #[arg(short = 'F', long)]
+        fixed_strings: bool,
+
+        /// Stop after N matches
+        #[arg(short, long, value_name = "N")]
+        max_count: Option<usize>,
+
+        /// Characters of context shown on each side of a match
+        #[arg(short = 'C', long, value_name = "CHARS", default_value_t = 60)]
+        context: usize,
+    },
+
+    /// Keep documents' text on this machine, for an instant grep and
+    /// searching without the server
+    Cache {
+        #[command(subcommand)]
+        what: CacheCommand,
+    },
+
+    /// Find a figure, table or equation by its printed label, without a
+    /// semantic search; small misspellings are forgiven
+    Element {
+        /// Label as printed, e.g. "Table 3.2", "Fig. 12" or "Tissot indicatrix"
+        label: String,
+
+        /// Document slug [default: the configured document]
+        #[arg(short, long, add = ArgValueCompleter::new(completion::document_slugs))]
+        document: Option<String>,
+
+        /// Display the image in the terminal
+        #[arg(short, long)]
+        show: bool,
+
+        /// Open the image in a GUI viewer
+        #[arg(short, long)]
+        open: bool,
+    },
+
+    /// Ask a question and get an LLM-powered answer with citations
+    Ask {
+        /// Your question
+        #[arg(required_unless_present = "edit")]
+        question: Option<String>,
+
+        /// Compose (or refine) the question in $EDITOR before sending
+        #[arg(short, long)]
+        edit: bool,
+
+        /// Expand the question as a topic into a template:
+        /// define, compare, derive, cite (more via config)
+        #[arg(short, long, value_name = "NAME")]
+        template: Option<String>,
+
+        /// Write the cited sources (document, page, snippet) to a JSON file
+        #[arg(long, value_name = "FILE")]
+        citations_out: Option<std::path::PathBuf>,
+
+        /// Maximum context results [default: 8, see config]
+        #[arg(short = 'n', long)]
+        limit: Option<i32>,
+
+        /// Filter by document slug
+        #[arg(short, long, add = ArgValueCompleter::new(completion::document_slugs))]
+        document: Option<String>,
+
+        /// Pick the 1-2 most relevant documents with a quick search first
+        /// and answer from those only
+        #[arg(long, conflicts_with = "document")]
+        auto_scope: bool,
+
+        /// Ask each of the top documents separately (in parallel), then
+        /// combine the answers with attribution
+        #[arg(long, conflicts_with_all = ["document", "auto_scope", "citations_out"])]
+        per_doc_synthesis: bool,
+
+        /// Documents to ask with --per-doc-synthesis
+        #[arg(long, value_name = "N", default_value_t = 3, requires = "per_doc_synthesis",
+              value_parser = clap::value_parser!(u8).range(2..=8))]
+        top_docs: u8,
+
+        /// Leave out a document (repeatable, or comma-separated)
+        #[arg(long = "exclude-doc", value_name = "SLUG", value_delimiter = ',', add = ArgValueCompleter::new(completion::document_slugs))]
+        exclude_doc: Vec<String>,
+
+        /// Max context tokens; the lowest-ranked sources are dropped to fit
+        #[arg(long, value_name = "TOKENS", value_parser = clap::value_parser!(i32).range(1..))]
+        context_budget: Option<i32>,
+
+        /// Your own text (a draft, notes) to answer alongside the library
+        /// sources, so the answer can compare them; - reads stdin
+        #[arg(long, value_name = "FILE")]
+        context_file: Option<std::path::PathBuf>,
+
+        /// Source listing after the answer: off (a count), compact (one
+        /// line each), full (with content snippets) [default: off]
+        #[arg(long, value_name = "VIEW", value_parser = config::SourcesView::parse)]
+        sources: Option<config::SourcesView>,
+
+        /// Print the answer and its sources as JSON (see the schema command)
+        #[arg(long, conflicts_with_all = ["per_doc_synthesis", "sources"])]
+        json: bool,
+
+        /// Print only the answer text, for scripts; --sources lists the
+        /// sources on stderr, --citations-out writes them to a file
+        #[arg(long, conflicts_with_all = ["per_doc_synthesis", "json"])]
+        answer_only: bool,
+    },
+
+    /// Check a statement against the library: supported, contradicted, or not found
+    Verify {
+        /// Statement to fact-check
+        statement: String,
+
+        /// Maximum context results [default: 8, see config]
+        #[arg(short = 'n', long)]
+        limit: Option<i32>,
+
+        /// Filter by document slug
+        #[arg(short, long, add = ArgValueCompleter::new(completion::document_slugs))]
+        document: Option<String>,
+    },
+
+    /// Look up what an acronym stands for, from definitions in the text
+    Acronym {
+        /// Acronym, matched case-sensitively (e.g. SAM, NDVI)
+        acronym: String,
+
+        /// Filter by document slug
+        #[arg(short, long, add = ArgValueCompleter::new(completion::document_slugs))]
+        document: Option<String>,
+
+        /// Sources shown per expansion
+        #[arg(short = 'n', long, default_value_t = 3)]
+        sources: i32,
+    },
+
+    /// Search for each short text copied to the clipboard, printing the top
+    /// hits (Ctrl-C stops)
+    WatchClipboard {
+        /// Filter by document slug
+        #[arg(short, long, add = ArgValueCompleter::new(completion::document_slugs))]
+        document: Option<String>,
+
+        /// Hits printed per copied text
+        #[arg(short = 'n', long, default_value_t = 3)]
+        limit: i32,
+
+        /// Longest copied text looked up, in characters
+        #[arg(long, value_name = "N", default_value_t = 300)]
+        max_chars: usize,
+    },
+
+    /// Interactive chat mode (default when no command given)
+    Chat {
+        /// Split screen: conversation below, latest image/page pinned above
+        #[arg(long)]
+        split: bool,
+
+        /// Source listing after answers: off, compact, full
+        /// [default: compact, see config]
+        #[arg(long, value_name = "VIEW", value_parser = config::SourcesView::parse)]
+        sources: Option<config::SourcesView>,
+
+        /// Keep a Markdown transcript of the session in FILE (questions,
+        /// answers and sources), updated after every answer
+        #[arg(long, value_name = "FILE")]
+        transcript: Option<std::path::PathBuf>,
+    },
+
+    /// Check an export against its manifest (--manifest): that every file
+    /// is unchanged and the signature, if any, is good
+    VerifyManifest {
+        /// The manifest, e.g. results.csv.manifest.json or notes/manifest.json
+        manifest: std::path::PathBuf,
+
+        /// Public key the manifest must be signed with (e.g. id_ed25519.pub)
+        #[arg(long, value_name = "FILE")]
+        key: Option<std::path::PathBuf>,
+    },
+
+    /// Provenance of a project's sources (searches and questions run with
+    /// --project NAME)
+    Project {
+        #[command(subcommand)]
+        what: ProjectCommand,
+    },
+
+    /// Check server health and connectivity
+    Health,
+
+    /// List all documents in the library
+    Docs {
+        #[command(flatten)]
+        args: DocsArgs,
+
+        /// Print the document list as JSON (see the schema command)
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Get detailed info about a specific document
+    Doc {
+        /// Document slug (e.g., 'usgs_snyder', 'torchgeo')
+        #[arg(add = ArgValueCompleter::new(completion::document_slugs))]
+        slug: String,
+
+        /// Print a citation entry instead: bibtex or ris
+        #[arg(long, value_name = "FORMAT", value_parser = cite::Style::parse)]
+        cite: Option<cite::Style>,
+    },
+
+    /// Download a document's original PDF, for reading it whole; an
+    /// interrupted download resumes when run again
+    Download {
+        /// Document slug
+        #[arg(add = ArgValueCompleter::new(completion::document_slugs))]
+        slug: String,
+
+        /// Directory to save it in [default: the export directory, or the
+        /// current one]
+        #[arg(short, long, value_name = "DIR")]
+        output: Option<std::path::PathBuf>,
+    },
+
+    /// Citation entries (BibTeX or RIS) for documents, from their metadata
+    Cite {
+        /// Document slugs
+        #[arg(required_unless_present = "all", add = ArgValueCompleter::new(completion::document_slugs))]
+        slugs: Vec<String>,
+
+        /// Cite every document in the library
+        #[arg(long, conflicts_with = "slugs")]
+        all: bool,
+
+        /// bibtex or ris
+        #[arg(short, long, value_name = "FORMAT", default_value = "bibtex", value_parser = cite::Style::parse)]
+        format: cite::Style,
+
+        /// Write the entries to a file instead of printing them
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<std::path::PathBuf>,
+    },
+
+    /// Compare two documents' metadata side by side
+    DocDiff {
+        /// First document slug
+        #[arg(add = ArgValueCompleter::new(completion::document_slugs))]
+        slug1: String,
+
+        /// Second document slug
+        #[arg(add = ArgValueCompleter::new(completion::document_slugs))]
+        slug2: String,
+    },
+
+    /// Read a document page by page, turning pages with single keys (n, p,
+    /// g, q) and fetching the next ones ahead
+    Read {
+        /// Document slug
+        #[arg(add = ArgValueCompleter::new(completion::document_slugs))]
+        slug: String,
+
+        /// Page to start on
+        #[arg(short, long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(i32).range(1..))]
+        start: i32,
+    },
+
+    /// List recently viewed documents, pages and elements, or reopen one
+    Recent {
+        /// Reopen the Nth most recent (e.g. 3)
+        n: Option<usize>,
+    },
+
+    /// Export library content to files
+    Export {
+        #[command(subcommand)]
+        what: ExportCommand,
+    },
+
+    /// Same as 'export elements'
+    ExportElements {
+        #[command(flatten)]
+        args: ElementsArgs,
+    },
+
+    /// Same as 'export pages'
+    Pages {
+        #[command(flatten)]
+        args: PagesArgs,
+    },
+
+    /// Print the JSON Schema of the --json output of search, ask or docs
+    Schema {
+        /// Command whose output to describe [default: all, keyed by command]
+        #[arg(value_parser = ["search", "ask", "docs"])]
+        command: Option<String>,
+    },
+
+    /// Print a shell completion script, e.g. for ~/.bashrc:
+    /// source <(osgeo-library completions bash)
+    Completions {
+        /// bash, zsh, fish, elvish or powershell
+        shell: clap_complete::Shell,
+
+        /// A script that never runs the client while completing (document
+        /// slugs aren't completed then)
+        #[arg(long = "static")]
+        static_only: bool,
+    },
+
+    /// Show or change settings in the config file
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+
+    /// Check the library for problems
+    Audit {
+        #[command(subcommand)]
+        what: AuditCommand,
+    },
+
+    /// Print what a bug report needs (versions, server health, config,
+    /// recent requests, terminal) as Markdown to paste into an issue
+    ReportBug {
+        /// Recent requests to include (logged with --verbose)
+        #[arg(short = 'n', long, default_value_t = 20)]
+        requests: usize,
+
+        /// Write the report to a file instead of printing it, so the
+        /// terminal can still be probed
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+
+    /// Upload PDFs to be extracted and added to the library (the server
+    /// must allow uploads, see allow_ingest in its config)
+    Ingest {
+        /// PDF files to upload
+        #[arg(required = true)]
+        files: Vec<std::path::PathBuf>,
+
+        /// Upload even if a document with the same file name or SHA-256 is
+        /// already in the library (one with the same file name is replaced)
+        #[arg(long)]
+        force: bool,
+
+        /// List what would be uploaded, skipped or replaced, with sizes,
+        /// without uploading anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuditCommand {
+    /// Find documents that look like copies of each other: same source
+    /// file, same text or nearly the same title
+    Duplicates {
+        /// Offer to delete copies, asking which and confirming (the server
+        /// must allow deleting, see allow_delete in its config)
+        #[arg(long)]
+        delete: bool,
+
+        /// With --delete: ask which copies to delete as usual, then list
+        /// them instead of deleting them
+        #[arg(long, requires = "delete")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// List the settings in effect, with --profile applied
+    List,
+
+    /// Print one setting (e.g. 'defaults.server')
+    Get {
+        /// Setting name, as listed by 'config list'
+        key: String,
+    },
+
+    /// Change a setting in the config file (in the profile with --profile)
+    Set {
+        /// Setting name (e.g. 'defaults.search_limit')
+        key: String,
+
+        /// New value; numbers and true/false are stored as such
+        value: String,
+    },
+}
+
+impl Commands {
+    /// Whether the command prints JSON or a bare answer for scripts, which
+    /// progress lines would corrupt.
+    fn prints_for_scripts(&self) -> bool {
+        matches!(
+            self,
+            Commands::Search { json: true, .. }
+                | Commands::Similar { json: true, .. }
+                | Commands::Ask { json: true, .. }
+                | Commands::Ask { answer_only: true, .. }
+                | Commands::Docs { json: true, .. }
+                | Commands::Schema { .. }
+        )
+    }
+}
+
+#[derive(Subcommand)]
+enum ProjectCommand {
+    /// Compile everything recorded under a project into a Markdown report:
+    /// the documents and pages cited, then each search and question
+    Report {
+        /// Project name, as given to --project
+        #[arg(value_parser = project::parse_name)]
+        name: String,
+
+        /// Include each answer in full, not only what it cited
+        #[arg(long)]
+        answers: bool,
+
+        /// Write the report to this file instead of printing it
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<std::path::PathBuf>,
+    },
+
+    /// List projects with their number of entries and latest activity
+    List,
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Fetch the text of documents (not their images), a batch of chunks at
+    /// a time; an interrupted fetch carries on where it stopped
+    Text {
+        /// Document slugs
+        #[arg(required = true, add = ArgValueCompleter::new(completion::document_slugs))]
+        slugs: Vec<String>,
+
+        /// Milliseconds to wait between requests
+        #[arg(long, value_name = "MS", default_value_t = textcache::DEFAULT_DELAY_MS)]
+        delay: u64,
+
+        /// Fetch documents already cached again
+        #[arg(long)]
+        refresh: bool,
+    },
+
+    /// Find a regex (or literal) pattern in every cached document, without
+    /// the server
+    Grep {
+        /// Regular expression, e.g. "EPSG:\d+"
+        pattern: String,
+
+        /// Case-insensitive matching
+        #[arg(short, long)]
+        ignore_case: bool,
+
+        /// Treat the pattern as a literal string
+        #[arg(short = 'F', long)]
+        fixed_strings: bool,
+
+        /// Stop after N matches
+        #[arg(short, long, value_name = "N")]
+        max_count: Option<usize>,
+
+        /// Characters of context shown on each side of a match
+        #[arg(short = 'C', long, value_name = "CHARS", default_value_t = 60)]
+        context: usize,
+    },
+
+    /// List cached documents with their size and when they were fetched
+    List,
+
+    /// Show how much is cached from each server, and how well it compressed
+    Stats,
+
+    /// Forget the cached text of documents
+    Remove {
+        /// Document slugs
+        #[arg(required = true)]
+        slugs: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ExportCommand {
+    /// Write a chapter as Markdown notes: page summaries with its figures,
+    /// tables and equations (images downloaded alongside)
+    Notes {
+        /// Document slug
+        #[arg(add = ArgValueCompleter::new(completion::document_slugs))]
+        slug: String,
+
+        /// Chapter number, detected from "Chapter N" headings
+        #[arg(short, long, required_unless_present = "pages", conflicts_with = "pages")]
+        chapter: Option<i32>,
+
+        /// Page range instead of a chapter, e.g. 45-78
+        #[arg(short, long, value_name = "FROM-TO", value_parser = notes::parse_page_range)]
+        pages: Option<(i32, i32)>,
+
+        /// Output directory [default: named from the [paths] name template,
+        /// e.g. usgs_snyder_45-78_notes]
+        #[arg(short, long, value_name = "DIR")]
+        output: Option<std::path::PathBuf>,
+
+        /// Include every equation, not only numbered (labelled) ones
+        #[arg(long)]
+        all_equations: bool,
+
+        /// List the images that would be downloaded and the files written,
+        /// with sizes, without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Continue an interrupted export: keep images already downloaded
+        /// into the output directory (checked by size and SHA-256)
+        #[arg(long)]
+        resume: bool,
+
+        /// Check images kept by --resume against the server's checksums,
+        /// replacing any that differ, and read back each one written
+        #[arg(long)]
+        verify: bool,
+
+        #[command(flatten)]
+        manifest: ManifestArgs,
+    },
+
+    /// Write a whole document (page summaries with figures, tables and
+    /// equations) as an e-book for offline reading
+    Doc {
+        /// Document slug
+        #[arg(add = ArgValueCompleter::new(completion::document_slugs))]
+        slug: String,
+
+        /// Output EPUB file [default: named from the [paths] name template,
+        /// e.g. usgs_snyder_doc.epub]
+        #[arg(long, value_name = "FILE")]
+        epub: Option<std::path::PathBuf>,
+
+        /// Leave out images for a smaller file (captions are kept)
+        #[arg(long)]
+        no_images: bool,
+
+        /// List the images that would be downloaded, with sizes, and the
+        /// file that would be written, without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Continue an interrupted export: keep images already downloaded
+        /// (checked by size and SHA-256)
+        #[arg(long)]
+        resume: bool,
+
+        /// Check images kept by --resume against the server's checksums,
+        /// replacing any that differ, and read back each one written
+        #[arg(long)]
+        verify: bool,
+
+        #[command(flatten)]
+        manifest: ManifestArgs,
+    },
+
+    /// Download the images of a document's figures, tables and equations,
+    /// named by label, with a manifest.json of captions and pages
+    Elements {
+        #[command(flatten)]
+        args: ElementsArgs,
+    },
+
+    /// Download a range of page images as one PDF, or as numbered image
+    /// files in a directory, e.g. a chapter to annotate offline
+    Pages {
+        #[command(flatten)]
+        args: PagesArgs,
+    },
+}
+
+/// Options of `export elements` (also `export-elements`).
+#[derive(clap::Args, Debug)]
+struct ElementsArgs {
+    /// Document slug
+    #[arg(add = ArgValueCompleter::new(completion::document_slugs))]
+    slug: String,
+
+    /// Only elements of this type: figure, table, equation, chart, diagram
+    #[arg(short, long, value_name = "TYPE")]
+    r#type: Option<String>,
+
+    /// Output directory [default: named from the [paths] name template,
+    /// e.g. usgs_snyder_figure]
+    #[arg(short, long, value_name = "DIR")]
+    output: Option<std::path::PathBuf>,
+
+    /// Images downloaded at once
+    #[arg(short, long, value_name = "N", default_value_t = 4,
+          value_parser = clap::value_parser!(u8).range(1..=16))]
+    jobs: u8,
+
+    /// List the images that would be downloaded, with sizes, without
+    /// writing anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Continue an interrupted export: keep images already downloaded
+    /// into the output directory (checked by size and SHA-256)
+    #[arg(long)]
+    resume: bool,
+
+    /// Check images kept by --resume against the server's checksums,
+    /// replacing any that differ, and read back each one written
+    #[arg(long)]
+    verify: bool,
+
+    #[command(flatten)]
+    manifest: ManifestArgs,
+}
+
+/// Options of `export pages` (also `pages`).
+#[derive(clap::Args, Debug)]
+struct PagesArgs {
+    /// Document slug
+    #[arg(add = ArgValueCompleter::new(completion::document_slugs))]
+    slug: String,
+
+    /// Pages to export, e.g. 10-25
+    #[arg(short, long, value_name = "FROM-TO", value_parser = notes::parse_page_range)]
+    range: (i32, i32),
+
+    /// A .pdf file, or a directory for one image per page [default:
+    /// named from the [paths] name template, e.g. usgs_snyder_10-25_pages.pdf]
+    #[arg(short, long, alias = "out", value_name = "PATH")]
+    output: Option<std::path::PathBuf>,
+
+    /// List the pages that would be downloaded and the files written,
+    /// without writing anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Continue an interrupted export: keep pages already downloaded
+    /// (checked by size and SHA-256)
+    #[arg(long)]
+    resume: bool,
+
+    #[command(flatten)]
+    manifest: ManifestArgs,
+}
+
+/// `--manifest` and `--sign`, shared by the exports.
+#[derive(clap::Args, Debug, Default)]
+struct ManifestArgs {
+    /// Also write a manifest for archiving: query or pages, time, server
+    /// and version, result ids and the SHA-256 of each file written (see
+    /// verify-manifest)
+    #[arg(long)]
+    manifest: bool,
+
+    /// Sign the manifest with this SSH private key (ssh-keygen -Y sign)
+    #[arg(long, value_name = "KEY", requires = "manifest")]
+    sign: Option<std::path::PathBuf>,
+}
+
+impl ManifestArgs {
+    fn request(self) -> Option<evidence::Request> {
+        self.manifest.then_some(evidence::Request { sign: self.sign })
+    }
+}
+
+/// Pagination, sort and filter options shared by `docs` on the command line
+/// and in chat.
+#[derive(clap::Args, Debug, Default)]
+struct DocsArgs {
+    /// Page number (1-indexed)
+    #[arg(short, long)]
+    page: Option<i32>,
+
+    /// Results per page [default: 20 (5 in chat), see config]
+    #[arg(short = 'n', long)]
+    limit: Option<i32>,
+
+    /// Sort by: title, date_added (or 'recent'), page_count (or 'pages')
+    #[arg(short, long)]
+    sort: Option<String>,
+
+    /// Only documents with a keyword containing this (e.g. projection)
+    #[arg(short, long)]
+    keyword: Option<String>,
+
+    /// Only documents whose license contains this (e.g. CC-BY)
+    #[arg(short, long)]
+    license: Option<String>,
+
+    /// Only documents whose title or slug contains this
+    #[arg(long, value_name = "TEXT")]
+    search: Option<String>,
+}
+
+impl DocsArgs {
+    fn filter(&self) -> DocumentFilter {
+        DocumentFilter {
+            keyword: self.keyword.clone(),
+            license: self.license.clone(),
+            search: self.search.clone(),
+        }
+    }
+}
+
+/// `again` in chat: re-send the previous question, overriding some of its
+/// request parameters.
+#[derive(Parser)]
+#[command(name = "again", disable_version_flag = true)]
+struct AgainCommand {
+    /// Maximum context results
+    #[arg(short = 'n', long)]
+    limit: Option<i32>,
+
+    /// Document slug to scope to ('all' for the whole library)
+    #[arg(short, long, add = ArgValueCompleter::new(completion::document_slugs))]
+    document: Option<String>,
+
+    /// LLM model to answer with (server default otherwise)
+    #[arg(short, long)]
+    model: Option<String>,
+}
+
+impl AgainCommand {
+    fn apply(self, mut req: ChatRequest) -> ChatRequest {
+        if let Some(limit) = self.limit {
+            req.limit = limit;
+        }
+        if let Some(doc) = self.document {
+            req.document_slug = (!doc.eq_ignore_ascii_case("all")).then_some(doc);
+        }
+        if let Some(model) = self.model {
+            req.model = Some(model);
+        }
+        req
+    }
+}
+
+/// `docs` as typed inside chat, parsed with the same options as the CLI.
+#[derive(Parser)]
+#[command(name = "docs", disable_version_flag = true)]
+struct ChatDocsCommand {
+    #[command(flatten)]
+    args: DocsArgs,
+}
+
+// -----------------------------------------------------------------------------
+// Display Helpers
+// -----------------------------------------------------------------------------
+
+/// Calculate chafa size string based on actual image dimensions and terminal size.
+/// Scales to fit within terminal while preserving aspect ratio.
+fn chafa_size(result: &SearchResult) -> String {
+    // Get actual terminal size, with sensible defaults
+    let (term_width, term_height) = terminal_size::terminal_size()
+        .map(|(w, h)| (w.0 as i32, h.0 as i32))
+        .unwrap_or((120, 40));
+    
+    // Leave some margin for borders and text
+    let max_width = (term_width - 4).max(40);
+    let max_height = (term_height - 8).max(20);  // Leave room for header/footer
+    
+    match (result.image_width, result.image_height) {
+        (Some(w), Some(h)) if w > 0 && h > 0 => {
+            // Terminal chars are roughly 2:1 aspect ratio (taller than wide)
+            // So we need to adjust: effective_height = height / 2
+            let aspect = w as f64 / h as f64;
+            
+            // Scale to fit max width first
+            let mut cols = max_width;
+            let mut rows = (cols as f64 / aspect / 2.0).ceil() as i32;
+            
+            // If too tall, scale down by height
+            if rows > max_height {
+                rows = max_height;
+                cols = (rows as f64 * aspect * 2.0).ceil() as i32;
+            }
+            
+            // Minimum sizes - tables need more height for readability
+            cols = cols.max(20);
+            let min_rows = match result.element_type.as_deref() {
+                Some("table") => 15,    // Tables need more vertical space
+                Some("equation") => 6,  // Equations are typically short
+                _ => 8,                 // Default minimum
+            };
+            rows = rows.max(min_rows);
+            
+            format!("{}x{}", cols, rows)
+        }
+        _ => {
+            // Fallback based on element type
+            let fallback_width = max_width.min(100);
+            match result.element_type.as_deref() {
+                Some("equation") => format!("{}x12", fallback_width),
+                Some("table") => format!("{}x{}", fallback_width, max_height.min(40)),
+                _ => format!("{}x{}", fallback_width.min(80), max_height.min(35)),
+            }
+        }
+    }
+}
+
+/// Report the size of a page image and how it arrived, in verbose mode.
+fn log_page(page: &PageResponse, width: Option<u32>, prefetched: bool) {
+    let asked = width.map(|w| format!(" (asked for {} px wide)", w)).unwrap_or_default();
+    let transfer = if prefetched {
+        "prefetched"
+    } else if page.image_base64.is_empty() {
+        "as raw bytes"
+    } else {
+        "in base64"
+    };
+    term::verbose_line(&format!(
+        "Page image: {}x{}{}, {} KB {}",
+        page.image_width,
+        page.image_height,
+        asked,
+        page.image.len() / 1024,
+        transfer
+    ));
+}
+
+fn display_page_image(bytes: &[u8], size: &str, title: &str) -> Result<()> {
+    // No inline images when output is plain (redirected, dumb terminal)
+    // or images are turned off
+    if !preview::shows_images() {
+        return Ok(());
+    }
+
+    // Write to a temp file of this process, so clients showing pages at the
+    // same time don't draw each other's
+    let temp_path = std::env::temp_dir().join(format!("osgeo-library-page-{}.png", std::process::id()));
+    std::fs::write(&temp_path, preview::prepare(bytes.to_vec())).context("Failed to write temp file")?;
+
+    let shown = display_image_file(&temp_path, size, title);
+    std::fs::remove_file(&temp_path).ok();
+    shown
+}
+
+fn open_page_image(bytes: &[u8]) -> Result<()> {
+    // Check for graphical display availability
+    if !term::env().gui {
+        anyhow::bail!(
+            "open requires a graphical display.\n\
+             Use 'page <slug> <N>' for terminal preview instead."
+        );
+    }
+
+    // Write to temp file with unique name
+    let temp_path = std::env::temp_dir().join(format!(
+        "osgeo-library-page-{}.png",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis()
+    ));
+    std::fs::write(&temp_path, bytes).context("Failed to write temp file")?;
+
+    // Open with platform-appropriate command
+    #[cfg(target_os = "linux")]
+    {
+        Command::new("xdg-open")
+            .arg(&temp_path)
+            .spawn()
+            .context("Failed to run 'xdg-open'")?;
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        Command::new("open")
+            .arg(&temp_path)
+            .spawn()
+            .context("Failed to run 'open'")?;
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        Command::new("cmd")
+            .args(["/C", "start", "", temp_path.to_str().unwrap()])
+            .spawn()
+            .context("Failed to open image")?;
+    }
+
+    Ok(())
+}
+
+fn fetch_and_display_image(client: &OsgeoClient, url: &str, size: &str, title: &str) -> Result<()> {
+    // No inline images when output is plain or images are turned off;
+    // print the URL instead
+    if !preview::shows_images() {
+        println!("Image: {}", url);
+        return Ok(());
+    }
+
+    let bytes = client.fetch_image(url)?;
+
+    // Write to a temp file of this process, removed once drawn
+    let temp_path = std::env::temp_dir().join(format!("osgeo-library-image-{}.png", std::process::id()));
+    let frames = preview::frame_count(&bytes);
+    std::fs::write(&temp_path, preview::prepare(bytes)).context("Failed to write temp file")?;
+
+    let shown = display_image_file(&temp_path, size, title);
+    std::fs::remove_file(&temp_path).ok();
+    shown?;
+    if frames > 1 {
+        println!("(Animated: first of {} frames shown; open it in a viewer to play)\n", frames);
+    }
+    Ok(())
+}
+
+/// Fetch image from server and open in GUI viewer.
+/// Uses xdg-open (Linux), open (macOS), or start (Windows).
+/// Requires a graphical display; use --show for terminal preview over SSH.
+fn fetch_and_open_image(client: &OsgeoClient, url: &str) -> Result<()> {
+    // Check for graphical display availability
+    if !term::env().gui {
+        let reason = if term::env().ssh {
+            "You appear to be connected via SSH without display forwarding."
+        } else {
+            "You appear to be running on a remote server without X11/Wayland forwarding."
+        };
+        anyhow::bail!(
+            "--open requires a graphical display.\n\
+             {}\n\n\
+             Options:\n\
+               1. Use --show for terminal preview instead\n\
+               2. Connect with X11 forwarding: ssh -X user@server\n\
+               3. Run the CLI on your local machine with SSH tunneling:\n\
+                  ssh -L 8095:localhost:8095 user@server\n\
+                  osgeo-library --server http://localhost:8095 search \"...\" --open",
+            reason
+        );
+    }
+
+    let bytes = client.fetch_image(url)?;
+
+    // Write to temp file with unique name; the real extension lets the
+    // viewer play animated GIFs
+    let temp_path = std::env::temp_dir().join(format!(
+        "osgeo-library-{}.{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis(),
+        preview::extension(&bytes)
+    ));
+    std::fs::write(&temp_path, &bytes).context("Failed to write temp file")?;
+
+    // Open with platform-appropriate command
+    #[cfg(target_os = "macos")]
+    {
+        let status = Command::new("open")
+            .arg(&temp_path)
+            .status()
+            .context("Failed to run 'open'")?;
+
+        if !status.success() {
+            anyhow::bail!("open failed with status: {}", status);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let status = Command::new("xdg-open")
+            .arg(&temp_path)
+            .status()
+            .context("Failed to run 'xdg-open'. Is xdg-utils installed?")?;
+
+        if !status.success() {
+            anyhow::bail!("xdg-open failed with status: {}", status);
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let status = Command::new("cmd")
+            .args(["/c", "start", "", temp_path.to_str().unwrap()])
+            .status()
+            .context("Failed to run 'start'")?;
+
+        if !status.success() {
+            anyhow::bail!("start failed with status: {}", status);
+        }
+    }
+
+    println!("Opened: {}", temp_path.display());
+    Ok(())
+}
+
+/// Draw an image file in the terminal, inline or into the split-pane viewer
+/// when `chat --split` is active. Drawn by the client unless the image
+/// backend is a chafa one, or the image can't be decoded here.
+fn display_image_file(path: &std::path::Path, size: &str, title: &str) -> Result<()> {
+    let backend = graphics::resolve(preview::backend());
+    if !backend.uses_chafa() {
+        // Graphics escapes can't be pinned in the viewer pane, so it gets half blocks
+        let pane = split::viewer_size();
+        let (backend, size) = match &pane {
+            Some(pane_size) => (config::ImageBackend::Halfblocks, pane_size.as_str()),
+            None => (backend, size),
+        };
+        let (cols, rows) = graphics::parse_size(size).unwrap_or((80, 24));
+        let rendered = std::fs::read(path)
+            .context("Failed to read image")
+            .and_then(|bytes| graphics::render(&bytes, backend, cols, rows));
+        match rendered {
+            Ok(text) if pane.is_some() => {
+                split::draw_viewer(&text, title)?;
+                println!("({} shown in viewer)", title);
+                return Ok(());
+            }
+            Ok(text) => {
+                print!("{}", text);
+                println!();
+                return Ok(());
+            }
+            Err(e) => term::verbose_line(&format!("Trying chafa: {:#}", e)),
+        }
+    }
+    display_with_chafa(path, size, title)
+}
+
+/// Render an image file with chafa, either inline or into the split-pane
+/// viewer.
+fn display_with_chafa(path: &std::path::Path, size: &str, title: &str) -> Result<()> {
+    let chafa_available = Command::new("which")
+        .arg("chafa")
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+
+    if chafa_available {
+        let path_str = path.to_str().unwrap();
+
+        // In split mode, capture the output and pin it in the viewer pane
+        if let Some(pane_size) = split::viewer_size() {
+            let output = Command::new("chafa")
+                .args([
+                    "--format", "symbols",
+                    "--size", &pane_size,
+                    "--symbols", "all",
+                    "-w", "9",
+                    "-c", "full",
+                    path_str,
+                ])
+                .output();
+
+            if let Ok(o) = output {
+                if o.status.success() {
+                    split::draw_viewer(&String::from_utf8_lossy(&o.stdout), title)?;
+                    println!("({} shown in viewer)", title);
+                    return Ok(());
+                }
+            }
+        } else {
+            let mut chafa = Command::new("chafa");
+            if let Some(format) = preview::backend().chafa_format() {
+                chafa.args(["--format", format]);
+            }
+            let status = chafa
+                .args([
+                    "--size", size,
+                    "--symbols", "all",     // Use all symbols for better detail
+                    "-w", "9",              // Work hardest for best quality
+                    "-c", "full",           // Full 24-bit color
+                    path_str,
+                ])
+                .status();
+
+            if let Ok(s) = status {
+                if s.success() {
+                    println!();
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    // Fallback: no terminal preview available
+    println!("(Install chafa for terminal preview: sudo apt install chafa)");
+    Ok(())
+}
+
+/// Re-order search results by the ranking boosts; with --verbose, report
+/// each adjustment and how it moved the result.
+fn apply_ranking(results: &mut Vec<SearchResult>, query: &str, ranking: &ranking::Ranking) {
+    if ranking.is_empty() {
+        return;
+    }
+
+    let mut scored: Vec<(f64, usize, Vec<ranking::Boost>, SearchResult)> = results
+        .drain(..)
+        .enumerate()
+        .map(|(i, r)| {
+            let kind = if r.source_type == "element" {
+                r.element_type.as_deref().unwrap_or("element")
+            } else {
+                "text"
+            };
+            let boosts = ranking.boosts(query, &r.document_slug, kind);
+            let score = r.score_pct + boosts.iter().map(|b| b.points).sum::<f64>();
+            (score, i, boosts, r)
+        })
+        .collect();
+    // Stable: equal scores keep the server's order
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    for (new_pos, (score, old_pos, boosts, r)) in scored.into_iter().enumerate() {
+        if !boosts.is_empty() {
+            let reasons: Vec<String> = boosts
+                .iter()
+                .map(|b| format!("{:+} {}", b.points, b.reason))
+                .collect();
+            term::verbose_line(&format!(
+                "ranking: [{}] -> [{}] {:.0}% {} = {:.0}%",
+                old_pos + 1,
+                new_pos + 1,
+                r.score_pct,
+                reasons.join(" "),
+                score
+            ));
+        }
+        results.push(r);
+    }
+}
+
+/// Why a result ranked where it did: the score from each retrieval method
+/// (the best one decides) and the client-side boosts, when `ranking` was
+/// applied to the listing.
+fn explain_ranking(result: &SearchResult, query: &str, ranking: Option<&ranking::Ranking>) -> Vec<String> {
+    let mut lines = Vec::new();
+    if result.score_breakdown.is_empty() {
+        lines.push(format!("Score {:.0}% (no breakdown from the server)", result.score_pct));
+    } else {
+        let mut methods: Vec<(&String, &f64)> = result.score_breakdown.iter().collect();
+        methods.sort_by(|a, b| b.1.total_cmp(a.1).then(a.0.cmp(b.0)));
+        let parts: Vec<String> = methods
+            .iter()
+            .map(|(method, pct)| format!("{} {:.0}%", method, pct))
+            .collect();
+        lines.push(format!(
+            "Score {:.0}% from best of: {}",
+            result.score_pct,
+            parts.join(", ")
+        ));
+    }
+
+    if let Some(ranking) = ranking {
+        let kind = if result.source_type == "element" {
+            result.element_type.as_deref().unwrap_or("element")
+        } else {
+            "text"
+        };
+        let boosts = ranking.boosts(query, &result.document_slug, kind);
+        if !boosts.is_empty() {
+            let total: f64 = boosts.iter().map(|b| b.points).sum();
+            let parts: Vec<String> = boosts
+                .iter()
+                .map(|b| format!("{:+} {}", b.points, b.reason))
+                .collect();
+            lines.push(format!(
+                "Boosts {} = {:.0}%",
+                parts.join(", "),
+                result.score_pct + total
+            ));
+        }
+    }
+    lines
+}
+
+/// Viewing hint for listings; 'open' is only offered with a graphical display.
+fn view_hint() -> &'static str {
+    if term::env().gui {
+        "'N', 'show N' or 'open N'"
+    } else {
+        "'N' or 'show N'"
+    }
+}
+
+/// One-line summary of a chat request, e.g. `"what is X" (limit 15, doc: snyder)`.
+fn describe_chat_request(req: &ChatRequest) -> String {
+    let mut params = vec![format!("limit {}", req.limit)];
+    params.push(format!("doc: {}", req.document_slug.as_deref().unwrap_or("all")));
+    if let Some(ref model) = req.model {
+        params.push(format!("model: {}", model));
+    }
+    format!("\"{}\" ({})", req.question, params.join(", "))
+}
+
+/// Map user-facing sort names to the API's sort_by values.
+fn normalize_sort(sort: &str) -> Result<&'static str> {
+    match sort.to_lowercase().as_str() {
+        "title" | "name" => Ok("title"),
+        "date_added" | "recent" | "date" | "newest" => Ok("date_added"),
+        "page_count" | "pages" | "size" => Ok("page_count"),
+        other => anyhow::bail!(
+            "Unknown sort '{}'. Use: title, date_added (recent), page_count (pages)",
+            other
+        ),
+    }
+}
+
+/// Document list pagination state, shared by the `docs` command and the chat
+/// `docs`/`next`/`prev` commands so both page and sort the same way.
+struct DocsPager {
+    /// Current page (0 = nothing listed yet)
+    page: i32,
+    page_size: i32,
+    sort: &'static str,
+    /// Documents listed, as the last `docs` asked
+    filter: DocumentFilter,
+    total_pages: i32,
+}
+
+impl DocsPager {
+    fn new(page_size: i32) -> Self {
+        Self {
+            page: 0,
+            page_size,
+            sort: "title",
+            filter: DocumentFilter::default(),
+            total_pages: 0,
+        }
+    }
+
+    /// Apply explicit size/sort options; they stick for later next/prev.
+    /// Filters are those given this time, none when none are.
+    fn apply(&mut self, args: &DocsArgs) -> Result<()> {
+        self.filter = args.filter();
+        if let Some(n) = args.limit {
+            if n < 1 {
+                anyhow::bail!("Page size must be at least 1");
+            }
+            self.page_size = n.min(100);
+        }
+        if let Some(ref sort) = args.sort {
+            self.sort = normalize_sort(sort)?;
+        }
+        Ok(())
+    }
+
+    fn fetch(&mut self, client: &OsgeoClient, page: i32) -> Result<DocumentListResponse> {
+        let response = if self.filter.is_empty() || capabilities::of(client).supports(Feature::DocsFilters) {
+            client.list_documents_matching(page.max(1), self.page_size, self.sort, &self.filter)?
+        } else {
+            self.fetch_filtered_here(client, page.max(1))?
+        };
+        self.page = response.page;
+        self.total_pages = response.total_pages;
+        Ok(response)
+    }
+
+    /// A page of the filtered list from a server that can't filter it: the
+    /// whole list read and filtered here, keeping only the matches on `page`.
+    fn fetch_filtered_here(&self, client: &OsgeoClient, page: i32) -> Result<DocumentListResponse> {
+        let wanted = ((page - 1) * self.page_size) as usize..(page * self.page_size) as usize;
+        let mut documents = Vec::new();
+        let mut matched = 0;
+        let mut from = 1;
+        loop {
+            let response = client.list_documents(from, 100, self.sort)?;
+            for document in response.documents.into_iter().filter(|d| self.filter.matches(d)) {
+                if wanted.contains(&matched) {
+                    documents.push(document);
+                }
+                matched += 1;
+            }
+            if from >= response.total_pages {
+                break;
+            }
+            from += 1;
+        }
+        let total_documents = matched as i32;
+        let total_pages = (total_documents + self.page_size - 1) / self.page_size;
+        Ok(DocumentListResponse {
+            documents,
+            page,
+            page_size: self.page_size,
+            total_pages,
+            total_documents,
+        })
+    }
+
+    /// Validate a page number typed by the user against the known page count.
+    fn goto(&self, page: i32) -> Result<i32> {
+        if page < 1 || (self.total_pages > 0 && page > self.total_pages) {
+            anyhow::bail!("Page {} out of range (1-{})", page, self.total_pages.max(1));
+        }
+        Ok(page)
+    }
+
+    /// Compact navigation footer, e.g. "page 7/12 — n/p/g<N>".
+    fn footer(&self) -> String {
+        format!("page {}/{} — n/p/g<N>", self.page, self.total_pages)
+    }
+
+    fn next_page(&self) -> Option<i32> {
+        (self.page < self.total_pages).then_some(self.page + 1)
+    }
+
+    fn prev_page(&self) -> Option<i32> {
+        (self.page > 1).then_some(self.page - 1)
+    }
+}
+
+/// Complete a multi-line chat entry. A trailing backslash continues on the
+/// next line; `:paste` collects lines until one containing only `:end` (or
+/// Ctrl-D). Ctrl-C abandons the entry.
+fn read_multiline(rl: &mut chatline::LineEditor, first: String) -> rustyline::Result<String> {
+    let read = |rl: &mut chatline::LineEditor, prompt: &str| match rl.readline(prompt) {
+        Ok(line) => Ok(Some(line)),
+        Err(ReadlineError::Eof) => Ok(None),
+        Err(e) => Err(e),
+    };
+
+    if first.trim().eq_ignore_ascii_case(":paste") {
+        term::progress("Paste mode: finish with ':end' on its own line");
+        let mut lines = Vec::new();
+        loop {
+            match read(rl, "") {
+                Ok(Some(line)) if line.trim() == ":end" => break,
+                Ok(Some(line)) => lines.push(line),
+                Ok(None) => break,
+                Err(ReadlineError::Interrupted) => return Ok(String::new()),
+                Err(e) => return Err(e),
+            }
+        }
+        return Ok(lines.join("\n"));
+    }
+
+    let mut text = first;
+    while text.trim_end().ends_with('\\') {
+        text.truncate(text.trim_end().len() - 1);
+        text.push('\n');
+        match read(rl, "... ") {
+            Ok(Some(line)) => text.push_str(&line),
+            Ok(None) => break,
+            Err(ReadlineError::Interrupted) => return Ok(String::new()),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(text)
+}
+
+/// The most recent numbered listing in chat, which bare numbers act on:
+/// `3` after `docs` opens document 3, after a search or answer shows result 3.
+#[derive(Clone, Copy)]
+enum Listing {
+    Docs,
+    Results,
+    Recent,
+}
+
+impl Listing {
+    fn command_for(self, n: usize) -> String {
+        match self {
+            Listing::Docs => format!("doc {}", n),
+            Listing::Results => format!("show {}", n),
+            Listing::Recent => format!("recent {}", n),
+        }
+    }
+}
+
+/// What 'recent' in chat did.
+enum Recent {
+    /// Listed the views, which bare numbers now reopen
+    Listed,
+    /// A document or page to reopen with this command
+    Reopen(String),
+    /// Showed an element (for the transcript), or only printed a message
+    Shown(Option<report::Entry>),
+}
+
+/// Page navigation typed in chat: `n`/`next`, `p`/`prev`, or `g<N>` / `g N`.
+enum Nav {
+    Next,
+    Prev,
+    Goto(i32),
+}
+
+fn parse_nav(lower: &str) -> Option<Nav> {
+    match lower {
+        "n" | "next" => Some(Nav::Next),
+        "p" | "prev" => Some(Nav::Prev),
+        _ => lower.strip_prefix('g')?.trim().parse().ok().map(Nav::Goto),
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Commands
+// -----------------------------------------------------------------------------
+
+fn cmd_project(what: ProjectCommand) -> Result<()> {
+    match what {
+        ProjectCommand::Report { name, answers, output } => {
+            let entries = project::entries(&name)?;
+            let report = redact::text(&project::report(&name, &entries, answers));
+            match output {
+                Some(path) => {
+                    let path = paths::resolve(&path)?;
+                    std::fs::write(&path, report).with_context(|| format!("Failed to write {}", path.display()))?;
+                    println!("Wrote {} ({} entries)", path.display(), entries.len());
+                }
+                None => print!("{}", report),
+            }
+        }
+        ProjectCommand::List => {
+            let projects = project::list();
+            if projects.is_empty() {
+                println!("No projects yet.");
+                term::hint("Run commands with --project NAME to start one.");
+            }
+            for (name, count, last) in projects {
+                println!("{}  {} entries, last {}", name.bold(), count, last.dimmed());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_health(client: &OsgeoClient) -> Result<()> {
+    let health = client.health()?;
+
+    println!("{}", "OSGeo Library Server Status".bold());
+    println!("{}", "=".repeat(40));
+
+    let status_color = if health.status == "healthy" {
+        health.status.green()
+    } else {
+        health.status.yellow()
+    };
+    println!("Server:     {}", client.base_url());
+    println!("Status:     {}", status_color);
+    println!("Version:    {}", health.version);
+    println!();
+
+    let check = |ok: bool| if ok { "OK".green() } else { "FAILED".red() };
+    println!("Embedding:  {}", check(health.embedding_server));
+    println!("LLM:        {}", check(health.llm_server));
+    println!("Database:   {}", check(health.database));
+
+    match capabilities::refresh(client)?.features {
+        Some(features) => println!("Features:   {}", features.join(", ")),
+        None => println!("Features:   {}", "not listed (older server)".dimmed()),
+    }
+
+    Ok(())
+}
+
+fn cmd_docs(client: &OsgeoClient, args: DocsArgs, default_page_size: i32, json: bool) -> Result<()> {
+    let mut pager = DocsPager::new(default_page_size);
+    pager.apply(&args)?;
+    let response = pager.fetch(client, args.page.unwrap_or(1))?;
+    if json {
+        return print_json(&response);
+    }
+
+    println!("{}", "OSGeo Document Library".bold());
+    println!("{}", "=".repeat(50));
+    let filtered = !pager.filter.is_empty();
+    if filtered && response.documents.is_empty() {
+        println!("No documents match.");
+        return Ok(());
+    }
+    println!(
+        "Page {} of {} ({} documents {})\n",
+        response.page,
+        response.total_pages,
+        response.total_documents,
+        if filtered { "match" } else { "total" }
+    );
+
+    // Wide terminals: title and slug/pages only, two documents per row
+    if let Some(width) = columns::column_width() {
+        let cells: Vec<Vec<String>> = response
+            .documents
+            .iter()
+            .map(|doc| {
+                let pages = format!("  |  Pages: {}", doc.total_pages);
+                let slug = columns::fit(&doc.slug, width.saturating_sub(8 + pages.len()));
+                vec![
+                    columns::fit(&doc.title, width).bold().to_string(),
+                    format!("  Slug: {}{}", slug.cyan(), pages),
+                    String::new(),
+                ]
+            })
+            .collect();
+        columns::print(&cells, width);
+    } else {
+        for doc in &response.documents {
+            println!("{}", doc.title.bold());
+            println!("  Slug: {}  |  Pages: {}", doc.slug.cyan(), doc.total_pages);
+        
+            if let Some(ref keywords) = doc.keywords {
+                if !keywords.is_empty() {
+                    let kw_str: String = keywords.iter().take(5).cloned().collect::<Vec<_>>().join(", ");
+                    println!("  Keywords: {}", kw_str.dimmed());
+                }
+            }
+        
+            if let Some(ref summary) = doc.summary {
+                // Truncate long summaries
+                let display_summary = if summary.len() > 150 {
+                    format!("{}...", &summary[..150])
+                } else {
+                    summary.clone()
+                };
+                println!("  {}", display_summary.dimmed());
+            }
+            println!();
+        }
+    }
+
+    if let Some(next) = pager.next_page() {
+        println!(
+            "Use {} to see more pages",
+            format!("--page {}", next).cyan()
+        );
+    }
+
+    Ok(())
+}
+
+/// Print a response for --json output.
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", redact::text(&serde_json::to_string_pretty(value).context("Failed to encode JSON")?));
+    Ok(())
+}
+
+/// Print the JSON Schema of a command's --json output, or of all of them
+/// keyed by command name.
+fn cmd_schema(command: Option<&str>) -> Result<()> {
+    let schema = |name: &str| match name {
+        "search" => schemars::schema_for!(SearchResponse),
+        "ask" => schemars::schema_for!(ChatResponse),
+        _ => schemars::schema_for!(DocumentListResponse),
+    };
+    match command {
+        Some(name) => print_json(&schema(name)),
+        None => {
+            let all: std::collections::BTreeMap<&str, _> =
+                ["search", "ask", "docs"].into_iter().map(|name| (name, schema(name))).collect();
+            print_json(&all)
+        }
+    }
+}
+
+/// Document a command is scoped to: its `-d` value, or the configured
+/// default document. "all" lifts the default and searches the whole library.
+fn document_scope(document: Option<String>, defaults: &config::Defaults) -> Option<String> {
+    match document {
+        Some(slug) if slug.eq_ignore_ascii_case("all") => None,
+        Some(slug) => Some(slug),
+        None => defaults.document.clone(),
+    }
+}
+
+fn cmd_config(action: &ConfigCommand, profile: Option<&str>) -> Result<()> {
+    match action {
+        ConfigCommand::Set { key, value } => {
+            let path = config::set(key, value, profile)?;
+            match profile {
+                Some(name) => println!("Set {} in profile '{}' ({})", key, name, path.display()),
+                None => println!("Set {} ({})", key, path.display()),
+            }
+        }
+        ConfigCommand::Get { key } => {
+            let settings = config::Config::load(profile)?.settings()?;
+            match settings.iter().find(|(k, _)| k == key) {
+                // Strings unquoted, for use in scripts
+                Some((_, toml::Value::String(s))) => println!("{}", s),
+                Some((_, value)) => println!("{}", value),
+                // A whole section, e.g. 'defaults'
+                None => {
+                    let prefix = format!("{}.", key);
+                    let section: Vec<_> = settings.iter().filter(|(k, _)| k.starts_with(&prefix)).collect();
+                    if section.is_empty() {
+                        anyhow::bail!("{} is not set (see 'config list')", key);
+                    }
+                    for (k, value) in section {
+                        println!("{} = {}", k, value);
+                    }
+                }
+            }
+        }
+        ConfigCommand::List => {
+            let settings = config::Config::load(profile)?.settings()?;
+            match config::path() {
+                Some(path) if path.exists() => println!("{}", format!("# {}", path.display()).dimmed()),
+                Some(path) => println!("{}", format!("# {} (not created yet, built-in defaults)", path.display()).dimmed()),
+                None => {}
+            }
+            if let Some(name) = profile {
+                println!("{}", format!("# profile: {}", name).dimmed());
+            }
+            for (key, value) in settings {
+                println!("{} = {}", key, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_doc(client: &OsgeoClient, slug: String) -> Result<()> {
+    let doc = client.get_document(&slug)?;
+    recent::record(client.base_url(), recent::Item::Document {
+        slug: doc.slug.clone(),
+        title: doc.title.clone(),
+    });
+
+    println!("{}", doc.title.bold());
+    println!("{}", "=".repeat(50));
+    println!("Slug:       {}", doc.slug.cyan());
+    println!("Pages:      {}", doc.total_pages);
+    
+    if let Some(ref source) = doc.source_file {
+        println!("Source:     {}", source);
+    }
+    
+    if let Some(ref date) = doc.extraction_date {
+        println!("Extracted:  {}", date);
+    }
+    
+    if let Some(ref license) = doc.license {
+        println!("License:    {}", license);
+    }
+
+    // Element counts
+    let total_elements: i32 = doc.element_counts.values().sum();
+    if total_elements > 0 {
+        println!("\n{}", "Elements:".bold());
+        for (elem_type, count) in &doc.element_counts {
+            if *count > 0 {
+                println!("  {:12} {}", format!("{}:", elem_type), count);
+            }
+        }
+    }
+
+    // Keywords
+    if let Some(ref keywords) = doc.keywords {
+        if !keywords.is_empty() {
+            println!("\n{}", "Keywords:".bold());
+            println!("  {}", keywords.join(", "));
+        }
+    }
+
+    // Summary
+    if let Some(ref summary) = doc.summary {
+        println!("\n{}", "Summary:".bold());
+        println!("{}", summary);
+    }
+
+    println!("\n{}", "Usage:".dimmed());
+    println!(
+        "  Search:  osgeo-library search \"query\" -d {}",
+        doc.slug
+    );
+    println!(
+        "  Chat:    osgeo-library ask \"question\" -d {}",
+        doc.slug
+    );
+
+    Ok(())
+}
+
+/// "[#########---------------]  37%  11.4 of 30.8 MB"
+fn download_progress(done: u64, total: Option<u64>) -> String {
+    const WIDTH: u64 = 24;
+    match total.filter(|&t| t > 0) {
+        Some(total) => {
+            let filled = (done.min(total) * WIDTH / total) as usize;
+            format!(
+                "[{}{}] {:>3}%  {} of {}",
+                "#".repeat(filled),
+                "-".repeat(WIDTH as usize - filled),
+                done.min(total) * 100 / total,
+                plan::format_size(done),
+                plan::format_size(total)
+            )
+        }
+        None => plan::format_size(done),
+    }
+}
+
+/// Download the original PDF of a document into `dir`, carrying on from a
+/// partial download left there (`<file>.part`).
+fn cmd_download(client: &OsgeoClient, slug: &str, dir: Option<std::path::PathBuf>) -> Result<()> {
+    use std::io::{Read, Write};
+
+    capabilities::require(client, Feature::Download)?;
+    let document = client.get_document(slug)?;
+    let name = document
+        .source_file
+        .as_deref()
+        .and_then(|f| std::path::Path::new(f).file_name())
+        .map(|n| n.to_os_string())
+        .unwrap_or_else(|| format!("{}.pdf", slug).into());
+    let dir = paths::resolve(dir.as_deref().unwrap_or(std::path::Path::new("")))?;
+    if !dir.as_os_str().is_empty() {
+        std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let path = dir.join(&name);
+    let mut part = path.clone().into_os_string();
+    part.push(".part");
+    let part = std::path::PathBuf::from(part);
+
+    let remote = client.document_file_info(slug)?;
+    if path.exists() {
+        let bytes = std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        if remote.sha256.as_deref() == Some(digest::sha256(&bytes).as_str()) {
+            println!("{} is already downloaded", path.display());
+            return Ok(());
+        }
+        anyhow::bail!("{} already exists; remove it to download {} again", path.display(), slug);
+    }
+
+    let mut offset = std::fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+    if remote.size.is_some_and(|size| offset > size) {
+        // Left from a different file
+        offset = 0;
+    }
+    if remote.size != Some(offset) {
+        let mut download = client.download_document(slug, offset)?;
+        if offset > 0 {
+            if download.start == offset {
+                term::progress(&format!("Resuming at {}", plan::format_size(offset)));
+            } else {
+                term::progress("The server can't resume downloads; starting over");
+            }
+        }
+        let mut file = if download.start == 0 {
+            std::fs::File::create(&part)
+        } else {
+            std::fs::OpenOptions::new().append(true).open(&part)
+        }
+        .with_context(|| format!("Failed to open {}", part.display()))?;
+
+        let mut done = download.start;
+        let mut buffer = vec![0; 64 * 1024];
+        let mut shown = None;
+        loop {
+            let read = match download.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) => {
+                    term::progress_done("");
+                    return Err(e).with_context(|| {
+                        format!("Download interrupted at {}; run the command again to resume", plan::format_size(done))
+                    });
+                }
+            };
+            file.write_all(&buffer[..read])
+                .with_context(|| format!("Failed to write {}", part.display()))?;
+            done += read as u64;
+            let status = download_progress(done, remote.size);
+            if shown.as_ref() != Some(&status) {
+                term::progress_inline(&format!("\r  Downloading {} {}", name.to_string_lossy(), status));
+                shown = Some(status);
+            }
+        }
+        term::progress_done("");
+        file.flush().with_context(|| format!("Failed to write {}", part.display()))?;
+        if let Some(size) = remote.size.filter(|&size| done < size) {
+            anyhow::bail!(
+                "Download ended early, at {} of {}; run the command again to resume",
+                plan::format_size(done),
+                plan::format_size(size)
+            );
+        }
+    }
+
+    let bytes = std::fs::read(&part).with_context(|| format!("Failed to read {}", part.display()))?;
+    if !digest::matches(remote.sha256.as_deref(), &bytes) {
+        std::fs::remove_file(&part).ok();
+        anyhow::bail!("{} arrived damaged (SHA-256 differs from the server's); run the command again", slug);
+    }
+    std::fs::rename(&part, &path).with_context(|| format!("Failed to write {}", path.display()))?;
+    println!("Downloaded {} ({})", path.display(), plan::format_size(bytes.len() as u64));
+    Ok(())
+}
+
+fn cmd_report_bug(cli: &Cli, requests: usize, output: Option<&std::path::Path>) -> Result<()> {
+    let profile = cli.profile.as_deref();
+    let config = config::Config::load(profile);
+    if let Ok(config) = &config {
+        preview::set_backend(cli.image_backend.unwrap_or(config.defaults.image_backend));
+        paths::init(cli.out_dir.clone().or_else(|| config.paths.exports.clone()), &config.paths.name);
+    }
+    let server_url = cli
+        .server
+        .first()
+        .cloned()
+        .or_else(|| config.as_ref().ok().and_then(|c| c.defaults.server.clone()))
+        .unwrap_or_else(|| DEFAULT_SERVER_URL.to_string());
+    let network = config.as_ref().map(|c| c.network.clone()).unwrap_or_default();
+    let policy = network.policy(cli.timeout, cli.connect_timeout, cli.retries);
+    let mirrors = match &config {
+        Ok(config) if cli.mirrors.is_empty() => config.defaults.mirrors.clone(),
+        _ => cli.mirrors.clone(),
+    };
+    let health = OsgeoClient::with_policy(&server_url, policy).and_then(|client| client.with_mirrors(&mirrors).health());
+    let report = bugreport::markdown(&bugreport::Inputs {
+        server_url: &server_url,
+        health,
+        config_path: config::path(),
+        profile,
+        settings: config.and_then(|c| c.settings()),
+        requests,
+    });
+
+    match output {
+        Some(path) => {
+            let path = paths::resolve(path)?;
+            std::fs::write(&path, &report).with_context(|| format!("Failed to write {}", path.display()))?;
+            println!("Wrote {}. Check it for anything private before attaching it to an issue.", path.display());
+        }
+        None => print!("{}", report),
+    }
+    Ok(())
+}
+
+/// Print or write citation entries for `slugs`, each as soon as its
+/// document is fetched. A file is written under a temporary name and
+/// renamed once every entry is in it.
+fn cmd_cite(
+    client: &OsgeoClient,
+    slugs: impl IntoIterator<Item = Result<String>>,
+    style: cite::Style,
+    output: Option<&std::path::Path>,
+) -> Result<()> {
+    let target = output.map(paths::resolve).transpose()?;
+    let part = target.as_ref().map(|path| {
+        let mut part = path.as_os_str().to_os_string();
+        part.push(".part");
+        std::path::PathBuf::from(part)
+    });
+    let mut out: Box<dyn std::io::Write> = match &part {
+        Some(part) => Box::new(std::io::BufWriter::new(
+            std::fs::File::create(part).with_context(|| format!("Failed to write {}", part.display()))?,
+        )),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    let mut written = 0;
+    let result = slugs.into_iter().try_for_each(|slug| {
+        let slug = slug?;
+        let doc = client.get_document(&slug).with_context(|| format!("Failed to cite '{}'", slug))?;
+        out.write_all(cite::entry(&doc, style).as_bytes())?;
+        written += 1;
+        Ok(())
+    });
+    let result = result.and_then(|()| Ok(out.flush()?));
+    drop(out);
+    if let (Some(part), Some(path)) = (part, target) {
+        if let Err(e) = result {
+            std::fs::remove_file(&part).ok();
+            return Err(e);
+        }
+        std::fs::rename(&part, &path).with_context(|| format!("Failed to write {}", path.display()))?;
+        println!("Wrote {} entr{} to {}", written, if written == 1 { "y" } else { "ies" }, path.display());
+    }
+    result
+}
+
+/// Seconds between checks on an upload's progress
+const INGEST_POLL_SECS: u64 = 3;
+
+/// What ingest needs to know of a document already in the library, to
+/// spot uploads of it again.
+struct Ingested {
+    slug: String,
+    source_file: Option<String>,
+    source_sha256: Option<String>,
+}
+
+/// Every document in the library, as far as ingest needs to know, read a
+/// page at a time without keeping summaries and keywords.
+fn ingested_documents(client: &OsgeoClient) -> Result<Vec<Ingested>> {
+    term::progress("Reading the library...");
+    client
+        .documents("title")
+        .map(|doc| {
+            doc.map(|doc| Ingested {
+                slug: doc.slug,
+                source_file: doc.source_file,
+                source_sha256: doc.source_sha256,
+            })
+        })
+        .collect()
+}
+
+fn cmd_ingest(client: &OsgeoClient, files: &[std::path::PathBuf], force: bool, dry_run: bool) -> Result<()> {
+    capabilities::require(client, Feature::Ingest)?;
+    let documents = ingested_documents(client)?;
+
+    if dry_run {
+        let mut plan = plan::Plan::default();
+        for path in files {
+            match read_pdf(path) {
+                Ok(pdf) => match (find_ingested(&documents, &pdf.file_name, &pdf.sha256), force) {
+                    (None, _) => plan.add(plan::Action::Upload, path.display().to_string(), Some(pdf.bytes.len() as u64)),
+                    (Some((doc, reason)), false) => plan.add(
+                        plan::Action::Skip,
+                        format!("{} (already in the library as {}, {})", path.display(), doc.slug, reason),
+                        None,
+                    ),
+                    (Some((doc, reason)), true) => {
+                        let what = match reason {
+                            "same file name" => format!("{} (replaces {})", path.display(), doc.slug),
+                            _ => format!("{} (a second copy of {}, {})", path.display(), doc.slug, reason),
+                        };
+                        plan.add(plan::Action::Upload, what, Some(pdf.bytes.len() as u64));
+                    }
+                },
+                Err(e) => plan.add(plan::Action::Skip, format!("{} ({:#})", path.display(), e), None),
+            }
+        }
+        print!("{}", plan.render());
+        return Ok(());
+    }
+
+    let mut not_ingested = 0;
+    for path in files {
+        match ingest_file(client, path, &documents, force) {
+            Ok(true) => {}
+            Ok(false) => not_ingested += 1,
+            Err(e) => {
+                println!("  {}: {:#}\n", "Error".red(), e);
+                not_ingested += 1;
+            }
+        }
+    }
+    if not_ingested > 0 {
+        anyhow::bail!("{} of {} file(s) not ingested", not_ingested, files.len());
+    }
+    Ok(())
+}
+
+/// Document in the library that a file with this name and SHA-256 would
+/// duplicate, and why. Documents ingested before servers recorded SHA-256
+/// can only be matched by file name.
+fn find_ingested<'a>(documents: &'a [Ingested], file_name: &str, sha256: &str) -> Option<(&'a Ingested, &'static str)> {
+    let same_file = documents.iter().find(|d| d.source_sha256.as_deref() == Some(sha256));
+    let same_name = || {
+        documents
+            .iter()
+            .find(|d| d.source_file.as_deref().is_some_and(|f| f.eq_ignore_ascii_case(file_name)))
+    };
+    same_file
+        .map(|d| (d, "same SHA-256"))
+        .or_else(|| same_name().map(|d| (d, "same file name")))
+}
+
+/// A PDF read for uploading.
+struct LocalPdf {
+    file_name: String,
+    bytes: Vec<u8>,
+    sha256: String,
+}
+
+fn read_pdf(path: &std::path::Path) -> Result<LocalPdf> {
+    use sha2::{Digest, Sha256};
+
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .with_context(|| format!("Not a file: {}", path.display()))?
+        .to_string();
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if !bytes.starts_with(b"%PDF") {
+        anyhow::bail!("{} is not a PDF", file_name);
+    }
+    let sha256 = format!("{:x}", Sha256::digest(&bytes));
+    Ok(LocalPdf {
+        file_name,
+        bytes,
+        sha256,
+    })
+}
+
+/// Upload one PDF and follow its processing. Returns false if it was not
+/// uploaded because it is already in the library.
+fn ingest_file(client: &OsgeoClient, path: &std::path::Path, documents: &[Ingested], force: bool) -> Result<bool> {
+    println!("{} {}", "Ingesting".bold(), path.display());
+    let LocalPdf {
+        file_name,
+        bytes: pdf,
+        sha256,
+    } = read_pdf(path)?;
+
+    if let Some((doc, reason)) = find_ingested(documents, &file_name, &sha256) {
+        if !force {
+            println!(
+                "  {} already in the library as {} ({}); use --force to upload anyway\n",
+                "Skipped:".yellow(),
+                doc.slug.cyan(),
+                reason
+            );
+            return Ok(false);
+        }
+        println!("  Already in the library as {} ({}), uploading anyway", doc.slug.cyan(), reason);
+    }
+
+    let megabytes = pdf.len() as f64 / 1_000_000.0;
+    let mut shown = None;
+    let uploaded = client.ingest_document(&file_name, pdf, force, move |sent, total| {
+        let percent = sent * 100 / total.max(1);
+        if shown != Some(percent) {
+            shown = Some(percent);
+            term::progress_inline(&format!("\r  Uploading {:.1} MB... {}%", megabytes, percent));
+        }
+    });
+    term::progress_done("");
+    let mut job = uploaded?;
+    println!(
+        "  Uploaded as {} (job {}); processing continues on the server if you stop waiting",
+        job.slug.cyan(),
+        job.job_id
+    );
+
+    let mut shown = String::new();
+    loop {
+        let status = match job.status.as_str() {
+            "extracting" if job.pages_total > 0 => format!("extracting page {} of {}", job.pages_done, job.pages_total),
+            status => status.to_string(),
+        };
+        if status != shown {
+            term::progress_inline(&format!("\r  {:<40}", status));
+            shown = status;
+        }
+        if job.finished() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_secs(INGEST_POLL_SECS));
+        job = match client.get_ingest_job(&job.job_id) {
+            Ok(job) => job,
+            Err(e) => {
+                term::progress_done("");
+                return Err(e);
+            }
+        };
+    }
+    term::progress_done("");
+
+    if job.status == "failed" {
+        anyhow::bail!("Processing failed: {}", job.error.as_deref().unwrap_or("unknown error"));
+    }
+    println!("  {} {} ({} pages)\n", "Added".green(), job.slug.cyan(), job.pages_total);
+    Ok(true)
+}
+
+fn cmd_audit_duplicates(client: &OsgeoClient, delete: bool, dry_run: bool) -> Result<()> {
+    if delete {
+        capabilities::require(client, Feature::Delete)?;
+    }
+    term::progress("Reading the library...");
+    let mut documents = Vec::new();
+    let mut page = 1;
+    loop {
+        let response = client.list_documents_with_checksums(page, 100)?;
+        documents.extend(response.documents);
+        if page >= response.total_pages {
+            break;
+        }
+        page += 1;
+    }
+
+    let groups = audit::find(&documents);
+    if groups.is_empty() {
+        println!("No suspected duplicates among {} documents.", documents.len());
+        return Ok(());
+    }
+    println!(
+        "{} group(s) of suspected duplicates among {} documents:\n",
+        groups.len(),
+        documents.len()
+    );
+    for (n, group) in groups.iter().enumerate() {
+        let reasons: Vec<&str> = group.reasons.iter().map(|r| r.describe()).collect();
+        println!("{} {}", format!("Group {}:", n + 1).bold(), reasons.join(", ").dimmed());
+        for (k, &i) in group.members.iter().enumerate() {
+            let doc = &documents[i];
+            println!(
+                "  [{}] {}  {} ({} pages)  {}",
+                k + 1,
+                doc.slug.cyan(),
+                doc.title,
+                doc.total_pages,
+                doc.source_file.as_deref().unwrap_or("-").dimmed()
+            );
+        }
+        println!();
+    }
+
+    if !delete {
+        term::hint("Compare two with 'osgeo-library doc-diff <slug1> <slug2>'; remove copies with --delete.");
+        return Ok(());
+    }
+
+    let stdin = std::io::stdin();
+    let ask = |question: &str| -> Result<String> {
+        print!("{}", question);
+        std::io::Write::flush(&mut std::io::stdout())?;
+        let mut line = String::new();
+        stdin.read_line(&mut line).context("Failed to read answer")?;
+        Ok(line.trim().to_string())
+    };
+    let mut plan = plan::Plan::default();
+    for (n, group) in groups.iter().enumerate() {
+        let answer = ask(&format!("Group {}: delete which? (e.g. '2' or '2 3', Enter to keep all) ", n + 1))?;
+        let picked: Vec<&DocumentListItem> = answer
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter_map(|s| s.parse::<usize>().ok())
+            .filter(|k| (1..=group.members.len()).contains(k))
+            .map(|k| &documents[group.members[k - 1]])
+            .collect();
+        if picked.is_empty() {
+            continue;
+        }
+        if picked.len() == group.members.len() {
+            println!("Keeping all: that would delete every copy.\n");
+            continue;
+        }
+        if dry_run {
+            for doc in picked {
+                plan.add(
+                    plan::Action::Delete,
+                    format!("{} ({}, {} pages)", doc.slug, doc.title, doc.total_pages),
+                    None,
+                );
+            }
+            println!();
+            continue;
+        }
+        let slugs: Vec<&str> = picked.iter().map(|d| d.slug.as_str()).collect();
+        let confirm = ask(&format!("Delete {} with all its pages and elements? [y/N] ", slugs.join(", ")))?;
+        if !confirm.eq_ignore_ascii_case("y") && !confirm.eq_ignore_ascii_case("yes") {
+            println!("Kept.\n");
+            continue;
+        }
+        for slug in slugs {
+            let deleted = client.delete_document(slug)?;
+            println!("Deleted {} ({})", deleted.slug.cyan(), deleted.title);
+        }
+        println!();
+    }
+    if dry_run {
+        print!("{}", plan.render());
+    }
+    Ok(())
+}
+
+/// List the recently viewed on the client's server, or reopen the `n`th.
+fn cmd_recent(client: &OsgeoClient, n: Option<usize>) -> Result<()> {
+    let views = recent::list(client.base_url());
+    let Some(n) = n else {
+        if views.is_empty() {
+            println!("Nothing viewed yet. Documents, pages and elements you view are listed here.");
+        } else {
+            print!("{}", format_recent(&views));
+        }
+        return Ok(());
+    };
+    let entry = n
+        .checked_sub(1)
+        .and_then(|i| views.get(i))
+        .with_context(|| match views.len() {
+            0 => "Nothing viewed yet".to_string(),
+            len => format!("No recent view {}; use 1-{}", n, len),
+        })?;
+    match &entry.item {
+        recent::Item::Document { slug, .. } => cmd_doc(client, slug.clone()),
+        recent::Item::Page { slug, page, .. } => {
+            let page = client.get_page(slug, *page, preview::page_width())?;
+            recent::record(client.base_url(), entry.item.clone());
+            println!("{} p.{}/{}\n", page.document_title.bold(), page.page_number, page.total_pages);
+            display_page_image(&page.image, "80x40", &format!("{} p.{}", page.document_slug, page.page_number))
+        }
+        recent::Item::Element { result } => {
+            show_element(client, result, None);
+            Ok(())
+        }
+    }
+}
+
+fn cmd_doc_diff(client: &OsgeoClient, slug1: &str, slug2: &str) -> Result<()> {
+    let async_client = AsyncOsgeoClient::with_policy(client.base_url(), client.policy())?.with_mirrors(&client.mirrors());
+    let (a, b) = runtime()?.block_on(async {
+        let first = tokio::spawn({
+            let client = async_client.clone();
+            let slug = slug1.to_string();
+            async move { client.get_document(&slug).await }
+        });
+        let b = async_client.get_document(slug2).await;
+        (first.await.unwrap_or_else(|_| Err(anyhow::anyhow!("request task panicked"))), b)
+    });
+    let (a, b) = (a?, b?);
+
+    let text = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+    let mut rows = vec![
+        docdiff::Row::new("Title", &a.title, &b.title),
+        docdiff::Row::new("Source", text(&a.source_file), text(&b.source_file)),
+        docdiff::Row::new("Pages", a.total_pages, b.total_pages),
+        docdiff::Row::new("License", text(&a.license), text(&b.license)),
+        docdiff::Row::new("Extracted", text(&a.extraction_date), text(&b.extraction_date)),
+    ];
+
+    // Element counts: the usual types first, then anything else either reports
+    let mut kinds: Vec<String> = ["figures", "tables", "equations", "diagrams", "charts"]
+        .iter()
+        .map(|k| k.to_string())
+        .collect();
+    let mut extra: Vec<&String> = a
+        .element_counts
+        .keys()
+        .chain(b.element_counts.keys())
+        .filter(|k| !kinds.contains(k))
+        .collect();
+    extra.sort();
+    extra.dedup();
+    kinds.extend(extra.into_iter().cloned());
+    for kind in &kinds {
+        let count = |doc: &DocumentDetailResponse| doc.element_counts.get(kind).copied().unwrap_or(0);
+        let label: String = kind
+            .chars()
+            .take(1)
+            .flat_map(char::to_uppercase)
+            .chain(kind.chars().skip(1))
+            .collect();
+        rows.push(docdiff::Row::new(&label, count(&a), count(&b)));
+    }
+
+    let keywords_a = a.keywords.clone().unwrap_or_default();
+    let keywords_b = b.keywords.clone().unwrap_or_default();
+    rows.push(docdiff::Row::new("Keywords", keywords_a.join(", "), keywords_b.join(", ")));
+    rows.push(docdiff::Row::new("Summary", text(&a.summary), text(&b.summary)));
+
+    println!("{}", docdiff::render(&a.slug, &b.slug, &rows, term::width().min(160)));
+
+    let differing: Vec<&str> = rows.iter().filter(|r| r.differs()).map(|r| r.label.as_str()).collect();
+    println!();
+    if differing.is_empty() {
+        println!("{}", "Metadata is identical.".green());
+    } else {
+        println!("{} {}", "Differs in:".bold(), differing.join(", "));
+    }
+
+    let (shared, only_a, only_b) = docdiff::keyword_diff(&keywords_a, &keywords_b);
+    if !only_a.is_empty() || !only_b.is_empty() {
+        println!("{} {} shared", "Keywords:".bold(), shared.len());
+        if !only_a.is_empty() {
+            println!("  only in {}: {}", a.slug.cyan(), only_a.join(", "));
+        }
+        if !only_b.is_empty() {
+            println!("  only in {}: {}", b.slug.cyan(), only_b.join(", "));
+        }
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cmd_search(
+    client: &OsgeoClient,
+    libraries: &[federation::Library],
+    query: String,
+    limit: i32,
+    document: Option<String>,
+    elements_only: bool,
+    chunks_only: bool,
+    element_type: Option<String>,
+    labels_only: bool,
+    has_number: bool,
+    min_score: Option<f64>,
+    pages: Option<(i32, i32)>,
+    exclude_docs: Vec<String>,
+    ranking: &ranking::Ranking,
+    explain: bool,
+    show: Option<String>,
+    copy: Option<usize>,
+    as_text: bool,
+    open: Option<String>,
+    first: bool,
+    pick: bool,
+    json: bool,
+    export: Option<std::path::PathBuf>,
+    archive: Option<evidence::Request>,
+) -> Result<()> {
+    if archive.is_some() && export.is_none() {
+        anyhow::bail!("--manifest describes an export; add --export FILE");
+    }
+    // Only elements have an image for --first to show
+    let images = first && (show.is_some() || open.is_some());
+    if images && chunks_only {
+        anyhow::bail!("--first with --show or --open shows an element; drop --chunks-only");
+    }
+    // Labels belong to elements; an element_type also implies elements only
+    let elements_only = elements_only || element_type.is_some() || labels_only || images;
+    // Quantities and pages are filtered client-side; over-fetch so enough
+    // remain (the server caps limit at 50)
+    let chunks_only = chunks_only || has_number;
+    let fetch_limit = if pages.is_some() {
+        limit.max(50)
+    } else if has_number {
+        (limit * 3).min(50).max(limit)
+    } else {
+        limit
+    };
+
+    let req = SearchRequest {
+        query: query.clone(),
+        limit: fetch_limit,
+        document_slug: document.clone(),
+        include_chunks: !elements_only,
+        include_elements: !chunks_only,
+        element_type,
+        labels_only,
+        exclude_documents: exclude_docs,
+        exclude_ids: Vec::new(),
+        min_score,
+    };
+
+    let mut response = if libraries.is_empty() {
+        if labels_only {
+            capabilities::require(client, Feature::LabelsSearch)?;
+        }
+        term::progress(&format!("Searching: {}", query));
+        let mut response = client.search(req)?;
+        response.drop_below(min_score);
+        response
+    } else {
+        let libraries = if labels_only {
+            federation::supporting(libraries, client.policy(), Feature::LabelsSearch)
+        } else {
+            libraries.to_vec()
+        };
+        term::progress(&format!("Searching {} libraries: {}", libraries.len(), query));
+        runtime()?.block_on(federation::search(&libraries, client.policy(), req))?
+    };
+
+    if let Some((from, to)) = pages {
+        let fetched = response.results.len();
+        response.results.retain(|r| (from..=to).contains(&r.page_number));
+        response.total = response.results.len() as i32;
+        term::verbose_line(&format!(
+            "{} of {} results on pages {}-{}",
+            response.results.len(),
+            fetched,
+            from,
+            to
+        ));
+    }
+
+    if has_number {
+        let fetched = response.results.len();
+        response.results.retain(|r| !quantity::quantities(&r.content).is_empty());
+        response.total = response.results.len() as i32;
+        term::verbose_line(&format!(
+            "{} of {} passages state a number with a unit",
+            response.results.len(),
+            fetched
+        ));
+    }
+
+    // Over-fetched for the filters above
+    if response.results.len() > limit as usize {
+        response.results.truncate(limit as usize);
+        response.total = limit;
+    }
+
+    apply_ranking(&mut response.results, &query, ranking);
+    // The rest ranked lower; what --show and --open name is the one kept
+    let (show, open) = if first {
+        response.results.truncate(1);
+        response.total = response.results.len() as i32;
+        (show.map(|_| "1".to_string()), open.map(|_| "1".to_string()))
+    } else {
+        (show, open)
+    };
+    project::record_results(client, "search", &query, &response.results);
+    if let Some(path) = export {
+        let path = paths::resolve(&path)?;
+        let rows: Vec<records::ResultRecord> = response
+            .results
+            .iter()
+            .enumerate()
+            .map(|(i, result)| records::ResultRecord::new(i + 1, result))
+            .collect();
+        records::write(&path, &rows)?;
+        // Stderr, so --json output stays parseable
+        eprintln!("Wrote {} result(s) to {}", rows.len(), path.display());
+        if let Some(request) = &archive {
+            let mut record = evidence::Manifest::new(client, "search");
+            record.query = Some(query.clone());
+            record.document = document;
+            record.results = response.results.iter().map(evidence::Item::new).collect();
+            let dir = path.parent().unwrap_or(std::path::Path::new(""));
+            record.add_file(dir, &path)?;
+            record.write(&evidence::path_for(&path), request)?;
+        }
+    }
+    if json {
+        print_json(&response)?;
+        return copy_search_result(client, libraries, &response.results, copy);
+    }
+
+    if response.results.is_empty() {
+        println!("\nNo results found.");
+        if let Some(note) = below_min_score_note(&response, min_score) {
+            term::hint(&note);
+        }
+        return copy_search_result(client, libraries, &response.results, copy);
+    }
+
+    if first {
+        println!();
+    } else {
+        println!(
+            "\n{} results:\n",
+            response.total.to_string().green().bold()
+        );
+    }
+    if let Some(note) = below_min_score_note(&response, min_score) {
+        term::hint(&note);
+    }
+
+    // --pick lists only the result chosen, and shows or opens it
+    let (picked, show, open) = if pick {
+        let Some(i) = picker::pick(&pick_lines(&response.results))? else {
+            println!("Nothing picked.");
+            return copy_search_result(client, libraries, &response.results, copy);
+        };
+        let image = (response.results[i].source_type == "element").then(|| (i + 1).to_string());
+        match open {
+            Some(_) => (Some(i), None, image),
+            None => (Some(i), image, None),
+        }
+    } else {
+        (None, show, open)
+    };
+
+    for (i, result) in response.results.iter().enumerate() {
+        if picked.is_some_and(|p| p != i) {
+            continue;
+        }
+        println!("{}", format_result(i + 1, result, true));
+        if has_number {
+            let found = quantity::quantities(&result.content);
+            println!("    {} {}", "Quantities:".dimmed(), found.join(", ").yellow());
+        }
+        if explain {
+            for line in explain_ranking(result, &query, Some(ranking)) {
+                println!("    {}", line.dimmed());
+            }
+        }
+        println!();
+    }
+
+    // Handle --show flag
+    if let Some(show_arg) = show {
+        // Parse indices: "1" or "1,3,5"
+        let indices: Vec<usize> = show_arg
+            .split(',')
+            .filter_map(|s| s.trim().parse::<usize>().ok())
+            .map(|n| n.saturating_sub(1)) // Convert to 0-indexed
+            .collect();
+
+        if indices.is_empty() {
+            return copy_search_result(client, libraries, &response.results, copy);
+        }
+
+        println!("{}", "=".repeat(40));
+
+        for idx in indices {
+            if idx >= response.results.len() {
+                term::warning(&format!("invalid index [{}]; use 1-{}", idx + 1, response.results.len()));
+                continue;
+            }
+
+            let result = &response.results[idx];
+
+            if result.source_type != "element" {
+                term::warning(&format!("[{}] is a text chunk, no image available", idx + 1));
+                continue;
+            }
+
+            if as_text {
+                if let Some(text) = table_text(result) {
+                    print_element_heading(result);
+                    println!("{}", text);
+                    continue;
+                }
+                term::hint(&format!("[{}] has no rows as text; showing the image.", idx + 1));
+            }
+
+            if let Some(image_path) = result.best_image_path() {
+                let elem_type = result
+                    .element_type
+                    .as_ref()
+                    .map(|s| s.to_uppercase())
+                    .unwrap_or_default();
+                let label = result.element_label.as_deref().unwrap_or("");
+
+                print_element_heading(result);
+                if result.element_type.as_deref() == Some("equation") && mathtext::enabled() {
+                    println!("{}\n", preview_text(result));
+                }
+
+                let image_url = result_image_url(client, libraries, result, image_path);
+
+                let size = chafa_size(result);
+                match fetch_and_display_image(client, &image_url, &size, &format!("{} {}", elem_type, label)) {
+                    Ok(_) => record_search_view(client, libraries, result),
+                    Err(e) => term::warning(&format!("failed to display image: {:#}", e)),
+                }
+            }
+        }
+    }
+
+    // Handle --open flag
+    if let Some(open_arg) = open {
+        let indices: Vec<usize> = open_arg
+            .split(',')
+            .filter_map(|s| s.trim().parse::<usize>().ok())
+            .map(|n| n.saturating_sub(1))
+            .collect();
+
+        if indices.is_empty() {
+            return copy_search_result(client, libraries, &response.results, copy);
+        }
+
+        for idx in indices {
+            if idx >= response.results.len() {
+                term::warning(&format!("invalid index [{}]; use 1-{}", idx + 1, response.results.len()));
+                continue;
+            }
+
+            let result = &response.results[idx];
+
+            if result.source_type != "element" {
+                term::warning(&format!("[{}] is a text chunk, no image available", idx + 1));
+                continue;
+            }
+
+            if let Some(image_path) = result.best_image_path() {
+                let image_url = result_image_url(client, libraries, result, image_path);
+
+                match fetch_and_open_image(client, &image_url) {
+                    Ok(_) => record_search_view(client, libraries, result),
+                    Err(e) => term::warning(&format!("failed to open image: {:#}", e)),
+                }
+            }
+        }
+    }
+
+    copy_search_result(client, libraries, &response.results, copy)
+}
+
+/// `search --copy N`: put result N's LaTeX or text on the clipboard.
+fn copy_search_result(
+    client: &OsgeoClient,
+    libraries: &[federation::Library],
+    results: &[SearchResult],
+    copy: Option<usize>,
+) -> Result<()> {
+    let Some(n) = copy else {
+        return Ok(());
+    };
+    let Some(result) = n.checked_sub(1).and_then(|i| results.get(i)) else {
+        anyhow::bail!("No result [{}] to copy ({} found)", n, results.len());
+    };
+    // Equations' LaTeX is listed by the library the result came from
+    let text = match federation::server_of(libraries, result) {
+        Some(server) => clipboard_text(&OsgeoClient::with_policy(server, client.policy())?, result),
+        None => clipboard_text(client, result),
+    };
+    if text.trim().is_empty() {
+        anyhow::bail!("Result [{}] has no text to copy", n);
+    }
+    clipboard::copy_and_hold(&text)?;
+    // Stderr, so --json output stays parseable
+    eprintln!("Copied {} of [{}] to the clipboard.", clipboard_what(result), n);
+    Ok(())
+}
+
+/// "3 results below 60% left out", when `--min-score` left any out.
+fn below_min_score_note(response: &SearchResponse, min_score: Option<f64>) -> Option<String> {
+    let min_score = min_score?;
+    match response.below_min_score {
+        0 => None,
+        1 => Some(format!("1 result below {}% left out", min_score)),
+        n => Some(format!("{} results below {}% left out", n, min_score)),
+    }
+}
+
+/// URL of a result's image, on the server of the library it came from.
+fn result_image_url(
+    client: &OsgeoClient,
+    libraries: &[federation::Library],
+    result: &SearchResult,
+    path: &str,
+) -> String {
+    match federation::server_of(libraries, result) {
+        Some(server) => format!("{}/image/{}/{}", server, result.document_slug, path),
+        None => client.image_url(&result.document_slug, path),
+    }
+}
+
+/// Handle 'pick' and 'pick open': the command acting on the result chosen,
+/// `show N` or `open N` for an element, `detail N` for a text passage.
+fn pick_command(sources: &[SearchResult], open: bool) -> Option<String> {
+    if sources.is_empty() {
+        println!("No results to pick from. Search or ask first.\n");
+        return None;
+    }
+    match picker::pick(&pick_lines(sources)) {
+        Ok(Some(i)) if sources[i].source_type != "element" => Some(format!("detail {}", i + 1)),
+        Ok(Some(i)) => Some(format!("{} {}", if open { "open" } else { "show" }, i + 1)),
+        Ok(None) => {
+            println!("Nothing picked.\n");
+            None
+        }
+        Err(e) => {
+            println!("{}: {:#}\n", "Error".red(), e);
+            None
+        }
+    }
+}
+
+/// One line per result for the picker: "[3] TABLE Table 3.2 | slug p.80"
+/// and the start of its content.
+fn pick_lines(results: &[SearchResult]) -> Vec<String> {
+    results
+        .iter()
+        .enumerate()
+        .map(|(i, result)| format!("[{}] {}  {}", i + 1, source_summary(result), source_snippet(result)))
+        .collect()
+}
+
+/// Add a result shown by `search --show` or `--open` to the recently viewed
+/// of the library it came from.
+fn record_search_view(client: &OsgeoClient, libraries: &[federation::Library], result: &SearchResult) {
+    let server = federation::server_of(libraries, result).unwrap_or(client.base_url());
+    recent::record(server, recent::Item::element(result));
+}
+
+/// List what is closest to a result or a page, leaving out the page itself.
+fn cmd_similar(client: &OsgeoClient, req: SimilarRequest, json: bool) -> Result<()> {
+    capabilities::require(client, Feature::Similar)?;
+    term::progress(&format!("Looking for content similar to {}", req.source));
+    let response = client.similar(req)?;
+    project::record_results(client, "similar", &response.query, &response.results);
+    if json {
+        return print_json(&response);
+    }
+    if response.results.is_empty() {
+        println!("\nNothing similar found.");
+        return Ok(());
+    }
+    println!("\n{} similar to {}:\n", response.total.to_string().green().bold(), response.query.cyan());
+    for (i, result) in response.results.iter().enumerate() {
+        println!("{}", format_result(i + 1, result, true));
+        println!();
+    }
+    Ok(())
+}
+
+/// Matches of a grep, printed as they are found.
+struct GrepRun {
+    matcher: grep::Matcher,
+    max_count: Option<usize>,
+    /// Name the document of each match, when several are searched
+    named: bool,
+    scanned: usize,
+    matches: usize,
+    /// Document and page of each match
+    pages: std::collections::BTreeSet<(String, i32)>,
+}
+
+impl GrepRun {
+    fn new(matcher: grep::Matcher, max_count: Option<usize>, named: bool) -> Self {
+        Self {
+            matcher,
+            max_count,
+            named,
+            scanned: 0,
+            matches: 0,
+            pages: Default::default(),
+        }
+    }
+
+    /// Print the matches in a chunk of `slug`; false once `max_count` have
+    /// been printed.
+    fn scan(&mut self, slug: &str, page: i32, chunk_index: i32, start_char: Option<i32>, content: &str) -> bool {
+        self.scanned += 1;
+        for hit in self.matcher.scan(page, chunk_index, start_char, content) {
+            let place = format!("p.{}:{}", hit.page, hit.chunk_index);
+            let place = if self.named { format!("{} {}", slug, place) } else { place };
+            println!("{} {}{}{}", place.cyan(), hit.before, hit.matched.red().bold(), hit.after);
+            self.pages.insert((slug.to_string(), hit.page));
+            self.matches += 1;
+            if self.max_count.is_some_and(|max| self.matches >= max) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Say how many matches there were, or that there were none in `what`.
+    fn summary(&self, what: &str) {
+        if self.matches == 0 {
+            println!("No matches in {} ({} chunks scanned).", what, self.scanned);
+            return;
+        }
+        term::progress(&format!(
+            "\n{} match{} on {} page{} ({} chunks scanned)",
+            self.matches,
+            if self.matches == 1 { "" } else { "es" },
+            self.pages.len(),
+            if self.pages.len() == 1 { "" } else { "s" },
+            self.scanned
+        ));
+    }
+}
+
+/// Scan a document's chunks for a pattern and print each match with its page.
+fn cmd_grep(client: &OsgeoClient, slug: &str, matcher: grep::Matcher, max_count: Option<usize>) -> Result<()> {
+    const BATCH: i32 = 500;
+
+    let mut run = GrepRun::new(matcher, max_count, false);
+    let mut offset = 0;
+    'fetch: loop {
+        let batch = client.list_chunks(slug, BATCH, offset)?;
+        let fetched = batch.chunks.len() as i32;
+        for chunk in &batch.chunks {
+            if !run.scan(slug, chunk.page_number, chunk.chunk_index, chunk.start_char, &chunk.content) {
+                break 'fetch;
+            }
+        }
+        offset += fetched;
+        if fetched == 0 || offset >= batch.total {
+            break;
+        }
+    }
+    run.summary(slug);
+    Ok(())
+}
+
+/// `grep` over cached text: one document's, or each of several in turn
+/// with its slug before each match when `named`.
+fn cmd_grep_cached(
+    documents: impl IntoIterator<Item = textcache::Cached>,
+    matcher: grep::Matcher,
+    max_count: Option<usize>,
+    named: bool,
+) -> Result<()> {
+    let mut run = GrepRun::new(matcher, max_count, named);
+    let mut searched = Vec::new();
+    'documents: for document in documents {
+        run.matcher.next_document();
+        searched.push(document.slug.clone());
+        for chunk in &document.chunks {
+            if !run.scan(&document.slug, chunk.page, chunk.index, chunk.start, &chunk.text) {
+                break 'documents;
+            }
+        }
+    }
+    match searched.as_slice() {
+        [slug] if !named => run.summary(slug),
+        _ => run.summary(&format!("{} cached document{}", searched.len(), if searched.len() == 1 { "" } else { "s" })),
+    }
+    Ok(())
+}
+
+/// "12.3 KB, 2.1 KB compressed"
+fn cached_size(bytes: u64, stored: u64) -> String {
+    format!("{}, {} compressed", plan::format_size(bytes), plan::format_size(stored))
+}
+
+/// Fetch, search, list, count or forget cached document text.
+fn cmd_cache(client: &OsgeoClient, what: CacheCommand) -> Result<()> {
+    let server = client.base_url().to_string();
+    match what {
+        CacheCommand::Text { slugs, delay, refresh } => {
+            for slug in &slugs {
+                if let Some(entry) = textcache::entry(&server, slug).filter(|e| e.complete() && !refresh) {
+                    println!(
+                        "{} already cached ({} chunks, {}); --refresh fetches it again",
+                        slug.cyan(),
+                        entry.chunks,
+                        entry.fetched
+                    );
+                    continue;
+                }
+                let entry = textcache::fetch(client, slug, std::time::Duration::from_millis(delay), refresh, |done, total| {
+                    term::progress_inline(&format!("\rCaching {}: {}/{} chunks", slug, done, total));
+                })?;
+                term::progress_done("");
+                println!(
+                    "Cached {}: {} chunks, {}",
+                    slug.cyan(),
+                    entry.chunks,
+                    cached_size(entry.bytes, entry.stored)
+                );
+            }
+        }
+        CacheCommand::Grep {
+            pattern,
+            ignore_case,
+            fixed_strings,
+            max_count,
+            context,
+        } => {
+            let matcher = grep::Matcher::new(&pattern, ignore_case, fixed_strings, context)?;
+            let slugs: Vec<String> = textcache::list(&server)
+                .into_iter()
+                .filter(|(_, entry)| entry.complete())
+                .map(|(slug, _)| slug)
+                .collect();
+            if slugs.is_empty() {
+                anyhow::bail!("No text cached from {} yet: run 'cache text <slug>' first", server);
+            }
+            // One document in memory at a time
+            let documents = slugs.iter().filter_map(|slug| {
+                let cached = textcache::load(&server, slug);
+                if cached.is_none() {
+                    term::warning(&format!("the cached text of {} can't be read; run 'cache text {} --refresh'", slug, slug));
+                }
+                cached
+            });
+            cmd_grep_cached(documents, matcher, max_count, true)?;
+        }
+        CacheCommand::List => {
+            let documents = textcache::list(&server);
+            if documents.is_empty() {
+                println!("No text cached from {}.", server);
+                term::hint("Run 'cache text <slug>' to cache some.");
+            }
+            for (slug, entry) in &documents {
+                let state = if entry.complete() {
+                    String::new()
+                } else {
+                    format!(", {} of {} chunks: run 'cache text {}' to finish", entry.chunks, entry.total, slug)
+                };
+                println!(
+                    "{}  {}, {}{}",
+                    slug.cyan(),
+                    cached_size(entry.bytes, entry.stored),
+                    entry.fetched.dimmed(),
+                    state
+                );
+            }
+        }
+        CacheCommand::Stats => {
+            let stats = textcache::stats();
+            if stats.is_empty() {
+                println!("Nothing cached yet.");
+                term::hint("Run 'cache text <slug>' to cache some.");
+                return Ok(());
+            }
+            let ratio = |bytes: u64, on_disk: u64| bytes as f64 / on_disk.max(1) as f64;
+            for s in &stats {
+                println!("{}", s.server.bold());
+                println!("  Documents:  {} ({} chunks)", s.documents, s.chunks);
+                println!("  Text:       {}", plan::format_size(s.bytes));
+                println!("  On disk:    {} ({:.1}x smaller)", plan::format_size(s.on_disk), ratio(s.bytes, s.on_disk));
+            }
+            if stats.len() > 1 {
+                let bytes: u64 = stats.iter().map(|s| s.bytes).sum();
+                let on_disk: u64 = stats.iter().map(|s| s.on_disk).sum();
+                println!(
+                    "\n{} {} of text in {} on disk ({:.1}x smaller)",
+                    "All servers:".bold(),
+                    plan::format_size(bytes),
+                    plan::format_size(on_disk),
+                    ratio(bytes, on_disk)
+                );
+            }
+        }
+        CacheCommand::Remove { slugs } => {
+            for slug in &slugs {
+                if textcache::remove(&server, slug)? {
+                    println!("Removed the cached text of {}", slug.cyan());
+                } else {
+                    println!("{} was not cached", slug);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Look up an element of `slug` by its label and print it, with its image
+/// on request.
+fn cmd_element(client: &OsgeoClient, label: &str, slug: &str, show: bool, open: bool) -> Result<()> {
+    let document = client.get_document(slug)?;
+    term::progress(&format!("Looking up {} in {}...", label, slug));
+    let mut elements = Vec::new();
+    loop {
+        let batch = client.list_elements(slug, None, None, 100, elements.len() as i32)?;
+        let fetched = batch.elements.len();
+        elements.extend(batch.elements);
+        if fetched == 0 || elements.len() as i32 >= batch.total {
+            break;
+        }
+    }
+
+    let found = lookup::find(label, &elements);
+    let Some((best, others)) = found.split_first() else {
+        anyhow::bail!("No element of {} is labelled like '{}'", slug, label);
+    };
+    if best.distance > 0 {
+        println!("{}", format!("No label reads '{}'; the closest:", label).dimmed());
+    }
+    let element = best.element;
+    let result = SearchResult {
+        id: element.id,
+        score_pct: 100.0,
+        content: element.description.clone().or_else(|| element.latex.clone()).unwrap_or_default(),
+        source_type: "element".to_string(),
+        document_slug: slug.to_string(),
+        document_title: document.title,
+        page_number: element.page_number,
+        element_type: Some(element.element_type.clone()),
+        element_label: element.label.clone(),
+        crop_path: element.crop_path.clone(),
+        rendered_path: element.rendered_path.clone(),
+        image_width: None,
+        image_height: None,
+        chunk_index: None,
+        score_breakdown: Default::default(),
+        library: None,
+    };
+
+    if show {
+        show_element(client, &result, None);
+    } else {
+        print_element_heading(&result);
+        match table_text(&result) {
+            Some(text) => println!("{}", text),
+            None if !result.content.is_empty() => println!("{}", preview_text(&result)),
+            None => {}
+        }
+        recent::record(client.base_url(), recent::Item::element(&result));
+    }
+    if open {
+        match result.best_image_path() {
+            Some(path) => {
+                fetch_and_open_image(client, &client.image_url(slug, path))?;
+                recent::record(client.base_url(), recent::Item::element(&result));
+            }
+            None => println!("{} has no image.", label),
+        }
+    }
+
+    if !others.is_empty() {
+        let close: Vec<String> = others
+            .iter()
+            .take(5)
+            .map(|m| format!("{} (p.{})", m.element.label.as_deref().unwrap_or(""), m.element.page_number))
+            .collect();
+        println!("\n{} {}", "Other matches:".dimmed(), close.join(", "));
+    }
+    Ok(())
+}
+
+/// Build the question for `ask`: expand the template, then let the user edit
+/// it when --edit is given. `None` means the edited question was left empty.
+fn resolve_question(
+    question: Option<String>,
+    template: Option<String>,
+    edit: bool,
+    custom_templates: &std::collections::HashMap<String, String>,
+) -> Result<Option<String>> {
+    let mut question = question.unwrap_or_default();
+    if let Some(name) = template {
+        question = templates::expand(&name, &question, custom_templates)?;
+    }
+    if edit {
+        return editor::compose(&question);
+    }
+    Ok(Some(question))
+}
+
+/// Longest text `ask --context-file` sends, as the server accepts
+const MAX_USER_CONTEXT_CHARS: usize = 20_000;
+
+/// The text of `ask --context-file`: the file, or stdin for `-`.
+fn read_context_file(path: &std::path::Path) -> Result<String> {
+    let (name, text) = if path.as_os_str() == "-" {
+        let text = std::io::read_to_string(std::io::stdin()).context("Failed to read the context from stdin")?;
+        ("stdin".to_string(), text)
+    } else {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        (path.display().to_string(), text)
+    };
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        anyhow::bail!("{} is empty", name);
+    }
+    let chars = text.chars().count();
+    if chars > MAX_USER_CONTEXT_CHARS {
+        anyhow::bail!(
+            "{} has {} characters, more than the {} the server takes; cut it to the part the question is about",
+            name,
+            chars,
+            MAX_USER_CONTEXT_CHARS
+        );
+    }
+    Ok(text)
+}
+
+/// Longest question sent as it is. A pasted page makes a request that
+/// proxies in front of the server may refuse (413 Payload Too Large), and
+/// the search behind the answer only makes sense of a question's start.
+const MAX_QUESTION_CHARS: usize = 20_000;
+
+/// Marks where `truncate_question` cut the middle out
+const CUT_MARK: &str = "\n[...]\n";
+
+/// Stop with a hint when a question or a statement to verify is longer than
+/// the server is sent at once.
+fn check_question_size(question: &str) -> Result<()> {
+    let chars = question.chars().count();
+    if chars > MAX_QUESTION_CHARS {
+        anyhow::bail!(
+            "The text has {} characters, more than the {} sent at once; shorten it, or put the long text \
+             in a file for 'ask --context-file' and ask a short question about it",
+            chars,
+            MAX_QUESTION_CHARS
+        );
+    }
+    Ok(())
+}
+
+/// `text` cut to MAX_QUESTION_CHARS by leaving out its middle, at spaces
+/// where there are some: a question asked before or after a pasted page
+/// is kept either way.
+fn truncate_question(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= MAX_QUESTION_CHARS {
+        return text.to_string();
+    }
+    let keep = MAX_QUESTION_CHARS - CUT_MARK.chars().count();
+    let mut head = keep * 3 / 4;
+    let mut tail = chars.len() - (keep - head);
+    // Back off to a word break within the last 200 characters of each part
+    if let Some(space) = (head.saturating_sub(200)..head).rev().find(|&i| chars[i].is_whitespace()) {
+        head = space;
+    }
+    if let Some(space) = (tail..(tail + 200).min(chars.len())).find(|&i| chars[i].is_whitespace()) {
+        tail = space + 1;
+    }
+    let head: String = chars[..head].iter().collect();
+    let tail: String = chars[tail..].iter().collect();
+    format!("{}{}{}", head.trim_end(), CUT_MARK, tail.trim_start())
+}
+
+/// A chat question longer than MAX_QUESTION_CHARS, made to fit as the user
+/// chooses: cut in the middle, or summarized by the server and the summary
+/// asked. `None` when they cancel.
+fn fit_question(client: &OsgeoClient, rl: &mut chatline::LineEditor, text: &str) -> Result<Option<String>> {
+    let can_summarize = capabilities::of(client).supports(Feature::UserContext);
+    term::warning(&format!(
+        "the question has {} characters, more than the {} sent at once",
+        text.chars().count(),
+        MAX_QUESTION_CHARS
+    ));
+    let prompt = if can_summarize {
+        "[t]runcate it, [s]ummarize it and ask the summary, or [c]ancel? "
+    } else {
+        "[t]runcate it or [c]ancel? "
+    };
+    let choice = match rl.readline(prompt) {
+        Ok(choice) => choice.trim().to_lowercase(),
+        Err(ReadlineError::Interrupted | ReadlineError::Eof) => String::new(),
+        Err(e) => return Err(e).context("Failed to read the choice"),
+    };
+    match choice.as_str() {
+        "t" | "truncate" => {
+            let question = truncate_question(text);
+            term::progress(&format!(
+                "Sending the first and last {} characters",
+                question.chars().count() - CUT_MARK.chars().count()
+            ));
+            Ok(Some(question))
+        }
+        "s" | "summarize" if can_summarize => {
+            term::progress("Summarizing the text...");
+            let response = client.chat(ChatRequest {
+                question: "Summarize the text above in one short paragraph. If it asks a question, \
+                           end with that question, word for word."
+                    .to_string(),
+                limit: 1,
+                document_slug: None,
+                document_slugs: Vec::new(),
+                model: None,
+                pinned_ids: Vec::new(),
+                exclude_documents: Vec::new(),
+                exclude_ids: Vec::new(),
+                context_budget: None,
+                user_context: Some(truncate_question(text)),
+            })?;
+            let summary = response.answer.trim().to_string();
+            if summary.is_empty() {
+                anyhow::bail!("The server returned an empty summary");
+            }
+            println!("{}: {}\n", "Summary".dimmed(), summary);
+            Ok(Some(summary))
+        }
+        _ => Ok(None),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cmd_ask(
+    client: &OsgeoClient,
+    question: String,
+    limit: i32,
+    document: Option<String>,
+    auto_scope: bool,
+    exclude_docs: Vec<String>,
+    context_budget: Option<i32>,
+    user_context: Option<String>,
+    citations_out: Option<std::path::PathBuf>,
+    sources_view: config::SourcesView,
+    json: bool,
+    answer_only: bool,
+) -> Result<()> {
+    if !json && !answer_only {
+        println!("{}: {}", "Question".dimmed(), question);
+    }
+
+    let document_slugs = if auto_scope {
+        let candidates = rank_documents(client, &question, &exclude_docs)?;
+        let chosen = auto_scope_documents(&candidates);
+        if !json && !answer_only {
+            print_scope(chosen);
+        }
+        chosen.iter().map(|d| d.slug.clone()).collect()
+    } else {
+        Vec::new()
+    };
+
+    let req = ChatRequest {
+        question: question.clone(),
+        limit,
+        document_slug: document,
+        document_slugs,
+        model: None,
+        pinned_ids: Vec::new(),
+        exclude_documents: exclude_docs,
+        exclude_ids: Vec::new(),
+        context_budget,
+        user_context,
+    };
+
+    if json {
+        let response = client.chat(req)?;
+        project::record_answer(client, "question", &question, &response.answer, &response.sources);
+        print_json(&response)?;
+        if let Some(path) = citations_out {
+            write_citations(&path, &question, &response)?;
+        }
+        return Ok(());
+    }
+
+    if answer_only {
+        let response = client.chat(req)?;
+        project::record_answer(client, "question", &question, &response.answer, &response.sources);
+        println!("{}", response.answer.trim_end());
+        // On stderr, so `$(osgeo-library ask --answer-only ...)` gets the answer alone
+        if sources_view != config::SourcesView::Off && !response.sources.is_empty() {
+            eprint!("{}", format_answer_sources(&response.sources, sources_view, 0));
+        }
+        if let Some(path) = citations_out {
+            write_citations(&path, &question, &response)?;
+        }
+        return Ok(());
+    }
+
+    term::progress("Thinking...");
+
+    let response = match chat_and_print(client, req, None) {
+        Err(e) if e.is::<cancel::Cancelled>() => cancel::exit(),
+        result => result?,
+    };
+    project::record_answer(client, "question", &question, &response.answer, &response.sources);
+
+    if !response.sources.is_empty() {
+        if sources_view == config::SourcesView::Off {
+            let elem_count = response
+                .sources
+                .iter()
+                .filter(|s| s.source_type == "element")
+                .count();
+            term::hint(&format!(
+                "({} sources, {} elements - use --sources compact or full to list them)",
+                response.sources.len(),
+                elem_count
+            ));
+        } else {
+            print_answer_sources(&response.sources, sources_view, 0);
+            println!();
+        }
+    }
+
+    if let Some(usage) = &response.context {
+        print_context_usage(usage);
+    }
+
+    if let Some(path) = citations_out {
+        write_citations(&path, &question, &response)?;
+    }
+
+    Ok(())
+}
+
+/// Report the documents chosen by --auto-scope.
+fn print_scope(chosen: &[DocumentCandidate]) {
+    if chosen.is_empty() {
+        println!("{}", "Scope: no matching documents, searching the whole library".dimmed());
+    } else {
+        let names: Vec<String> = chosen
+            .iter()
+            .map(|d| format!("{} ({})", d.slug, d.title))
+            .collect();
+        println!("{}: {}", "Scope".dimmed(), names.join(", "));
+    }
+}
+
+/// Runtime for the concurrent requests of a command. The blocking client
+/// must not be used inside it.
+fn runtime() -> Result<tokio::runtime::Runtime> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to start the async runtime")
+}
+
+/// Ask the question of each of the top `top_docs` documents concurrently,
+/// then have the server merge the answers with per-document attribution.
+fn cmd_ask_synthesis(
+    client: &OsgeoClient,
+    question: String,
+    limit: i32,
+    top_docs: usize,
+    exclude_docs: Vec<String>,
+    context_budget: Option<i32>,
+    user_context: Option<String>,
+) -> Result<()> {
+    // Before asking each document, which takes a while
+    capabilities::require(client, Feature::Synthesize)?;
+    println!("{}: {}", "Question".dimmed(), question);
+
+    let mut candidates = rank_documents(client, &question, &exclude_docs)?;
+    candidates.truncate(top_docs);
+    if candidates.is_empty() {
+        anyhow::bail!("No documents match the question");
+    }
+    let slugs: Vec<&str> = candidates.iter().map(|d| d.slug.as_str()).collect();
+    println!("{}: {}", "Documents".dimmed(), slugs.join(", "));
+    term::progress(&format!("Asking {} documents...", candidates.len()));
+
+    let async_client = AsyncOsgeoClient::with_policy(client.base_url(), client.policy())?.with_mirrors(&client.mirrors());
+    let replies: Vec<Result<ChatResponse>> = runtime()?.block_on(async {
+        let tasks: Vec<_> = candidates
+            .iter()
+            .map(|candidate| {
+                let client = async_client.clone();
+                let req = ChatRequest {
+                    question: question.clone(),
+                    limit,
+                    document_slug: Some(candidate.slug.clone()),
+                    document_slugs: Vec::new(),
+                    model: None,
+                    pinned_ids: Vec::new(),
+                    exclude_documents: Vec::new(),
+                    exclude_ids: Vec::new(),
+                    context_budget,
+                    user_context: user_context.clone(),
+                };
+                tokio::spawn(async move { client.chat(req).await })
+            })
+            .collect();
+        let mut replies = Vec::new();
+        for task in tasks {
+            replies.push(task.await.unwrap_or_else(|_| Err(anyhow::anyhow!("request task panicked"))));
+        }
+        replies
+    });
+
+    let mut answers = Vec::new();
+    for (candidate, reply) in candidates.iter().zip(replies) {
+        println!("\n{} {}", candidate.slug.bold(), format!("({})", candidate.title).dimmed());
+        match reply {
+            // The server falls back to the whole library when a document has no
+            // matches; such an answer isn't attributable to this document
+            Ok(response) if !response.sources.iter().any(|s| s.document_slug == candidate.slug) => {
+                println!("{}", "No relevant passages in this document.".dimmed());
+            }
+            Ok(response) => {
+                println!("{}", response.answer);
+                project::record_answer(client, "question", &question, &response.answer, &response.sources);
+                answers.push(DocumentAnswer {
+                    document_slug: candidate.slug.clone(),
+                    document_title: candidate.title.clone(),
+                    answer: response.answer,
+                });
+            }
+            Err(e) => println!("{}", format!("Failed: {:#}", e).yellow()),
+        }
+    }
+
+    match answers.len() {
+        0 => anyhow::bail!("None of the documents produced an answer"),
+        1 => println!("\n{}\n", "Only one document answered; nothing to synthesize.".dimmed()),
+        n => {
+            term::progress(&format!("\nSynthesizing {} answers...", n));
+            let synthesis = client.synthesize(SynthesisRequest { question, answers })?;
+            println!("\n{}\n\n{}\n", "Synthesis".bold(), synthesis.answer);
+        }
+    }
+    Ok(())
+}
+
+/// What the thread running a chat request sends back.
+enum ChatReply {
+    Text(String),
+    /// The response, whether it was streamed, and whether the server was reached
+    Done(Result<(ChatResponse, bool)>, bool),
+}
+
+/// Send a chat request and print the answer, after `header` if given.
+///
+/// In a rich terminal the answer is streamed and rendered as Markdown while
+/// it is generated. Plain output, or a server without `/chat/stream`, gets
+/// the answer as received once it is complete. The request runs on its own
+/// thread so Ctrl-C can abandon it at any point, even before the first
+/// token; that prints what arrived so far and returns `cancel::Cancelled`.
+fn chat_and_print(client: &OsgeoClient, req: ChatRequest, header: Option<&str>) -> Result<ChatResponse> {
+    let stream = !term::env().plain && capabilities::of(client).supports(Feature::ChatStream);
+    let (sender, replies) = std::sync::mpsc::channel();
+    let worker = client.clone();
+    std::thread::spawn(move || {
+        let result = (|| {
+            if stream {
+                // Sending fails once the answer is cancelled, which ends the stream
+                let streamed = worker.chat_stream(&req, |text| {
+                    sender.send(ChatReply::Text(text.to_string())).map_err(|_| cancel::Cancelled.into())
+                })?;
+                if let Some(response) = streamed {
+                    return Ok((response, true));
+                }
+            }
+            Ok((worker.chat(req)?, false))
+        })();
+        sender.send(ChatReply::Done(result, worker.is_reachable())).ok();
+    });
+
+    let armed = cancel::arm();
+    let height = split::conversation_rows().unwrap_or_else(term::height);
+    let mut live: Option<live::LiveMarkdown> = None;
+    loop {
+        match replies.recv_timeout(std::time::Duration::from_millis(50)) {
+            Ok(ChatReply::Text(text)) => {
+                let live = live.get_or_insert_with(|| {
+                    match header {
+                        Some(h) => println!("\n{}", h),
+                        None => println!(),
+                    }
+                    live::LiveMarkdown::new(term::width(), height)
+                });
+                live.push(&text).context("Failed to write answer")?;
+            }
+            Ok(ChatReply::Done(result, reachable)) => {
+                client.set_reachable(reachable);
+                let (response, streamed) = result?;
+                match live {
+                    Some(live) => {
+                        live.finish().context("Failed to write answer")?;
+                        println!();
+                    }
+                    // The model sent nothing
+                    None if streamed => println!("\n{}\n", header.unwrap_or_default()),
+                    None => match header {
+                        Some(h) => println!("\n{} {}\n", h, response.answer),
+                        None => println!("\n{}\n", response.answer),
+                    },
+                }
+                return Ok(response);
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) if armed.interrupted() => {
+                if let Some(live) = live {
+                    live.finish().context("Failed to write answer")?;
+                }
+                println!("\n{}\n", "Cancelled.".dimmed());
+                return Err(cancel::Cancelled.into());
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => anyhow::bail!("Chat request thread panicked"),
+        }
+    }
+}
+
+/// A document's share of the results of a routing search.
+struct DocumentCandidate {
+    slug: String,
+    title: String,
+    /// Sum of the document's result scores
+    score: f64,
+}
+
+/// Run a quick search (no LLM) for `question` and rank documents by the
+/// total score of their results, best first.
+fn rank_documents(client: &OsgeoClient, question: &str, exclude_docs: &[String]) -> Result<Vec<DocumentCandidate>> {
+    term::progress("Choosing documents...");
+    let response = client.search(SearchRequest {
+        query: question.to_string(),
+        limit: 20,
+        document_slug: None,
+        include_chunks: true,
+        include_elements: true,
+        element_type: None,
+        labels_only: false,
+        exclude_documents: exclude_docs.to_vec(),
+        exclude_ids: Vec::new(),
+        min_score: None,
+    })?;
+
+    let mut candidates: Vec<DocumentCandidate> = Vec::new();
+    for result in &response.results {
+        match candidates.iter_mut().find(|c| c.slug == result.document_slug) {
+            Some(candidate) => candidate.score += result.score_pct,
+            None => candidates.push(DocumentCandidate {
+                slug: result.document_slug.clone(),
+                title: result.document_title.clone(),
+                score: result.score_pct,
+            }),
+        }
+    }
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Ok(candidates)
+}
+
+/// Section `number` of `slug` ("4.2", or "4" for chapter 4).
+fn find_section(client: &OsgeoClient, slug: &str, number: &str) -> Result<Option<SectionItem>> {
+    let number = number.trim_end_matches('.');
+    let response = client.list_sections(slug)?;
+    Ok(response.sections.into_iter().find(|s| s.number == number))
+}
+
+/// One line of the `sections` listing, indented by level.
+fn format_section(section: &SectionItem) -> String {
+    let pages = if section.start_page == section.end_page {
+        format!("p.{}", section.start_page)
+    } else {
+        format!("pp.{}-{}", section.start_page, section.end_page)
+    };
+    let heading = match &section.title {
+        Some(title) => format!("{} {}", section.number, title),
+        None => section.number.clone(),
+    };
+    format!("{}{}  {}", "  ".repeat((section.level.max(1) - 1) as usize), heading, pages.dimmed())
+}
+
+/// Page of `slug` whose content best matches `query` (that of the top search
+/// result), and the other matching pages in order of their best result.
+fn find_page(client: &OsgeoClient, slug: &str, query: &str) -> Result<Option<(i32, Vec<i32>)>> {
+    term::progress(&format!("Searching {}...", slug));
+    let response = client.search(SearchRequest {
+        query: query.to_string(),
+        limit: 20,
+        document_slug: Some(slug.to_string()),
+        include_chunks: true,
+        include_elements: true,
+        element_type: None,
+        labels_only: false,
+        exclude_documents: Vec::new(),
+        exclude_ids: Vec::new(),
+        min_score: None,
+    })?;
+
+    let mut pages: Vec<i32> = Vec::new();
+    for result in &response.results {
+        if result.document_slug == slug && !pages.contains(&result.page_number) {
+            pages.push(result.page_number);
+        }
+    }
+    if pages.is_empty() {
+        return Ok(None);
+    }
+    let best = pages.remove(0);
+    pages.truncate(5);
+    Ok(Some((best, pages)))
+}
+
+/// The best document, plus the runner-up when it scores at least half as well.
+fn auto_scope_documents(candidates: &[DocumentCandidate]) -> &[DocumentCandidate] {
+    match candidates {
+        [first, second, ..] if second.score >= first.score / 2.0 => &candidates[..2],
+        [] => candidates,
+        _ => &candidates[..1],
+    }
+}
+
+/// Print how much of the model's window the context used, with warnings
+/// when sources were dropped or cut short.
+fn print_context_usage(usage: &ContextUsage) {
+    let pct = usage.prompt_tokens as f64 * 100.0 / usage.context_window.max(1) as f64;
+    let mut line = format!(
+        "Context: ~{} tokens of sources, ~{} prompt / {} window ({:.0}%)",
+        usage.context_tokens, usage.prompt_tokens, usage.context_window, pct
+    );
+    if let Some(budget) = usage.budget {
+        line.push_str(&format!(", budget {}", budget));
+    }
+    println!("{}", line.dimmed());
+
+    if usage.dropped_sources > 0 {
+        term::warning(&format!(
+            "{} lower-ranked source(s) dropped to fit the budget; the answer may miss what they covered",
+            usage.dropped_sources
+        ));
+    }
+    if usage.prompt_tokens > usage.context_window {
+        term::warning("prompt exceeds the model's context window; the model likely truncated it and the answer may be degraded");
+    }
+    if usage.truncated_sources > 0 {
+        term::hint(&format!(
+            "Note: {} source(s) were cut to a short preview in the context",
+            usage.truncated_sources
+        ));
+    }
+    println!();
+}
+
+/// A cited source, as written by `ask --citations-out`.
+#[derive(Serialize)]
+struct CitationRecord<'a> {
+    /// Citation number used in the answer ([1] -> 1)
+    number: usize,
+    document_slug: &'a str,
+    document_title: &'a str,
+    page: i32,
+    source_type: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    element_type: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    element_label: Option<&'a str>,
+    snippet: String,
+}
+
+#[derive(Serialize)]
+struct CitationsFile<'a> {
+    question: &'a str,
+    answer: &'a str,
+    citations: Vec<CitationRecord<'a>>,
+}
+
+/// Write the sources cited in an answer to `path` as JSON.
+fn write_citations(path: &std::path::Path, question: &str, response: &ChatResponse) -> Result<()> {
+    let path = paths::resolve(path)?;
+    let mut citations = Vec::new();
+    for number in citations::cited_numbers(&response.answer) {
+        let Some(source) = response.sources.get(number - 1) else {
+            term::warning(&format!("answer cites [{}] but only {} sources were returned", number, response.sources.len()));
+            continue;
+        };
+        citations.push(CitationRecord {
+            number,
+            document_slug: &source.document_slug,
+            document_title: &source.document_title,
+            page: source.page_number,
+            source_type: &source.source_type,
+            element_type: source.element_type.as_deref(),
+            element_label: source.element_label.as_deref(),
+            snippet: records::snippet(&source.content),
+        });
+    }
+
+    let count = citations.len();
+    let json = serde_json::to_string_pretty(&CitationsFile {
+        question,
+        answer: &response.answer,
+        citations,
+    })?;
+    std::fs::write(&path, redact::text(&json) + "\n").with_context(|| format!("Failed to write {}", path.display()))?;
+    println!("Wrote {} citation(s) to {}", count, path.display());
+    Ok(())
+}
+
+fn cmd_verify(client: &OsgeoClient, statement: String, limit: i32, document: Option<String>) -> Result<()> {
+    capabilities::require(client, Feature::Verify)?;
+    println!("{}: {}", "Statement".dimmed(), statement);
+    term::progress("Checking against the library...");
+
+    let response = client.verify(VerifyRequest {
+        statement: statement.clone(),
+        limit,
+        document_slug: document,
+    })?;
+    print_verdict(&response);
+    let verdict = format!("Verdict: {}\n\n{}", response.verdict, response.explanation);
+    project::record_answer(client, "verify", &statement, &verdict, &response.sources);
+    Ok(())
+}
+
+/// How the exports go about downloading.
+#[derive(Clone, Copy)]
+struct ExportOptions {
+    /// Only list what would be downloaded and written
+    dry_run: bool,
+    /// Keep images downloaded by an interrupted run
+    resume: bool,
+    /// Check kept images against the server and new ones once written
+    verify: bool,
+}
+
+/// Export a chapter (or page range) as Markdown notes with downloaded images.
+#[allow(clippy::too_many_arguments)]
+fn cmd_export_notes(
+    client: &OsgeoClient,
+    slug: &str,
+    chapter: Option<i32>,
+    pages: Option<(i32, i32)>,
+    output: Option<std::path::PathBuf>,
+    all_equations: bool,
+    options: ExportOptions,
+    archive: Option<evidence::Request>,
+) -> Result<()> {
+    let ExportOptions { dry_run, resume, .. } = options;
+    let doc = client.get_document(slug)?;
+
+    let (title, start, end, label) = match (chapter, pages) {
+        (Some(number), _) => {
+            let chapters = client.list_chapters(slug)?.chapters;
+            let Some(found) = chapters.iter().find(|c| c.number == number) else {
+                if chapters.is_empty() {
+                    anyhow::bail!(
+                        "No chapter headings found in '{}'; use --pages FROM-TO instead",
+                        slug
+                    );
+                }
+                let available: Vec<String> = chapters
+                    .iter()
+                    .map(|c| format!("{} (pages {}-{})", c.number, c.start_page, c.end_page))
+                    .collect();
+                anyhow::bail!("Chapter {} not found. Available: {}", number, available.join(", "));
+            };
+            let title = match &found.title {
+                Some(t) => format!("Chapter {}: {}", number, t),
+                None => format!("Chapter {}", number),
+            };
+            (title, found.start_page, found.end_page, Some(format!("chapter-{}", number)))
+        }
+        (None, Some((start, end))) => {
+            if end > doc.total_pages {
+                anyhow::bail!("Page {} out of range (document has {} pages)", end, doc.total_pages);
+            }
+            (doc.title.clone(), start, end, None)
+        }
+        (None, None) => anyhow::bail!("Give --chapter N or --pages FROM-TO"),
+    };
+
+    let dir = match output {
+        Some(dir) => paths::resolve(&dir)?,
+        None => {
+            let pages = if start == end { start.to_string() } else { format!("{}-{}", start, end) };
+            let export = paths::Export {
+                slug: Some(slug),
+                page: Some(pages),
+                kind: "notes",
+                label: label.as_deref(),
+            };
+            paths::default(&export, "")?
+        }
+    };
+    let images_dir = dir.join("figures");
+    if !dry_run {
+        std::fs::create_dir_all(&images_dir)
+            .with_context(|| format!("Failed to create {}", images_dir.display()))?;
+    }
+
+    let mut plan = plan::Plan::default();
+    let mut manifest = resume::Manifest::open(&dir, resume)?;
+    let mut tally = ExportTally::default();
+    let mut note_pages = Vec::new();
+    let mut image_count = 0;
+    let mut image_names = std::collections::HashSet::new();
+    let mut record = archive.as_ref().map(|_| evidence::Manifest::new(client, "notes"));
+
+    // Summaries only, without the page images: pages are numbered from 1,
+    // so page N is the Nth in the listing
+    let mut summaries = std::collections::HashMap::new();
+    let mut offset = start - 1;
+    loop {
+        let batch = client.list_page_summaries(slug, (end - offset).clamp(1, 500), offset)?;
+        let fetched = batch.pages.len() as i32;
+        let last = batch.pages.last().map_or(end, |p| p.page_number);
+        summaries.extend(batch.pages.into_iter().map(|p| (p.page_number, p)));
+        offset += fetched;
+        if fetched == 0 || last >= end || offset >= batch.total {
+            break;
+        }
+    }
+
+    for number in start..=end {
+        term::progress_inline(&format!("\rExporting page {} ({}-{})...", number, start, end));
+        let page = summaries.remove(&number);
+        let listing = client.list_elements(slug, None, Some(number), 100, 0)?;
+
+        let mut elements = Vec::new();
+        for element in listing.elements {
+            let is_equation = element.element_type == "equation";
+            if is_equation && element.label.is_none() && !all_equations {
+                continue;
+            }
+            // Equations with LaTeX are written as math; everything else needs its image
+            let source = if is_equation && element.latex.is_some() {
+                None
+            } else if is_equation {
+                element.rendered_path.as_deref().or(element.crop_path.as_deref())
+            } else {
+                element.crop_path.as_deref()
+            };
+            let mut image = None;
+            if let Some(source) = source {
+                let export = paths::Export {
+                    slug: Some(slug),
+                    page: Some(number.to_string()),
+                    kind: &element.element_type,
+                    label: element.label.as_deref(),
+                };
+                let name = notes::unique_image_name(&paths::name(&export), source, &mut image_names);
+                let url = client.image_url(slug, source);
+                let file = format!("figures/{}", name);
+                let target = images_dir.join(&name).display().to_string();
+                match export_image(client, &url, source, &file, &mut manifest, options, &mut tally)? {
+                    ExportImage::Failed(e) => {
+                        term::progress_done("");
+                        term::warning(&format!("{} on page {}: {}", source, number, e));
+                    }
+                    fetched => {
+                        match fetched {
+                            ExportImage::Kept(_) if dry_run => {
+                                plan.add(plan::Action::Skip, format!("{} (already downloaded)", target), None)
+                            }
+                            ExportImage::Sized(size) => plan.add(plan::Action::Download, target, size),
+                            _ => {}
+                        }
+                        image = Some(file);
+                        image_count += 1;
+                    }
+                }
+            }
+            if let Some(record) = &mut record {
+                record.results.push(evidence::Item::element(slug, &element));
+            }
+            elements.push(notes::Element {
+                element_type: element.element_type,
+                label: element.label,
+                description: element.description,
+                latex: element.latex,
+                image,
+            });
+        }
+
+        let (summary, keywords) = page.map_or((None, None), |p| (p.summary, p.keywords));
+        note_pages.push(notes::Page {
+            number,
+            summary,
+            keywords: keywords.unwrap_or_default(),
+            elements,
+        });
+    }
+    term::progress_done(" done\n");
+
+    let heading = notes::Heading {
+        title,
+        document_title: doc.title,
+        slug: slug.to_string(),
+        start_page: start,
+        end_page: end,
+    };
+    let path = dir.join("notes.md");
+    let markdown = notes::render(&heading, &note_pages);
+    if dry_run {
+        plan.add(plan::Action::Write, path.display().to_string(), Some(markdown.len() as u64));
+        print!("{}", plan.render());
+        return Ok(());
+    }
+    std::fs::write(&path, redact::text(&markdown)).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let element_count: usize = note_pages.iter().map(|p| p.elements.len()).sum();
+    println!(
+        "Wrote {} ({} pages, {} elements, {} images{})",
+        path.display(),
+        note_pages.len(),
+        element_count,
+        image_count,
+        tally.note()
+    );
+    tally.print_hint();
+
+    if let (Some(mut record), Some(request)) = (record, archive) {
+        record.document = Some(slug.to_string());
+        record.pages = Some(format!("{}-{}", start, end));
+        record.add_file(&dir, &path)?;
+        let images = note_pages.iter().flat_map(|p| &p.elements).filter_map(|e| e.image.as_ref());
+        for image in images {
+            record.add_file(&dir, &dir.join(image))?;
+        }
+        record.write(&dir.join("manifest.json"), &request)?;
+    }
+    Ok(())
+}
+
+/// An export's image, from `export_image`.
+enum ExportImage {
+    /// Downloaded by an earlier run (`--resume`)
+    Kept(Vec<u8>),
+    /// Downloaded now and recorded in the manifest
+    Downloaded(Vec<u8>),
+    /// Not downloaded, in a dry run: its size, when the server gives it
+    Sized(Option<u64>),
+    /// Could not be downloaded; the export goes on without it
+    Failed(anyhow::Error),
+}
+
+/// Images kept, replaced and missed by an export.
+#[derive(Default)]
+struct ExportTally {
+    kept: usize,
+    /// Kept files that `--verify` found differ from the server's
+    replaced: usize,
+    failed: usize,
+}
+
+impl ExportTally {
+    /// ", N kept from the last run, M replaced" for the export's summary.
+    fn note(&self) -> String {
+        let mut note = String::new();
+        if self.kept > 0 {
+            note.push_str(&format!(", {} kept from the last run", self.kept));
+        }
+        if self.replaced > 0 {
+            note.push_str(&format!(", {} damaged or outdated replaced", self.replaced));
+        }
+        note
+    }
+
+    /// After an export, how to fetch the images that failed to download.
+    fn print_hint(&self) {
+        if self.failed > 0 {
+            term::hint(&format!(
+                "{} image(s) could not be downloaded; run the same command with --resume to fetch only those.",
+                self.failed
+            ));
+        }
+    }
+}
+
+/// Image `name` (relative to the manifest's directory) for an export, from
+/// `source` on the server at `url`: kept from an earlier run when the
+/// manifest has it intact, and with --verify the server's checksum agrees;
+/// otherwise downloaded and recorded, or in a dry run only sized. Download
+/// failures are returned as `Failed`; failing to write is an error.
+fn export_image(
+    client: &OsgeoClient,
+    url: &str,
+    source: &str,
+    name: &str,
+    manifest: &mut resume::Manifest,
+    options: ExportOptions,
+    tally: &mut ExportTally,
+) -> Result<ExportImage> {
+    if let Some(bytes) = kept_image(client, url, source, name, manifest, options, tally) {
+        return Ok(ExportImage::Kept(bytes));
+    }
+    if options.dry_run {
+        // A dry run only asks for the size
+        return Ok(match client.image_info(url) {
+            Ok(remote) => ExportImage::Sized(remote.size),
+            Err(e) => ExportImage::Failed(e),
+        });
+    }
+    let bytes = match client.fetch_image(url) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tally.failed += 1;
+            return Ok(ExportImage::Failed(e));
+        }
+    };
+    manifest.save(name, source, &bytes)?;
+    // Read back what was written, to catch a failing disk
+    if options.verify && manifest.completed(name, source).is_none() {
+        anyhow::bail!("{} reads back differently from what was written", name);
+    }
+    Ok(ExportImage::Downloaded(bytes))
+}
+
+/// Image `name` as an earlier run left it, when the manifest has it intact
+/// from `source` and with --verify the server's checksum agrees.
+fn kept_image(
+    client: &OsgeoClient,
+    url: &str,
+    source: &str,
+    name: &str,
+    manifest: &resume::Manifest,
+    options: ExportOptions,
+    tally: &mut ExportTally,
+) -> Option<Vec<u8>> {
+    let bytes = manifest.completed(name, source)?;
+    if !options.verify || matches_server(client, url, &bytes) {
+        tally.kept += 1;
+        return Some(bytes);
+    }
+    if !options.dry_run {
+        tally.replaced += 1;
+    }
+    None
+}
+
+/// Whether `kept`, a file downloaded earlier from `url`, matches the
+/// server's copy: by checksum, or by size from servers without checksums.
+/// Taken as not matching when the server can't be asked.
+fn matches_server(client: &OsgeoClient, url: &str, kept: &[u8]) -> bool {
+    match client.image_info(url) {
+        Ok(remote) => match (remote.sha256, remote.size) {
+            (Some(sha256), _) => sha256 == digest::sha256(kept),
+            (None, Some(size)) => size == kept.len() as u64,
+            (None, None) => true,
+        },
+        Err(_) => false,
+    }
+}
+
+/// `manifest.json` of `export elements`.
+#[derive(Serialize)]
+struct ElementsManifest {
+    document: String,
+    title: String,
+    server: String,
+    /// "YYYY-MM-DDThh:mm:ssZ"
+    exported: String,
+    /// The --type asked for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    element_type: Option<String>,
+    elements: Vec<ExportedElement>,
+}
+
+/// An element whose image `export elements` wrote.
+#[derive(Serialize)]
+struct ExportedElement {
+    /// Relative to the manifest
+    file: String,
+    /// "element:12"
+    id: String,
+    element_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    page: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    caption: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latex: Option<String>,
+}
+
+/// Archiving manifest of `export elements --manifest`, beside its own
+/// manifest.json
+const ARCHIVE_MANIFEST: &str = "manifest.archive.json";
+
+/// Download the image of every element of `slug` (of `element_type`, when
+/// given) into `dir`, `jobs` at a time, named from their labels, then write
+/// `manifest.json` listing what each file shows.
+#[allow(clippy::too_many_arguments)]
+fn cmd_export_elements(
+    client: &OsgeoClient,
+    slug: &str,
+    element_type: Option<&str>,
+    dir: &std::path::Path,
+    jobs: usize,
+    options: ExportOptions,
+    archive: Option<evidence::Request>,
+) -> Result<()> {
+    let ExportOptions { dry_run, resume, verify } = options;
+    let doc = client.get_document(slug)?;
+    term::progress(&format!("Listing the elements of {}...", slug));
+    let mut elements = Vec::new();
+    loop {
+        let batch = client.list_elements(slug, element_type, None, 100, elements.len() as i32)?;
+        let fetched = batch.elements.len();
+        elements.extend(batch.elements);
+        if fetched == 0 || elements.len() as i32 >= batch.total {
+            break;
+        }
+    }
+
+    let mut plan = plan::Plan::default();
+    let manifest = resume::Manifest::open(dir, resume)?;
+    let mut tally = ExportTally::default();
+    let mut record = archive.as_ref().map(|_| evidence::Manifest::new(client, "elements"));
+    // Named in page order, so the same export names files the same way
+    let mut names = std::collections::HashSet::new();
+    let mut without_image = 0;
+    let mut done = Vec::new();
+    let mut queue = std::collections::VecDeque::new();
+    for element in elements {
+        let source = if element.element_type == "equation" {
+            element.rendered_path.clone().or(element.crop_path.clone())
+        } else {
+            element.crop_path.clone()
+        };
+        let Some(source) = source else {
+            without_image += 1;
+            continue;
+        };
+        let stem = match element.label.as_deref().map(paths::file_safe).filter(|l| !l.is_empty()) {
+            Some(label) => label,
+            None => format!("{}-p{}-{}", element.element_type, element.page_number, element.id),
+        };
+        let name = notes::unique_image_name(&stem, &source, &mut names);
+        let url = client.image_url(slug, &source);
+        let target = dir.join(&name).display().to_string();
+        if kept_image(client, &url, &source, &name, &manifest, options, &mut tally).is_some() {
+            if dry_run {
+                plan.add(plan::Action::Skip, format!("{} (already downloaded)", target), None);
+            }
+            done.push((name, element));
+        } else if dry_run {
+            match client.image_info(&url) {
+                Ok(remote) => plan.add(plan::Action::Download, target, remote.size),
+                Err(e) => term::warning(&format!("{} on page {}: {:#}", source, element.page_number, e)),
+            }
+            done.push((name, element));
+        } else {
+            queue.push_back((name, source, url, element));
+        }
+    }
+    if queue.is_empty() && done.is_empty() {
+        let what = element_type.map(|t| format!("{} ", t)).unwrap_or_default();
+        anyhow::bail!("No {}element images in '{}'", what, slug);
+    }
+    if dry_run {
+        plan.add(plan::Action::Write, dir.join("manifest.json").display().to_string(), None);
+        print!("{}", plan.render());
+        return Ok(());
+    }
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let total = queue.len();
+    let queue = std::sync::Arc::new(std::sync::Mutex::new(queue));
+    // Shared, so each image is recorded for --resume as soon as it is written
+    let manifest = std::sync::Arc::new(std::sync::Mutex::new(manifest));
+    let count = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let async_client = AsyncOsgeoClient::with_policy(client.base_url(), client.policy())?.with_mirrors(&client.mirrors());
+    let results: Vec<(String, ElementListItem, Result<()>)> = runtime()?.block_on(async {
+        let workers: Vec<_> = (0..jobs.min(total))
+            .map(|_| {
+                let client = async_client.clone();
+                let (queue, manifest, count) = (queue.clone(), manifest.clone(), count.clone());
+                tokio::spawn(async move {
+                    let mut results = Vec::new();
+                    loop {
+                        let next = queue.lock().unwrap_or_else(|e| e.into_inner()).pop_front();
+                        let Some((name, source, url, element)) = next else {
+                            break;
+                        };
+                        let written = client.fetch_image(&url).await.and_then(|bytes| {
+                            let mut manifest = manifest.lock().unwrap_or_else(|e| e.into_inner());
+                            manifest.save(&name, &source, &bytes)?;
+                            // Read back what was written, to catch a failing disk
+                            if verify && manifest.completed(&name, &source).is_none() {
+                                anyhow::bail!("{} reads back differently from what was written", name);
+                            }
+                            Ok(())
+                        });
+                        let n = count.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+                        term::progress_inline(&format!("\rDownloading images ({}/{})...", n, total));
+                        results.push((name, element, written));
+                    }
+                    results
+                })
+            })
+            .collect();
+        let mut results = Vec::new();
+        for worker in workers {
+            // A worker that panicked leaves its images out; they are counted as missing
+            results.extend(worker.await.unwrap_or_default());
+        }
+        results
+    });
+    if total > 0 {
+        term::progress_done(" done");
+    }
+
+    tally.failed = total - results.len();
+    for (name, element, written) in results {
+        match written {
+            Ok(()) => done.push((name, element)),
+            Err(e) => {
+                term::warning(&format!("{} on page {}: {:#}", name, element.page_number, e));
+                tally.failed += 1;
+            }
+        }
+    }
+    let mut exported: Vec<ExportedElement> = done
+        .into_iter()
+        .map(|(name, element)| {
+            if let Some(record) = &mut record {
+                record.results.push(evidence::Item::element(slug, &element));
+            }
+            ExportedElement {
+                file: name,
+                id: format!("element:{}", element.id),
+                element_type: element.element_type,
+                label: element.label,
+                page: element.page_number,
+                caption: element.description,
+                latex: element.latex,
+            }
+        })
+        .collect();
+    exported.sort_by(|a, b| (a.page, &a.file).cmp(&(b.page, &b.file)));
+
+    let manifest = ElementsManifest {
+        document: slug.to_string(),
+        title: doc.title,
+        server: client.base_url().to_string(),
+        exported: crate::epub::utc_timestamp(),
+        element_type: element_type.map(str::to_string),
+        elements: exported,
+    };
+    let path = dir.join("manifest.json");
+    let json = serde_json::to_string_pretty(&manifest).context("Failed to encode manifest")?;
+    std::fs::write(&path, redact::text(&json) + "\n").with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let mut note = tally.note();
+    if without_image > 0 {
+        note.push_str(&format!(", {} without an image skipped", without_image));
+    }
+    println!(
+        "Wrote {} images and {} to {}{}",
+        manifest.elements.len(),
+        path.file_name().unwrap_or_default().to_string_lossy(),
+        dir.display(),
+        note
+    );
+    tally.print_hint();
+
+    if let (Some(mut record), Some(request)) = (record, archive) {
+        record.document = Some(slug.to_string());
+        record.add_file(dir, &path)?;
+        for element in &manifest.elements {
+            record.add_file(dir, &dir.join(&element.file))?;
+        }
+        record.write(&dir.join(ARCHIVE_MANIFEST), &request)?;
+    }
+    Ok(())
+}
+
+/// Download pages `from`-`to` of `slug` into `path`: a PDF when it ends in
+/// ".pdf", otherwise a directory of page images named by page number. Page
+/// images are recorded for --resume where they are written: in the
+/// directory, or for a PDF in "<path>.partial" until the PDF is written.
+fn cmd_export_pages(
+    client: &OsgeoClient,
+    slug: &str,
+    (from, to): (i32, i32),
+    path: &std::path::Path,
+    options: ExportOptions,
+    archive: Option<evidence::Request>,
+) -> Result<()> {
+    let ExportOptions { dry_run, resume, .. } = options;
+    let doc = client.get_document(slug)?;
+    if to > doc.total_pages {
+        anyhow::bail!("Page {} out of range (document has {} pages)", to, doc.total_pages);
+    }
+    let as_pdf = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("pdf"));
+    let dir = if as_pdf {
+        let mut partial = path.as_os_str().to_os_string();
+        partial.push(".partial");
+        std::path::PathBuf::from(partial)
+    } else {
+        path.to_path_buf()
+    };
+    if !dry_run {
+        std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let mut plan = plan::Plan::default();
+    let mut manifest = resume::Manifest::open(&dir, resume)?;
+    let mut tally = ExportTally::default();
+    let mut book = (as_pdf && !dry_run).then(|| report::PageBook::new(&format!("{}, pages {}-{}", doc.title, from, to)));
+    let mut files = Vec::new();
+    // Zero-padded so the files sort in page order
+    let digits = to.to_string().len();
+    let mut bytes = 0;
+    for number in from..=to {
+        let source = format!("page/{}", number);
+        let stem = format!("page-{:0width$}", number, width = digits);
+        // The extension follows the server's image type, known once downloaded
+        let kept = ["png", "jpg"].into_iter().find_map(|extension| {
+            let name = format!("{}.{}", stem, extension);
+            manifest.completed(&name, &source).map(|image| (name, image))
+        });
+        let (name, image) = match kept {
+            // Pages of a PDF are only held until it is written
+            Some(_) if dry_run && as_pdf => {
+                plan.add(plan::Action::Skip, format!("page {}, already downloaded", number), None);
+                continue;
+            }
+            None if dry_run && as_pdf => {
+                plan.add(plan::Action::Fetch, format!("page {}", number), None);
+                continue;
+            }
+            Some((name, _)) if dry_run => {
+                plan.add(plan::Action::Skip, format!("{} (already downloaded)", dir.join(name).display()), None);
+                continue;
+            }
+            None if dry_run => {
+                plan.add(plan::Action::Download, format!("{}.*", dir.join(&stem).display()), None);
+                continue;
+            }
+            Some(kept) => {
+                tally.kept += 1;
+                kept
+            }
+            None => {
+                term::progress_inline(&format!("\rDownloading page {} ({}-{})...", number, from, to));
+                match client.get_page(slug, number, None) {
+                    Ok(page) => {
+                        let extension = if page.mime_type == "image/jpeg" { "jpg" } else { "png" };
+                        let name = format!("{}.{}", stem, extension);
+                        manifest.save(&name, &source, &page.image)?;
+                        (name, page.image)
+                    }
+                    Err(e) => {
+                        term::progress_done("");
+                        term::warning(&format!("page {}: {:#}", number, e));
+                        tally.failed += 1;
+                        continue;
+                    }
+                }
+            }
+        };
+        bytes += image.len() as u64;
+        // One page held at a time: each is added to the PDF as it comes
+        if let Some(book) = &mut book {
+            book.add(&image).with_context(|| format!("Page {} can't be added", number))?;
+        }
+        files.push(dir.join(name));
+    }
+    if dry_run {
+        if as_pdf {
+            plan.add(plan::Action::Write, path.display().to_string(), None);
+        }
+        print!("{}", plan.render());
+        return Ok(());
+    }
+    term::progress_done(" done");
+    if let Some(book) = book {
+        if tally.failed > 0 {
+            tally.print_hint();
+            anyhow::bail!("{} not written: {} of its pages are missing", path.display(), tally.failed);
+        }
+        book.write(path)?;
+        std::fs::remove_dir_all(&dir).ok();
+    }
+    println!(
+        "Wrote {} ({} pages, {} of page images{})",
+        path.display(),
+        files.len(),
+        plan::format_size(bytes),
+        tally.note()
+    );
+    tally.print_hint();
+
+    if let Some(request) = archive {
+        let mut record = evidence::Manifest::new(client, "pages");
+        record.document = Some(slug.to_string());
+        record.pages = Some(format!("{}-{}", from, to));
+        if as_pdf {
+            let parent = path.parent().unwrap_or(std::path::Path::new(""));
+            record.add_file(parent, path)?;
+            record.write(&evidence::path_for(path), &request)?;
+        } else {
+            for file in &files {
+                record.add_file(path, file)?;
+            }
+            record.write(&path.join("manifest.json"), &request)?;
+        }
+    }
+    Ok(())
+}
+
+fn cmd_acronym(client: &OsgeoClient, acronym: &str, document: Option<String>, sources: i32) -> Result<()> {
+    capabilities::require(client, Feature::Acronym)?;
+    term::progress(&format!("Looking up {}...", acronym));
+    let response = client.acronym(acronym, document.as_deref(), sources)?;
+
+    if response.expansions.is_empty() {
+        println!(
+            "No definition of {} found ({} passages mention it).",
+            response.acronym.bold(),
+            response.chunks_scanned
+        );
+        term::hint(&format!("Try: osgeo-library ask \"What does {} stand for?\"", response.acronym));
+        return Ok(());
+    }
+
+    println!("\n{}\n", response.acronym.bold());
+    for (i, exp) in response.expansions.iter().enumerate() {
+        println!(
+            "{}. {}  {}",
+            i + 1,
+            exp.expansion.green().bold(),
+            format!(
+                "({} page{} in {} document{})",
+                exp.count,
+                if exp.count == 1 { "" } else { "s" },
+                exp.documents,
+                if exp.documents == 1 { "" } else { "s" }
+            )
+            .dimmed()
+        );
+        for source in &exp.sources {
+            println!(
+                "   {} {}",
+                format!("{} p.{}", source.document_slug, source.page_number).cyan(),
+                format!("\"…{}…\"", source.snippet).dimmed()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Export a whole document as an EPUB e-book.
+fn cmd_export_epub(
+    client: &OsgeoClient,
+    slug: &str,
+    path: &std::path::Path,
+    no_images: bool,
+    options: ExportOptions,
+    archive: Option<evidence::Request>,
+) -> Result<()> {
+    let ExportOptions { dry_run, resume, .. } = options;
+    let doc = client.get_document(slug)?;
+    let mut plan = plan::Plan::default();
+    let mut record = archive.as_ref().map(|_| evidence::Manifest::new(client, "doc"));
+
+    // Images are kept next to the book until it is written, for --resume
+    let mut partial = path.as_os_str().to_os_string();
+    partial.push(".partial");
+    let partial = std::path::PathBuf::from(partial);
+    if !dry_run && !no_images {
+        std::fs::create_dir_all(&partial).with_context(|| format!("Failed to create {}", partial.display()))?;
+    }
+    let mut manifest = resume::Manifest::open(&partial, resume)?;
+    let mut tally = ExportTally::default();
+
+    let mut summaries = Vec::new();
+    loop {
+        term::progress_inline(&format!("\rFetching page summaries ({})...", summaries.len()));
+        let batch = client.list_page_summaries(slug, 500, summaries.len() as i32)?;
+        let fetched = batch.pages.len();
+        summaries.extend(batch.pages);
+        if fetched == 0 || summaries.len() as i32 >= batch.total {
+            break;
+        }
+    }
+
+    let mut elements = Vec::new();
+    loop {
+        term::progress_inline(&format!("\rFetching elements ({})...          ", elements.len()));
+        let batch = client.list_elements(slug, None, None, 100, elements.len() as i32)?;
+        let fetched = batch.elements.len();
+        elements.extend(batch.elements);
+        if fetched == 0 || elements.len() as i32 >= batch.total {
+            break;
+        }
+    }
+    term::progress_done("");
+
+    let mut images = Vec::new();
+    let mut pages: Vec<notes::Page> = summaries
+        .into_iter()
+        .map(|p| notes::Page {
+            number: p.page_number,
+            summary: p.summary,
+            keywords: p.keywords.unwrap_or_default(),
+            elements: Vec::new(),
+        })
+        .collect();
+
+    let total = elements.len();
+    for (i, element) in elements.into_iter().enumerate() {
+        let is_equation = element.element_type == "equation";
+        // Unnumbered equations are mostly inline fragments
+        if is_equation && element.label.is_none() {
+            continue;
+        }
+        let Some(page) = pages.iter_mut().find(|p| p.number == element.page_number) else {
+            continue;
+        };
+        let source = if is_equation {
+            element.rendered_path.as_deref().or(element.crop_path.as_deref())
+        } else {
+            element.crop_path.as_deref()
+        };
+        let mut image = None;
+        if let (Some(source), false) = (source, no_images) {
+            let doing = if dry_run { "Sizing" } else { "Downloading" };
+            term::progress_inline(&format!("\r{} images ({}/{})...", doing, i + 1, total));
+            let name = notes::image_file_name(
+                element.page_number,
+                &element.element_type,
+                element.label.as_deref(),
+                element.id,
+                source,
+            );
+            let url = client.image_url(slug, source);
+            let what = format!("{} (page {})", source, element.page_number);
+            match export_image(client, &url, source, &name, &mut manifest, options, &mut tally)? {
+                ExportImage::Failed(e) => {
+                    term::progress_done("");
+                    term::warning(&format!("{} on page {}: {}", source, element.page_number, e));
+                }
+                fetched => {
+                    image = Some(format!("images/{}", name));
+                    match fetched {
+                        ExportImage::Kept(_) if dry_run => {
+                            plan.add(plan::Action::Skip, format!("{}, already downloaded", what), None)
+                        }
+                        ExportImage::Sized(size) => plan.add(plan::Action::Fetch, what, size),
+                        ExportImage::Kept(bytes) | ExportImage::Downloaded(bytes) => {
+                            images.push(epub::Image { name, bytes })
+                        }
+                        ExportImage::Failed(_) => {}
+                    }
+                }
+            }
+        }
+        if let Some(record) = &mut record {
+            record.results.push(evidence::Item::element(slug, &element));
+        }
+        page.elements.push(notes::Element {
+            element_type: element.element_type,
+            label: element.label,
+            description: element.description,
+            latex: element.latex,
+            image,
+        });
+    }
+    if !no_images {
+        term::progress_done(" done");
+    }
+    if dry_run {
+        // The e-book's size depends on how well it compresses
+        plan.add(plan::Action::Write, path.display().to_string(), None);
+        print!("{}", plan.render());
+        return Ok(());
+    }
+
+    let chapters = client.list_chapters(slug)?.chapters;
+    let book = epub::Book {
+        title: doc.title,
+        slug: slug.to_string(),
+        summary: doc.summary,
+        keywords: doc.keywords.unwrap_or_default(),
+        license: doc.license,
+        sections: epub_sections(pages, &chapters),
+    };
+    epub::write(path, &book, &images)?;
+    // Kept while images are missing, so --resume fetches only those
+    if tally.failed == 0 {
+        std::fs::remove_dir_all(&partial).ok();
+    }
+
+    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    println!(
+        "Wrote {} ({} sections, {} images{}, {:.1} MB)",
+        path.display(),
+        book.sections.len(),
+        images.len(),
+        tally.note(),
+        size as f64 / 1_048_576.0
+    );
+    tally.print_hint();
+
+    if let (Some(mut record), Some(request)) = (record, archive) {
+        record.document = Some(slug.to_string());
+        let dir = path.parent().unwrap_or(std::path::Path::new(""));
+        record.add_file(dir, path)?;
+        record.write(&evidence::path_for(path), &request)?;
+    }
+    Ok(())
+}
+
+/// Group pages into book sections: detected chapters (with front and back
+/// matter around them), or runs of 20 pages when there are none.
+fn epub_sections(pages: Vec<notes::Page>, chapters: &[ChapterItem]) -> Vec<epub::Section> {
+    const PAGES_PER_SECTION: usize = 20;
+
+    if chapters.is_empty() {
+        let mut sections = Vec::new();
+        let mut pages = pages.into_iter().peekable();
+        while pages.peek().is_some() {
+            let run: Vec<notes::Page> = pages.by_ref().take(PAGES_PER_SECTION).collect();
+            let (first, last) = (run[0].number, run[run.len() - 1].number);
+            sections.push(epub::Section {
+                title: format!("Pages {}–{}", first, last),
+                pages: run,
+            });
+        }
+        return sections;
+    }
+
+    let first_start = chapters.iter().map(|c| c.start_page).min().unwrap_or(1);
+    let last_end = chapters.iter().map(|c| c.end_page).max().unwrap_or(i32::MAX);
+    let mut front = Vec::new();
+    let mut back = Vec::new();
+    let mut by_chapter: Vec<Vec<notes::Page>> = chapters.iter().map(|_| Vec::new()).collect();
+    for page in pages {
+        match chapters
+            .iter()
+            .position(|c| (c.start_page..=c.end_page).contains(&page.number))
+        {
+            Some(i) => by_chapter[i].push(page),
+            None if page.number < first_start => front.push(page),
+            None if page.number > last_end => back.push(page),
+            // Gaps between chapters stay with the preceding one
+            None => {
+                let i = chapters
+                    .iter()
+                    .rposition(|c| c.end_page < page.number)
+                    .unwrap_or(0);
+                by_chapter[i].push(page);
+            }
+        }
+    }
+
+    let mut sections = Vec::new();
+    if !front.is_empty() {
+        sections.push(epub::Section {
+            title: "Front matter".to_string(),
+            pages: front,
+        });
+    }
+    for (chapter, pages) in chapters.iter().zip(by_chapter) {
+        let title = match &chapter.title {
+            Some(t) => format!("Chapter {}: {}", chapter.number, t),
+            None => format!("Chapter {}", chapter.number),
+        };
+        sections.push(epub::Section { title, pages });
+    }
+    if !back.is_empty() {
+        sections.push(epub::Section {
+            title: "Back matter".to_string(),
+            pages: back,
+        });
+    }
+    sections
+}
+
+/// Print a fact-check verdict, its explanation, and the sources it cites.
+fn print_verdict(response: &VerifyResponse) {
+    let verdict = match response.verdict.as_str() {
+        "supported" => "SUPPORTED".green().bold(),
+        "contradicted" => "CONTRADICTED".red().bold(),
+        _ => "NOT FOUND".yellow().bold(),
+    };
+    println!("\n{} {}\n", "Verdict:".bold(), verdict);
+    println!("{}\n", response.explanation);
+
+    let cited: Vec<usize> = citations::cited_numbers(&response.explanation)
+        .into_iter()
+        .filter(|n| *n <= response.sources.len())
+        .collect();
+    if !cited.is_empty() {
+        println!("{}:", "Cited".dimmed());
+        for n in cited {
+            let source = &response.sources[n - 1];
+            println!("  [{}] {}", n.to_string().yellow(), source_summary(source));
+        }
+        println!();
+    }
+}
+
+/// Reading mode: page `start` of `slug`, then the pages chosen with single
+/// keys (see reader.rs) until the reader leaves. Pages are drawn on a screen
+/// of their own, given back on leaving, or in the viewer of `chat --split`.
+/// The page last shown and the document's page count.
+fn read_document(client: &OsgeoClient, pages: &prefetch::PageCache, slug: &str, start: i32) -> Result<(i32, i32)> {
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        anyhow::bail!("Reading mode needs a terminal; use 'page' to view pages instead");
+    }
+    let width = preview::page_width();
+    let mut page = pages.get(client, slug, start, width)?.0;
+    let mut screen = tty::Guard::default();
+    let in_viewer = split::viewer_size().is_some();
+    if !in_viewer {
+        screen.alternate_screen().context("Failed to switch the terminal to a screen for reading")?;
+    }
+
+    let mut note = String::new();
+    loop {
+        if !in_viewer {
+            print!("\x1B[2J\x1B[1;1H");
+        }
+        println!("{} p.{}/{}", page.document_title.bold(), page.page_number, page.total_pages);
+        pages.prefetch_around(client, slug, page.page_number, page.total_pages, width);
+        if preview::shows_images() {
+            // Title and key line above and below
+            let size = format!("{}x{}", term::width(), term::height().saturating_sub(3).max(10));
+            let title = format!("{} p.{}", page.document_slug, page.page_number);
+            if let Err(e) = display_page_image(&page.image, &size, &title) {
+                println!("{}: {}", "Error displaying image".red(), e);
+            }
+        } else {
+            // Without images the summary is all there is to read
+            println!("\n{}\n", page.summary.as_deref().unwrap_or("(no summary of this page)"));
+        }
+        recent::record(
+            client.base_url(),
+            recent::Item::Page {
+                slug: slug.to_string(),
+                page: page.page_number,
+                title: page.document_title.clone(),
+            },
+        );
+        print!("{}", format!("n next · p previous · g go to page · q quit{}", note).dimmed());
+        std::io::Write::flush(&mut std::io::stdout()).ok();
+        note.clear();
+
+        let (current, total) = (page.page_number, page.total_pages);
+        let wanted = loop {
+            let wanted = match reader::next_move()? {
+                reader::Move::Quit => {
+                    println!();
+                    return Ok((current, total));
+                }
+                reader::Move::Next => current + 1,
+                reader::Move::Prev => current - 1,
+                reader::Move::First => 1,
+                reader::Move::Last => total,
+                reader::Move::Goto(n) => n,
+            };
+            // At either end, or a page out of range: stay put
+            if (1..=total).contains(&wanted) && wanted != current {
+                break wanted;
+            }
+        };
+        match pages.get(client, slug, wanted, width) {
+            Ok((next, prefetched)) => {
+                log_page(&next, width, prefetched);
+                page = next;
+            }
+            Err(e) => note = format!("  (page {}: {:#})", wanted, e),
+        }
+    }
+}
+
+fn cmd_chat(
+    client: &OsgeoClient,
+    config: &config::Config,
+    split: bool,
+    mut sources_view: config::SourcesView,
+    transcript_file: Option<std::path::PathBuf>,
+) -> Result<()> {
+    // Split-pane layout needs a real terminal; keep the guard alive for the session
+    let _split_guard = if split && !term::env().plain {
+        match split::enter() {
+            Ok(guard) => Some(guard),
+            Err(e) => {
+                println!("{}: {}", "Split mode unavailable".yellow(), e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    println!("{}", "OSGeo Library Chat".bold());
+    println!("{}", "=".repeat(40));
+
+    // Check server health first
+    match client.health() {
+        Ok(h) if h.status == "healthy" => {
+            println!("Server: {} | Type 'help' for commands\n", "connected".green());
+        }
+        Ok(h) => {
+            println!(
+                "Server: {} (some services unavailable)\n",
+                "degraded".yellow()
+            );
+            if !h.embedding_server {
+                println!("  {} Embedding server unavailable", "!".red());
+            }
+            if !h.llm_server {
+                println!("  {} LLM server unavailable", "!".red());
+            }
+            if !h.database {
+                println!("  {} Database unavailable", "!".red());
+            }
+            println!();
+        }
+        Err(e) => {
+            return Err(e);
+        }
+    }
+
+    // Detect if stdin is piped (not interactive)
+    let is_piped = !std::io::stdin().is_terminal();
+
+    let mut rl = chatline::editor(client, term::env().plain, is_piped)?;
+    // Alt-Enter inserts a newline instead of submitting
+    rl.bind_sequence(KeyEvent(KeyCode::Enter, Modifiers::ALT), Cmd::Newline);
+    let prompt_style = prompt::Prompt::new(&config.prompt, term::env().plain)?;
+    let mut last_sources: Vec<SearchResult> = Vec::new();
+    // Query and result keys of the last ranked 'search' listing (for 'why N')
+    let mut ranked_search: Option<(String, Vec<String>)> = None;
+    let mut docs_pager = DocsPager::new(config.defaults.chat_docs_page_size);
+    let mut docs_slugs: Vec<String> = Vec::new();  // slugs from current docs page
+    let mut last_listing: Option<Listing> = None;  // what a bare number refers to
+    let mut current_doc = config.defaults.document.clone();  // current document being viewed
+    let mut xref_doc: Option<String> = None;  // document of the last 'detail', for 'xref'
+    let mut last_page_view: Option<(String, i32, i32)> = None;  // (slug, page_num, total_pages)
+    let pages = prefetch::PageCache::default();
+    let mut last_chat: Option<ChatRequest> = None;  // for 'again' and ':edit'
+    let mut pinned: Vec<SearchResult> = Vec::new();  // always sent as context
+    let mut excluded = Exclusions::default();  // left out of retrieval
+    let mut transcript: Vec<report::Entry> = Vec::new();  // for 'save' and --transcript
+    let mut recent_views: Vec<recent::Entry> = Vec::new();  // as last listed, for 'recent N'
+    let mut recorded = 0;  // entries in the --transcript file
+    let started = epub::utc_timestamp();
+    if let Some(path) = &transcript_file {
+        write_transcript(client, path, &started, &transcript);
+        println!("Recording the session to {}\n", path.display());
+    }
+
+    let (plugins, plugin_errors) = plugins::Plugins::load();
+    for e in plugin_errors {
+        println!("{}: {:#}", "Plugin not loaded".yellow(), e);
+    }
+    chatline::add_commands(&mut rl, plugins.commands().iter().map(|c| c.name.clone()));
+    let plugin_host = PluginHost { client, config };
+
+    loop {
+        if let Some(path) = &transcript_file {
+            if transcript.len() != recorded {
+                write_transcript(client, path, &started, &transcript);
+                recorded = transcript.len();
+            }
+        }
+        let prompt = prompt_style.render(&prompt::PromptState {
+            doc: current_doc.as_deref(),
+            page: last_page_view.as_ref().map(|(_, page, _)| *page),
+            online: client.is_reachable(),
+        });
+        let readline = rl
+            .readline(&prompt)
+            .and_then(|first| read_multiline(&mut rl, first));
+
+        match readline {
+            Ok(line) => {
+                let input = line.trim();
+                if input.is_empty() {
+                    continue;
+                }
+                
+                // Echo command when piped for test visibility
+                if is_piped {
+                    println!("{}{}", prompt, input);
+                }
+
+                chatline::remember(&mut rl, input)?;
+
+                // A bare number acts on the last numbered listing;
+                // 'pick' chooses from the last results by typing part of one;
+                // ':edit' composes the entry in $EDITOR, starting from the last question;
+                // 'again' re-sends the last question with overrides
+                let expanded;
+                let mut pending_chat: Option<ChatRequest> = None;
+                let first_word = input.split_whitespace().next().unwrap_or("").to_lowercase();
+                let input = match (input.parse::<usize>(), last_listing) {
+                    (Ok(n), Some(listing)) => {
+                        expanded = listing.command_for(n);
+                        expanded.as_str()
+                    }
+                    _ if first_word == "again" => {
+                        let Some(previous) = last_chat.clone() else {
+                            println!("No previous question to repeat.\n");
+                            continue;
+                        };
+                        let words = std::iter::once("again").chain(input.split_whitespace().skip(1));
+                        let overrides = match AgainCommand::try_parse_from(words) {
+                            Ok(cmd) => cmd,
+                            Err(e) => {
+                                println!("{}", e);
+                                continue;
+                            }
+                        };
+                        let req = overrides.apply(previous);
+                        term::progress(&format!("Re-asking: {}", describe_chat_request(&req)));
+                        expanded = req.question.clone();
+                        pending_chat = Some(req);
+                        expanded.as_str()
+                    }
+                    _ if input.eq_ignore_ascii_case(":edit") => {
+                        let last_question = last_chat.as_ref().map(|r| r.question.as_str());
+                        match editor::compose(last_question.unwrap_or("")) {
+                            Ok(Some(text)) => {
+                                if is_piped {
+                                    println!("{}", text);
+                                }
+                                chatline::remember(&mut rl, &text)?;
+                                expanded = text;
+                                expanded.as_str()
+                            }
+                            Ok(None) => {
+                                println!("Empty question, nothing sent.\n");
+                                continue;
+                            }
+                            Err(e) => {
+                                println!("{}: {:#}\n", "Error".red(), e);
+                                continue;
+                            }
+                        }
+                    }
+                    _ if matches!(input.to_lowercase().as_str(), "pick" | "pick open") => {
+                        match pick_command(&last_sources, input.len() > 4) {
+                            Some(command) => {
+                                expanded = command;
+                                expanded.as_str()
+                            }
+                            None => continue,
+                        }
+                    }
+                    _ => input,
+                };
+
+                // 'recent N' reopens documents and pages through their commands. Only
+                // "recent" and "recent 3": questions starting with the word go out
+                let reopened;
+                let recent_arg = input
+                    .strip_prefix("recent")
+                    .or_else(|| input.strip_prefix("Recent"))
+                    .map(str::trim)
+                    .filter(|arg| arg.is_empty() || arg.parse::<usize>().is_ok());
+                let input = if let Some(arg) = recent_arg {
+                    match handle_recent_command(client, arg, &mut recent_views, config.hooks.on_show.as_deref()) {
+                        Recent::Listed => {
+                            last_listing = Some(Listing::Recent);
+                            continue;
+                        }
+                        Recent::Reopen(command) => {
+                            reopened = command;
+                            reopened.as_str()
+                        }
+                        Recent::Shown(entry) => {
+                            transcript.extend(entry);
+                            continue;
+                        }
+                    }
+                } else {
+                    input
+                };
+
+                // Handle commands
+                let lower = input.to_lowercase();
+
+                if lower == "quit" || lower == "exit" || lower == "q" {
+                    println!("Goodbye!");
+                    break;
+                }
+
+                if lower == "health" || lower == "status" || lower == "stats" || lower == "info" {
+                    match client.health() {
+                        Ok(health) => {
+                            println!("\n{}", "Server Status".bold());
+                            let status_color = if health.status == "healthy" {
+                                health.status.green()
+                            } else {
+                                health.status.yellow()
+                            };
+                            println!("Status:     {}", status_color);
+                            println!("Version:    {}", health.version);
+                            let check = |ok: bool| if ok { "OK".green() } else { "FAILED".red() };
+                            println!("Embedding:  {}", check(health.embedding_server));
+                            println!("LLM:        {}", check(health.llm_server));
+                            println!("Database:   {}", check(health.database));
+                            println!();
+                        }
+                        Err(e) => println!("{}: {}\n", "Error".red(), e),
+                    }
+                    continue;
+                }
+
+                if lower == "version" || lower == "ver" {
+                    println!("\n{} {}", "osgeo-library".bold(), env!("CARGO_PKG_VERSION"));
+                    match client.health() {
+                        Ok(health) => println!("Server API:  {}\n", health.version),
+                        Err(_) => println!("Server:      not connected\n"),
+                    }
+                    continue;
+                }
+
+                if lower == "clear" || lower == "cls" {
+                    if term::env().plain {
+                        continue;
+                    }
+                    if split::viewer_size().is_some() {
+                        split::clear_conversation().ok();
+                        continue;
+                    }
+                    print!("\x1B[2J\x1B[1;1H");
+                    std::io::Write::flush(&mut std::io::stdout()).ok();
+                    continue;
+                }
+
+                if lower == "help" || lower == "?" {
+                    println!("\n{}", "Browse:".bold());
+                    println!("  docs [-n N] [-s S] List documents (sort: title, recent, pages)");
+                    println!("  docs page <N>     Jump to a docs page ('docs last' for the last one)");
+                    println!("  docs -k/-l/--search <text>");
+                    println!("                    Only documents with that keyword, license or title");
+                    println!("  doc <N|slug>      Select document (e.g., 'doc 1' or 'doc usgs_snyder')");
+                    println!("  page [slug] <N>   View page (e.g., 'page 55' or 'page usgs_snyder 55')");
+                    println!("  page find <query> View the page of the current document that best matches");
+                    println!("  sections [slug]   List chapters and sections with their pages");
+                    println!("  section <N>       View the first page of a section (e.g., 'section 4.2')");
+                    println!("  next/n, prev/p    Navigate to next/previous page");
+                    println!("  read [slug] [--start N]");
+                    println!("                    Read page by page: n/p/g/q turn pages with one key");
+                    println!("  g<N>              Go to page N of the current listing or document");
+                    println!("  recent [N]        List recently viewed documents, pages and elements,");
+                    println!("                    or reopen item N ('recent 3')");
+                    println!();
+                    println!("{}", "Elements:".bold());
+                    println!("  figures           List figures on current page (or 'figures all')");
+                    println!("  tables            List tables on current page (or 'tables all')");
+                    println!("  equations         List equations on current page (or 'equations all')");
+                    println!();
+                    println!("{}", "View:".bold());
+                    println!("  show <N>          Show element in terminal (e.g., 'show 1' or 'show 1,2,3');");
+                    println!("                    'show 2 --as-text' prints a table's rows instead");
+                    println!("  open <N>          Open element in GUI viewer");
+                    println!("  open page <N>     Open page in GUI viewer");
+                    println!("  detail <N>        Everything about a result: content, metadata, siblings");
+                    println!("  xref <ref>        Show the figure, table or equation a text refers to");
+                    println!("                    (e.g., 'xref Figure 12'), in the last 'detail' document");
+                    println!("  verify-element <N>");
+                    println!("                    Check that result N's label is on its page (extraction bugs)");
+                    println!("  copy <N>          Copy result N to the clipboard: LaTeX for equations, else text");
+                    println!("  pick [open]       Choose a result by typing part of it, then show (or open) it");
+                    println!("  pipe <N> <cmd>    Run a command on result N's image or text ({{path}} = file,");
+                    println!("                    otherwise sent to its stdin)");
+                    println!("  <N>               Open item N of the last listing (doc after 'docs', show after results,");
+                    println!("                    recent after 'recent')");
+                    println!();
+                    println!("{}", "Search:".bold());
+                    println!("  search <query>    Semantic search (no LLM); 'search --min-score 60 <query>'");
+                    println!("                    leaves out results scoring below 60%");
+                    println!("  sources           Show sources from last answer");
+                    println!("  set sources <off|compact|full>");
+                    println!("                    Source listing after answers: a count, one line each,");
+                    println!("                    or with content snippets ('set' shows settings)");
+                    println!("  why <N>           Explain how result N was scored and ranked");
+                    println!("  similar <N>       Find passages and elements close to result N elsewhere");
+                    println!("  pin <N>           Keep result N in context for later questions ('pins' lists)");
+                    println!("  unpin <N|all>     Stop including a pinned source");
+                    println!("  exclude <N>       Leave result N out of later retrieval ('exclude doc <slug>',");
+                    println!("                    'exclude' lists, 'exclude clear' resets)");
+                    println!("  <question>        Ask a question (uses LLM)");
+                    println!("  verify <claim>    Check a statement: supported, contradicted, or not found");
+                    println!("  :paste            Enter a multi-line question, finished by ':end'");
+                    println!("                    (or end lines with '\\', or press Alt-Enter for a newline)");
+                    println!("  :edit             Compose the question in $EDITOR (starts from the last one)");
+                    println!("  again [-n N] [-d SLUG|all] [-m MODEL]");
+                    println!("                    Re-ask the last question with different parameters");
+                    println!();
+                    if !plugins.commands().is_empty() {
+                        println!("{}", "Plugins:".bold());
+                        for command in plugins.commands().iter() {
+                            println!("  {:<17} {} ({})", command.name, command.help, command.plugin);
+                        }
+                        println!();
+                    }
+                    println!("{}", "Other:".bold());
+                    println!("  health/status     Show server status");
+                    println!("  version           Show client and server version");
+                    println!("  save FILE.md      Save this session (questions, answers, sources) as Markdown");
+                    println!("  save --pdf FILE   Save this session (answers, sources, shown images) as PDF");
+                    println!("  clear             Clear screen");
+                    println!("  help              Show this help");
+                    println!("  quit/exit/q       Exit\n");
+                    continue;
+                }
+
+                if lower == "sources" {
+                    if last_sources.is_empty() {
+                        println!("No sources available. Ask a question first.\n");
+                    } else if sources_view == config::SourcesView::Full {
+                        println!();
+                        for (line, result) in format_sources(&last_sources).lines().zip(&last_sources) {
+                            println!("{}", line);
+                            println!("    {}", source_snippet(result).dimmed());
+                        }
+                        println!();
+                    } else {
+                        println!("\n{}", format_sources(&last_sources));
+                        println!();
+                    }
+                    continue;
+                }
+
+                if lower == "copy" || lower.starts_with("copy ") {
+                    handle_copy_command(client, input[4..].trim(), &last_sources);
+                    continue;
+                }
+
+                if lower == "pipe" || lower.starts_with("pipe ") {
+                    handle_pipe_command(client, input[4..].trim(), &last_sources);
+                    continue;
+                }
+
+                if lower == "set" || lower.starts_with("set ") {
+                    handle_set_command(input[3..].trim(), &mut sources_view);
+                    continue;
+                }
+
+                if lower == "save" || lower.starts_with("save ") {
+                    handle_save_command(client, input[4..].trim(), &transcript);
+                    continue;
+                }
+
+                if lower == "pin" || lower == "pins" || lower.starts_with("pin ") {
+                    handle_pin_command(input[3..].trim_start_matches('s').trim(), &last_sources, &mut pinned);
+                    continue;
+                }
+
+                if lower == "exclude" || lower.starts_with("exclude ") {
+                    handle_exclude_command(input[7..].trim(), &last_sources, &mut excluded);
+                    continue;
+                }
+
+                if lower.starts_with("unpin ") {
+                    handle_unpin_command(input[6..].trim(), &mut pinned);
+                    continue;
+                }
+
+                if lower.starts_with("detail ") {
+                    if let Some(slug) = handle_detail_command(client, input[7..].trim(), &last_sources) {
+                        xref_doc = Some(slug);
+                    }
+                    continue;
+                }
+
+                if lower.starts_with("verify-element ") {
+                    handle_verify_element_command(client, input[15..].trim(), &last_sources);
+                    continue;
+                }
+
+                // xref <reference> - follow "see Figure 12" within a document
+                if lower == "xref" || lower.starts_with("xref ") {
+                    // The document of the result last inspected, or the one being read
+                    let Some(slug) = xref_doc.clone().or_else(|| current_doc.clone()) else {
+                        println!("Use 'detail <N>' or 'doc <slug>' first to choose the document.\n");
+                        continue;
+                    };
+                    transcript.extend(handle_xref_command(client, input[4..].trim(), &slug, config.hooks.on_show.as_deref()));
+                    continue;
+                }
+
+                if lower == "why" || lower.starts_with("why ") {
+                    let arg = input[3..].trim();
+                    match arg.parse::<usize>() {
+                        Ok(n) if n >= 1 && n <= last_sources.len() => {
+                            let result = &last_sources[n - 1];
+                            // Boosts only apply to 'search' listings, not answer sources
+                            let ranked = ranked_search.as_ref().filter(|(_, keys)| {
+                                keys.iter().cloned().eq(last_sources.iter().map(SearchResult::key))
+                            });
+                            println!("\n{}", source_summary(result));
+                            let query = ranked.map(|(q, _)| q.as_str()).unwrap_or("");
+                            for line in explain_ranking(result, query, ranked.map(|_| &config.ranking)) {
+                                println!("  {}", line);
+                            }
+                            if ranked.is_none() {
+                                println!("  {}", "Answer sources keep the server's order (no client boosts).".dimmed());
+                            }
+                            println!();
+                        }
+                        _ if last_sources.is_empty() => {
+                            println!("No results yet. Search or ask a question first.\n");
+                        }
+                        _ => println!("Usage: why <N> (1-{})\n", last_sources.len()),
+                    }
+                    continue;
+                }
+
+                if lower == "similar" || lower.starts_with("similar ") {
+                    let source = match input[7..].trim().parse::<usize>() {
+                        Ok(n) if n >= 1 && n <= last_sources.len() => last_sources[n - 1].key(),
+                        _ if last_sources.is_empty() => {
+                            println!("No results yet. Search or ask a question first.\n");
+                            continue;
+                        }
+                        _ => {
+                            println!("Usage: similar <N> (1-{})\n", last_sources.len());
+                            continue;
+                        }
+                    };
+                    let req = SimilarRequest {
+                        source,
+                        limit: config.defaults.search_limit,
+                        document_slug: None,
+                        include_chunks: true,
+                        include_elements: true,
+                    };
+                    term::progress("Looking for similar content...");
+                    match capabilities::require(client, Feature::Similar).and_then(|()| client.similar(req)) {
+                        Ok(response) if response.results.is_empty() => println!("Nothing similar found.\n"),
+                        Ok(response) => {
+                            project::record_results(client, "similar", &response.query, &response.results);
+                            println!("\n{} similar:\n", response.results.len().to_string().green());
+                            for (i, result) in response.results.iter().enumerate() {
+                                println!("{}", format_result(i + 1, result, true));
+                                println!();
+                            }
+                            last_sources = response.results;
+                            last_listing = Some(Listing::Results);
+                        }
+                        Err(e) => println!("{}: {}\n", "Error".red(), e),
+                    }
+                    continue;
+                }
+
+                if lower.starts_with("show ") {
+                    let arg = input[5..].trim();
+                    
+                    // Check if it's "show page <slug> <N>" or "show page <N>"
+                    if arg.to_lowercase().starts_with("page ") {
+                        let page_arg = arg[5..].trim();
+                        let parts: Vec<&str> = page_arg.split_whitespace().collect();
+                        
+                        let (doc_slug, page_num) = match parts.len() {
+                            1 => {
+                                match parts[0].parse::<i32>() {
+                                    Ok(n) if n > 0 => {
+                                        match &current_doc {
+                                            Some(slug) => (slug.clone(), n),
+                                            None => {
+                                                println!("Use 'doc <slug>' first, or specify: show page <slug> <N>\n");
+                                                continue;
+                                            }
+                                        }
+                                    }
+                                    _ => {
+                                        println!("Usage: show page <N> or show page <slug> <N>\n");
+                                        continue;
+                                    }
+                                }
+                            }
+                            2 => {
+                                let slug = parts[0].to_string();
+                                match parts[1].parse::<i32>() {
+                                    Ok(n) if n > 0 => (slug, n),
+                                    _ => {
+                                        println!("Usage: show page <N> or show page <slug> <N>\n");
+                                        continue;
+                                    }
+                                }
+                            }
+                            _ => {
+                                println!("Usage: show page <N> or show page <slug> <N>\n");
+                                continue;
+                            }
+                        };
+                        
+                        term::progress_inline(&format!("Loading page {}...", page_num));
+                        let width = preview::page_width();
+                        match pages.get(client, &doc_slug, page_num, width) {
+                            Ok((page, prefetched)) => {
+                                term::progress_done(" done\n");
+                                log_page(&page, width, prefetched);
+                                pages.prefetch_around(client, &doc_slug, page.page_number, page.total_pages, width);
+                                println!("{} p.{}/{}", 
+                                    page.document_title.bold(),
+                                    page.page_number,
+                                    page.total_pages
+                                );
+                                
+                                if let Some(summary) = &page.summary {
+                                    println!("{}: {}", "Summary".dimmed(), summary);
+                                }
+                                
+                                if let Some(keywords) = &page.keywords {
+                                    if !keywords.is_empty() {
+                                        println!("{}: {}", "Keywords".dimmed(), keywords.join(", "));
+                                    }
+                                }
+                                
+                                println!();
+                                
+                                if let Err(e) = display_page_image(&page.image, "80x40", &format!("{} p.{}", page.document_slug, page.page_number)) {
+                                    println!("{}: {}", "Error displaying image".red(), e);
+                                }
+                                
+                                // Save state for next/prev navigation and set current doc
+                                last_page_view = Some((doc_slug.clone(), page.page_number, page.total_pages));
+                                current_doc = Some(doc_slug.clone());
+                            }
+                            Err(e) => {
+                                println!("\n{}: {}\n", "Error".red(), e);
+                            }
+                        }
+                    } else {
+                        // Original behavior: show source by index
+                        transcript.extend(handle_show_command(client, arg, &last_sources, config.hooks.on_show.as_deref()));
+                    }
+                    continue;
+                }
+
+                if lower.starts_with("open ") {
+                    let arg = input[5..].trim();
+                    
+                    // Check if it's "open page <slug> <N>" or "open page <N>"
+                    if arg.to_lowercase().starts_with("page ") {
+                        let page_arg = arg[5..].trim();
+                        let parts: Vec<&str> = page_arg.split_whitespace().collect();
+                        
+                        let (doc_slug, page_num) = match parts.len() {
+                            1 => {
+                                match parts[0].parse::<i32>() {
+                                    Ok(n) if n > 0 => {
+                                        match &current_doc {
+                                            Some(slug) => (slug.clone(), n),
+                                            None => {
+                                                println!("Use 'doc <slug>' first, or specify: open page <slug> <N>\n");
+                                                continue;
+                                            }
+                                        }
+                                    }
+                                    _ => {
+                                        println!("Usage: open page <N> or open page <slug> <N>\n");
+                                        continue;
+                                    }
+                                }
+                            }
+                            2 => {
+                                let slug = parts[0].to_string();
+                                match parts[1].parse::<i32>() {
+                                    Ok(n) if n > 0 => (slug, n),
+                                    _ => {
+                                        println!("Usage: open page <N> or open page <slug> <N>\n");
+                                        continue;
+                                    }
+                                }
+                            }
+                            _ => {
+                                println!("Usage: open page <N> or open page <slug> <N>\n");
+                                continue;
+                            }
+                        };
+                        
+                        term::progress_inline(&format!("Loading page {}...", page_num));
+                        
+                        match client.get_page(&doc_slug, page_num, None) {
+                            Ok(page) => {
+                                term::progress_done(" opening");
+                                if let Err(e) = open_page_image(&page.image) {
+                                    println!("{}: {}\n", "Error".red(), e);
+                                }
+                                
+                                // Update state for next/prev and figures/tables/equations
+                                last_page_view = Some((doc_slug.clone(), page.page_number, page.total_pages));
+                                current_doc = Some(doc_slug.clone());
+                            }
+                            Err(e) => {
+                                println!("\n{}: {}\n", "Error".red(), e);
+                            }
+                        }
+                    } else {
+                        // Original behavior: open source by index
+                        handle_open_command(client, arg, &last_sources);
+                    }
+                    continue;
+                }
+
+                // sections [slug] - chapters and numbered sections of a document
+                if lower == "sections" || lower.starts_with("sections ") {
+                    let Some(slug) = input.split_whitespace().nth(1).map(str::to_string).or_else(|| current_doc.clone()) else {
+                        println!("Use 'doc <slug>' first, or specify: sections <slug>\n");
+                        continue;
+                    };
+                    match client.list_sections(&slug) {
+                        Ok(response) if response.sections.is_empty() => {
+                            println!("No chapter or section headings found in {}.\n", slug);
+                        }
+                        Ok(response) => {
+                            println!();
+                            for section in &response.sections {
+                                println!("{}", format_section(section));
+                            }
+                            println!();
+                            current_doc = Some(slug);
+                        }
+                        Err(e) => println!("\n{}: {}\n", "Error".red(), e),
+                    }
+                    continue;
+                }
+
+                // read [slug] [--start N] - reading mode, pages turned with single keys
+                if lower == "read" || lower.starts_with("read ") {
+                    let mut slug = None;
+                    let mut start = None;
+                    let mut words = input[4..].split_whitespace();
+                    let mut valid = true;
+                    while let Some(word) = words.next() {
+                        match word {
+                            "--start" | "-s" => match words.next().and_then(|n| n.parse::<i32>().ok()).filter(|n| *n > 0) {
+                                Some(n) => start = Some(n),
+                                None => valid = false,
+                            },
+                            _ if slug.is_none() => slug = Some(word.to_string()),
+                            _ => valid = false,
+                        }
+                    }
+                    let Some(slug) = slug.or_else(|| current_doc.clone()).filter(|_| valid) else {
+                        println!("Usage: read [slug] [--start N] (the current document without a slug)\n");
+                        continue;
+                    };
+                    // Where the last page view of this document left off, by default
+                    let start = start.unwrap_or(match &last_page_view {
+                        Some((viewed, page, _)) if *viewed == slug => *page,
+                        _ => 1,
+                    });
+                    match read_document(client, &pages, &slug, start) {
+                        Ok((page, total)) => {
+                            println!("Read {} up to page {} of {}.\n", slug, page, total);
+                            last_page_view = Some((slug.clone(), page, total));
+                            current_doc = Some(slug);
+                        }
+                        Err(e) => println!("\n{}: {}\n", "Error".red(), e),
+                    }
+                    continue;
+                }
+
+                // page <N>, page <slug> <N>, page find <query> or section <N> - view a page of a document
+                let section = lower.starts_with("section ");
+                if lower.starts_with("page ") || section {
+                    let arg = input[if section { 8 } else { 5 }..].trim();
+                    let parts: Vec<&str> = arg.split_whitespace().collect();
+
+                    let (doc_slug, page_num) = if section {
+                        let Some(slug) = current_doc.clone() else {
+                            println!("Use 'doc <slug>' first to choose the document.\n");
+                            continue;
+                        };
+                        match find_section(client, &slug, arg) {
+                            Ok(Some(found)) => {
+                                println!("{}", format_section(&found).trim_start().bold());
+                                (slug, found.start_page)
+                            }
+                            Ok(None) => {
+                                println!("Section {} not found in {}. Use 'sections' to list them.\n", arg, slug);
+                                continue;
+                            }
+                            Err(e) => {
+                                println!("\n{}: {}\n", "Error".red(), e);
+                                continue;
+                            }
+                        }
+                    } else if parts.first().is_some_and(|w| w.eq_ignore_ascii_case("find")) {
+                        let query = arg[4..].trim().trim_matches('"');
+                        let Some(slug) = current_doc.clone() else {
+                            println!("Use 'doc <slug>' first to choose the document to search.\n");
+                            continue;
+                        };
+                        if query.is_empty() {
+                            println!("Usage: page find <query>\n");
+                            continue;
+                        }
+                        match find_page(client, &slug, query) {
+                            Ok(Some((best, others))) => {
+                                if !others.is_empty() {
+                                    let others: Vec<String> = others.iter().map(|p| format!("p.{}", p)).collect();
+                                    println!("{}: {}", "Also matching".dimmed(), others.join(", "));
+                                }
+                                (slug, best)
+                            }
+                            Ok(None) => {
+                                println!("No page of {} matches '{}'.\n", slug, query);
+                                continue;
+                            }
+                            Err(e) => {
+                                println!("\n{}: {}\n", "Error".red(), e);
+                                continue;
+                            }
+                        }
+                    } else {
+                        match parts.len() {
+                            1 => {
+                                // page <N> - use current document
+                                match arg.parse::<i32>() {
+                                    Ok(n) if n > 0 => {
+                                        match &current_doc {
+                                            Some(slug) => (slug.clone(), n),
+                                            None => {
+                                                println!("Use 'doc <slug>' first, or specify: page <slug> <N>\n");
+                                                continue;
+                                            }
+                                        }
+                                    }
+                                    _ => {
+                                        println!("Usage: page <N> or page <slug> <N>\n");
+                                        continue;
+                                    }
+                                }
+                            }
+                            2 => {
+                                // page <slug> <N>
+                                let slug = parts[0].to_string();
+                                match parts[1].parse::<i32>() {
+                                    Ok(n) if n > 0 => (slug, n),
+                                    _ => {
+                                        println!("Usage: page <N> or page <slug> <N>\n");
+                                        continue;
+                                    }
+                                }
+                            }
+                            _ => {
+                                println!("Usage: page <N> or page <slug> <N>\n");
+                                continue;
+                            }
+                        }
+                    };
+                    
+                    // Fetch and display page
+                    term::progress_inline(&format!("Loading page {}...", page_num));
+                    let width = preview::page_width();
+                    match pages.get(client, &doc_slug, page_num, width) {
+                        Ok((page, prefetched)) => {
+                            term::progress_done(" done\n");
+                            log_page(&page, width, prefetched);
+                            pages.prefetch_around(client, &doc_slug, page.page_number, page.total_pages, width);
+                            println!("{} p.{}/{}", 
+                                page.document_title.bold(),
+                                page.page_number,
+                                page.total_pages
+                            );
+                            
+                            // Show summary if available
+                            if let Some(summary) = &page.summary {
+                                println!("{}: {}", "Summary".dimmed(), summary);
+                            }
+                            
+                            // Show keywords if available
+                            if let Some(keywords) = &page.keywords {
+                                if !keywords.is_empty() {
+                                    println!("{}: {}", "Keywords".dimmed(), keywords.join(", "));
+                                }
+                            }
+                            
+                            println!();
+                            
+                            // Display image
+                            if let Err(e) = display_page_image(&page.image, "80x40", &format!("{} p.{}", page.document_slug, page.page_number)) {
+                                println!("{}: {}", "Error displaying image".red(), e);
+                            }
+                            
+                            recent::record(client.base_url(), recent::Item::Page {
+                                slug: doc_slug.clone(),
+                                page: page.page_number,
+                                title: page.document_title.clone(),
+                            });
+
+                            // Save state for next/prev navigation and set current doc
+                            last_page_view = Some((doc_slug.clone(), page.page_number, page.total_pages));
+                            current_doc = Some(doc_slug.clone());
+                        }
+                        Err(e) => {
+                            println!("\n{}: {}\n", "Error".red(), e);
+                        }
+                    }
+                    continue;
+                }
+
+                let first_word = lower.split_whitespace().next().unwrap_or("");
+                let is_docs_cmd = matches!(first_word, "docs" | "list" | "ls");
+                let nav = parse_nav(&lower);
+                if is_docs_cmd || nav.is_some() {
+                    // Check if we're navigating pages (after viewing a page)
+                    if let (Some(nav), Some((slug, current_page, total))) = (&nav, &last_page_view) {
+                        let new_page = match nav {
+                            Nav::Next if *current_page >= *total => {
+                                println!("Already on last page ({}/{}).\n", current_page, total);
+                                continue;
+                            }
+                            Nav::Next => current_page + 1,
+                            Nav::Prev if *current_page <= 1 => {
+                                println!("Already on first page.\n");
+                                continue;
+                            }
+                            Nav::Prev => current_page - 1,
+                            Nav::Goto(n) if *n < 1 || *n > *total => {
+                                println!("Page {} out of range (1-{})\n", n, total);
+                                continue;
+                            }
+                            Nav::Goto(n) => *n,
+                        };
+                        
+                        term::progress_inline(&format!("Loading page {}...", new_page));
+                        let width = preview::page_width();
+                        match pages.get(client, slug, new_page, width) {
+                            Ok((page, prefetched)) => {
+                                term::progress_done(" done\n");
+                                log_page(&page, width, prefetched);
+                                pages.prefetch_around(client, slug, page.page_number, page.total_pages, width);
+                                println!("{} p.{}/{}", 
+                                    page.document_title.bold(),
+                                    page.page_number,
+                                    page.total_pages
+                                );
+                                
+                                if let Some(summary) = &page.summary {
+                                    println!("{}: {}", "Summary".dimmed(), summary);
+                                }
+                                
+                                if let Some(keywords) = &page.keywords {
+                                    if !keywords.is_empty() {
+                                        println!("{}: {}", "Keywords".dimmed(), keywords.join(", "));
+                                    }
+                                }
+                                
+                                println!();
+                                
+                                if let Err(e) = display_page_image(&page.image, "80x40", &format!("{} p.{}", page.document_slug, page.page_number)) {
+                                    println!("{}: {}", "Error displaying image".red(), e);
+                                }
+                                
+                                recent::record(client.base_url(), recent::Item::Page {
+                                    slug: slug.clone(),
+                                    page: page.page_number,
+                                    title: page.document_title.clone(),
+                                });
+                                last_page_view = Some((slug.clone(), page.page_number, page.total_pages));
+                            }
+                            Err(e) => {
+                                println!("\n{}: {}\n", "Error".red(), e);
+                            }
+                        }
+                        continue;
+                    }
+                    
+                    // Otherwise, handle document list pagination
+                    // Determine which page to fetch
+                    let docs_rest: Vec<&str> = input.split_whitespace().skip(1).collect();
+                    let target = if is_docs_cmd && docs_rest.len() == 2 && docs_rest[0].eq_ignore_ascii_case("page") {
+                        // docs page N
+                        match docs_rest[1].parse() {
+                            Ok(n) => docs_pager.goto(n),
+                            Err(_) => Err(anyhow::anyhow!("Usage: docs page <N>")),
+                        }
+                    } else if is_docs_cmd && docs_rest.len() == 1 && docs_rest[0].eq_ignore_ascii_case("last") {
+                        // docs last: the page count is only known after a first fetch
+                        if docs_pager.total_pages == 0 {
+                            docs_pager.fetch(client, 1).map(|_| docs_pager.total_pages)
+                        } else {
+                            Ok(docs_pager.total_pages)
+                        }
+                    } else if is_docs_cmd {
+                        // Same options as the CLI: docs [-p N] [-n N] [-s SORT] [-k KEYWORD] ...
+                        let words = std::iter::once("docs").chain(input.split_whitespace().skip(1));
+                        let args = match ChatDocsCommand::try_parse_from(words) {
+                            Ok(cmd) => cmd.args,
+                            Err(e) => {
+                                println!("{}", e);
+                                continue;
+                            }
+                        };
+                        docs_pager.apply(&args).map(|_| args.page.unwrap_or(1))
+                    } else {
+                        match nav {
+                            // Auto-start at page 1 if not viewing docs yet
+                            _ if docs_pager.page == 0 => Ok(1),
+                            Some(Nav::Next) => docs_pager
+                                .next_page()
+                                .ok_or_else(|| anyhow::anyhow!("Already on last page.")),
+                            Some(Nav::Prev) => docs_pager
+                                .prev_page()
+                                .ok_or_else(|| anyhow::anyhow!("Already on first page.")),
+                            Some(Nav::Goto(n)) => docs_pager.goto(n),
+                            None => Ok(1),
+                        }
+                    };
+                    let target_page = match target {
+                        Ok(page) => page,
+                        Err(e) => {
+                            println!("{}\n", e);
+                            continue;
+                        }
+                    };
+
+                    match docs_pager.fetch(client, target_page) {
+                        Ok(response) => {
+                            docs_slugs = response.documents.iter().map(|d| d.slug.clone()).collect();
+                            last_listing = Some(Listing::Docs);
+                            if !docs_pager.filter.is_empty() && response.documents.is_empty() {
+                                println!("No documents match.\n");
+                                continue;
+                            }
+                            
+                            let heading = if docs_pager.filter.is_empty() {
+                                "Documents in library:"
+                            } else {
+                                "Matching documents:"
+                            };
+                            println!("\n{} (page {}/{})", heading.bold(), docs_pager.page, docs_pager.total_pages);
+                            println!("{}", "=".repeat(50));
+                            if let Some(width) = columns::column_width() {
+                                let cells: Vec<Vec<String>> = response.documents.iter().enumerate()
+                                    .map(|(i, doc)| {
+                                        let number = (i + 1).to_string();
+                                        let pages = format!(" - {} pages", doc.total_pages);
+                                        let slug = columns::fit(&doc.slug, width.saturating_sub(number.len() + 3 + pages.len()));
+                                        vec![
+                                            format!("[{}] {}{}", number.yellow(), slug.cyan(), pages),
+                                            format!("    {}", columns::fit(&doc.title, width.saturating_sub(4))),
+                                        ]
+                                    })
+                                    .collect();
+                                columns::print(&cells, width);
+                            } else {
+                                for (i, doc) in response.documents.iter().enumerate() {
+                                    println!("[{}] {} - {} pages", 
+                                        (i + 1).to_string().yellow(),
+                                        doc.slug.cyan(),
+                                        doc.total_pages);
+                                    println!("    {}", doc.title);
+                                    if let Some(ref keywords) = doc.keywords {
+                                        if !keywords.is_empty() {
+                                            let kw: String = keywords.iter().take(4).cloned().collect::<Vec<_>>().join(", ");
+                                            println!("    {}", kw.dimmed());
+                                        }
+                                    }
+                                }
+                            }
+                            let nav_hint = if docs_pager.total_pages > 1 {
+                                format!(" | {}", docs_pager.footer())
+                            } else {
+                                String::new()
+                            };
+                            println!("\n'N', 'doc N' or 'doc <slug>' for details{}\n", nav_hint.dimmed());
+                        }
+                        Err(e) => println!("{}: {}\n", "Error".red(), e),
+                    }
+                    continue;
+                }
+
+                if lower.starts_with("doc ") {
+                    let arg = input[4..].trim();
+                    if arg.is_empty() {
+                        println!("Usage: doc <N> or doc <slug> (e.g., 'doc 1' or 'doc usgs_snyder')\n");
+                        continue;
+                    }
+                    // Check if arg is a number (index into docs_slugs)
+                    let slug = if let Ok(n) = arg.parse::<usize>() {
+                        if n == 0 || n > docs_slugs.len() {
+                            if docs_slugs.is_empty() {
+                                println!("Use 'docs' first to list documents.\n");
+                            } else {
+                                println!("Invalid index. Use 1-{}.\n", docs_slugs.len());
+                            }
+                            continue;
+                        }
+                        docs_slugs[n - 1].as_str()
+                    } else {
+                        arg
+                    };
+                    match client.get_document(slug) {
+                        Ok(doc) => {
+                            current_doc = Some(doc.slug.clone());
+                            recent::record(client.base_url(), recent::Item::Document {
+                                slug: doc.slug.clone(),
+                                title: doc.title.clone(),
+                            });
+                            
+                            println!("\n{}", doc.title.bold());
+                            println!("{}", "=".repeat(50));
+                            println!("Slug:    {}", doc.slug.cyan());
+                            println!("Pages:   {}", doc.total_pages);
+                            if let Some(ref source) = doc.source_file {
+                                println!("Source:  {}", source);
+                            }
+                            
+                            // Element counts
+                            let total: i32 = doc.element_counts.values().sum();
+                            if total > 0 {
+                                println!("\n{}", "Elements:".bold());
+                                for (t, c) in &doc.element_counts {
+                                    if *c > 0 {
+                                        println!("  {}: {}", t, c);
+                                    }
+                                }
+                                println!("\nUse 'figures', 'tables', or 'equations' to browse");
+                            }
+                            
+                            if let Some(ref keywords) = doc.keywords {
+                                if !keywords.is_empty() {
+                                    println!("\n{}", "Keywords:".bold());
+                                    println!("  {}", keywords.join(", "));
+                                }
+                            }
+                            
+                            if let Some(ref summary) = doc.summary {
+                                println!("\n{}", "Summary:".bold());
+                                println!("{}", summary);
+                            }
+                            println!();
+                        }
+                        Err(e) => println!("{}: {}\n", "Error".red(), e),
+                    }
+                    continue;
+                }
+
+                // Browse elements - from current page if viewing, otherwise from document
+                if lower == "figures" || lower == "tables" || lower == "equations" {
+                    let (doc_slug, page_filter) = match (&last_page_view, &current_doc) {
+                        (Some((slug, page_num, _)), _) => (slug.clone(), Some(*page_num)),
+                        (None, Some(slug)) => (slug.clone(), None),
+                        (None, None) => {
+                            println!("Use 'doc <slug>' or view a page first.\n");
+                            continue;
+                        }
+                    };
+                    
+                    let element_type = match lower.as_str() {
+                        "figures" => "figure",
+                        "tables" => "table",
+                        "equations" => "equation",
+                        _ => unreachable!(),
+                    };
+                    
+                    // Get more results so we can filter by page if needed
+                    let req = SearchRequest {
+                        query: "*".to_string(),  // Match all
+                        limit: if page_filter.is_some() { 50 } else { 20 },
+                        document_slug: Some(doc_slug.clone()),
+                        include_chunks: false,
+                        include_elements: true,
+                        element_type: Some(element_type.to_string()),
+                        labels_only: false,
+                        exclude_documents: Vec::new(),
+                        exclude_ids: Vec::new(),
+                        min_score: None,
+                    };
+                    
+                    match client.search(req) {
+                        Ok(response) => {
+                            // Filter by page if we're viewing a specific page
+                            let results: Vec<_> = if let Some(page_num) = page_filter {
+                                response.results.into_iter()
+                                    .filter(|r| r.page_number == page_num)
+                                    .collect()
+                            } else {
+                                response.results
+                            };
+                            
+                            if results.is_empty() {
+                                if let Some(page_num) = page_filter {
+                                    println!("No {} on page {} of {}.", lower, page_num, doc_slug);
+                                    println!("Use '{} all' to see all {} in document.\n", lower, lower);
+                                } else {
+                                    println!("No {} found in {}.\n", lower, doc_slug);
+                                }
+                            } else {
+                                let scope = if let Some(page_num) = page_filter {
+                                    format!("{} p.{}", doc_slug, page_num)
+                                } else {
+                                    doc_slug.clone()
+                                };
+                                
+                                println!("\n{} in {} ({} found):", 
+                                    lower.to_uppercase().bold(), 
+                                    scope.cyan(),
+                                    results.len());
+                                println!("{}", "=".repeat(50));
+                                
+                                for (i, result) in results.iter().enumerate() {
+                                    let label = result.element_label.as_deref().unwrap_or("(unlabeled)");
+                                    let page = result.page_number;
+                                    let content = preview_text(result);
+                                    let preview = content.chars().take(60).collect::<String>();
+                                    let preview = if content.chars().count() > 60 {
+                                        format!("{}...", preview)
+                                    } else {
+                                        preview
+                                    };
+                                    println!("[{}] {} (p.{})", (i + 1).to_string().yellow(), label, page);
+                                    println!("    {}", preview.dimmed());
+                                }
+                                
+                                last_sources = results;
+                                last_listing = Some(Listing::Results);
+                                println!("\nUse {} to view.\n", view_hint());
+                            }
+                        }
+                        Err(e) => println!("{}: {}\n", "Error".red(), e),
+                    }
+                    continue;
+                }
+                
+                // Browse ALL elements in document (ignoring page context)
+                if lower == "figures all" || lower == "tables all" || lower == "equations all" {
+                    let doc_slug = match &current_doc {
+                        Some(slug) => slug.clone(),
+                        None => {
+                            println!("Use 'doc <slug>' first to select a document.\n");
+                            continue;
+                        }
+                    };
+                    
+                    let element_type = match lower.as_str() {
+                        "figures all" => "figure",
+                        "tables all" => "table",
+                        "equations all" => "equation",
+                        _ => unreachable!(),
+                    };
+                    let type_plural = match lower.as_str() {
+                        "figures all" => "figures",
+                        "tables all" => "tables",
+                        "equations all" => "equations",
+                        _ => unreachable!(),
+                    };
+                    
+                    let req = SearchRequest {
+                        query: "*".to_string(),
+                        limit: 50,  // Show more for "all"
+                        document_slug: Some(doc_slug.clone()),
+                        include_chunks: false,
+                        include_elements: true,
+                        element_type: Some(element_type.to_string()),
+                        labels_only: false,
+                        exclude_documents: Vec::new(),
+                        exclude_ids: Vec::new(),
+                        min_score: None,
+                    };
+                    
+                    match client.search(req) {
+                        Ok(response) => {
+                            if response.results.is_empty() {
+                                println!("No {} found in {}.\n", type_plural, doc_slug);
+                            } else {
+                                println!("\n{} in {} ({} found):", 
+                                    type_plural.to_uppercase().bold(), 
+                                    doc_slug.cyan(),
+                                    response.results.len());
+                                println!("{}", "=".repeat(50));
+                                
+                                for (i, result) in response.results.iter().enumerate() {
+                                    let label = result.element_label.as_deref().unwrap_or("(unlabeled)");
+                                    let page = result.page_number;
+                                    let content = preview_text(result);
+                                    let preview = content.chars().take(60).collect::<String>();
+                                    let preview = if content.chars().count() > 60 {
+                                        format!("{}...", preview)
+                                    } else {
+                                        preview
+                                    };
+                                    println!("[{}] {} (p.{})", (i + 1).to_string().yellow(), label, page);
+                                    println!("    {}", preview.dimmed());
+                                }
+                                
+                                last_sources = response.results;
+                                last_listing = Some(Listing::Results);
+                                println!("\nUse {} to view.\n", view_hint());
+                            }
+                        }
+                        Err(e) => println!("{}: {}\n", "Error".red(), e),
+                    }
+                    continue;
+                }
+
+                // Fast search (no LLM)
+                if lower.starts_with("search ") {
+                    let query = input[7..].trim();
+                    let (min_score, query) = match query.strip_prefix("--min-score") {
+                        Some(rest) => {
+                            let rest = rest.trim_start();
+                            let (score, query) = rest.split_once(' ').unwrap_or((rest, ""));
+                            match ranking::parse_min_score(score) {
+                                Ok(score) => (Some(score), query.trim()),
+                                Err(e) => {
+                                    println!("{}: {}\n", "Error".red(), e);
+                                    continue;
+                                }
+                            }
+                        }
+                        None => (None, query),
+                    };
+                    if query.is_empty() {
+                        println!("Usage: search [--min-score <pct>] <query>\n");
+                        continue;
+                    }
+                    
+                    let req = SearchRequest {
+                        query: query.to_string(),
+                        limit: config.defaults.search_limit,
+                        document_slug: None,  // Always search all documents
+                        include_chunks: true,
+                        include_elements: true,
+                        element_type: None,
+                        labels_only: false,
+                        exclude_documents: excluded.documents.clone(),
+                        exclude_ids: excluded.ids(),
+                        min_score,
+                    };
+                    
+                    term::progress("Searching all documents...");
+                    
+                    match client.search(req) {
+                        Ok(mut response) => {
+                            response.drop_below(min_score);
+                            let note = below_min_score_note(&response, min_score);
+                            if response.results.is_empty() {
+                                project::record_results(client, "search", query, &response.results);
+                                println!("No results found.\n");
+                                if let Some(note) = note {
+                                    println!("{}\n", note.dimmed());
+                                }
+                            } else {
+                                apply_ranking(&mut response.results, query, &config.ranking);
+                                project::record_results(client, "search", query, &response.results);
+                                ranked_search = Some((
+                                    query.to_string(),
+                                    response.results.iter().map(SearchResult::key).collect(),
+                                ));
+                                println!("\n{} results:\n", response.results.len().to_string().green());
+                                if let Some(note) = note {
+                                    println!("{}\n", note.dimmed());
+                                }
+                                
+                                for (i, result) in response.results.iter().enumerate() {
+                                    println!("{}", format_result(i + 1, result, true));
+                                    println!();
+                                }
+                                
+                                last_sources = response.results;
+                                last_listing = Some(Listing::Results);
+                                
+                                let has_elements = last_sources.iter().any(|s| s.source_type == "element");
+                                if has_elements {
+                                    println!("Use {} to view images.\n", view_hint());
+                                }
+                            }
+                        }
+                        Err(e) => println!("{}: {}\n", "Error".red(), e),
+                    }
+                    continue;
+                }
+
+                if lower.starts_with("verify ") {
+                    let statement = input[7..].trim();
+                    if let Err(e) = check_question_size(statement) {
+                        println!("{}: {}\n", "Error".red(), e);
+                        continue;
+                    }
+                    term::progress("Checking against the library...");
+                    let req = VerifyRequest {
+                        statement: statement.to_string(),
+                        limit: config.defaults.ask_limit,
+                        document_slug: current_doc.clone(),
+                    };
+                    match capabilities::require(client, Feature::Verify).and_then(|()| client.verify(req)) {
+                        Ok(response) => {
+                            print_verdict(&response);
+                            let verdict = format!("Verdict: {}\n\n{}", response.verdict, response.explanation);
+                            project::record_answer(client, "verify", statement, &verdict, &response.sources);
+                            transcript.push(report::Entry::Exchange {
+                                question: format!("Verify: {}", statement),
+                                answer: verdict,
+                                sources: numbered_sources(&response.sources),
+                            });
+                            last_sources = response.sources;
+                            last_listing = Some(Listing::Results);
+                        }
+                        Err(e) => println!("{}: {}\n", "Error".red(), e),
+                    }
+                    continue;
+                }
+
+                // Commands added by plugins (built-in commands take precedence)
+                let command = lower.split_whitespace().next().unwrap_or("");
+                if pending_chat.is_none() && plugins.has(command) {
+                    let context = serde_json::json!({
+                        "sources": last_sources,
+                        "document": current_doc,
+                        "page": last_page_view.as_ref().map(|(_, page, _)| *page),
+                    });
+                    let args = input[command.len()..].trim();
+                    match plugins.run(command, args, &plugin_host, &context) {
+                        Ok(()) => println!(),
+                        Err(e) => println!("{}: {:#}\n", "Error".red(), e),
+                    }
+                    continue;
+                }
+
+                // Regular question (LLM-powered); a pasted page is cut or summarized first
+                let fitted;
+                let input = if pending_chat.is_none() && input.chars().count() > MAX_QUESTION_CHARS {
+                    match fit_question(client, &mut rl, input) {
+                        Ok(Some(question)) => {
+                            fitted = question;
+                            fitted.as_str()
+                        }
+                        Ok(None) => {
+                            println!("Nothing sent.\n");
+                            continue;
+                        }
+                        Err(e) => {
+                            println!("{}: {:#}\n", "Error".red(), e);
+                            continue;
+                        }
+                    }
+                } else {
+                    input
+                };
+                term::progress("Searching...");
+
+                let mut req = pending_chat.take().unwrap_or_else(|| ChatRequest {
+                    question: input.to_string(),
+                    limit: config.defaults.ask_limit,
+                    document_slug: current_doc.clone(),
+                    document_slugs: Vec::new(),
+                    model: None,
+                    pinned_ids: Vec::new(),
+                    exclude_documents: Vec::new(),
+                    exclude_ids: Vec::new(),
+                    context_budget: None,
+                    user_context: None,
+                });
+                req.pinned_ids = pinned.iter().map(SearchResult::key).collect();
+                req.exclude_documents = excluded.documents.clone();
+                req.exclude_ids = excluded.ids();
+                if !pinned.is_empty() {
+                    term::progress(&format!("Including {} pinned source(s)", pinned.len()));
+                }
+                last_chat = Some(req.clone());
+
+                let question = req.question.clone();
+                let header = "Assistant:".blue().bold().to_string();
+                match chat_and_print(client, req, Some(&header)) {
+                    Ok(response) => {
+                        project::record_answer(client, "question", &question, &response.answer, &response.sources);
+                        transcript.push(report::Entry::Exchange {
+                            question,
+                            answer: response.answer.clone(),
+                            sources: numbered_sources(&response.sources),
+                        });
+                        last_sources = response.sources;
+                        last_listing = Some(Listing::Results);
+
+                        if !last_sources.is_empty() {
+                            print_answer_sources(&last_sources, sources_view, config.defaults.sources_display);
+
+                            let has_elements = last_sources.iter().any(|s| s.source_type == "element");
+                            if has_elements {
+                                println!("\nUse 'N' or 'show N' to view, or 'page <slug> <N>' for full page.\n");
+                            } else {
+                                println!("\nUse 'page <slug> <N>' to view full page.\n");
+                            }
+                        }
+                    }
+                    // Already reported
+                    Err(e) if e.is::<cancel::Cancelled>() => {}
+                    Err(e) => {
+                        println!("{}: {}\n", "Error".red(), e);
+                    }
+                }
+            }
+            Err(ReadlineError::Interrupted) => {
+                println!("\nGoodbye!");
+                break;
+            }
+            Err(ReadlineError::Eof) => {
+                println!("\nGoodbye!");
+                break;
+            }
+            Err(err) => {
+                println!("Error: {:?}", err);
+                break;
+            }
+        }
+    }
+    if let (Some(path), true) = (&transcript_file, transcript.len() != recorded) {
+        write_transcript(client, path, &started, &transcript);
+    }
+
+    Ok(())
+}
+
+/// List an answer's sources at the chosen verbosity. Compact and full
+/// listings stop after `max` sources (0 = all).
+fn print_answer_sources(sources: &[SearchResult], view: config::SourcesView, max: usize) {
+    print!("{}", format_answer_sources(sources, view, max));
+}
+
+/// The listing `print_answer_sources` prints, one line per source.
+fn format_answer_sources(sources: &[SearchResult], view: config::SourcesView, max: usize) -> String {
+    if view == config::SourcesView::Off {
+        return format!("{}: {} ('sources' to list them)\n", "Sources".dimmed(), sources.len());
+    }
+    let mut out = format!("{} ({}):\n", "Sources".dimmed(), sources.len());
+    let shown = match max {
+        0 => sources.len(),
+        n => n,
+    };
+    for (i, result) in sources.iter().enumerate().take(shown) {
+        let (type_str, label) = if result.source_type == "element" {
+            let t = result.element_type.as_ref()
+                .map(|t| t.to_uppercase())
+                .unwrap_or_else(|| "ELEMENT".to_string());
+            let l = result.element_label.as_deref().unwrap_or("").to_string();
+            (t, l)
+        } else {
+            let chunk_num = result.chunk_index.unwrap_or(0) + 1;
+            ("CHUNK".to_string(), format!("#{}", chunk_num))
+        };
+        out.push_str(&format!(
+            "  [{}] {} {} - {} p.{}\n",
+            (i + 1).to_string().yellow(),
+            type_str.cyan(),
+            label,
+            result.document_slug.dimmed(),
+            result.page_number
+        ));
+        if view == config::SourcesView::Full {
+            out.push_str(&format!("      {}\n", source_snippet(result).dimmed()));
+        }
+    }
+    if sources.len() > shown {
+        out.push_str(&format!("  ... {} more ('sources' to list all)\n", sources.len() - shown));
+    }
+    out
+}
+
+/// Handle 'set' (show settings) and 'set sources off|compact|full'.
+fn handle_set_command(arg: &str, sources_view: &mut config::SourcesView) {
+    let mut parts = arg.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (None, _) => println!("sources: {}\n", sources_view.name()),
+        (Some(name), Some(value)) if name.eq_ignore_ascii_case("sources") => {
+            match config::SourcesView::parse(value) {
+                Ok(view) => {
+                    *sources_view = view;
+                    println!("Sources: {}\n", view.name());
+                }
+                Err(e) => println!("{}\n", e),
+            }
+        }
+        _ => println!("Usage: set sources off|compact|full\n"),
+    }
+}
+
+/// Chat session report with `transcript`, headed by `when` ("Saved ..." or
+/// "Started ...").
+fn session_report<'a>(client: &OsgeoClient, when: String, transcript: &'a [report::Entry]) -> report::Report<'a> {
+    report::Report {
+        title: "OSGeo Library chat session".to_string(),
+        meta: vec![when, redact::text(&format!("Server: {}", client.base_url()))],
+        entries: transcript,
+    }
+}
+
+/// "2025-01-01 12:30 UTC" from an `epub::utc_timestamp`.
+fn utc_minutes(timestamp: &str) -> String {
+    format!("{} {} UTC", &timestamp[..10], &timestamp[11..16])
+}
+
+/// Rewrite the --transcript file with the session so far. Failures are
+/// reported and the session goes on.
+fn write_transcript(client: &OsgeoClient, path: &std::path::Path, started: &str, transcript: &[report::Entry]) {
+    let report = session_report(client, format!("Started {}", utc_minutes(started)), transcript);
+    if let Err(e) = std::fs::write(path, redact::text(&transcript::markdown(&report))) {
+        println!("{}: failed to write {}: {}\n", "Transcript".yellow(), path.display(), e);
+    }
+}
+
+/// Handle 'save [--pdf|--md] FILE': write the session transcript as a PDF
+/// report or as Markdown, by the option or else the file's extension.
+fn handle_save_command(client: &OsgeoClient, arg: &str, transcript: &[report::Entry]) {
+    let (pdf, path) = match arg.split_once(char::is_whitespace) {
+        Some(("--pdf", path)) => (true, path.trim()),
+        Some(("--md", path)) => (false, path.trim()),
+        // An option without a file, or one we don't know
+        _ if arg.starts_with("--") => (false, ""),
+        _ => (arg.to_lowercase().ends_with(".pdf"), arg),
+    };
+    if path.is_empty() {
+        println!("Usage: save FILE.md, or save --pdf FILE\n");
+        return;
+    }
+    if transcript.is_empty() {
+        println!("Nothing to save yet. Ask a question first.\n");
+        return;
+    }
+
+    let report = session_report(client, format!("Saved {}", utc_minutes(&epub::utc_timestamp())), transcript);
+    let images = transcript.iter().filter(|e| matches!(e, report::Entry::Image { .. })).count();
+    if images > 0 && pdf {
+        term::progress(&format!("Fetching {} image(s)...", images));
+    }
+    let written = paths::resolve(std::path::Path::new(path)).and_then(|path| {
+        if pdf {
+            report::write(&path, &report, |url| client.fetch_image(url))?;
+        } else {
+            std::fs::write(&path, redact::text(&transcript::markdown(&report)))
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        Ok(path)
+    });
+    match written {
+        Ok(path) => {
+            let questions = transcript.len() - images;
+            println!("Saved {} question(s) and {} image(s) to {}\n", questions, images, path.display());
+        }
+        Err(e) => println!("{}: {}\n", "Error".red(), e),
+    }
+}
+
+/// Handle 'pin N[,M]' (pin results from the last listing) and bare 'pin'/'pins'
+/// (list what is pinned).
+fn handle_pin_command(arg: &str, sources: &[SearchResult], pinned: &mut Vec<SearchResult>) {
+    if arg.is_empty() {
+        if pinned.is_empty() {
+            println!("Nothing pinned. Use 'pin N' after a search or answer.\n");
+        } else {
+            println!("\n{} ({}):", "Pinned".bold(), pinned.len());
+            for (i, result) in pinned.iter().enumerate() {
+                println!("  [{}] {}", (i + 1).to_string().yellow(), source_summary(result));
+            }
+            println!("\n'unpin N' or 'unpin all' to remove.\n");
+        }
+        return;
+    }
+
+    if sources.is_empty() {
+        println!("No results to pin. Ask a question or search first.\n");
+        return;
+    }
+
+    let indices: Vec<usize> = arg
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|s| s.trim().parse::<usize>().ok())
+        .collect();
+    if indices.is_empty() {
+        println!("Usage: pin <number> or pin 1,2,3\n");
+        return;
+    }
+
+    for n in indices {
+        let Some(result) = n.checked_sub(1).and_then(|i| sources.get(i)) else {
+            println!("Invalid index [{}]. Use 1-{}", n, sources.len());
+            continue;
+        };
+        if pinned.iter().any(|p| p.key() == result.key()) {
+            println!("Already pinned: {}", source_summary(result));
+            continue;
+        }
+        println!("{} {}", "Pinned:".green(), source_summary(result));
+        pinned.push(result.clone());
+    }
+    println!("Pinned sources are included in every question until unpinned.\n");
+}
+
+/// Documents and results left out of chat and search retrieval.
+#[derive(Default)]
+struct Exclusions {
+    documents: Vec<String>,
+    results: Vec<SearchResult>,
+}
+
+impl Exclusions {
+    fn ids(&self) -> Vec<String> {
+        self.results.iter().map(SearchResult::key).collect()
+    }
+}
+
+/// Handle 'exclude N[,M]' (results from the last listing), 'exclude doc <slug>',
+/// 'exclude clear', and bare 'exclude' (list exclusions).
+fn handle_exclude_command(arg: &str, sources: &[SearchResult], excluded: &mut Exclusions) {
+    if arg.is_empty() {
+        if excluded.documents.is_empty() && excluded.results.is_empty() {
+            println!("Nothing excluded. Use 'exclude N' or 'exclude doc <slug>'.\n");
+            return;
+        }
+        println!("\n{}:", "Excluded from retrieval".bold());
+        for slug in &excluded.documents {
+            println!("  document {}", slug.cyan());
+        }
+        for result in &excluded.results {
+            println!("  {}", source_summary(result));
+        }
+        println!("\n'exclude clear' to reset.\n");
+        return;
+    }
+
+    if arg.eq_ignore_ascii_case("clear") {
+        *excluded = Exclusions::default();
+        println!("Exclusions cleared.\n");
+        return;
+    }
+
+    if arg.to_lowercase().starts_with("doc ") {
+        let slug = arg[4..].trim();
+        if !excluded.documents.iter().any(|d| d == slug) {
+            excluded.documents.push(slug.to_string());
+        }
+        println!("{} document {} left out of future searches and questions.\n", "Excluded:".yellow(), slug.cyan());
+        return;
+    }
+
+    if sources.is_empty() {
+        println!("No results to exclude. Ask a question or search first.\n");
+        return;
+    }
+
+    let indices: Vec<usize> = arg
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|s| s.trim().parse::<usize>().ok())
+        .collect();
+    if indices.is_empty() {
+        println!("Usage: exclude <number>, exclude 1,2,3 or exclude doc <slug>\n");
+        return;
+    }
+
+    for n in indices {
+        let Some(result) = n.checked_sub(1).and_then(|i| sources.get(i)) else {
+            println!("Invalid index [{}]. Use 1-{}", n, sources.len());
+            continue;
+        };
+        if !excluded.results.iter().any(|r| r.key() == result.key()) {
+            excluded.results.push(result.clone());
+        }
+        println!("{} {}", "Excluded:".yellow(), source_summary(result));
+    }
+    println!();
+}
+
+/// Handle 'unpin N[,M]' (numbers from the 'pins' list) or 'unpin all'.
+fn handle_unpin_command(arg: &str, pinned: &mut Vec<SearchResult>) {
+    if arg.eq_ignore_ascii_case("all") {
+        println!("Unpinned {} source(s).\n", pinned.len());
+        pinned.clear();
+        return;
+    }
+
+    let mut indices: Vec<usize> = arg
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|s| s.trim().parse::<usize>().ok())
+        .filter(|n| *n >= 1 && *n <= pinned.len())
+        .collect();
+    if indices.is_empty() {
+        println!("Usage: unpin <number> or unpin all (see 'pins')\n");
+        return;
+    }
+
+    // Remove from the back so earlier indices stay valid
+    indices.sort_unstable();
+    indices.dedup();
+    for n in indices.into_iter().rev() {
+        let removed = pinned.remove(n - 1);
+        println!("Unpinned: {}", source_summary(&removed));
+    }
+    println!();
+}
+
+/// Returns the images shown, for the session transcript. `on_show` is a
+/// command run on each image after it is displayed (see hooks.rs).
+fn handle_show_command(
+    client: &OsgeoClient,
+    arg: &str,
+    sources: &[SearchResult],
+    on_show: Option<&str>,
+) -> Vec<report::Entry> {
+    let mut shown = Vec::new();
+    if sources.is_empty() {
+        println!("No results to show. Ask a question first.\n");
+        return shown;
+    }
+
+    // Parse indices: "1,2,3" or "1 2 3", and --as-text anywhere
+    let words: Vec<&str> = arg.split(|c: char| c == ',' || c.is_whitespace()).collect();
+    let as_text = words.contains(&"--as-text");
+    let indices: Vec<usize> = words
+        .iter()
+        .filter_map(|s| s.trim().parse::<usize>().ok())
+        .map(|n| n.saturating_sub(1)) // Convert to 0-indexed
+        .collect();
+
+    if indices.is_empty() {
+        println!("Usage: show <number>, show 1,2,3, or show 2 --as-text for a table's rows as text\n");
+        return shown;
+    }
+
+    for idx in indices {
+        if idx >= sources.len() {
+            println!("Invalid index [{}]. Use 1-{}\n", idx + 1, sources.len());
+            continue;
+        }
+
+        let result = &sources[idx];
+
+        if result.source_type != "element" {
+            println!(
+                "[{}] is a text chunk, no image available.\n",
+                idx + 1
+            );
+            println!("Content: {}...\n", &result.content[..200.min(result.content.len())]);
+            continue;
+        }
+
+        if as_text {
+            if let Some(text) = table_text(result) {
+                print_element_heading(result);
+                println!("{}\n", text);
+                continue;
+            }
+            println!("{}", format!("[{}] has no rows as text; showing the image.", idx + 1).dimmed());
+        }
+
+        if result.best_image_path().is_some() {
+            if let Some(url) = show_element(client, result, on_show) {
+                shown.push(report::Entry::Image {
+                    caption: format!("[{}] {}", idx + 1, source_summary(result)),
+                    url,
+                });
+            }
+        } else {
+            println!("[{}] has no image path.\n", idx + 1);
+        }
+    }
+    shown
+}
+
+/// "TABLE: Table 3.2" and the document and page it is from.
+fn print_element_heading(result: &SearchResult) {
+    println!(
+        "\n{}: {}",
+        result.element_type.as_deref().unwrap_or_default().to_uppercase().yellow(),
+        result.element_label.as_deref().unwrap_or("")
+    );
+    println!("From: {}, page {}\n", result.document_title, result.page_number);
+}
+
+/// A table element's extracted rows as a text table, after its caption.
+/// `None` for other elements, and tables whose content has no rows.
+fn table_text(result: &SearchResult) -> Option<String> {
+    if result.element_type.as_deref() != Some("table") {
+        return None;
+    }
+    let parsed = table::parse(&result.content)?;
+    let mut out = String::new();
+    if let Some(caption) = &parsed.caption {
+        out.push_str(&format!("{}\n", caption));
+    }
+    out.push_str(&table::render(&parsed, term::width(), usize::MAX));
+    Some(out)
+}
+
+/// Display an element's image with its label and page, running the
+/// `on_show` hook. Returns the image URL when it was shown.
+fn show_element(client: &OsgeoClient, result: &SearchResult, on_show: Option<&str>) -> Option<String> {
+    let image_path = result.best_image_path()?;
+    let elem_type = result
+        .element_type
+        .as_ref()
+        .map(|s| s.to_uppercase())
+        .unwrap_or_default();
+    let label = result.element_label.as_deref().unwrap_or("");
+
+    print_element_heading(result);
+    if result.element_type.as_deref() == Some("equation") && mathtext::enabled() {
+        println!("{}\n", preview_text(result));
+    }
+
+    // Fetch image from server and display with chafa
+    let image_url = client.image_url(&result.document_slug, image_path);
+
+    let size = chafa_size(result);
+    match fetch_and_display_image(client, &image_url, &size, &format!("{} {}", elem_type, label)) {
+        Ok(_) => {
+            recent::record(client.base_url(), recent::Item::element(result));
+            if let Some(hook) = on_show {
+                if let Err(e) = hook_payload(client, result).and_then(|p| hooks::run(hook, &p)) {
+                    println!("{}: {:#}", "on_show hook failed".red(), e);
+                }
+            }
+            Some(image_url)
+        }
+        Err(e) => {
+            println!("{}: {}", "Failed to display image".red(), e);
+            println!(
+                "{}: {}/{}",
+                "Image path".dimmed(),
+                result.document_slug,
+                image_path
+            );
+            None
+        }
+    }
+}
+
+/// Elements of `slug` that `reference` points at, best label match first.
+fn resolve_reference(client: &OsgeoClient, slug: &str, reference: &xref::Reference) -> Result<Vec<SearchResult>> {
+    term::progress(&format!("Looking up {} in {}...", reference.label(), slug));
+    let response = client.search(SearchRequest {
+        query: reference.number.clone(),
+        limit: 50,
+        document_slug: Some(slug.to_string()),
+        include_chunks: false,
+        include_elements: true,
+        labels_only: true,
+        ..Default::default()
+    })?;
+    Ok(response
+        .results
+        .into_iter()
+        .filter(|r| {
+            let label = r.element_label.as_deref().unwrap_or("");
+            reference.matches(r.element_type.as_deref().unwrap_or(""), label)
+        })
+        .collect())
+}
+
+/// Handle 'xref <reference>': show the element a reference like "Figure 12"
+/// points at, in `slug`. Returns the image shown, for the transcript.
+fn handle_xref_command(client: &OsgeoClient, arg: &str, slug: &str, on_show: Option<&str>) -> Option<report::Entry> {
+    let Some(reference) = xref::parse(arg.trim_matches('"')) else {
+        println!("Usage: xref <reference>, e.g. 'xref Figure 12', 'xref Table 3.2' or 'xref Eq. 4-7'\n");
+        return None;
+    };
+    let found = match resolve_reference(client, slug, &reference) {
+        Ok(found) => found,
+        Err(e) => {
+            println!("\n{}: {}\n", "Error".red(), e);
+            return None;
+        }
+    };
+    let Some((first, others)) = found.split_first() else {
+        println!("No element labelled {} in {}.\n", reference.label(), slug);
+        return None;
+    };
+    if !others.is_empty() {
+        let pages: Vec<String> = others.iter().map(|r| format!("p.{}", r.page_number)).collect();
+        println!("{}: {}", format!("Also labelled {}", reference.label()).dimmed(), pages.join(", "));
+    }
+    let url = show_element(client, first, on_show)?;
+    Some(report::Entry::Image {
+        caption: source_summary(first),
+        url,
+    })
+}
+
+/// Handle 'recent' and 'recent N': list the recently viewed, or reopen one.
+/// Numbers refer to the last listing in `views`, so reopening one doesn't
+/// renumber the rest.
+fn handle_recent_command(
+    client: &OsgeoClient,
+    arg: &str,
+    views: &mut Vec<recent::Entry>,
+    on_show: Option<&str>,
+) -> Recent {
+    if arg.is_empty() {
+        *views = recent::list(client.base_url());
+        if views.is_empty() {
+            println!("Nothing viewed yet. Documents, pages and elements you view are listed here.\n");
+            return Recent::Shown(None);
+        }
+        print!("\n{}", format_recent(views));
+        println!("\n'N' or 'recent N' to reopen\n");
+        return Recent::Listed;
+    }
+    let Ok(n) = arg.parse::<usize>() else {
+        println!("Usage: recent or recent <N>\n");
+        return Recent::Shown(None);
+    };
+    if views.is_empty() {
+        *views = recent::list(client.base_url());
+    }
+    let Some(entry) = n.checked_sub(1).and_then(|i| views.get(i)) else {
+        match views.len() {
+            0 => println!("Nothing viewed yet.\n"),
+            len => println!("Invalid index. Use 1-{}.\n", len),
+        }
+        return Recent::Shown(None);
+    };
+    match &entry.item {
+        recent::Item::Document { slug, .. } => Recent::Reopen(format!("doc {}", slug)),
+        recent::Item::Page { slug, page, .. } => Recent::Reopen(format!("page {} {}", slug, page)),
+        recent::Item::Element { result } => Recent::Shown(show_element(client, result, on_show).map(|url| {
+            report::Entry::Image {
+                caption: source_summary(result),
+                url,
+            }
+        })),
+    }
+}
+
+/// Numbered lines for the recently viewed, most recent first.
+fn format_recent(views: &[recent::Entry]) -> String {
+    let mut out = format!("{}\n", "Recently viewed:".bold());
+    for (i, entry) in views.iter().enumerate() {
+        out.push_str(&format!(
+            "  [{}] {:8} {} {}\n",
+            i + 1,
+            entry.item.kind(),
+            entry.item.describe(),
+            entry.age().dimmed()
+        ));
+    }
+    out
+}
+
+/// Handle 'copy N': put a result's LaTeX or text on the clipboard.
+fn handle_copy_command(client: &OsgeoClient, arg: &str, sources: &[SearchResult]) {
+    if sources.is_empty() {
+        println!("No results. Search or ask a question first.\n");
+        return;
+    }
+    let Some(n) = arg.parse::<usize>().ok().filter(|n| (1..=sources.len()).contains(n)) else {
+        println!("Usage: copy <N>, with N from 1 to {}\n", sources.len());
+        return;
+    };
+    let result = &sources[n - 1];
+    let text = clipboard_text(client, result);
+    if text.trim().is_empty() {
+        println!("[{}] has no text to copy.\n", n);
+        return;
+    }
+    match clipboard::copy(&text) {
+        Ok(()) => println!("Copied {} of [{}] to the clipboard.\n", clipboard_what(result), n),
+        Err(e) => println!("{}: {:#}\n", "Error".red(), e),
+    }
+}
+
+/// What `copy` puts on the clipboard: an equation's LaTeX source, as listed
+/// with the document's elements, or else the result's text.
+fn clipboard_text(client: &OsgeoClient, result: &SearchResult) -> String {
+    if result.element_type.as_deref() != Some("equation") {
+        return result.content.clone();
+    }
+    client
+        .list_elements(&result.document_slug, Some("equation"), Some(result.page_number), 100, 0)
+        .ok()
+        .and_then(|list| list.elements.into_iter().find(|e| e.id == result.id))
+        .and_then(|element| element.latex)
+        .unwrap_or_else(|| mathtext::source(&result.content))
+}
+
+/// "the LaTeX" or "the text", for the message after copying `result`.
+fn clipboard_what(result: &SearchResult) -> &'static str {
+    if result.element_type.as_deref() == Some("equation") {
+        "the LaTeX"
+    } else {
+        "the text"
+    }
+}
+
+/// Handle 'pipe N <command>': run an external command on a result.
+fn handle_pipe_command(client: &OsgeoClient, arg: &str, sources: &[SearchResult]) {
+    let (index, command) = match arg.split_once(char::is_whitespace) {
+        Some((n, command)) if !command.trim().is_empty() => (n.parse::<usize>().ok(), command.trim()),
+        _ => {
+            println!("Usage: pipe <N> <command>, e.g. 'pipe 2 tesseract {{path}} -' or 'pipe 1 wc -w'\n");
+            return;
+        }
+    };
+    if sources.is_empty() {
+        println!("No results. Search or ask a question first.\n");
+        return;
+    }
+    let Some(result) = index.filter(|n| (1..=sources.len()).contains(n)).map(|n| &sources[n - 1]) else {
+        println!("Invalid index. Use 1-{}.\n", sources.len());
+        return;
+    };
+
+    match hook_payload(client, result).and_then(|payload| hooks::run(command, &payload)) {
+        Ok(()) => println!(),
+        Err(e) => println!("{}: {:#}\n", "Error".red(), e),
+    }
+}
+
+/// A result's image (or its text when it has none) and placeholder values,
+/// for external commands.
+fn hook_payload(client: &OsgeoClient, result: &SearchResult) -> Result<hooks::Payload> {
+    let (bytes, extension) = match result.best_image_path() {
+        Some(path) if result.source_type == "element" => {
+            let url = client.image_url(&result.document_slug, path);
+            let extension = std::path::Path::new(path)
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("png")
+                .to_string();
+            (client.fetch_image(&url)?, extension)
+        }
+        _ => (preview_text(result).into_bytes(), "txt".to_string()),
+    };
+    Ok(hooks::Payload {
+        bytes,
+        name: format!("{}-{}", result.source_type, result.id),
+        extension,
+        vars: vec![
+            ("id", result.id.to_string()),
+            ("slug", result.document_slug.clone()),
+            ("page", result.page_number.to_string()),
+            ("type", result.element_type.clone().unwrap_or_else(|| "text".to_string())),
+            ("label", result.element_label.clone().unwrap_or_default()),
+            ("title", result.document_title.clone()),
+        ],
+    })
+}
+
+/// The client as seen by plugin commands.
+struct PluginHost<'a> {
+    client: &'a OsgeoClient,
+    config: &'a config::Config,
+}
+
+impl plugins::Api for PluginHost<'_> {
+    fn search(&self, query: &str, options: &plugins::Options) -> Result<serde_json::Value> {
+        let response = self.client.search(SearchRequest {
+            query: query.to_string(),
+            limit: options.limit.unwrap_or(self.config.defaults.search_limit),
+            document_slug: options.document.clone(),
+            include_chunks: true,
+            include_elements: true,
+            element_type: None,
+            labels_only: false,
+            exclude_documents: Vec::new(),
+            exclude_ids: Vec::new(),
+            min_score: None,
+        })?;
+        Ok(serde_json::to_value(response.results)?)
+    }
+
+    fn ask(&self, question: &str, options: &plugins::Options) -> Result<serde_json::Value> {
+        let response = self.client.chat(ChatRequest {
+            question: question.to_string(),
+            limit: options.limit.unwrap_or(self.config.defaults.ask_limit),
+            document_slug: options.document.clone(),
+            document_slugs: Vec::new(),
+            model: None,
+            pinned_ids: Vec::new(),
+            exclude_documents: Vec::new(),
+            exclude_ids: Vec::new(),
+            context_budget: None,
+            user_context: None,
+        })?;
+        Ok(serde_json::to_value(response)?)
+    }
+
+    fn document(&self, slug: &str) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self.client.get_document(slug)?)?)
+    }
+
+    fn image(&self, result: serde_json::Value) -> Result<Option<Vec<u8>>> {
+        let result: SearchResult = serde_json::from_value(result).context("Not a search result")?;
+        match result.best_image_path() {
+            Some(path) if result.source_type == "element" => {
+                let url = self.client.image_url(&result.document_slug, path);
+                Ok(Some(self.client.fetch_image(&url)?))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Print everything known about one result: full content, metadata, image
+/// availability, other elements on the same page, and follow-up commands.
+/// Pages either side of an element's page searched for a label missing
+/// from it, to spot elements filed under the wrong page.
+const NEARBY_PAGES: i32 = 2;
+
+/// Whether `page` has any extracted text, and where `label` is mentioned in
+/// it (with some text around it).
+fn label_on_page(client: &OsgeoClient, slug: &str, page: i32, label: &str) -> Result<(bool, Option<String>)> {
+    let chunks = client.list_page_chunks(slug, page)?.chunks;
+    let has_text = chunks.iter().any(|c| !c.content.trim().is_empty());
+    let snippet = chunks.iter().find_map(|chunk| {
+        let at = xref::find_label(&chunk.content, label)?;
+        Some(format!(
+            "{}{}{}",
+            grep::tail(&chunk.content[..at.start], 50),
+            grep::one_line(&chunk.content[at.clone()]).bold(),
+            grep::head(&chunk.content[at.end..], 50)
+        ))
+    });
+    Ok((has_text, snippet))
+}
+
+/// Handle 'verify-element N': check that result N's label appears in the
+/// text of the page it is filed under, and if not, on the pages nearby.
+/// Prints the evidence in a form that can go into an extraction bug report.
+fn handle_verify_element_command(client: &OsgeoClient, arg: &str, sources: &[SearchResult]) {
+    if sources.is_empty() {
+        println!("No results to check. Search or ask a question first.\n");
+        return;
+    }
+    let result = match arg.parse::<usize>() {
+        Ok(n) if n >= 1 && n <= sources.len() => &sources[n - 1],
+        _ => {
+            println!("Usage: verify-element <N> (1-{})\n", sources.len());
+            return;
+        }
+    };
+    if result.source_type != "element" {
+        println!("[{}] is a text chunk; verify-element checks figures, tables and equations.\n", arg);
+        return;
+    }
+    let kind = result.element_type.as_deref().unwrap_or("element");
+    let Some(label) = result.element_label.as_deref().filter(|l| !l.trim().is_empty()) else {
+        println!("[{}] has no label, so there is nothing to look for on its page.\n", arg);
+        return;
+    };
+    let slug = &result.document_slug;
+    let page = result.page_number;
+
+    println!("\n{} {} \"{}\" (element {})", "Checking".bold(), kind, label, result.id);
+    println!("Filed under: {} page {}\n", slug.cyan(), page);
+
+    let verdict = match label_on_page(client, slug, page, label) {
+        Ok((_, Some(snippet))) => {
+            println!("  {} label appears on page {}: {}", "OK".green(), page, snippet);
+            format!("label found on page {}", page)
+        }
+        Ok((has_text, None)) => {
+            if has_text {
+                println!("  {} label not in the text of page {}", "MISMATCH".red(), page);
+            } else {
+                println!("  {} page {} has no extracted text", "MISMATCH".red(), page);
+            }
+            // Where it is instead, nearest pages first
+            let mut found = Vec::new();
+            for distance in 1..=NEARBY_PAGES {
+                for other in [page - distance, page + distance] {
+                    if other < 1 {
+                        continue;
+                    }
+                    if let Ok((_, Some(snippet))) = label_on_page(client, slug, other, label) {
+                        println!("  {} label appears on page {}: {}", "found".yellow(), other, snippet);
+                        found.push(other);
+                    }
+                }
+            }
+            let missing = if has_text { "label not in its text" } else { "page has no text" };
+            match found.first() {
+                Some(other) => format!("{}; label found on page {} (offset {:+})", missing, other, other - page),
+                None => {
+                    println!(
+                        "  label not found on pages {}-{} either",
+                        (page - NEARBY_PAGES).max(1),
+                        page + NEARBY_PAGES
+                    );
+                    format!("{}; not found within {} pages", missing, NEARBY_PAGES)
+                }
+            }
+        }
+        Err(e) => {
+            println!("\n{}: {}\n", "Error".red(), e);
+            return;
+        }
+    };
+
+    // Plain lines to paste into an issue
+    println!("\n{}", "Evidence:".dimmed());
+    println!("  document: {} ({})", slug, result.document_title);
+    println!("  element:  {} {} \"{}\"", result.id, kind, label);
+    if let Some(path) = result.best_image_path() {
+        println!("  image:    {}", client.image_url(slug, path));
+    }
+    println!("  page:     {}: {}", page, verdict);
+    println!("  client:   osgeo-library {}, server {}\n", env!("CARGO_PKG_VERSION"), client.base_url());
+}
+
+/// Handle 'detail N'. Returns the result's document, which 'xref' then
+/// looks references up in.
+fn handle_detail_command(client: &OsgeoClient, arg: &str, sources: &[SearchResult]) -> Option<String> {
+    if sources.is_empty() {
+        println!("No results to inspect. Search or ask a question first.\n");
+        return None;
+    }
+
+    let idx = match arg.parse::<usize>() {
+        Ok(n) if n >= 1 && n <= sources.len() => n - 1,
+        _ => {
+            println!("Usage: detail <N> (1-{})\n", sources.len());
+            return None;
+        }
+    };
+    let result = &sources[idx];
+    let n = idx + 1;
+
+    // Header
+    let kind = if result.source_type == "element" {
+        result
+            .element_type
+            .as_ref()
+            .map(|s| s.to_uppercase())
+            .unwrap_or_else(|| "ELEMENT".to_string())
+    } else {
+        "TEXT CHUNK".to_string()
+    };
+    let label = if result.source_type == "element" {
+        result.element_label.as_deref().unwrap_or("(unlabeled)").to_string()
+    } else {
+        format!("#{}", result.chunk_index.unwrap_or(0) + 1)
+    };
+    println!("\n[{}] {} {}", n.to_string().yellow(), kind.cyan(), label.bold());
+    println!("{}", "=".repeat(50));
+
+    // Metadata
+    println!("Document:   {} ({})", result.document_title, result.document_slug.cyan());
+    println!("Page:       {}", result.page_number);
+    println!("ID:         {}", result.key());
+    println!("Score:      {:.1}% (semantic + keyword relevance)", result.score_pct);
+
+    // Image availability
+    if result.source_type == "element" {
+        let dims = match (result.image_width, result.image_height) {
+            (Some(w), Some(h)) => format!(" ({}x{})", w, h),
+            _ => String::new(),
+        };
+        match (&result.crop_path, &result.rendered_path) {
+            (_, Some(rendered)) => println!("Image:      {} (rendered LaTeX){}", rendered, dims),
+            (Some(crop), None) => println!("Image:      {}{}", crop, dims),
+            (None, None) => println!("Image:      none"),
+        }
+    } else {
+        println!("Image:      none (text chunk)");
+    }
+
+    // Full content
+    println!("\n{}", "Content:".bold());
+    if result.content.is_empty() {
+        println!("  (empty)");
+    } else if result.element_type.as_deref() == Some("equation") && mathtext::enabled() {
+        println!("{}", preview_text(result));
+        println!("{}", result.content.dimmed());
+    } else if let Some(text) = table_text(result) {
+        println!("{}", text);
+    } else {
+        println!("{}", result.content);
+    }
+
+    // Sibling elements on the same page
+    match client.list_elements(&result.document_slug, None, Some(result.page_number), 50, 0) {
+        Ok(list) => {
+            let siblings: Vec<_> = list.elements.iter().filter(|e| e.id != result.id).collect();
+            if !siblings.is_empty() {
+                println!("\n{}", format!("Also on page {}:", result.page_number).bold());
+                for e in siblings {
+                    println!(
+                        "  {} {}",
+                        e.element_type.to_uppercase().cyan(),
+                        e.label.as_deref().unwrap_or("(unlabeled)")
+                    );
+                }
+            }
+        }
+        Err(e) => println!("\n{}: {}", "Could not list page elements".dimmed(), e),
+    }
+
+    // Figures, tables and equations the text refers to
+    let references = xref::find(&result.content);
+    let own_label = result.element_label.as_deref().unwrap_or("");
+    let references: Vec<_> = references
+        .iter()
+        .filter(|r| !r.matches(result.element_type.as_deref().unwrap_or(""), own_label))
+        .collect();
+    if !references.is_empty() {
+        let labels: Vec<String> = references.iter().map(|r| r.label()).collect();
+        println!("\n{} {}", "Refers to:".bold(), labels.join(", "));
+    }
+
+    // Follow-up commands
+    println!("\n{}", "Next:".dimmed());
+    if result.source_type == "element" {
+        println!("  {:<28} View image in terminal", format!("show {}", n));
+        if term::env().gui {
+            println!("  {:<28} Open image in GUI viewer", format!("open {}", n));
+        }
+    }
+    println!(
+        "  {:<28} View full page",
+        format!("page {} {}", result.document_slug, result.page_number)
+    );
+    if let Some(reference) = references.first() {
+        println!("  {:<28} Show what the text refers to", format!("xref {}", reference.label()));
+    }
+    println!("  {:<28} Document details\n", format!("doc {}", result.document_slug));
+    Some(result.document_slug.clone())
+}
+
+fn handle_open_command(client: &OsgeoClient, arg: &str, sources: &[SearchResult]) {
+    if sources.is_empty() {
+        println!("No results to open. Ask a question first.\n");
+        return;
+    }
+
+    // Parse indices: "1,2,3" or "1 2 3"
+    let indices: Vec<usize> = arg
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|s| s.trim().parse::<usize>().ok())
+        .map(|n| n.saturating_sub(1))
+        .collect();
+
+    if indices.is_empty() {
+        println!("Usage: open <number> or open 1,2,3\n");
+        return;
+    }
+
+    for idx in indices {
+        if idx >= sources.len() {
+            println!("Invalid index [{}]. Use 1-{}\n", idx + 1, sources.len());
+            continue;
+        }
+
+        let result = &sources[idx];
+
+        if result.source_type != "element" {
+            println!("[{}] is a text chunk, no image available.\n", idx + 1);
+            continue;
+        }
+
+        if let Some(image_path) = result.best_image_path() {
+            let elem_type = result
+                .element_type
+                .as_ref()
+                .map(|s| s.to_uppercase())
+                .unwrap_or_default();
+            let label = result.element_label.as_deref().unwrap_or("");
+
+            println!("Opening {}: {}", elem_type.yellow(), label);
+
+            let image_url = client.image_url(&result.document_slug, image_path);
+
+            match fetch_and_open_image(client, &image_url) {
+                Ok(_) => recent::record(client.base_url(), recent::Item::element(result)),
+                Err(e) => {
+                    println!("{}: {}", "Failed to open image".red(), e);
+                }
+            }
+        } else {
+            println!("[{}] has no image path.\n", idx + 1);
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Main
+// -----------------------------------------------------------------------------
+
+/// Entry point of the `osgeo-library-cli` binary.
+pub fn main() -> Result<()> {
+    let started = std::time::Instant::now();
+    // Called back by a completion script: print the completions and exit
+    clap_complete::CompleteEnv::with_factory(Cli::command).complete();
+    crash::install();
+    let cli = Cli::parse();
+    if cli.debug {
+        term::set_debug(started);
+        term::startup_step("arguments parsed");
+    }
+    term::init(cli.plain || cli.command.as_ref().is_some_and(Commands::prints_for_scripts));
+    term::set_verbose(cli.verbose);
+    term::set_hints(!cli.no_hints);
+    shared::set_enabled(!cli.no_shared_state);
+    cancel::install();
+    mathtext::set_enabled(!cli.no_mathtext);
+    preview::set_upscale(cli.upscale);
+
+    // Before loading the config, so 'config set' can fix a broken one
+    if let Some(Commands::Config { action }) = &cli.command {
+        if let Err(e) = cmd_config(action, cli.profile.as_deref()) {
+            eprintln!("{}: {:#}", "Error".red().bold(), e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    // No config or server needed
+    if let Some(Commands::Completions { shell, static_only }) = &cli.command {
+        return completion::write_script(&mut Cli::command(), *shell, *static_only);
+    }
+    // A broken config goes into the report rather than stopping it
+    if let Some(Commands::ReportBug { requests, output }) = &cli.command {
+        return cmd_report_bug(&cli, *requests, output.as_deref());
+    }
+
+    let mut config = match config::Config::load(cli.profile.as_deref()) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{}: {:#}", "Error".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+    term::startup_step("config loaded");
+    let defaults = &config.defaults;
+    preview::set_backend(cli.image_backend.unwrap_or(defaults.image_backend));
+    paths::init(cli.out_dir.clone().or_else(|| config.paths.exports.clone()), &config.paths.name);
+    // Named in the User-Agent, so the server's operators can tell how the client is used
+    let mut features = Vec::new();
+    if preview::shows_images() {
+        features.push("images");
+    }
+    if matches!(cli.command, None | Some(Commands::Chat { .. })) {
+        features.push("repl");
+    }
+    crate::client::set_user_agent(&features, config.network.user_agent_suffix.as_deref());
+
+    let server_url = cli
+        .server
+        .first()
+        .cloned()
+        .or_else(|| defaults.server.clone())
+        .unwrap_or_else(|| DEFAULT_SERVER_URL.to_string());
+    let libraries = federation::libraries(&cli.server, &server_url, &config.federation);
+    let mirrors = if cli.mirrors.is_empty() { &defaults.mirrors } else { &cli.mirrors };
+    if cli.redact {
+        let servers: Vec<String> = std::iter::once(server_url.clone())
+            .chain(libraries.iter().map(|l| l.url.clone()))
+            .chain(mirrors.iter().cloned())
+            .collect();
+        redact::enable(&servers);
+    }
+    if let Some(name) = &cli.project {
+        project::enable(name);
+    }
+
+    // Create client and handle connection errors with helpful messages
+    let policy = config.network.policy(cli.timeout, cli.connect_timeout, cli.retries);
+    let client = match OsgeoClient::with_policy(&server_url, policy) {
+        Ok(c) => c.with_mirrors(mirrors),
+        Err(e) => {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+
+    // Check if we can connect to the server
+    let check_connection = |client: &OsgeoClient| -> Result<()> {
+        match client.health() {
+            Ok(_) => {
+                term::startup_step("server reachable");
+                Ok(())
+            }
+            Err(_) => {
+                let mirrors = client.mirrors();
+                if mirrors.is_empty() {
+                    eprintln!("{}: Could not connect to server at {}\n", "Error".red().bold(), server_url);
+                } else {
+                    eprintln!(
+                        "{}: Could not connect to server at {} or its mirrors ({})\n",
+                        "Error".red().bold(),
+                        server_url,
+                        mirrors.join(", ")
+                    );
+                }
+                eprintln!("The osgeo-library server is not running or not accessible.\n");
+                eprintln!("If you're on the server:");
+                eprintln!("  - Check the server log: tail ~/logs/osgeo-library.log");
+                eprintln!("  - Start manually: ~/github/osgeo-library/servers/start-server.sh &\n");
+                eprintln!("If you're on a remote machine:");
+                eprintln!("  - Set up SSH port forwarding:");
+                eprintln!("    ssh -L 8095:localhost:8095 osgeo7-gallery\n");
+                std::process::exit(1);
+            }
+        }
+    };
+
+    term::startup_step("ready");
+    let result = match cli.command {
+        Some(Commands::VerifyManifest { manifest, key }) => evidence::verify(&manifest, key.as_deref()),
+        Some(Commands::Project { what }) => cmd_project(what),
+        Some(Commands::Health) => cmd_health(&client),
+        Some(Commands::Docs { args, json }) => {
+            check_connection(&client)?;
+            cmd_docs(&client, args, defaults.docs_page_size, json)
+        }
+        Some(Commands::Doc { slug, cite: None }) => {
+            check_connection(&client)?;
+            cmd_doc(&client, slug)
+        }
+        Some(Commands::Doc { slug, cite: Some(style) }) => {
+            check_connection(&client)?;
+            cmd_cite(&client, [Ok(slug)], style, None)
+        }
+        Some(Commands::Download { slug, output }) => {
+            check_connection(&client).and_then(|_| cmd_download(&client, &slug, output))
+        }
+        Some(Commands::Read { slug, start }) => check_connection(&client)
+            .and_then(|_| read_document(&client, &prefetch::PageCache::default(), &slug, start))
+            .map(|_| ()),
+        Some(Commands::Recent { n }) => cmd_recent(&client, n),
+        Some(Commands::Cite {
+            slugs,
+            all,
+            format,
+            output,
+        }) => {
+            check_connection(&client)?;
+            if all {
+                let slugs = client.documents("title").map(|doc| doc.map(|d| d.slug));
+                cmd_cite(&client, slugs, format, output.as_deref())
+            } else {
+                cmd_cite(&client, slugs.into_iter().map(Ok), format, output.as_deref())
+            }
+        }
+        Some(Commands::Search {
+            query,
+            interactive,
+            limit,
+            document,
+            elements_only,
+            chunks_only,
+            r#type,
+            search_in,
+            has_number,
+            min_score,
+            pages,
+            explain_ranking,
+            show,
+            copy,
+            as_text,
+            open,
+            first,
+            pick,
+            exclude_doc,
+            boost_doc,
+            boost_type,
+            json,
+            export,
+            manifest,
+        }) => {
+            check_connection(&client)?;
+            let limit = limit.unwrap_or(defaults.search_limit);
+            let filters = builder::Filters {
+                query: query.unwrap_or_default(),
+                document: document_scope(document, defaults),
+                elements_only,
+                chunks_only,
+                element_type: r#type,
+                pages,
+                min_score,
+            };
+            let filters = if !interactive {
+                Ok(Some(filters))
+            } else if std::io::stdin().is_terminal() {
+                builder::run(&client, filters)
+            } else {
+                Err(anyhow::anyhow!("search --interactive needs a terminal"))
+            };
+            match filters {
+                Ok(Some(filters)) => {
+                    // Flags add to (and override) the configured boosts
+                    config.ranking.documents.extend(boost_doc);
+                    config.ranking.types.extend(boost_type);
+                    cmd_search(
+                        &client,
+                        &libraries,
+                        filters.query,
+                        limit,
+                        filters.document,
+                        filters.elements_only,
+                        filters.chunks_only,
+                        filters.element_type,
+                        search_in.as_deref() == Some("labels"),
+                        has_number,
+                        filters.min_score,
+                        filters.pages,
+                        exclude_doc,
+                        &config.ranking,
+                        explain_ranking,
+                        show,
+                        copy,
+                        as_text,
+                        open,
+                        first,
+                        pick,
+                        json,
+                        export,
+                        manifest.request(),
+                    )
+                }
+                Ok(None) => {
+                    eprintln!("Search cancelled.");
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            }
+        }
+        Some(Commands::Similar {
+            source,
+            limit,
+            document,
+            elements_only,
+            chunks_only,
+            json,
+        }) => {
+            let req = SimilarRequest {
+                source,
+                limit: limit.unwrap_or(defaults.search_limit),
+                document_slug: document,
+                include_chunks: !elements_only,
+                include_elements: !chunks_only,
+            };
+            check_connection(&client).and_then(|_| cmd_similar(&client, req, json))
+        }
+        Some(Commands::Grep {
+            slug,
+            pattern,
+            ignore_case,
+            fixed_strings,
+            max_count,
+            context,
+        }) => match grep::Matcher::new(&pattern, ignore_case, fixed_strings, context) {
+            Ok(matcher) => match textcache::load(client.base_url(), &slug).filter(|c| c.complete()) {
+                Some(cached) => {
+                    term::progress(&format!("Searching the text cached {} ('cache text {} --refresh' updates it)", cached.fetched, slug));
+                    cmd_grep_cached([cached], matcher, max_count, false)
+                }
+                None => check_connection(&client).and_then(|_| cmd_grep(&client, &slug, matcher, max_count)),
+            },
+            Err(e) => Err(e),
+        },
+        Some(Commands::Cache { what: what @ CacheCommand::Text { .. } }) => {
+            check_connection(&client).and_then(|_| cmd_cache(&client, what))
+        }
+        Some(Commands::Cache { what }) => cmd_cache(&client, what),
+        Some(Commands::Element {
+            label,
+            document,
+            show,
+            open,
+        }) => match document_scope(document, defaults) {
+            Some(slug) => check_connection(&client).and_then(|_| cmd_element(&client, &label, &slug, show, open)),
+            None => Err(anyhow::anyhow!("Which document? Give one with -d SLUG")),
+        },
+        Some(Commands::Ask {
+            question,
+            edit,
+            template,
+            citations_out,
+            limit,
+            document,
+            auto_scope,
+            per_doc_synthesis,
+            top_docs,
+            exclude_doc,
+            context_budget,
+            context_file,
+            sources,
+            json,
+            answer_only,
+        }) => {
+            match resolve_question(question, template, edit, &config.templates) {
+                Ok(Some(question)) => check_question_size(&question).and_then(|_| check_connection(&client)).and_then(|_| {
+                    let limit = limit.unwrap_or(defaults.ask_limit);
+                    let user_context = match &context_file {
+                        Some(path) => {
+                            capabilities::require(&client, Feature::UserContext)?;
+                            Some(read_context_file(path)?)
+                        }
+                        None => None,
+                    };
+                    if per_doc_synthesis {
+                        return cmd_ask_synthesis(
+                            &client,
+                            question,
+                            limit,
+                            top_docs as usize,
+                            exclude_doc,
+                            context_budget,
+                            user_context,
+                        );
+                    }
+                    // Auto-scoping picks the documents itself
+                    let document = if auto_scope { document } else { document_scope(document, defaults) };
+                    cmd_ask(
+                        &client,
+                        question,
+                        limit,
+                        document,
+                        auto_scope,
+                        exclude_doc,
+                        context_budget,
+                        user_context,
+                        citations_out,
+                        sources.unwrap_or(config::SourcesView::Off),
+                        json,
+                        answer_only,
+                    )
+                }),
+                Ok(None) => {
+                    eprintln!("Empty question, nothing sent.");
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            }
+        }
+        Some(Commands::Verify {
+            statement,
+            limit,
+            document,
+        }) => {
+            check_question_size(&statement)?;
+            check_connection(&client)?;
+            cmd_verify(&client, statement, limit.unwrap_or(defaults.ask_limit), document_scope(document, defaults))
+        }
+        Some(Commands::DocDiff { slug1, slug2 }) => {
+            check_connection(&client)?;
+            cmd_doc_diff(&client, &slug1, &slug2)
+        }
+        Some(Commands::Export {
+            what:
+                ExportCommand::Notes {
+                    slug,
+                    chapter,
+                    pages,
+                    output,
+                    all_equations,
+                    dry_run,
+                    resume,
+                    verify,
+                    manifest,
+                },
+        }) => {
+            check_connection(&client)?;
+            let options = ExportOptions { dry_run, resume, verify };
+            cmd_export_notes(&client, &slug, chapter, pages, output, all_equations, options, manifest.request())
+        }
+        Some(Commands::Export {
+            what:
+                ExportCommand::Doc {
+                    slug,
+                    epub,
+                    no_images,
+                    dry_run,
+                    resume,
+                    verify,
+                    manifest,
+                },
+        }) => {
+            check_connection(&client)?;
+            let path = match epub {
+                Some(path) => paths::resolve(&path)?,
+                None => paths::default(
+                    &paths::Export {
+                        slug: Some(&slug),
+                        kind: "doc",
+                        ..Default::default()
+                    },
+                    "epub",
+                )?,
+            };
+            let options = ExportOptions { dry_run, resume, verify };
+            cmd_export_epub(&client, &slug, &path, no_images, options, manifest.request())
+        }
+        Some(Commands::Export {
+            what: ExportCommand::Elements { args },
+        })
+        | Some(Commands::ExportElements { args }) => {
+            check_connection(&client)?;
+            let dir = match args.output {
+                Some(dir) => paths::resolve(&dir)?,
+                None => paths::default(
+                    &paths::Export {
+                        slug: Some(&args.slug),
+                        kind: args.r#type.as_deref().unwrap_or("elements"),
+                        ..Default::default()
+                    },
+                    "",
+                )?,
+            };
+            let options = ExportOptions {
+                dry_run: args.dry_run,
+                resume: args.resume,
+                verify: args.verify,
+            };
+            cmd_export_elements(
+                &client,
+                &args.slug,
+                args.r#type.as_deref(),
+                &dir,
+                args.jobs.into(),
+                options,
+                args.manifest.request(),
+            )
+        }
+        Some(Commands::Export {
+            what: ExportCommand::Pages { args },
+        })
+        | Some(Commands::Pages { args }) => {
+            check_connection(&client)?;
+            let (from, to) = args.range;
+            let path = match args.output {
+                Some(path) => paths::resolve(&path)?,
+                None => paths::default(
+                    &paths::Export {
+                        slug: Some(&args.slug),
+                        page: Some(format!("{}-{}", from, to)),
+                        kind: "pages",
+                        label: None,
+                    },
+                    "pdf",
+                )?,
+            };
+            let options = ExportOptions {
+                dry_run: args.dry_run,
+                resume: args.resume,
+                verify: false,
+            };
+            cmd_export_pages(&client, &args.slug, args.range, &path, options, args.manifest.request())
+        }
+        Some(Commands::Acronym {
+            acronym,
+            document,
+            sources,
+        }) => {
+            check_connection(&client)?;
+            cmd_acronym(&client, &acronym, document_scope(document, defaults), sources)
+        }
+        Some(Commands::WatchClipboard {
+            document,
+            limit,
+            max_chars,
+        }) => {
+            check_connection(&client)?;
+            let options = watch::Options {
+                document: document_scope(document, defaults),
+                limit,
+                max_chars,
+            };
+            watch::run(&client, &options)
+        }
+        Some(Commands::Schema { command }) => cmd_schema(command.as_deref()),
+        Some(Commands::Config { .. }) => Ok(()), // handled before loading the config
+        Some(Commands::Completions { .. }) => Ok(()), // likewise
+        Some(Commands::ReportBug { .. }) => Ok(()), // likewise
+        Some(Commands::Audit {
+            what: AuditCommand::Duplicates { delete, dry_run },
+        }) => {
+            check_connection(&client)?;
+            cmd_audit_duplicates(&client, delete, dry_run)
+        }
+        Some(Commands::Ingest { files, force, dry_run }) => {
+            check_connection(&client)?;
+            cmd_ingest(&client, &files, force, dry_run)
+        }
+        Some(Commands::Chat {
+            split,
+            sources,
+            transcript,
+        }) => {
+            check_connection(&client)?;
+            let transcript = transcript.map(|path| paths::resolve(&path)).transpose()?;
+            cmd_chat(&client, &config, split, sources.unwrap_or(defaults.sources), transcript)
+        }
+        None => {
+            // Chat is default (when no subcommand given)
+            check_connection(&client)?;
+            cmd_chat(&client, &config, false, defaults.sources, None)
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("{}: {}", "Error".red().bold(), e);
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
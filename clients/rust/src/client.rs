@@ -0,0 +1,927 @@
+//! Blocking HTTP client for the OSGeo Library REST API.
+
+use crate::types::*;
+use crate::{digest, failover, retry};
+use anyhow::{Context, Result};
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+/// Server used when none is given (localhost only)
+pub const DEFAULT_SERVER_URL: &str = "http://127.0.0.1:8095";
+
+/// Time allowed for uploading a PDF, longer than for other requests
+pub(crate) const UPLOAD_TIMEOUT_SECS: u64 = 600;
+
+/// Time allowed for downloading a PDF; an interrupted download resumes
+pub(crate) const DOWNLOAD_TIMEOUT_SECS: u64 = 1800;
+
+/// Documents per request when going through the whole library
+pub(crate) const LISTING_PAGE_SIZE: i32 = 100;
+
+/// User-Agent of requests, once `set_user_agent` has said more than the
+/// client's name and version
+static USER_AGENT: OnceLock<String> = OnceLock::new();
+
+/// Name requests with the features in use (e.g. "images", "repl") and
+/// `suffix` from now on: "osgeo-library-cli/0.1.0 (features: images,repl) suffix".
+pub fn set_user_agent(features: &[&str], suffix: Option<&str>) {
+    let mut agent = concat!("osgeo-library-cli/", env!("CARGO_PKG_VERSION")).to_string();
+    if !features.is_empty() {
+        agent.push_str(&format!(" (features: {})", features.join(",")));
+    }
+    if let Some(suffix) = suffix.map(str::trim).filter(|s| !s.is_empty()) {
+        agent.push_str(&format!(" {}", suffix));
+    }
+    USER_AGENT.set(agent).ok();
+}
+
+/// User-Agent of requests from this client.
+pub fn user_agent() -> &'static str {
+    USER_AGENT
+        .get()
+        .map(String::as_str)
+        .unwrap_or(concat!("osgeo-library-cli/", env!("CARGO_PKG_VERSION")))
+}
+
+/// Timeouts and retries of a client's requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestPolicy {
+    /// Time allowed for connecting to the server
+    pub connect_timeout: Duration,
+    /// Time allowed for a request, from connecting to the end of the response
+    pub timeout: Duration,
+    /// Time allowed for requests answered by the language model (chat,
+    /// synthesize, verify), which take longer
+    pub answer_timeout: Duration,
+    /// Times a request that is safe to repeat (GET, HEAD and search) is sent
+    /// again after a transient failure, with growing waits in between (0 for
+    /// none)
+    pub retries: u32,
+}
+
+impl Default for RequestPolicy {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(10),
+            timeout: Duration::from_secs(120),
+            answer_timeout: Duration::from_secs(300),
+            retries: 3,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct OsgeoClient {
+    /// Built at the first request, shared by clones: setting up HTTP and TLS
+    /// takes longer than most commands that never send one
+    client: Arc<OnceLock<Client>>,
+    /// Server and mirrors, in the order they are tried
+    servers: Vec<String>,
+    /// Index in `servers` of the one requests go to, shared by clones
+    current: Arc<AtomicUsize>,
+    policy: RequestPolicy,
+    /// Whether the last request reached the server (shown in the chat prompt)
+    reachable: std::cell::Cell<bool>,
+}
+
+impl OsgeoClient {
+    /// Client for the server at `base_url` (e.g. `DEFAULT_SERVER_URL`).
+    pub fn new(base_url: &str) -> Result<Self> {
+        Self::with_policy(base_url, RequestPolicy::default())
+    }
+
+    /// Client for the server at `base_url`, with the given timeouts and retries.
+    pub fn with_policy(base_url: &str, policy: RequestPolicy) -> Result<Self> {
+        Ok(Self {
+            client: Arc::new(OnceLock::new()),
+            servers: failover::servers(base_url, &[]),
+            current: Arc::new(AtomicUsize::new(0)),
+            policy,
+            reachable: std::cell::Cell::new(true),
+        })
+    }
+
+    /// The same client, falling back on `mirrors` (server URLs) when the
+    /// server can't be reached (see `failover`).
+    pub fn with_mirrors(mut self, mirrors: &[String]) -> Self {
+        self.servers = failover::servers(&self.servers[0], mirrors);
+        self
+    }
+
+    fn http(&self) -> &Client {
+        self.client.get_or_init(|| {
+            let client = Client::builder()
+                .user_agent(user_agent())
+                .connect_timeout(self.policy.connect_timeout)
+                .timeout(self.policy.timeout)
+                .build()
+                // Only when TLS can't be set up at all, which no request survives
+                .unwrap_or_else(|e| panic!("Failed to create HTTP client: {}", e));
+            crate::term::startup_step("HTTP client created");
+            client
+        })
+    }
+
+    /// URL of the server requests go to, without a trailing slash: the one
+    /// given, or the mirror that answered when it couldn't be reached.
+    pub fn base_url(&self) -> &str {
+        &self.servers[self.current.load(Ordering::Relaxed)]
+    }
+
+    /// The servers to fall back on from the current one, in order.
+    pub fn mirrors(&self) -> Vec<String> {
+        failover::after(self.current.load(Ordering::Relaxed), self.servers.len())
+            .map(|i| self.servers[i].clone())
+            .collect()
+    }
+
+    /// Timeouts and retries of the client's requests.
+    pub fn policy(&self) -> RequestPolicy {
+        self.policy
+    }
+
+    /// URL of an element image, from a result's slug and image path
+    /// (see `SearchResult::best_image_path`).
+    pub fn image_url(&self, slug: &str, path: &str) -> String {
+        format!("{}/image/{}/{}", self.base_url(), slug, path)
+    }
+
+    /// Whether the last request reached the server.
+    pub fn is_reachable(&self) -> bool {
+        self.reachable.get()
+    }
+
+    /// Record the reachability seen by a clone used on another thread.
+    pub(crate) fn set_reachable(&self, reachable: bool) {
+        self.reachable.set(reachable);
+    }
+
+    /// Send a request, remembering whether the server could be reached.
+    /// GET and HEAD requests are retried after transient failures (`retry`).
+    fn send(&self, request: reqwest::blocking::RequestBuilder) -> reqwest::Result<reqwest::blocking::Response> {
+        let request = request.build()?;
+        let repeatable = matches!(*request.method(), reqwest::Method::GET | reqwest::Method::HEAD);
+        self.send_request(request, repeatable)
+    }
+
+    /// Send a request that only reads, retrying it after transient failures
+    /// whatever its method.
+    fn send_repeatable(
+        &self,
+        request: reqwest::blocking::RequestBuilder,
+    ) -> reqwest::Result<reqwest::blocking::Response> {
+        self.send_request(request.build()?, true)
+    }
+
+    /// Send a request once, to a mirror if the server can't be reached.
+    fn execute(&self, mut request: reqwest::blocking::Request) -> reqwest::Result<reqwest::blocking::Response> {
+        if let Some(url) = failover::moved(request.url(), &self.servers, self.current.load(Ordering::Relaxed)) {
+            *request.url_mut() = url;
+        }
+        let spare = (self.servers.len() > 1).then(|| request.try_clone()).flatten();
+        let result = self.execute_once(request);
+        match (result, spare) {
+            (Err(error), Some(spare)) if error.is_connect() => self.fail_over(spare, error),
+            (result, _) => result,
+        }
+    }
+
+    /// Send `request`, which couldn't connect with `error`, to each mirror in
+    /// turn until one answers, and make that one the server.
+    fn fail_over(
+        &self,
+        request: reqwest::blocking::Request,
+        error: reqwest::Error,
+    ) -> reqwest::Result<reqwest::blocking::Response> {
+        let Some((from, _)) = failover::locate(request.url().as_str(), &self.servers) else {
+            return Err(error);
+        };
+        let mut last = Err(error);
+        for to in failover::after(from, self.servers.len()) {
+            let (Some(mut attempt), Some(mirror_url)) =
+                (request.try_clone(), failover::moved(request.url(), &self.servers, to))
+            else {
+                break;
+            };
+            *attempt.url_mut() = mirror_url;
+            last = self.execute_once(attempt);
+            if !matches!(&last, Err(e) if e.is_connect()) {
+                failover::report(&self.servers[from], &self.servers[to]);
+                self.current.store(to, Ordering::Relaxed);
+                break;
+            }
+        }
+        last
+    }
+
+    /// Send a request once, remembering whether the server could be reached.
+    fn execute_once(&self, request: reqwest::blocking::Request) -> reqwest::Result<reqwest::blocking::Response> {
+        let (method, url) = (request.method().to_string(), request.url().to_string());
+        let started = std::time::Instant::now();
+        let result = self.http().execute(request);
+        self.reachable.set(result.is_ok());
+        let outcome = result.as_ref().map(|r| r.status().as_u16()).map_err(|e| e.to_string());
+        crate::httplog::record(&method, &url, outcome, started.elapsed());
+        result
+    }
+
+    fn send_request(
+        &self,
+        request: reqwest::blocking::Request,
+        repeatable: bool,
+    ) -> reqwest::Result<reqwest::blocking::Response> {
+        let (method, url) = (request.method().to_string(), request.url().to_string());
+        let retries = if repeatable { self.policy.retries } else { 0 };
+        let mut request = Some(request);
+        let mut retry = 0;
+        loop {
+            // A request with a streamed body can't be copied, and is sent once
+            let current = request.take().expect("request");
+            let next = (retry < retries).then(|| current.try_clone()).flatten();
+            let result = self.execute(current);
+
+            let problem = match &result {
+                Ok(response) if retry::transient_status(response.status()) => response.status().to_string(),
+                Err(error) if retry::transient_error(error) => error.to_string(),
+                _ => return result,
+            };
+            let Some(next) = next else {
+                return result;
+            };
+            retry += 1;
+            let wait = retry::delay(retry);
+            retry::report(&method, &url, &problem, retry, retries, wait);
+            std::thread::sleep(wait);
+            request = Some(next);
+        }
+    }
+
+    pub fn health(&self) -> Result<HealthResponse> {
+        let url = format!("{}/health", self.base_url());
+        let response = self
+            .send(self.http().get(&url))
+            .context("Failed to connect to server")?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Server returned error: {}", response.status());
+        }
+
+        parse_json(response).context("Failed to parse health response")
+    }
+
+    /// Optional features of the server; `None` for servers older than the
+    /// endpoint.
+    pub fn capabilities(&self) -> Result<Option<CapabilitiesResponse>> {
+        let url = format!("{}/capabilities", self.base_url());
+        let response = self
+            .send(self.http().get(&url))
+            .context("Failed to fetch server capabilities")?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            anyhow::bail!("Server returned error: {}", response.status());
+        }
+
+        parse_json(response).map(Some).context("Failed to parse capabilities response")
+    }
+
+    pub fn search(&self, req: SearchRequest) -> Result<SearchResponse> {
+        let url = format!("{}/search", self.base_url());
+        let response = self
+            .send_repeatable(self.http().post(&url).json(&req))
+            .context("Failed to send search request")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            anyhow::bail!("Search failed ({}): {}", status, body);
+        }
+
+        parse_json(response).context("Failed to parse search response")
+    }
+
+    /// Chunks and elements nearest to `req.source`, best first.
+    pub fn similar(&self, req: SimilarRequest) -> Result<SearchResponse> {
+        let url = format!("{}/similar", self.base_url());
+        let response = self
+            .send_repeatable(self.http().post(&url).json(&req))
+            .context("Failed to send similar request")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            anyhow::bail!("Similar search failed ({}): {}", status, body);
+        }
+
+        parse_json(response).context("Failed to parse similar response")
+    }
+
+    pub fn chat(&self, req: ChatRequest) -> Result<ChatResponse> {
+        let url = format!("{}/chat", self.base_url());
+        let response = self
+            .send(self.http().post(&url).json(&req).timeout(self.policy.answer_timeout))
+            .context("Failed to send chat request")?;
+
+        if !response.status().is_success() {
+            return Err(question_failed("Chat", response));
+        }
+
+        parse_json(response).context("Failed to parse chat response")
+    }
+
+    /// Like `chat`, but calls `on_text` with each piece of the answer as it
+    /// is generated. Returns `None` if the server has no streaming endpoint.
+    pub fn chat_stream(
+        &self,
+        req: &ChatRequest,
+        mut on_text: impl FnMut(&str) -> Result<()>,
+    ) -> Result<Option<ChatResponse>> {
+        use std::io::BufRead;
+
+        let url = format!("{}/chat/stream", self.base_url());
+        let response = self
+            .send(self.http().post(&url).json(req).timeout(self.policy.answer_timeout))
+            .context("Failed to send chat request")?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::METHOD_NOT_ALLOWED {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            return Err(question_failed("Chat", response));
+        }
+
+        let mut meta = None;
+        let mut answer = String::new();
+        for line in std::io::BufReader::new(response).lines() {
+            let line = line.context("Chat stream interrupted")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line).context("Failed to parse chat stream")? {
+                ChatEvent::Meta(response) => meta = Some(response),
+                ChatEvent::Token { text } => {
+                    answer.push_str(&text);
+                    on_text(&text)?;
+                }
+                ChatEvent::Done => break,
+            }
+        }
+
+        let mut response: ChatResponse = meta.context("Chat stream ended before sending sources")?;
+        response.answer = answer;
+        Ok(Some(response))
+    }
+
+    pub fn synthesize(&self, req: SynthesisRequest) -> Result<SynthesisResponse> {
+        let url = format!("{}/synthesize", self.base_url());
+        let response = self
+            .send(self.http().post(&url).json(&req).timeout(self.policy.answer_timeout))
+            .context("Failed to send synthesis request")?;
+
+        if !response.status().is_success() {
+            return Err(question_failed("Synthesis", response));
+        }
+
+        parse_json(response).context("Failed to parse synthesis response")
+    }
+
+    pub fn verify(&self, req: VerifyRequest) -> Result<VerifyResponse> {
+        let url = format!("{}/verify", self.base_url());
+        let response = self
+            .send(self.http().post(&url).json(&req).timeout(self.policy.answer_timeout))
+            .context("Failed to send verify request")?;
+
+        if !response.status().is_success() {
+            return Err(question_failed("Verify", response));
+        }
+
+        parse_json(response).context("Failed to parse verify response")
+    }
+
+    pub fn acronym(&self, acronym: &str, document: Option<&str>, max_sources: i32) -> Result<AcronymResponse> {
+        let mut url = format!(
+            "{}/acronym/{}?max_sources={}",
+            self.base_url(), acronym, max_sources
+        );
+        if let Some(slug) = document {
+            url.push_str(&format!("&document_slug={}", slug));
+        }
+        let response = self
+            .send(self.http().get(&url))
+            .context("Failed to look up acronym")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            anyhow::bail!("Acronym lookup failed ({}): {}", status, body);
+        }
+
+        parse_json(response).context("Failed to parse acronym response")
+    }
+
+    pub fn list_documents(&self, page: i32, page_size: i32, sort_by: &str) -> Result<DocumentListResponse> {
+        self.list_documents_matching(page, page_size, sort_by, &DocumentFilter::default())
+    }
+
+    /// Every document in the library, sorted by `sort_by`, fetched a page at
+    /// a time as the iterator is consumed. Only one page is held at once, and
+    /// the next one is not asked for until the caller has handled the last,
+    /// so a large library is printed as it arrives rather than after it has
+    /// all been read. A failed page ends the iteration with its error.
+    pub fn documents<'a>(&'a self, sort_by: &'a str) -> impl Iterator<Item = Result<DocumentListItem>> + 'a {
+        let mut page = 0;
+        let mut last_page = 1;
+        let mut current = Vec::new().into_iter();
+        std::iter::from_fn(move || loop {
+            if let Some(doc) = current.next() {
+                return Some(Ok(doc));
+            }
+            if page >= last_page {
+                return None;
+            }
+            page += 1;
+            match self.list_documents(page, LISTING_PAGE_SIZE, sort_by) {
+                Ok(response) => {
+                    last_page = response.total_pages;
+                    current = response.documents.into_iter();
+                }
+                Err(e) => {
+                    last_page = page;
+                    return Some(Err(e));
+                }
+            }
+        })
+    }
+
+    /// Like `list_documents`, only the documents passing `filter` (on servers
+    /// with the `docs_filters` capability; others ignore it).
+    pub fn list_documents_matching(
+        &self,
+        page: i32,
+        page_size: i32,
+        sort_by: &str,
+        filter: &DocumentFilter,
+    ) -> Result<DocumentListResponse> {
+        let url = format!(
+            "{}/documents?page={}&page_size={}&sort_by={}",
+            self.base_url(), page, page_size, sort_by
+        );
+        let response = self
+            .send(self.http().get(&url).query(filter))
+            .context("Failed to fetch documents")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            anyhow::bail!("Failed to list documents ({}): {}", status, body);
+        }
+
+        parse_json(response).context("Failed to parse documents response")
+    }
+
+    /// Like `list_documents` (by title), with each document's
+    /// `content_checksum`: the same for the same PDF ingested twice under
+    /// different names. Slower, as the server reads every page's text.
+    pub fn list_documents_with_checksums(&self, page: i32, page_size: i32) -> Result<DocumentListResponse> {
+        let url = format!(
+            "{}/documents?page={}&page_size={}&checksums=true",
+            self.base_url(), page, page_size
+        );
+        let response = self
+            .send(self.http().get(&url))
+            .context("Failed to fetch documents")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            anyhow::bail!("Failed to list documents ({}): {}", status, body);
+        }
+
+        parse_json(response).context("Failed to parse documents response")
+    }
+
+    /// Delete a document with its pages, chunks and elements. Servers refuse
+    /// (403) unless deleting is enabled in their config.
+    pub fn delete_document(&self, slug: &str) -> Result<DocumentDeleteResponse> {
+        let url = format!("{}/documents/{}", self.base_url(), slug);
+        let response = self
+            .send(self.http().delete(&url))
+            .context("Failed to send delete request")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            anyhow::bail!("Failed to delete document ({}): {}", status, body);
+        }
+
+        parse_json(response).context("Failed to parse delete response")
+    }
+
+    /// Upload a PDF to be extracted, enriched and ingested in the background
+    /// (see `get_ingest_job`). `on_sent` is called with the bytes sent so far
+    /// and the total. Servers refuse (409) files already in the library
+    /// unless `force`, and (403) uploads unless enabled in their config.
+    pub fn ingest_document<F>(&self, file_name: &str, pdf: Vec<u8>, force: bool, on_sent: F) -> Result<IngestJob>
+    where
+        F: FnMut(u64, u64) + Send + 'static,
+    {
+        let url = format!("{}/ingest", self.base_url());
+        let total = pdf.len() as u64;
+        let body = reqwest::blocking::Body::sized(
+            Upload {
+                data: std::io::Cursor::new(pdf),
+                total,
+                on_sent,
+            },
+            total,
+        );
+        let request = self
+            .http()
+            .post(&url)
+            .query(&[("filename", file_name), ("force", if force { "true" } else { "false" })])
+            .header(reqwest::header::CONTENT_TYPE, "application/pdf")
+            .timeout(Duration::from_secs(UPLOAD_TIMEOUT_SECS))
+            .body(body);
+        let response = self.send(request).context("Failed to upload document")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            anyhow::bail!("Upload refused ({}): {}", status, body);
+        }
+
+        parse_json(response).context("Failed to parse ingest response")
+    }
+
+    /// Size and checksum of the original PDF of `slug`, without downloading
+    /// it. Servers refuse (403) unless downloads are enabled in their config.
+    pub fn document_file_info(&self, slug: &str) -> Result<RemoteFile> {
+        let url = format!("{}/documents/{}/file", self.base_url(), slug);
+        let response = self
+            .send(self.http().head(&url))
+            .context("Failed to fetch document file size")?;
+
+        match response.status() {
+            status if status.is_success() => {}
+            reqwest::StatusCode::NOT_FOUND => anyhow::bail!("No original PDF of '{}' on the server", slug),
+            status => anyhow::bail!("Failed to get document file ({})", status),
+        }
+
+        let headers = response.headers();
+        Ok(RemoteFile {
+            size: headers
+                .get(reqwest::header::CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok()),
+            sha256: digest::expected(headers),
+        })
+    }
+
+    /// Start downloading the original PDF of `slug` from byte `offset`, to
+    /// resume an earlier download. The body is read from the returned
+    /// `Download` as it arrives.
+    pub fn download_document(&self, slug: &str, offset: u64) -> Result<Download> {
+        let url = format!("{}/documents/{}/file", self.base_url(), slug);
+        let mut request = self
+            .http()
+            .get(&url)
+            .timeout(Duration::from_secs(DOWNLOAD_TIMEOUT_SECS));
+        if offset > 0 {
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+        }
+        let response = self.send(request).context("Failed to download document")?;
+
+        let start = match response.status() {
+            reqwest::StatusCode::PARTIAL_CONTENT => offset,
+            status if status.is_success() => 0,
+            status => {
+                let body = response.text().unwrap_or_default();
+                anyhow::bail!("Failed to download document ({}): {}", status, body);
+            }
+        };
+        Ok(Download { start, response })
+    }
+
+    /// Progress of an upload started with `ingest_document`.
+    pub fn get_ingest_job(&self, job_id: &str) -> Result<IngestJob> {
+        let url = format!("{}/ingest/{}", self.base_url(), job_id);
+        let response = self
+            .send(self.http().get(&url))
+            .context("Failed to fetch ingest progress")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            anyhow::bail!("Failed to get ingest progress ({}): {}", status, body);
+        }
+
+        parse_json(response).context("Failed to parse ingest progress")
+    }
+
+    pub fn get_document(&self, slug: &str) -> Result<DocumentDetailResponse> {
+        let url = format!("{}/documents/{}", self.base_url(), slug);
+        let response = self
+            .send(self.http().get(&url))
+            .context("Failed to fetch document")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            anyhow::bail!("Failed to get document ({}): {}", status, body);
+        }
+
+        parse_json(response).context("Failed to parse document response")
+    }
+
+    pub fn list_elements(
+        &self,
+        slug: &str,
+        element_type: Option<&str>,
+        page: Option<i32>,
+        limit: i32,
+        offset: i32,
+    ) -> Result<ElementListResponse> {
+        let mut url = format!(
+            "{}/documents/{}/elements?limit={}&offset={}",
+            self.base_url(), slug, limit, offset
+        );
+        if let Some(t) = element_type {
+            url.push_str(&format!("&element_type={}", t));
+        }
+        if let Some(p) = page {
+            url.push_str(&format!("&page={}", p));
+        }
+        let response = self
+            .send(self.http().get(&url))
+            .context("Failed to fetch elements")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            anyhow::bail!("Failed to list elements ({}): {}", status, body);
+        }
+
+        parse_json(response).context("Failed to parse elements response")
+    }
+
+    pub fn list_chunks(&self, slug: &str, limit: i32, offset: i32) -> Result<ChunkListResponse> {
+        let url = format!(
+            "{}/documents/{}/chunks?limit={}&offset={}",
+            self.base_url(), slug, limit, offset
+        );
+        let response = self
+            .send(self.http().get(&url))
+            .context("Failed to fetch chunks")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            anyhow::bail!("Failed to list chunks ({}): {}", status, body);
+        }
+
+        parse_json(response).context("Failed to parse chunks response")
+    }
+
+    /// Text chunks of one page, in reading order.
+    pub fn list_page_chunks(&self, slug: &str, page: i32) -> Result<ChunkListResponse> {
+        let url = format!("{}/documents/{}/chunks?page={}&limit=500", self.base_url(), slug, page);
+        let response = self
+            .send(self.http().get(&url))
+            .context("Failed to fetch chunks")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            anyhow::bail!("Failed to list chunks ({}): {}", status, body);
+        }
+
+        parse_json(response).context("Failed to parse chunks response")
+    }
+
+    pub fn list_page_summaries(&self, slug: &str, limit: i32, offset: i32) -> Result<PageSummaryListResponse> {
+        let url = format!(
+            "{}/documents/{}/pages?limit={}&offset={}",
+            self.base_url(), slug, limit, offset
+        );
+        let response = self
+            .send(self.http().get(&url))
+            .context("Failed to fetch page summaries")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            anyhow::bail!("Failed to list pages ({}): {}", status, body);
+        }
+
+        parse_json(response).context("Failed to parse pages response")
+    }
+
+    pub fn list_chapters(&self, slug: &str) -> Result<ChapterListResponse> {
+        let url = format!("{}/documents/{}/chapters", self.base_url(), slug);
+        let response = self
+            .send(self.http().get(&url))
+            .context("Failed to fetch chapters")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            anyhow::bail!("Failed to list chapters ({}): {}", status, body);
+        }
+
+        parse_json(response).context("Failed to parse chapters response")
+    }
+
+    /// Chapters and numbered sections ("4.2", "4.2.1"), in page order.
+    pub fn list_sections(&self, slug: &str) -> Result<SectionListResponse> {
+        let url = format!("{}/documents/{}/sections", self.base_url(), slug);
+        let response = self
+            .send(self.http().get(&url))
+            .context("Failed to fetch sections")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            anyhow::bail!("Failed to list sections ({}): {}", status, body);
+        }
+
+        parse_json(response).context("Failed to parse sections response")
+    }
+
+    /// Fetch a page image with metadata, scaled down to `width` pixels when
+    /// given. Servers without scaling ignore the parameter and send the page
+    /// at full resolution. The image is asked for as raw bytes; older servers
+    /// send it base64-encoded in JSON.
+    pub fn get_page(&self, slug: &str, page_number: i32, width: Option<u32>) -> Result<PageResponse> {
+        let url = format!("{}/page/{}/{}", self.base_url(), slug, page_number);
+        let mut request = self.http().get(&url).header(reqwest::header::ACCEPT, PAGE_ACCEPT);
+        if let Some(width) = width {
+            request = request.query(&[("width", width)]);
+        }
+        for _ in 0..digest::ATTEMPTS {
+            let response = self
+                .send(request.try_clone().context("Failed to fetch page")?)
+                .context("Failed to fetch page")?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let body = response.text().unwrap_or_default();
+                anyhow::bail!("Failed to get page ({}): {}", status, body);
+            }
+
+            let metadata = response.headers().get(PAGE_METADATA).cloned();
+            let expected = digest::expected(response.headers());
+            let body = response.bytes().context("Failed to read page response")?;
+            if digest::matches(expected.as_deref(), &body) {
+                return decode_page(metadata.as_ref(), &body);
+            }
+        }
+        Err(digest::damaged("Page image"))
+    }
+
+    /// Fetch image bytes from the server, again when they don't match the
+    /// server's checksum.
+    pub fn fetch_image(&self, url: &str) -> Result<Vec<u8>> {
+        for _ in 0..digest::ATTEMPTS {
+            let response = self
+                .send(self.http().get(url))
+                .context("Failed to fetch image")?;
+
+            if !response.status().is_success() {
+                anyhow::bail!("Image not found ({})", response.status());
+            }
+
+            let expected = digest::expected(response.headers());
+            let bytes = response.bytes().context("Failed to read image bytes")?;
+            if digest::matches(expected.as_deref(), &bytes) {
+                return Ok(bytes.to_vec());
+            }
+        }
+        Err(digest::damaged("Image"))
+    }
+
+    /// Size and checksum of the image at `url`, without downloading it.
+    /// Both unknown when the server doesn't say (servers before HEAD support
+    /// answer 405).
+    pub fn image_info(&self, url: &str) -> Result<RemoteFile> {
+        let response = self
+            .send(self.http().head(url))
+            .context("Failed to fetch image size")?;
+
+        match response.status() {
+            status if status.is_success() => {}
+            reqwest::StatusCode::METHOD_NOT_ALLOWED => return Ok(RemoteFile::default()),
+            status => anyhow::bail!("Image not found ({})", status),
+        }
+
+        let headers = response.headers();
+        Ok(RemoteFile {
+            size: headers
+                .get(reqwest::header::CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok()),
+            sha256: digest::expected(headers),
+        })
+    }
+}
+
+/// A download started by `download_document`; reading it reads the body.
+pub struct Download {
+    /// Byte of the file the body starts at: the offset asked for, or 0 when
+    /// the server sent the whole file
+    pub start: u64,
+    response: reqwest::blocking::Response,
+}
+
+impl std::io::Read for Download {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.response.read(buf)
+    }
+}
+
+/// What the server says about a file, from a HEAD request.
+#[derive(Debug, Default)]
+pub struct RemoteFile {
+    pub size: Option<u64>,
+    /// Lowercase hex
+    pub sha256: Option<String>,
+}
+
+/// Accept header for page requests: the image itself rather than base64 in JSON
+pub(crate) const PAGE_ACCEPT: &str = "image/*, application/json;q=0.5";
+/// Header holding the page's other fields when the image is sent as raw bytes
+pub(crate) const PAGE_METADATA: &str = "x-page-metadata";
+
+/// Page from a `/page` response: the raw image with base64-encoded JSON
+/// metadata in a header, or (older servers) JSON with a base64 image.
+pub(crate) fn decode_page(metadata: Option<&reqwest::header::HeaderValue>, body: &[u8]) -> Result<PageResponse> {
+    use base64::{engine::general_purpose, Engine as _};
+
+    match metadata {
+        Some(metadata) => {
+            let json = general_purpose::STANDARD
+                .decode(metadata.as_bytes())
+                .context("Failed to decode page metadata")?;
+            let mut page: PageResponse = serde_json::from_slice(&json).context("Failed to parse page metadata")?;
+            page.image = body.to_vec();
+            Ok(page)
+        }
+        None => {
+            let mut page: PageResponse = serde_json::from_slice(body).context("Failed to parse page response")?;
+            page.image = general_purpose::STANDARD
+                .decode(&page.image_base64)
+                .context("Failed to decode base64 image")?;
+            Ok(page)
+        }
+    }
+}
+
+/// Parse a JSON response from the connection as it is read, rather than
+/// reading the whole body into memory first. The value itself is built
+/// whole, so listings of the whole library are read a page at a time (see
+/// `OsgeoClient::documents`) to keep each response small.
+fn parse_json<T: serde::de::DeserializeOwned>(response: reqwest::blocking::Response) -> serde_json::Result<T> {
+    serde_json::from_reader(std::io::BufReader::new(response))
+}
+
+/// The error of a question that failed. A proxy in front of the server that
+/// refuses a large request says so with a 413 and an HTML page at best, so
+/// that one is put in words.
+fn question_failed(what: &str, response: reqwest::blocking::Response) -> anyhow::Error {
+    let status = response.status();
+    if status == reqwest::StatusCode::PAYLOAD_TOO_LARGE {
+        return anyhow::anyhow!("{} failed ({}): the request is too large for the server; shorten the question", what, status);
+    }
+    let body = response.text().unwrap_or_default();
+    anyhow::anyhow!("{} failed ({}): {}", what, status, body)
+}
+
+/// One line of a `/chat/stream` response.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(crate) enum ChatEvent {
+    /// Sources, query and context usage, sent before the answer
+    Meta(ChatResponse),
+    Token { text: String },
+    Done,
+}
+
+/// Request body of an upload, reporting how much has been read (sent).
+struct Upload<F> {
+    data: std::io::Cursor<Vec<u8>>,
+    total: u64,
+    on_sent: F,
+}
+
+impl<F: FnMut(u64, u64)> std::io::Read for Upload<F> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.data.read(buf)?;
+        (self.on_sent)(self.data.position(), self.total);
+        Ok(n)
+    }
+}
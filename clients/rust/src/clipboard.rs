@@ -0,0 +1,63 @@
+//! Copying to the system clipboard (`copy N` in chat, `search --copy N`),
+//! and reading from it (`watch-clipboard`).
+//!
+//! On Linux the clipboard holds no data itself: the program that copied
+//! hands the text to whoever pastes, until something else is copied. Chat
+//! keeps its clipboard open for the whole session. A one-off command waits,
+//! up to `HOLD_SECS`, for a clipboard manager or a paste to take the text
+//! before it exits; desktops with a clipboard manager return at once.
+
+use anyhow::{Context, Result};
+use std::sync::Mutex;
+
+/// Longest a one-off command keeps serving the copied text
+#[cfg(target_os = "linux")]
+const HOLD_SECS: u64 = 60;
+
+/// The session's clipboard, kept so that what chat copied stays pasteable
+static CLIPBOARD: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+
+/// Put `text` on the clipboard for the rest of the session.
+pub fn copy(text: &str) -> Result<()> {
+    with_session(|clipboard| clipboard.set_text(text).context("Failed to copy to the clipboard"))
+}
+
+/// The text on the clipboard; `None` when it holds none (nothing, or only
+/// an image).
+pub fn text() -> Result<Option<String>> {
+    with_session(|clipboard| match clipboard.get_text() {
+        Ok(text) => Ok(Some(text)),
+        Err(arboard::Error::ContentNotAvailable) => Ok(None),
+        Err(e) => Err(e).context("Failed to read the clipboard"),
+    })
+}
+
+fn with_session<T>(f: impl FnOnce(&mut arboard::Clipboard) -> Result<T>) -> Result<T> {
+    let mut clipboard = CLIPBOARD.lock().unwrap_or_else(|e| e.into_inner());
+    if clipboard.is_none() {
+        *clipboard = Some(open()?);
+    }
+    f(clipboard.as_mut().expect("clipboard was just opened"))
+}
+
+/// Put `text` on the clipboard from a command about to exit.
+#[cfg(target_os = "linux")]
+pub fn copy_and_hold(text: &str) -> Result<()> {
+    use arboard::SetExtLinux;
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(HOLD_SECS);
+    open()?
+        .set()
+        .wait_until(deadline)
+        .text(text)
+        .context("Failed to copy to the clipboard")
+}
+
+/// Put `text` on the clipboard from a command about to exit.
+#[cfg(not(target_os = "linux"))]
+pub fn copy_and_hold(text: &str) -> Result<()> {
+    copy(text)
+}
+
+fn open() -> Result<arboard::Clipboard> {
+    arboard::Clipboard::new().context("No clipboard available (is a display running?)")
+}
@@ -0,0 +1,65 @@
+//! Two-column listings on wide terminals.
+//!
+//! Document listings grow with the library; on a terminal at least
+//! `MIN_WIDTH` columns wide, entries are laid out in two columns (filled top
+//! to bottom, then left to right, like `ls`) to halve the scrolling.
+//! Narrower terminals, and output that isn't a terminal, keep one column.
+
+const MIN_WIDTH: usize = 100;
+/// Spaces between the two columns
+const GAP: usize = 3;
+
+/// Width of each column when the terminal fits two, or `None` for one.
+pub fn column_width() -> Option<usize> {
+    let width = crate::term::width();
+    (width >= MIN_WIDTH).then(|| (width - GAP) / 2)
+}
+
+/// Print cells side by side, each a few lines that may be colored but are
+/// no wider than `column_width` once printed (see `fit`).
+pub fn print(cells: &[Vec<String>], column_width: usize) {
+    let rows = cells.len().div_ceil(2);
+    for (row, left) in cells.iter().take(rows).enumerate() {
+        let right = cells.get(row + rows);
+        let height = left.len().max(right.map_or(0, Vec::len));
+        for line in 0..height {
+            let l = left.get(line).map(String::as_str).unwrap_or("");
+            match right.and_then(|r| r.get(line)).filter(|r| !r.is_empty()) {
+                Some(r) => {
+                    let pad = column_width.saturating_sub(visible_width(l)) + GAP;
+                    println!("{}{}{}", l, " ".repeat(pad), r);
+                }
+                None => println!("{}", l),
+            }
+        }
+    }
+}
+
+/// Shorten `text` to at most `width` characters, marking the cut with "…".
+pub fn fit(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+/// Printed width of `text`, not counting ANSI color sequences.
+fn visible_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip "ESC [ params letter"
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
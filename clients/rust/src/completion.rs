@@ -0,0 +1,150 @@
+//! Shell completion (`completions <shell>`), document slugs included.
+//!
+//! The script `completions` prints calls back into the client at each Tab
+//! (with `COMPLETE=<shell>` set, see `clap_complete::env`), so besides
+//! commands and flags it can complete values only the server knows: the
+//! slugs after `doc`, `-d/--document` and the like. Slugs are listed from
+//! the configured server (`OSGEO_SERVER_URL`, else `defaults.server`) and
+//! cached for `MAX_AGE_SECS`, so repeated Tabs don't each wait on it; a
+//! server that doesn't answer within a couple of seconds completes nothing.
+//! `completions --static` prints a script that never runs the client, for
+//! shells or machines where that is unwanted, without slugs.
+
+use crate::client::{OsgeoClient, DEFAULT_SERVER_URL};
+use crate::config::Config;
+use anyhow::{Context, Result};
+use clap_complete::engine::CompletionCandidate;
+use clap_complete::env::Shells;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// How long the slugs listed are trusted before asking again
+const MAX_AGE_SECS: u64 = 300;
+
+/// Longest wait on the server while a Tab is pressed
+const TIMEOUT_SECS: u64 = 2;
+
+/// Environment variable the script sets when it calls back
+const VAR: &str = "COMPLETE";
+
+/// Print the completion script for `shell`.
+pub fn write_script(cmd: &mut clap::Command, shell: clap_complete::Shell, static_only: bool) -> Result<()> {
+    // The name the client was run by, which the script completes
+    let bin = std::env::args_os()
+        .next()
+        .map(PathBuf::from)
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| cmd.get_name().to_string());
+    // Written in one piece: clap_complete panics on a closed pipe
+    let mut script = Vec::new();
+    if static_only {
+        clap_complete::generate(shell, cmd, bin, &mut script);
+    } else {
+        let shells = Shells::builtins();
+        let completer = shells
+            .completer(&shell.to_string())
+            .with_context(|| format!("No completions for {}", shell))?;
+        let exe = std::env::current_exe().context("Can't tell where the client is installed")?;
+        let name = cmd.get_name().to_string();
+        completer.write_registration(VAR, &name, &bin, &exe.to_string_lossy(), &mut script)?;
+    }
+    let mut out = std::io::stdout().lock();
+    out.write_all(&script)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Document slugs starting with `current`, with their titles.
+pub fn document_slugs(current: &OsStr) -> Vec<CompletionCandidate> {
+    let current = current.to_string_lossy();
+    documents()
+        .into_iter()
+        .filter(|(slug, _)| slug.starts_with(current.as_ref()))
+        .map(|(slug, title)| CompletionCandidate::new(slug).help(Some(title.into())))
+        .collect()
+}
+
+#[derive(Serialize, Deserialize)]
+struct Cached {
+    /// Unix time the server was asked
+    fetched: u64,
+    /// Slug and title of each document
+    documents: Vec<(String, String)>,
+}
+
+/// The configured server's documents, from the cache when fresh; none when
+/// the server can't be asked.
+fn documents() -> Vec<(String, String)> {
+    let Some(server) = server() else {
+        return Vec::new();
+    };
+    let mut cache = read_cache();
+    if let Some(cached) = cache.remove(&server).filter(|c| now().saturating_sub(c.fetched) < MAX_AGE_SECS) {
+        return cached.documents;
+    }
+    let Ok(documents) = fetch(&server) else {
+        return Vec::new();
+    };
+    write_cache(&server, &documents);
+    documents
+}
+
+/// The server commands would use, by the environment and the config. Flags
+/// on the line being completed aren't seen.
+fn server() -> Option<String> {
+    if let Some(url) = std::env::var("OSGEO_SERVER_URL").ok().filter(|u| !u.is_empty()) {
+        return Some(url);
+    }
+    let profile = std::env::var("OSGEO_PROFILE").ok();
+    let config = Config::load(profile.as_deref()).ok()?;
+    Some(config.defaults.server.unwrap_or_else(|| DEFAULT_SERVER_URL.to_string()))
+}
+
+fn fetch(server: &str) -> Result<Vec<(String, String)>> {
+    let policy = Config::default().network.policy(Some(TIMEOUT_SECS), Some(TIMEOUT_SECS), Some(0));
+    list(&OsgeoClient::with_policy(server, policy)?)
+}
+
+/// Slug and title of every document on `client`'s server.
+pub fn list(client: &OsgeoClient) -> Result<Vec<(String, String)>> {
+    client.documents("title").map(|doc| doc.map(|d| (d.slug, d.title))).collect()
+}
+
+fn path() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("osgeo-library").join("slugs.json"))
+}
+
+/// Cached documents by server URL; empty when there is no usable cache.
+fn read_cache() -> BTreeMap<String, Cached> {
+    path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn write_cache(server: &str, documents: &[(String, String)]) {
+    let Some(path) = path() else {
+        return;
+    };
+    let cached = Cached {
+        fetched: now(),
+        documents: documents.to_vec(),
+    };
+    // A cache that can't be written only costs a request next time
+    crate::shared::update(&path, |text| {
+        let mut servers: BTreeMap<String, Cached> = serde_json::from_str(&text).unwrap_or_default();
+        servers.insert(server.to_string(), cached);
+        Ok(serde_json::to_string_pretty(&servers)?)
+    })
+    .ok();
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
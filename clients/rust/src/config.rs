@@ -0,0 +1,433 @@
+//! User configuration.
+//!
+//! Loaded once at startup from `~/.config/osgeo-library/config.toml`
+//! (or `$XDG_CONFIG_HOME/osgeo-library/config.toml`, or the path in
+//! `OSGEO_CONFIG`). A missing file means built-in defaults; every key is
+//! optional. Profiles are partial configs under `[profiles.NAME]`, laid over
+//! the rest of the file with `--profile NAME`. `osgeo-library config`
+//! reads and edits the file.
+//!
+//! ```toml
+//! [defaults]
+//! server = "http://127.0.0.1:8095" # --server
+//! mirrors = ["http://mirror:8095"]  # --mirror, tried when the server is down
+//! document = "usgs_snyder"  # search/ask -d and the chat document ('-d all' to lift)
+//! image_backend = "auto"  # --image-backend: auto, halfblocks, kitty, iterm, chafa, symbols, sixels, none
+//! search_limit = 25       # search -n
+//! ask_limit = 8           # ask -n / chat context results
+//! docs_page_size = 20     # docs -n
+//! chat_docs_page_size = 5 # docs listing inside chat
+//! sources_display = 0     # sources listed after a chat answer (0 = all)
+//! sources = "compact"     # source listing in chat: off, compact, full
+//!
+//! [prompt]
+//! format = "{doc}:{page}> " # see prompt.rs for placeholders
+//! color = "cyan"            # any terminal color name, or "none"
+//!
+//! [ranking.types]           # score boosts, see ranking.rs
+//! equation = 5
+//!
+//! [templates]               # ask --template, see templates.rs
+//! epsg = "What is the EPSG definition of {topic}?"
+//!
+//! [hooks]                   # external commands, see hooks.rs
+//! on_show = "my-script {path}"
+//!
+//! [paths]                   # see paths.rs
+//! exports = "~/osgeo-exports" # --out-dir
+//! name = "{slug}_{page}_{type}"
+//!
+//! [federation]              # searched with the server, see federation.rs
+//! hydro = "http://hydro.example.org:8095"
+//!
+//! [network]                 # seconds; see retry.rs
+//! timeout = 120             # --timeout
+//! answer_timeout = 300      # chat, synthesize, verify (--timeout sets both)
+//! connect_timeout = 10      # --connect-timeout
+//! retries = 3               # --retries
+//! user_agent_suffix = "acme-gis-team" # after the client's name and version
+//!
+//! [profiles.remote.defaults] # --profile remote
+//! server = "http://localhost:18095"
+//! ```
+
+use crate::client::RequestPolicy;
+use crate::ranking::Ranking;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub defaults: Defaults,
+    pub prompt: PromptConfig,
+    pub ranking: Ranking,
+    /// Extra or overriding question templates, keyed by name
+    pub templates: HashMap<String, String>,
+    pub hooks: Hooks,
+    pub paths: Paths,
+    pub network: Network,
+    /// Other libraries `search` queries along with the server, by name
+    pub federation: BTreeMap<String, String>,
+    /// Named partial configs, applied with `--profile`
+    #[serde(skip_serializing)]
+    pub profiles: HashMap<String, toml::Table>,
+}
+
+/// Defaults used when no flag is given: server, document, image output and
+/// limits per command.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Defaults {
+    /// Server URL; `--server` and `OSGEO_SERVER_URL` take precedence
+    pub server: Option<String>,
+    /// Servers with the same library, tried in order when the server can't
+    /// be reached; `--mirror` takes precedence
+    pub mirrors: Vec<String>,
+    /// Document searches, questions and chat are scoped to
+    pub document: Option<String>,
+    pub image_backend: ImageBackend,
+    pub search_limit: i32,
+    pub ask_limit: i32,
+    pub docs_page_size: i32,
+    pub chat_docs_page_size: i32,
+    pub sources_display: usize,
+    pub sources: SourcesView,
+}
+
+impl Default for Defaults {
+    fn default() -> Self {
+        Self {
+            server: None,
+            mirrors: Vec::new(),
+            document: None,
+            image_backend: ImageBackend::Auto,
+            search_limit: 10,
+            ask_limit: 8,
+            docs_page_size: 20,
+            chat_docs_page_size: 5,
+            sources_display: 0,
+            sources: SourcesView::Compact,
+        }
+    }
+}
+
+/// How images are drawn in the terminal (`--image-backend`). Half blocks,
+/// Kitty, iTerm2 and sixel graphics are drawn by the client; `chafa` and
+/// `symbols` run chafa.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageBackend {
+    /// The best the terminal supports: Kitty, iTerm2 or sixel graphics,
+    /// otherwise half blocks
+    Auto,
+    /// Truecolor half-block characters, two pixels per cell; works in any
+    /// terminal with 24-bit color
+    Halfblocks,
+    /// Kitty graphics protocol (kitty, WezTerm, Ghostty)
+    Kitty,
+    /// iTerm2 inline images (iTerm2, WezTerm)
+    Iterm,
+    /// chafa, with the format it detects for the terminal
+    Chafa,
+    /// chafa's Unicode block symbols
+    Symbols,
+    /// Sixel graphics (xterm, foot, mlterm, ...), up to 256 colors
+    Sixels,
+    /// No terminal images
+    None,
+}
+
+impl ImageBackend {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "halfblocks" => Ok(Self::Halfblocks),
+            "kitty" => Ok(Self::Kitty),
+            "iterm" => Ok(Self::Iterm),
+            "chafa" => Ok(Self::Chafa),
+            "symbols" => Ok(Self::Symbols),
+            "sixels" => Ok(Self::Sixels),
+            "none" => Ok(Self::None),
+            _ => Err(format!(
+                "Unknown image backend '{}'. Use: auto, halfblocks, kitty, iterm, chafa, symbols, sixels, none",
+                value
+            )),
+        }
+    }
+
+    /// Whether images are handed to chafa.
+    pub fn uses_chafa(self) -> bool {
+        matches!(self, Self::Chafa | Self::Symbols)
+    }
+
+    /// chafa's `--format` (also when chafa stands in for sixels); `None` to
+    /// let chafa detect it.
+    pub fn chafa_format(self) -> Option<&'static str> {
+        match self {
+            Self::Symbols => Some("symbols"),
+            Self::Sixels => Some("sixels"),
+            _ => None,
+        }
+    }
+}
+
+/// How much of each source is listed after an answer (`--sources`,
+/// `set sources` in chat).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourcesView {
+    /// Just the number of sources
+    Off,
+    /// One line per source
+    Compact,
+    /// One line per source plus a snippet of its content
+    Full,
+}
+
+impl SourcesView {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().as_str() {
+            "off" => Ok(Self::Off),
+            "compact" => Ok(Self::Compact),
+            "full" => Ok(Self::Full),
+            _ => Err(format!("Unknown sources view '{}'. Use: off, compact, full", value)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Compact => "compact",
+            Self::Full => "full",
+        }
+    }
+}
+
+/// External commands run on results (see hooks.rs).
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hooks {
+    /// Run after `show` displays an element image in chat
+    pub on_show: Option<String>,
+}
+
+/// Where exports are written and how they are named (see paths.rs).
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Paths {
+    /// Export directory; `--out-dir` takes precedence
+    pub exports: Option<PathBuf>,
+    /// Template for names the client chooses
+    pub name: String,
+}
+
+impl Default for Paths {
+    fn default() -> Self {
+        Self {
+            exports: None,
+            name: crate::paths::DEFAULT_NAME.to_string(),
+        }
+    }
+}
+
+/// Timeouts in seconds, and retries of requests that are safe to repeat
+/// (see retry.rs). Flags take precedence.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Network {
+    /// Requests other than questions and uploads; `--timeout`
+    pub timeout: u64,
+    /// Questions answered by the language model; `--timeout`
+    pub answer_timeout: u64,
+    /// Connecting to the server; `--connect-timeout`
+    pub connect_timeout: u64,
+    /// `--retries`
+    pub retries: u32,
+    /// Added to the User-Agent of every request, so the server's operators
+    /// can tell this client's traffic apart (e.g. "acme-gis-team")
+    pub user_agent_suffix: Option<String>,
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        let policy = RequestPolicy::default();
+        Self {
+            timeout: policy.timeout.as_secs(),
+            answer_timeout: policy.answer_timeout.as_secs(),
+            connect_timeout: policy.connect_timeout.as_secs(),
+            retries: policy.retries,
+            user_agent_suffix: None,
+        }
+    }
+}
+
+impl Network {
+    /// The policy for a client, with the timeouts given on the command line
+    /// in place of the configured ones.
+    pub fn policy(&self, timeout: Option<u64>, connect_timeout: Option<u64>, retries: Option<u32>) -> RequestPolicy {
+        RequestPolicy {
+            connect_timeout: Duration::from_secs(connect_timeout.unwrap_or(self.connect_timeout)),
+            timeout: Duration::from_secs(timeout.unwrap_or(self.timeout)),
+            answer_timeout: Duration::from_secs(timeout.unwrap_or(self.answer_timeout)),
+            retries: retries.unwrap_or(self.retries),
+        }
+    }
+}
+
+/// Chat prompt appearance. Without a format the prompt is "You: "
+/// ("> " in plain output).
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PromptConfig {
+    pub format: Option<String>,
+    pub color: String,
+}
+
+impl Default for PromptConfig {
+    fn default() -> Self {
+        Self {
+            format: None,
+            color: "green".to_string(),
+        }
+    }
+}
+
+/// Location of the config file (which may not exist).
+pub fn path() -> Option<PathBuf> {
+    if let Some(p) = std::env::var_os("OSGEO_CONFIG") {
+        return Some(PathBuf::from(p));
+    }
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|h| h.join(".config")))?;
+    Some(base.join("osgeo-library").join("config.toml"))
+}
+
+impl Config {
+    /// Load the config file with `profile` applied, falling back to defaults
+    /// when it doesn't exist.
+    pub fn load(profile: Option<&str>) -> Result<Self> {
+        let Some(path) = path().filter(|p| p.exists()) else {
+            return Self::parse("", profile);
+        };
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let invalid = || format!("Invalid config file {}", path.display());
+        let mut table: toml::Table = toml::from_str(&text).with_context(invalid)?;
+        if let Some(name) = profile {
+            apply_profile(&mut table, name)?;
+        }
+        toml::Value::Table(table).try_into().with_context(invalid)
+    }
+
+    fn parse(text: &str, profile: Option<&str>) -> Result<Self> {
+        let mut table: toml::Table = toml::from_str(text)?;
+        if let Some(name) = profile {
+            apply_profile(&mut table, name)?;
+        }
+        Ok(toml::Value::Table(table).try_into()?)
+    }
+
+    /// Every setting in effect, as dotted keys ("defaults.search_limit")
+    /// with TOML values, in key order. Unset optional settings are left out.
+    pub fn settings(&self) -> Result<Vec<(String, toml::Value)>> {
+        let toml::Value::Table(table) = toml::Value::try_from(self)? else {
+            anyhow::bail!("Config is not a table");
+        };
+        let mut settings = Vec::new();
+        flatten("", table, &mut settings);
+        settings.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(settings)
+    }
+}
+
+/// Lay `[profiles.NAME]` over the rest of the config.
+fn apply_profile(table: &mut toml::Table, name: &str) -> Result<()> {
+    let profiles = table.get("profiles").and_then(|p| p.as_table());
+    let Some(overrides) = profiles.and_then(|p| p.get(name)).and_then(|p| p.as_table()) else {
+        let names: Vec<&str> = profiles.map(|p| p.keys().map(String::as_str).collect()).unwrap_or_default();
+        anyhow::bail!(
+            "No profile '{}' in the config file (defined: {})",
+            name,
+            if names.is_empty() { "none".to_string() } else { names.join(", ") }
+        );
+    };
+    let overrides = overrides.clone();
+    merge(table, overrides);
+    Ok(())
+}
+
+/// Lay `overrides` over `base`, merging tables key by key.
+fn merge(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(value)) => merge(base, value),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn flatten(prefix: &str, table: toml::Table, out: &mut Vec<(String, toml::Value)>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() { key } else { format!("{}.{}", prefix, key) };
+        match value {
+            toml::Value::Table(table) => flatten(&key, table, out),
+            value => out.push((key, value)),
+        }
+    }
+}
+
+/// Set `key` (dotted, e.g. "defaults.server") to `value` in the config file,
+/// under `[profiles.NAME]` when a profile is given, keeping the rest of the
+/// file as written. `value` is read as TOML when it parses (numbers,
+/// booleans, quoted strings, arrays) and as a plain string otherwise. The
+/// file is only written when the result is a valid config.
+pub fn set(key: &str, value: &str, profile: Option<&str>) -> Result<PathBuf> {
+    let path = path().context("No config location: set OSGEO_CONFIG or HOME")?;
+    // Locked, so that `config set` in two clients at once keeps both changes
+    crate::shared::update(&path, |text| edit(&path, &text, key, value, profile))?;
+    Ok(path)
+}
+
+/// `text` of the config file at `path` with `key` set to `value`.
+fn edit(path: &Path, text: &str, key: &str, value: &str, profile: Option<&str>) -> Result<String> {
+    let mut doc: toml_edit::DocumentMut = text
+        .parse()
+        .with_context(|| format!("Invalid config file {}", path.display()))?;
+
+    let mut parts: Vec<&str> = Vec::new();
+    if let Some(name) = profile {
+        parts.extend(["profiles", name]);
+    }
+    parts.extend(key.split('.'));
+    if parts.iter().any(|p| p.is_empty()) {
+        anyhow::bail!("Invalid setting name '{}'", key);
+    }
+    let (last, tables) = parts.split_last().expect("key has at least one part");
+
+    let mut table = doc.as_table_mut();
+    for part in tables {
+        let entry = table.entry(part).or_insert_with(|| {
+            let mut t = toml_edit::Table::new();
+            t.set_implicit(true);
+            toml_edit::Item::Table(t)
+        });
+        table = entry
+            .as_table_mut()
+            .with_context(|| format!("'{}' in {} is not a table", part, key))?;
+    }
+    let value = value
+        .parse::<toml_edit::Value>()
+        .unwrap_or_else(|_| toml_edit::Value::from(value));
+    table.insert(last, toml_edit::value(value));
+
+    let text = doc.to_string();
+    Config::parse(&text, profile).with_context(|| format!("{} not changed", path.display()))?;
+    Ok(text)
+}
@@ -0,0 +1,101 @@
+//! What happens when the client panics.
+//!
+//! A panic used to leave a bare Rust message, and in `chat --split` it was
+//! printed on the alternate screen and lost when the screen was restored,
+//! sometimes with the terminal still in raw mode. The hook installed here
+//! puts the terminal back first, saves the details (with a backtrace) to a
+//! log file, and says how to report the bug, with an issue link that has
+//! the version and the panic message filled in.
+
+use std::path::PathBuf;
+
+/// Where bugs are reported
+const ISSUES_URL: &str = "https://github.com/ominiverdi/osgeo-library/issues/new";
+
+/// Longest panic message put in the issue link, in characters
+const MESSAGE_IN_LINK: usize = 500;
+
+/// Crash log, `None` where there is no cache directory.
+pub fn path() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("osgeo-library").join("crash.log"))
+}
+
+/// Replace the default panic message. Panics in background threads (page
+/// prefetching) are only logged, as chat carries on without them.
+pub fn install() {
+    std::panic::set_hook(Box::new(|info| {
+        let message = match info.payload().downcast_ref::<&str>() {
+            Some(s) => s.to_string(),
+            None => info.payload().downcast_ref::<String>().cloned().unwrap_or_default(),
+        };
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}", l.file(), l.line()))
+            .unwrap_or_default();
+        let thread = std::thread::current().name().unwrap_or("unnamed").to_string();
+        let logged = log(&message, &location, &thread);
+
+        if thread != "main" {
+            crate::term::verbose_line(&format!("Background task failed: {} ({})", message, location));
+            return;
+        }
+
+        crate::tty::restore();
+        eprintln!("\nosgeo-library crashed: {}", message);
+        eprintln!("This is a bug, not something you did.");
+        if let Some(path) = logged {
+            eprintln!("Details were saved to {}", path.display());
+        }
+        eprintln!("\nPlease report it (the link fills in what is known):");
+        eprintln!("  {}", issue_url(&message, &location));
+        eprintln!("Adding the output of 'osgeo-library report-bug' helps too.");
+    }));
+}
+
+/// Append the panic with a backtrace to the crash log. Returns its path if
+/// it could be written (not with `--no-shared-state`).
+fn log(message: &str, location: &str, thread: &str) -> Option<PathBuf> {
+    if !crate::shared::enabled() {
+        return None;
+    }
+    let path = path()?;
+    let entry = format!(
+        "--- {} osgeo-library {} ({} {}), thread '{}'\n{}\nat {}\n{}\n",
+        crate::epub::utc_timestamp(),
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        thread,
+        message,
+        location,
+        std::backtrace::Backtrace::force_capture()
+    );
+    crate::shared::append(&path, &entry).ok()?;
+    Some(path)
+}
+
+/// New-issue link with a title and body filled in.
+fn issue_url(message: &str, location: &str) -> String {
+    let message: String = message.chars().take(MESSAGE_IN_LINK).collect();
+    let title = format!("Crash: {}", message.lines().next().unwrap_or_default());
+    let body = format!(
+        "osgeo-library {} ({} {}) panicked at {}:\n\n```\n{}\n```\n\nWhat I was doing:\n\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        location,
+        message
+    );
+    format!("{}?title={}&body={}", ISSUES_URL, encode(&title), encode(&body))
+}
+
+/// Percent-encode a query parameter value.
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -0,0 +1,47 @@
+//! Checksums of downloaded images.
+//!
+//! Servers send the SHA-256 of element and page images in a `Repr-Digest`
+//! header (RFC 9530), on HEAD requests too. A download that doesn't match it
+//! was damaged on the way, typically by a proxy or a tunnel dropping bytes,
+//! and is fetched again rather than shown, exported or kept in an export's
+//! manifest (`resume`). Responses without the header, from older servers,
+//! are taken as they come.
+
+use base64::{engine::general_purpose, Engine as _};
+use reqwest::header::HeaderMap;
+use sha2::{Digest, Sha256};
+
+/// Downloads tried before giving up on an image that keeps arriving damaged
+pub const ATTEMPTS: usize = 3;
+
+/// SHA-256 the server gives for the body, as lowercase hex.
+pub fn expected(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get("repr-digest")?.to_str().ok()?;
+    // e.g. "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:, sha-512=:...:"
+    let encoded = value
+        .split(',')
+        .find_map(|d| d.trim().strip_prefix("sha-256="))?
+        .trim_matches(':');
+    let bytes = general_purpose::STANDARD.decode(encoded).ok()?;
+    Some(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Whether `bytes` match `expected` (anything matches when the server gave
+/// no checksum).
+pub fn matches(expected: Option<&str>, bytes: &[u8]) -> bool {
+    expected.is_none_or(|e| e == sha256(bytes))
+}
+
+/// SHA-256 of `bytes` as lowercase hex.
+pub fn sha256(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Error for an image that arrived damaged `ATTEMPTS` times.
+pub fn damaged(what: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "{} arrived damaged {} times (SHA-256 differs from the server's)",
+        what,
+        ATTEMPTS
+    )
+}
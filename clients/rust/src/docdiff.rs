@@ -0,0 +1,115 @@
+//! Side-by-side comparison of two documents' metadata (`doc-diff`).
+//!
+//! Each field becomes a row with the two values in wrapped columns; rows
+//! whose values differ are marked so duplicate ingests or two editions of
+//! a report can be told apart at a glance.
+
+use colored::Colorize;
+
+/// One compared field.
+pub struct Row {
+    pub label: String,
+    pub left: String,
+    pub right: String,
+}
+
+impl Row {
+    pub fn new(label: &str, left: impl ToString, right: impl ToString) -> Self {
+        Self {
+            label: label.to_string(),
+            left: left.to_string(),
+            right: right.to_string(),
+        }
+    }
+
+    pub fn differs(&self) -> bool {
+        self.left.trim() != self.right.trim()
+    }
+}
+
+const LABEL_WIDTH: usize = 12;
+const GAP: usize = 3;
+
+/// Render rows under a header of the two slugs, wrapped to `width` columns.
+pub fn render(left_name: &str, right_name: &str, rows: &[Row], width: usize) -> String {
+    // Label, marker, two columns with a gap between them
+    let column = (width.saturating_sub(LABEL_WIDTH + 2 + GAP) / 2).max(20);
+
+    let mut out = vec![format!(
+        "{:label$}   {}{}{}",
+        "",
+        pad(left_name, column).bold(),
+        " ".repeat(GAP),
+        right_name.bold(),
+        label = LABEL_WIDTH
+    )];
+    for row in rows {
+        let left = wrap(&row.left, column);
+        let right = wrap(&row.right, column);
+        let marker = if row.differs() { "≠".yellow().bold() } else { " ".normal() };
+        for i in 0..left.len().max(right.len()) {
+            let (label, mark) = if i == 0 {
+                (row.label.as_str(), marker.clone())
+            } else {
+                ("", " ".normal())
+            };
+            let l = left.get(i).map(String::as_str).unwrap_or("");
+            let r = right.get(i).map(String::as_str).unwrap_or("");
+            let line = format!(
+                "{:label$} {} {}{}{}",
+                label,
+                mark,
+                pad(l, column),
+                " ".repeat(GAP),
+                r,
+                label = LABEL_WIDTH
+            );
+            out.push(line.trim_end().to_string());
+        }
+    }
+    out.join("\n")
+}
+
+/// Keywords in both lists, only in the left, and only in the right
+/// (case-insensitive, in original order).
+pub fn keyword_diff(left: &[String], right: &[String]) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let contains = |list: &[String], k: &str| list.iter().any(|o| o.eq_ignore_ascii_case(k));
+    let shared = left.iter().filter(|k| contains(right, k)).cloned().collect();
+    let only_left = left.iter().filter(|k| !contains(right, k)).cloned().collect();
+    let only_right = right.iter().filter(|k| !contains(left, k)).cloned().collect();
+    (shared, only_left, only_right)
+}
+
+/// Word-wrap `text` to lines of at most `width` characters; paragraphs are kept.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let len = line.chars().count();
+            if len > 0 && len + 1 + word.chars().count() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+            // Hard-split words longer than a whole line
+            while line.chars().count() > width {
+                let head: String = line.chars().take(width).collect();
+                line = line.chars().skip(width).collect();
+                lines.push(head);
+            }
+        }
+        lines.push(line);
+    }
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines
+}
+
+fn pad(s: &str, width: usize) -> String {
+    let len = s.chars().count();
+    format!("{}{}", s, " ".repeat(width.saturating_sub(len)))
+}
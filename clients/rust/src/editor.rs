@@ -0,0 +1,58 @@
+//! Composing questions in an external editor.
+//!
+//! Works like `git commit`: the editor named by `$VISUAL` or `$EDITOR` opens
+//! a temporary file, lines starting with `#` are dropped afterwards, and an
+//! empty result cancels.
+
+use anyhow::{Context, Result};
+use std::process::Command;
+
+const INSTRUCTIONS: &str = "\n\
+# Write your question above. Lines starting with '#' are ignored;\n\
+# an empty question cancels.\n";
+
+/// Open the user's editor on `initial` and return the edited question, or
+/// `None` when it was left empty.
+pub fn compose(initial: &str) -> Result<Option<String>> {
+    let path = std::env::temp_dir().join(format!("osgeo-library-question-{}.md", std::process::id()));
+    std::fs::write(&path, format!("{}\n{}", initial, INSTRUCTIONS))
+        .context("Failed to write temp file")?;
+
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| default_editor().to_string());
+    // Allow editors with arguments, e.g. EDITOR="code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().context("$EDITOR is empty")?;
+
+    let status = Command::new(program)
+        .args(parts)
+        .arg(&path)
+        .status()
+        .with_context(|| format!("Failed to start editor '{}'", editor))?;
+
+    let text = std::fs::read_to_string(&path).context("Failed to read edited question");
+    std::fs::remove_file(&path).ok();
+    if !status.success() {
+        anyhow::bail!("Editor exited with {}; question not sent", status);
+    }
+
+    let question = text?
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string();
+    Ok((!question.is_empty()).then_some(question))
+}
+
+#[cfg(windows)]
+fn default_editor() -> &'static str {
+    "notepad"
+}
+
+#[cfg(not(windows))]
+fn default_editor() -> &'static str {
+    "vi"
+}
@@ -0,0 +1,344 @@
+//! EPUB export of a document (`export doc <slug> --epub FILE`).
+//!
+//! Page summaries are grouped into sections (detected chapters, or runs of
+//! pages when a document has none), each page followed by its figures,
+//! tables and equations, so library content can be read offline on
+//! e-readers and tablets. The book is EPUB 3 with an EPUB 2 table of
+//! contents (toc.ncx) as well, for older readers.
+
+use crate::notes::{self, Element, Page};
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+pub struct Book {
+    pub title: String,
+    pub slug: String,
+    pub summary: Option<String>,
+    pub keywords: Vec<String>,
+    pub license: Option<String>,
+    pub sections: Vec<Section>,
+}
+
+/// A chapter of the book: a titled run of pages.
+pub struct Section {
+    pub title: String,
+    pub pages: Vec<Page>,
+}
+
+/// An image stored in the book; elements refer to it as "images/<name>".
+pub struct Image {
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+const STYLE: &str = "body { font-family: serif; line-height: 1.4; }
+h1, h2, h3 { font-family: sans-serif; }
+h3 { margin-top: 1.5em; border-bottom: 1px solid #999; }
+.keywords { font-size: 0.85em; color: #555; }
+figure { margin: 1em 0; text-align: center; page-break-inside: avoid; }
+figure img { max-width: 100%; }
+figcaption { font-size: 0.85em; text-align: left; }
+pre { white-space: pre-wrap; font-size: 0.85em; }
+";
+
+/// Write the book to `path`.
+pub fn write(path: &Path, book: &Book, images: &[Image]) -> Result<()> {
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // The mimetype entry must come first and be stored uncompressed
+    zip.start_file("mimetype", SimpleFileOptions::default().compression_method(CompressionMethod::Stored))?;
+    zip.write_all(b"application/epub+zip")?;
+
+    let mut add = |name: &str, bytes: &[u8], options: SimpleFileOptions| -> Result<()> {
+        zip.start_file(name, options)?;
+        zip.write_all(bytes).with_context(|| format!("Failed to write {}", name))
+    };
+    add("META-INF/container.xml", CONTAINER.as_bytes(), deflated)?;
+    add("OEBPS/content.opf", package(book, images).as_bytes(), deflated)?;
+    add("OEBPS/nav.xhtml", nav(book).as_bytes(), deflated)?;
+    add("OEBPS/toc.ncx", ncx(book).as_bytes(), deflated)?;
+    add("OEBPS/style.css", STYLE.as_bytes(), deflated)?;
+    add("OEBPS/title.xhtml", title_page(book).as_bytes(), deflated)?;
+    for (i, section) in book.sections.iter().enumerate() {
+        add(&section_file(i), section_page(section).as_bytes(), deflated)?;
+    }
+    // Images are already compressed
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    for image in images {
+        add(&format!("OEBPS/images/{}", image.name), &image.bytes, stored)?;
+    }
+
+    zip.finish().context("Failed to finish EPUB archive")?;
+    Ok(())
+}
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+fn section_file(index: usize) -> String {
+    format!("OEBPS/section-{:03}.xhtml", index + 1)
+}
+
+/// Path of a section relative to content.opf.
+fn section_href(index: usize) -> String {
+    format!("section-{:03}.xhtml", index + 1)
+}
+
+fn package(book: &Book, images: &[Image]) -> String {
+    let mut manifest = vec![
+        r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>"#.to_string(),
+        r#"<item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>"#.to_string(),
+        r#"<item id="style" href="style.css" media-type="text/css"/>"#.to_string(),
+        r#"<item id="title" href="title.xhtml" media-type="application/xhtml+xml"/>"#.to_string(),
+    ];
+    let mut spine = vec![r#"<itemref idref="title"/>"#.to_string()];
+    for i in 0..book.sections.len() {
+        manifest.push(format!(
+            r#"<item id="s{}" href="{}" media-type="application/xhtml+xml"/>"#,
+            i + 1,
+            section_href(i)
+        ));
+        spine.push(format!(r#"<itemref idref="s{}"/>"#, i + 1));
+    }
+    for (i, image) in images.iter().enumerate() {
+        manifest.push(format!(
+            r#"<item id="img{}" href="images/{}" media-type="{}"/>"#,
+            i + 1,
+            escape(&image.name),
+            media_type(&image.name)
+        ));
+    }
+
+    let mut metadata = vec![
+        format!("<dc:identifier id=\"book-id\">urn:osgeo-library:{}</dc:identifier>", escape(&book.slug)),
+        format!("<dc:title>{}</dc:title>", escape(&book.title)),
+        "<dc:language>en</dc:language>".to_string(),
+        "<dc:publisher>OSGeo Library</dc:publisher>".to_string(),
+        format!("<meta property=\"dcterms:modified\">{}</meta>", utc_timestamp()),
+    ];
+    if let Some(summary) = &book.summary {
+        metadata.push(format!("<dc:description>{}</dc:description>", escape(summary)));
+    }
+    for keyword in &book.keywords {
+        metadata.push(format!("<dc:subject>{}</dc:subject>", escape(keyword)));
+    }
+    if let Some(license) = &book.license {
+        metadata.push(format!("<dc:rights>{}</dc:rights>", escape(license)));
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    {}
+  </metadata>
+  <manifest>
+    {}
+  </manifest>
+  <spine toc="ncx">
+    {}
+  </spine>
+</package>
+"#,
+        metadata.join("\n    "),
+        manifest.join("\n    "),
+        spine.join("\n    ")
+    )
+}
+
+fn nav(book: &Book) -> String {
+    let items: Vec<String> = book
+        .sections
+        .iter()
+        .enumerate()
+        .map(|(i, s)| format!(r#"<li><a href="{}">{}</a></li>"#, section_href(i), escape(&s.title)))
+        .collect();
+    xhtml(
+        "Contents",
+        &format!(
+            "<nav epub:type=\"toc\" id=\"toc\">\n<h1>Contents</h1>\n<ol>\n<li><a href=\"title.xhtml\">{}</a></li>\n{}\n</ol>\n</nav>",
+            escape(&book.title),
+            items.join("\n")
+        ),
+    )
+}
+
+fn ncx(book: &Book) -> String {
+    let mut points = vec![nav_point(1, &book.title, "title.xhtml")];
+    for (i, section) in book.sections.iter().enumerate() {
+        points.push(nav_point(i + 2, &section.title, &section_href(i)));
+    }
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <head><meta name="dtb:uid" content="urn:osgeo-library:{}"/></head>
+  <docTitle><text>{}</text></docTitle>
+  <navMap>
+{}
+  </navMap>
+</ncx>
+"#,
+        escape(&book.slug),
+        escape(&book.title),
+        points.join("\n")
+    )
+}
+
+fn nav_point(order: usize, label: &str, href: &str) -> String {
+    format!(
+        r#"    <navPoint id="p{0}" playOrder="{0}"><navLabel><text>{1}</text></navLabel><content src="{2}"/></navPoint>"#,
+        order,
+        escape(label),
+        href
+    )
+}
+
+fn title_page(book: &Book) -> String {
+    let mut body = format!("<h1>{}</h1>\n", escape(&book.title));
+    if let Some(summary) = &book.summary {
+        body.push_str(&paragraphs(summary));
+    }
+    if !book.keywords.is_empty() {
+        body.push_str(&format!(
+            "<p class=\"keywords\">Keywords: {}</p>\n",
+            escape(&book.keywords.join(", "))
+        ));
+    }
+    if let Some(license) = &book.license {
+        body.push_str(&format!("<p class=\"keywords\">License: {}</p>\n", escape(license)));
+    }
+    body.push_str(&format!(
+        "<p class=\"keywords\">Exported from the OSGeo Library ({}).</p>",
+        escape(&book.slug)
+    ));
+    xhtml(&book.title, &body)
+}
+
+fn section_page(section: &Section) -> String {
+    let mut body = format!("<h2>{}</h2>\n", escape(&section.title));
+    for page in &section.pages {
+        body.push_str(&format!("<h3 id=\"page-{0}\">Page {0}</h3>\n", page.number));
+        match page.summary.as_deref().map(str::trim) {
+            Some(summary) if !summary.is_empty() => body.push_str(&paragraphs(summary)),
+            _ => body.push_str("<p><em>No summary for this page.</em></p>\n"),
+        }
+        if !page.keywords.is_empty() {
+            body.push_str(&format!(
+                "<p class=\"keywords\">{}</p>\n",
+                escape(&page.keywords.join(", "))
+            ));
+        }
+        for element in &page.elements {
+            body.push_str(&figure(element, page.number));
+        }
+    }
+    xhtml(&section.title, &body)
+}
+
+fn figure(element: &Element, page: i32) -> String {
+    let name = notes::element_name(element);
+    let mut caption = format!("<strong>{}</strong> (p. {})", escape(&name), page);
+    if let Some(desc) = element.description.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+        caption.push_str(&format!(": {}", escape(desc)));
+    }
+    let content = match (&element.image, &element.latex) {
+        (Some(image), _) => format!("<img src=\"{}\" alt=\"{}\"/>\n", escape(image), escape(&name)),
+        // E-readers can't typeset LaTeX; show the source
+        (None, Some(latex)) => format!("<pre>{}</pre>\n", escape(latex.trim())),
+        (None, None) => String::new(),
+    };
+    format!("<figure>\n{}<figcaption>{}</figcaption>\n</figure>\n", content, caption)
+}
+
+fn paragraphs(text: &str) -> String {
+    text.split("\n\n")
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|p| format!("<p>{}</p>\n", escape(p)))
+        .collect()
+}
+
+fn xhtml(title: &str, body: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="en" lang="en">
+<head>
+<title>{}</title>
+<link rel="stylesheet" type="text/css" href="style.css"/>
+</head>
+<body>
+{}
+</body>
+</html>
+"#,
+        escape(title),
+        body
+    )
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            // Control characters are not allowed in XML
+            c if c.is_control() && c != '\n' && c != '\t' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn media_type(name: &str) -> &'static str {
+    match Path::new(name).extension().and_then(|e| e.to_str()).map(str::to_lowercase).as_deref() {
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        _ => "image/png",
+    }
+}
+
+/// Current time as "YYYY-MM-DDThh:mm:ssZ", required by EPUB 3 metadata.
+pub fn utc_timestamp() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let (days, rem) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+
+    // Days since 1970-01-01 to a civil date (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        (rem / 60) % 60,
+        rem % 60
+    )
+}
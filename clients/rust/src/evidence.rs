@@ -0,0 +1,282 @@
+//! Manifests of exports, for archiving (`--manifest`, `verify-manifest`).
+//!
+//! A report that cites the library may need to show later what it was
+//! based on. With `--manifest`, `search --export` and the exports (`export
+//! notes`, `doc`, `elements` and `pages`) write a manifest next to what they
+//! export: the query or document and pages, when and from which server
+//! (and server version) it was exported, the id, document and page of
+//! every result or element in it, and the size and SHA-256 of every file
+//! written. `--sign KEY` signs the manifest with an SSH key (`ssh-keygen -Y
+//! sign`, the way git signs commits), leaving the signature in
+//! `<manifest>.sig`.
+//!
+//! `verify-manifest` checks the files against the manifest, then the
+//! signature: against a public key with `--key`, otherwise only that it is
+//! intact, printing the fingerprint of the key that made it.
+
+use crate::digest::sha256;
+use crate::types::{ElementListItem, SearchResult};
+use crate::{capabilities, redact, OsgeoClient};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Manifest format, for readers to tell versions apart
+const FORMAT: &str = "osgeo-library-manifest/1";
+
+/// What a signature is for, so one made for something else doesn't verify
+const NAMESPACE: &str = "osgeo-library-manifest";
+
+/// Identity the public key given to `verify-manifest --key` is trusted for
+const SIGNER: &str = "signer";
+
+/// What `--manifest` asked for.
+#[derive(Debug, Clone, Default)]
+pub struct Request {
+    /// SSH private key to sign the manifest with (`--sign`)
+    pub sign: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: String,
+    /// Client name and version
+    pub client: String,
+    /// "YYYY-MM-DDThh:mm:ssZ"
+    pub created: String,
+    pub server: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_version: Option<String>,
+    /// "search", "notes", "doc", "elements" or "pages"
+    pub export: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document: Option<String>,
+    /// "45-78"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pages: Option<String>,
+    pub results: Vec<Item>,
+    pub files: Vec<File>,
+}
+
+/// A result or element in the export.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Item {
+    /// "element:12", "chunk:34"
+    pub id: String,
+    pub document: String,
+    pub page: i32,
+    /// Library it came from, in a federated search
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub library: Option<String>,
+}
+
+impl Item {
+    /// An element of document `slug` in a notes or e-book export.
+    pub fn element(slug: &str, element: &ElementListItem) -> Self {
+        Self {
+            id: format!("element:{}", element.id),
+            document: slug.to_string(),
+            page: element.page_number,
+            library: None,
+        }
+    }
+
+    pub fn new(result: &SearchResult) -> Self {
+        Self {
+            id: result.key(),
+            document: result.document_slug.clone(),
+            page: result.page_number,
+            library: result.library.clone(),
+        }
+    }
+}
+
+/// A file written by the export.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct File {
+    /// Relative to the manifest's directory, with "/" separators
+    pub path: String,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+impl Manifest {
+    /// An empty manifest of an `export` ("search", "notes", "doc", "elements", "pages") from
+    /// `client`'s server, made now.
+    pub fn new(client: &OsgeoClient, export: &str) -> Self {
+        Self {
+            format: FORMAT.to_string(),
+            client: format!("osgeo-library {}", env!("CARGO_PKG_VERSION")),
+            created: crate::epub::utc_timestamp(),
+            server: client.base_url().to_string(),
+            server_version: capabilities::of(client).version,
+            export: export.to_string(),
+            query: None,
+            document: None,
+            pages: None,
+            results: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Record `path`, in the directory `dir` the manifest goes in, as it
+    /// is on disk.
+    pub fn add_file(&mut self, dir: &Path, path: &Path) -> Result<()> {
+        let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let relative = path.strip_prefix(dir).unwrap_or(path);
+        self.files.push(File {
+            path: relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"),
+            bytes: bytes.len() as u64,
+            sha256: sha256(&bytes),
+        });
+        Ok(())
+    }
+
+    /// Write the manifest to `path`, and sign it as `request` asks.
+    pub fn write(&self, path: &Path, request: &Request) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).context("Failed to encode manifest")?;
+        std::fs::write(path, redact::text(&json) + "\n").with_context(|| format!("Failed to write {}", path.display()))?;
+        eprintln!("Wrote manifest {}", path.display());
+        if let Some(key) = &request.sign {
+            sign(path, key)?;
+            eprintln!("Signed it: {}", signature_path(path).display());
+        }
+        Ok(())
+    }
+}
+
+/// Manifest path for an export written to the file `output`:
+/// "results.csv" -> "results.csv.manifest.json".
+pub fn path_for(output: &Path) -> PathBuf {
+    let mut path = output.as_os_str().to_os_string();
+    path.push(".manifest.json");
+    PathBuf::from(path)
+}
+
+fn signature_path(manifest: &Path) -> PathBuf {
+    let mut path = manifest.as_os_str().to_os_string();
+    path.push(".sig");
+    PathBuf::from(path)
+}
+
+fn sign(manifest: &Path, key: &Path) -> Result<()> {
+    let output = Command::new("ssh-keygen")
+        .args(["-q", "-Y", "sign", "-n", NAMESPACE, "-f"])
+        .arg(key)
+        .arg(manifest)
+        .stdin(Stdio::inherit())
+        .output()
+        .context("Failed to run ssh-keygen, needed to sign manifests (install OpenSSH 8.1 or later)")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to sign {} with {}: {}",
+            manifest.display(),
+            key.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Check the files listed in the manifest at `path`, then its signature,
+/// against the public key `key` when given. An error when anything fails.
+pub fn verify(path: &Path, key: Option<&Path>) -> Result<()> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let manifest: Manifest =
+        serde_json::from_str(&text).with_context(|| format!("{} is not an export manifest", path.display()))?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    println!(
+        "{} export of {} from {}{}",
+        manifest.export,
+        manifest.query.as_deref().or(manifest.document.as_deref()).unwrap_or("?"),
+        manifest.server,
+        manifest.server_version.as_deref().map(|v| format!(" (version {})", v)).unwrap_or_default()
+    );
+    println!("Made {} by {}, {} result(s)\n", manifest.created, manifest.client, manifest.results.len());
+
+    let mut problems = 0;
+    for file in &manifest.files {
+        let status = match std::fs::read(dir.join(&file.path)) {
+            Err(_) => Some("missing"),
+            Ok(bytes) if bytes.len() as u64 != file.bytes || sha256(&bytes) != file.sha256 => Some("changed"),
+            Ok(_) => None,
+        };
+        match status {
+            Some(status) => {
+                problems += 1;
+                println!("  {} {}", status.red().bold(), file.path);
+            }
+            None => println!("  {} {}", "ok".green(), file.path),
+        }
+    }
+
+    let signature = signature_path(path);
+    if signature.exists() {
+        match check_signature(path, &signature, key) {
+            Ok(note) => println!("\n{} {}", "Signature:".bold(), note),
+            Err(e) => {
+                problems += 1;
+                println!("\n{} {}", "Signature:".bold(), format!("{:#}", e).red());
+            }
+        }
+    } else if key.is_some() {
+        problems += 1;
+        println!("\n{} {}", "Signature:".bold(), format!("none ({} not found)", signature.display()).red());
+    } else {
+        println!("\n{} none", "Signature:".bold());
+    }
+
+    if problems > 0 {
+        anyhow::bail!("{} does not match what was exported ({} problem(s))", path.display(), problems);
+    }
+    println!("\n{}", "Everything matches the manifest.".green());
+    Ok(())
+}
+
+/// What ssh-keygen says of a good signature, or why it isn't one.
+fn check_signature(manifest: &Path, signature: &Path, key: Option<&Path>) -> Result<String> {
+    let mut command = Command::new("ssh-keygen");
+    let mut allowed = None;
+    match key {
+        Some(key) => {
+            let public = std::fs::read_to_string(key).with_context(|| format!("Failed to read {}", key.display()))?;
+            // ssh-keygen only verifies against a list of allowed signers
+            let list = std::env::temp_dir().join(format!("osgeo-library-signers-{}", std::process::id()));
+            std::fs::write(&list, format!("{} {}\n", SIGNER, public.trim()))
+                .with_context(|| format!("Failed to write {}", list.display()))?;
+            command.args(["-Y", "verify", "-I", SIGNER, "-f"]).arg(&list);
+            allowed = Some(list);
+        }
+        None => {
+            command.args(["-Y", "check-novalidate"]);
+        }
+    }
+    command.args(["-n", NAMESPACE, "-s"]).arg(signature);
+    let input = std::fs::File::open(manifest).with_context(|| format!("Failed to read {}", manifest.display()))?;
+    let output = command
+        .stdin(input)
+        .output()
+        .context("Failed to run ssh-keygen, needed to check signatures (install OpenSSH 8.1 or later)");
+    if let Some(list) = allowed {
+        std::fs::remove_file(list).ok();
+    }
+    let output = output?;
+    let said = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let said = said.trim();
+    if !output.status.success() {
+        anyhow::bail!("not valid: {}", said);
+    }
+    Ok(match key {
+        Some(key) => format!("{} ({})", said, key.display()),
+        None => format!("{}; give --key to check whose key it is", said),
+    })
+}
@@ -0,0 +1,53 @@
+//! Switching to a mirror when the server can't be reached.
+//!
+//! A library replicated on several hosts is listed as the server and its
+//! mirrors (`--mirror`, `mirrors` in the config). When a request can't
+//! connect to the server it is sent to the next mirror, and so on round the
+//! list; the first one that answers becomes the server for the requests
+//! after it, so a chat session carries on while a host is down for
+//! maintenance. URLs made for another server, such as image links from
+//! earlier results, are sent to the current one. Only connection failures
+//! count: a request that reached a server and failed there may have been
+//! acted on, and is not sent to another.
+
+use crate::term;
+
+/// Index of the server `url` is on, and the rest of it (path and query).
+pub fn locate<'a>(url: &'a str, servers: &[String]) -> Option<(usize, &'a str)> {
+    servers.iter().enumerate().find_map(|(i, server)| {
+        let rest = url.strip_prefix(server.as_str())?;
+        (rest.is_empty() || rest.starts_with('/') || rest.starts_with('?')).then_some((i, rest))
+    })
+}
+
+/// `url` moved to server `to`, if it is on one of the others.
+pub fn moved(url: &reqwest::Url, servers: &[String], to: usize) -> Option<reqwest::Url> {
+    let (from, rest) = locate(url.as_str(), servers)?;
+    if from == to {
+        return None;
+    }
+    format!("{}{}", servers[to], rest).parse().ok()
+}
+
+/// Indexes of the servers to try after `from`, in order.
+pub fn after(from: usize, count: usize) -> impl Iterator<Item = usize> {
+    (1..count).map(move |i| (from + i) % count)
+}
+
+/// Servers as given, without trailing slashes and duplicates.
+pub fn servers(base_url: &str, mirrors: &[String]) -> Vec<String> {
+    let mut servers: Vec<String> = Vec::new();
+    for url in std::iter::once(base_url).chain(mirrors.iter().map(String::as_str)) {
+        let url = url.trim_end_matches('/').to_string();
+        if !servers.contains(&url) {
+            servers.push(url);
+        }
+    }
+    servers
+}
+
+/// Tell the user which server answers now (a hint on stderr, to keep results
+/// apart; --no-hints leaves it out).
+pub fn report(from: &str, to: &str) {
+    term::hint(&format!("Note: {} is not answering; switched to {}", from, to));
+}
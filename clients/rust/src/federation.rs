@@ -0,0 +1,138 @@
+//! Searching several libraries at once (`--server` given more than once,
+//! `[federation]` in the config).
+//!
+//! Teams run separate libraries by theme, each on its own server. A
+//! federated `search` sends the query to all of them concurrently and merges
+//! the results by score, each tagged with the library it came from (the
+//! `library` of a `SearchResult`). Images of a result are fetched from its
+//! own library. A library that can't be reached, or can't do what the
+//! search asks (`capabilities`), is reported and left out; the search fails
+//! only when none answers.
+
+use crate::capabilities::{self, Feature};
+use crate::client::RequestPolicy;
+use crate::types::{SearchRequest, SearchResponse, SearchResult};
+use crate::{AsyncOsgeoClient, OsgeoClient};
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// A library taking part in a federated search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Library {
+    /// Name results are tagged with: the config key, or the server's host
+    pub name: String,
+    /// Server URL, without a trailing slash
+    pub url: String,
+}
+
+impl Library {
+    /// Library named after the host (and port) of `url`.
+    pub fn at(url: &str) -> Self {
+        let url = url.trim_end_matches('/');
+        let host = url.split("://").last().unwrap_or(url);
+        Self {
+            name: host.split('/').next().unwrap_or(host).to_string(),
+            url: url.to_string(),
+        }
+    }
+}
+
+/// Libraries to search: the `servers` given on the command line when there
+/// are several, otherwise `server` and the configured `federation`. Empty
+/// when there is only one library, for a plain search.
+pub fn libraries(servers: &[String], server: &str, federation: &BTreeMap<String, String>) -> Vec<Library> {
+    let mut libraries: Vec<Library> = if servers.len() > 1 {
+        servers.iter().map(|url| Library::at(url)).collect()
+    } else if federation.is_empty() {
+        return Vec::new();
+    } else {
+        std::iter::once(Library::at(server))
+            .chain(federation.iter().map(|(name, url)| Library {
+                name: name.clone(),
+                url: url.trim_end_matches('/').to_string(),
+            }))
+            .collect()
+    };
+    // The same server twice would return every result twice
+    let mut seen = Vec::new();
+    libraries.retain(|library| {
+        let new = !seen.contains(&library.url);
+        seen.push(library.url.clone());
+        new
+    });
+    if libraries.len() > 1 {
+        libraries
+    } else {
+        Vec::new()
+    }
+}
+
+/// The libraries with `feature`, warning about the others.
+pub fn supporting(libraries: &[Library], policy: RequestPolicy, feature: Feature) -> Vec<Library> {
+    libraries
+        .iter()
+        .filter(|library| {
+            // A library that can't be asked is kept, to fail or answer in the search
+            let supported = OsgeoClient::with_policy(&library.url, policy)
+                .map(|client| capabilities::of(&client).supports(feature))
+                .unwrap_or(true);
+            if !supported {
+                crate::term::warning(&format!("{} ({}) left out: {}", library.name, library.url, feature.missing()));
+            }
+            supported
+        })
+        .cloned()
+        .collect()
+}
+
+/// Send `req` to every library at once and merge the results, best first,
+/// keeping `req.limit` of them.
+pub async fn search(libraries: &[Library], policy: RequestPolicy, req: SearchRequest) -> Result<SearchResponse> {
+    let mut tasks = Vec::new();
+    for library in libraries {
+        let client = AsyncOsgeoClient::with_policy(&library.url, policy)?;
+        let req = req.clone();
+        tasks.push(tokio::spawn(async move { client.search(req).await }));
+    }
+
+    let mut results: Vec<SearchResult> = Vec::new();
+    let mut failures = Vec::new();
+    let mut below_min_score = 0;
+    for (library, task) in libraries.iter().zip(tasks) {
+        match task.await.unwrap_or_else(|_| Err(anyhow::anyhow!("request task panicked"))) {
+            Ok(mut response) => {
+                response.drop_below(req.min_score);
+                below_min_score += response.below_min_score;
+                results.extend(response.results.into_iter().map(|mut result| {
+                    result.library = Some(library.name.clone());
+                    result
+                }));
+            }
+            Err(e) => failures.push((library, e)),
+        }
+    }
+    if failures.len() == libraries.len() {
+        let (library, e) = failures.remove(0);
+        return Err(e.context(format!("No library answered (first: {} at {})", library.name, library.url)));
+    }
+    for (library, e) in failures {
+        crate::term::warning(&format!("{} ({}) left out: {}", library.name, library.url, e.root_cause()));
+    }
+
+    // Stable, so equal scores keep the order of the libraries
+    results.sort_by(|a, b| b.score_pct.total_cmp(&a.score_pct));
+    results.truncate(req.limit.max(0) as usize);
+    Ok(SearchResponse {
+        query: req.query,
+        total: results.len() as i32,
+        results,
+        below_min_score,
+    })
+}
+
+/// Server URL of the library `result` came from, for results of a
+/// federated search.
+pub fn server_of<'a>(libraries: &'a [Library], result: &SearchResult) -> Option<&'a str> {
+    let name = result.library.as_deref()?;
+    libraries.iter().find(|l| l.name == name).map(|l| l.url.as_str())
+}
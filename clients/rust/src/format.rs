@@ -0,0 +1,181 @@
+//! Plain-text rendering of search results and sources, as printed by the
+//! command-line client. Colors follow the `colored` crate's global setting.
+
+use crate::types::SearchResult;
+use crate::{mathtext, table, term};
+use colored::*;
+
+pub fn get_source_tag(result: &SearchResult) -> &'static str {
+    if result.source_type == "element" {
+        match result.element_type.as_deref() {
+            Some("figure") => "f",
+            Some("table") => "tb",
+            Some("equation") => "eq",
+            Some("chart") => "ch",
+            Some("diagram") => "d",
+            _ => "e",
+        }
+    } else {
+        "t"
+    }
+}
+
+/// Content used for text previews. Equation LaTeX is rendered as Unicode
+/// math unless disabled with --no-mathtext.
+pub fn preview_text(result: &SearchResult) -> String {
+    if result.element_type.as_deref() == Some("equation") && mathtext::enabled() {
+        mathtext::to_unicode(&result.content)
+    } else {
+        result.content.clone()
+    }
+}
+
+pub fn format_result(i: usize, result: &SearchResult, verbose: bool) -> String {
+    let mut lines = Vec::new();
+
+    if result.source_type == "element" {
+        let elem_type = result
+            .element_type
+            .as_ref()
+            .map(|s| s.to_uppercase())
+            .unwrap_or_else(|| "UNKNOWN".to_string());
+        let label = result
+            .element_label
+            .as_deref()
+            .unwrap_or("(unlabeled)");
+
+        lines.push(format!(
+            "[{}] {} {}",
+            i.to_string().yellow(),
+            elem_type.cyan(),
+            label
+        ));
+        lines.push(format!(
+            "    {}{} p.{} | {:.0}%",
+            library_tag(result),
+            result.document_slug.cyan(),
+            result.page_number,
+            result.score_pct
+        ));
+    } else {
+        let chunk_idx = result.chunk_index.unwrap_or(0);
+        lines.push(format!(
+            "[{}] TEXT chunk {}",
+            i.to_string().yellow(),
+            chunk_idx
+        ));
+        lines.push(format!(
+            "    {}{} p.{} | {:.0}%",
+            library_tag(result),
+            result.document_slug.cyan(),
+            result.page_number,
+            result.score_pct
+        ));
+    }
+
+    if verbose && !result.content.is_empty() {
+        // Tables with extracted rows render as a box table; everything else
+        // (and tables whose content isn't tabular) gets a flat preview
+        let parsed = if result.element_type.as_deref() == Some("table") {
+            table::parse(&result.content)
+        } else {
+            None
+        };
+
+        if let Some(parsed) = parsed {
+            if let Some(ref caption) = parsed.caption {
+                let caption: String = caption.chars().take(200).collect();
+                lines.push(format!("    {}", caption.dimmed()));
+            }
+            let width = term::width().saturating_sub(4);
+            for line in table::render(&parsed, width, 8).lines() {
+                lines.push(format!("    {}", line));
+            }
+        } else {
+            let preview: String = preview_text(result).chars().take(200).collect();
+            lines.push(format!("    {}", preview.dimmed()));
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// "hydro: " for a result of a federated search, otherwise nothing.
+fn library_tag(result: &SearchResult) -> String {
+    match &result.library {
+        Some(library) => format!("{}: ", library.magenta()),
+        None => String::new(),
+    }
+}
+
+pub fn format_sources(sources: &[SearchResult]) -> String {
+    if sources.is_empty() {
+        return "No sources available.".to_string();
+    }
+
+    sources
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            let tag = get_source_tag(r);
+            if r.source_type == "element" {
+                let elem_type = r
+                    .element_type
+                    .as_ref()
+                    .map(|s| s.to_uppercase())
+                    .unwrap_or_else(|| "?".to_string());
+                let label = r.element_label.as_deref().unwrap_or("");
+                format!(
+                    "[{}:{}] {} {} | {} p.{} | {:.0}%",
+                    tag,
+                    i + 1,
+                    elem_type,
+                    label,
+                    r.document_title,
+                    r.page_number,
+                    r.score_pct
+                )
+            } else {
+                format!(
+                    "[{}:{}] TEXT chunk | {} p.{} | {:.0}%",
+                    tag,
+                    i + 1,
+                    r.document_title,
+                    r.page_number,
+                    r.score_pct
+                )
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// One-line description of a source for pin listings.
+pub fn source_summary(result: &SearchResult) -> String {
+    let kind = if result.source_type == "element" {
+        let elem_type = result.element_type.as_deref().unwrap_or("element").to_uppercase();
+        format!("{} {}", elem_type, result.element_label.as_deref().unwrap_or(""))
+    } else {
+        "TEXT chunk".to_string()
+    };
+    format!("{} | {} p.{}", kind.trim_end(), result.document_slug, result.page_number)
+}
+
+/// The start of a source's content on one line, fitted to the terminal.
+pub fn source_snippet(result: &SearchResult) -> String {
+    let text = preview_text(result).split_whitespace().collect::<Vec<_>>().join(" ");
+    let max = term::width().saturating_sub(8).clamp(40, 200);
+    if text.chars().count() <= max {
+        return text;
+    }
+    format!("{}...", text.chars().take(max - 3).collect::<String>())
+}
+
+/// One "[N] TYPE label | slug p.N" line per source, for the transcript.
+pub fn numbered_sources(sources: &[SearchResult]) -> Vec<String> {
+    sources
+        .iter()
+        .enumerate()
+        .map(|(i, source)| format!("[{}] {}", i + 1, source_summary(source)))
+        .collect()
+}
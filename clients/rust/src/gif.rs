@@ -0,0 +1,167 @@
+//! Just enough GIF decoding for a still preview.
+//!
+//! Some chart elements are animated GIFs; chafa would play them in a loop
+//! (or print garbage when its output is captured for the split viewer), so
+//! the terminal gets the first frame only. `frame_count` walks the block
+//! structure without decompressing anything.
+
+use anyhow::{Context, Result};
+
+/// Whether `bytes` is a GIF.
+pub fn is_gif(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a")
+}
+
+/// Number of frames (image blocks) in the GIF.
+pub fn frame_count(bytes: &[u8]) -> Result<usize> {
+    let mut count = 0;
+    walk(bytes, |_| {
+        count += 1;
+        false
+    })?;
+    Ok(count)
+}
+
+/// First frame as RGBA samples, with the frame's size: (width, height, samples).
+/// Pixels outside the frame and transparent pixels are fully transparent.
+pub fn first_frame(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>)> {
+    let width = usize::from(u16_at(bytes, 6)?);
+    let height = usize::from(u16_at(bytes, 8)?);
+    let mut canvas = vec![0u8; width * height * 4];
+    let mut found = false;
+    walk(bytes, |frame| {
+        found = true;
+        let indexes = decode_indexes(frame);
+        let rows: Vec<usize> = if frame.interlaced {
+            // Rows stored in passes: every 8th from 0, every 8th from 4,
+            // every 4th from 2, every 2nd from 1
+            [(0, 8), (4, 8), (2, 4), (1, 2)]
+                .iter()
+                .flat_map(|&(start, step)| (start..frame.height).step_by(step))
+                .collect()
+        } else {
+            (0..frame.height).collect()
+        };
+        for (i, &index) in indexes.iter().take(frame.width * frame.height).enumerate() {
+            let (x, y) = (frame.left + i % frame.width, frame.top + rows[i / frame.width]);
+            if x >= width || y >= height || Some(index) == frame.transparent {
+                continue;
+            }
+            if let Some(rgb) = frame.palette.get(usize::from(index) * 3..usize::from(index) * 3 + 3) {
+                let at = (y * width + x) * 4;
+                canvas[at..at + 3].copy_from_slice(rgb);
+                canvas[at + 3] = 255;
+            }
+        }
+        true
+    })?;
+    anyhow::ensure!(found, "GIF without images");
+    Ok((width as u32, height as u32, canvas))
+}
+
+/// One image block of a GIF.
+struct Frame<'a> {
+    left: usize,
+    top: usize,
+    width: usize,
+    height: usize,
+    interlaced: bool,
+    palette: &'a [u8],
+    transparent: Option<u8>,
+    min_code_size: u8,
+    /// LZW data, still split in sub-blocks
+    data: Vec<&'a [u8]>,
+}
+
+/// Call `on_frame` for each image block until it returns true.
+fn walk<'a>(bytes: &'a [u8], mut on_frame: impl FnMut(&Frame<'a>) -> bool) -> Result<()> {
+    anyhow::ensure!(is_gif(bytes), "not a GIF");
+    let flags = *bytes.get(10).context("truncated GIF")?;
+    let mut pos = 13;
+    let mut global_palette: &[u8] = &[];
+    if flags & 0x80 != 0 {
+        let size = 3 << ((flags & 7) + 1);
+        global_palette = bytes.get(pos..pos + size).context("truncated GIF")?;
+        pos += size;
+    }
+
+    let mut transparent = None;
+    loop {
+        match *bytes.get(pos).context("truncated GIF")? {
+            // Extension: graphic control (for transparency) or anything else
+            0x21 => {
+                let label = *bytes.get(pos + 1).context("truncated GIF")?;
+                let (blocks, next) = sub_blocks(bytes, pos + 2)?;
+                if label == 0xf9 {
+                    if let Some(&[packed, _, _, index]) = blocks.first().map(|b| &b[..b.len().min(4)]) {
+                        transparent = (packed & 1 != 0).then_some(index);
+                    }
+                }
+                pos = next;
+            }
+            0x2c => {
+                let descriptor = bytes.get(pos + 1..pos + 10).context("truncated GIF")?;
+                let flags = descriptor[8];
+                pos += 10;
+                let mut palette = global_palette;
+                if flags & 0x80 != 0 {
+                    let size = 3 << ((flags & 7) + 1);
+                    palette = bytes.get(pos..pos + size).context("truncated GIF")?;
+                    pos += size;
+                }
+                let min_code_size = *bytes.get(pos).context("truncated GIF")?;
+                let (data, next) = sub_blocks(bytes, pos + 1)?;
+                let frame = Frame {
+                    left: usize::from(u16::from_le_bytes([descriptor[0], descriptor[1]])),
+                    top: usize::from(u16::from_le_bytes([descriptor[2], descriptor[3]])),
+                    width: usize::from(u16::from_le_bytes([descriptor[4], descriptor[5]])),
+                    height: usize::from(u16::from_le_bytes([descriptor[6], descriptor[7]])),
+                    interlaced: flags & 0x40 != 0,
+                    palette,
+                    transparent: transparent.take(),
+                    min_code_size,
+                    data,
+                };
+                if on_frame(&frame) {
+                    return Ok(());
+                }
+                pos = next;
+            }
+            0x3b => return Ok(()),
+            other => anyhow::bail!("corrupt GIF (block 0x{:02x})", other),
+        }
+    }
+}
+
+/// Sub-blocks starting at `pos` (each a length byte then data, ended by an
+/// empty one), and the position after them.
+fn sub_blocks(bytes: &[u8], mut pos: usize) -> Result<(Vec<&[u8]>, usize)> {
+    let mut blocks = Vec::new();
+    loop {
+        let len = usize::from(*bytes.get(pos).context("truncated GIF")?);
+        pos += 1;
+        if len == 0 {
+            return Ok((blocks, pos));
+        }
+        blocks.push(bytes.get(pos..pos + len).context("truncated GIF")?);
+        pos += len;
+    }
+}
+
+/// Palette indexes of a frame. Damaged data gives as many as could be
+/// decoded, which still shows most of the image.
+fn decode_indexes(frame: &Frame) -> Vec<u8> {
+    let data: Vec<u8> = frame.data.concat();
+    let mut out = Vec::with_capacity(frame.width * frame.height);
+    let code_size = frame.min_code_size.clamp(2, 11);
+    let _ = weezl::decode::Decoder::new(weezl::BitOrder::Lsb, code_size)
+        .into_vec(&mut out)
+        .decode(&data)
+        .status;
+    out
+}
+
+fn u16_at(bytes: &[u8], pos: usize) -> Result<u16> {
+    let pair = bytes.get(pos..pos + 2).context("truncated GIF")?;
+    Ok(u16::from_le_bytes([pair[0], pair[1]]))
+}
@@ -0,0 +1,269 @@
+//! Terminal graphics drawn by the client, without chafa.
+//!
+//! Spawning chafa for every image was slow, and machines without it showed
+//! no images at all. Four ways of drawing are built in:
+//!
+//! - half blocks: each character cell shows two pixels, the upper half
+//!   block (▀) in the top pixel's color over the bottom pixel's as
+//!   background, in 24-bit color. Works in any truecolor terminal.
+//! - the Kitty graphics protocol (kitty, WezTerm, Ghostty), which shows the
+//!   image at full resolution.
+//! - iTerm2 inline images (iTerm2, WezTerm), likewise.
+//! - sixel graphics (xterm, foot, mlterm, Windows Terminal, ...), at full
+//!   resolution in up to 256 colors.
+//!
+//! `auto` picks the best the terminal supports: Kitty or iTerm2 graphics
+//! when the environment names a terminal that has them, otherwise what the
+//! terminal answers to a capability query (`term::graphics`), and half
+//! blocks when it supports neither Kitty nor sixel graphics. Inside tmux or
+//! screen, which don't pass Kitty or iTerm2 escapes through, only sixels
+//! are considered (tmux 3.4 can show them).
+
+use crate::config::ImageBackend;
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use image::{imageops::FilterType, ImageFormat, RgbaImage};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+/// Cell size assumed when the terminal doesn't report its pixel size
+const CELL_SIZE: (u32, u32) = (8, 16);
+/// Base64 bytes per Kitty graphics escape (the protocol's limit)
+const KITTY_CHUNK: usize = 4096;
+/// Pixels at least this opaque are drawn; fainter ones show the background
+const OPAQUE: u8 = 128;
+/// Sixel palette size (the most terminals keep)
+const SIXEL_COLORS: usize = 256;
+/// Sampling factor for building the sixel palette: 1 looks at every pixel,
+/// 30 (the fastest) at every 30th
+const SIXEL_SAMPLING: i32 = 10;
+
+/// The backend `backend` stands for here: `auto` resolved for this terminal.
+pub fn resolve(backend: ImageBackend) -> ImageBackend {
+    if backend != ImageBackend::Auto {
+        return backend;
+    }
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+    let term = var("TERM");
+    let program = var("TERM_PROGRAM");
+    if std::env::var_os("TMUX").is_some() || term.starts_with("screen") || term.starts_with("tmux") {
+        if crate::term::graphics().sixel {
+            ImageBackend::Sixels
+        } else {
+            ImageBackend::Halfblocks
+        }
+    } else if std::env::var_os("KITTY_WINDOW_ID").is_some()
+        || term == "xterm-kitty"
+        || term == "xterm-ghostty"
+        || program == "ghostty"
+        || program == "WezTerm"
+    {
+        ImageBackend::Kitty
+    } else if program == "iTerm.app" || var("LC_TERMINAL") == "iTerm2" {
+        ImageBackend::Iterm
+    } else {
+        let graphics = crate::term::graphics();
+        if graphics.kitty {
+            ImageBackend::Kitty
+        } else if graphics.sixel {
+            ImageBackend::Sixels
+        } else {
+            ImageBackend::Halfblocks
+        }
+    }
+}
+
+/// Columns and rows of a "WxH" size string.
+pub fn parse_size(size: &str) -> Option<(u32, u32)> {
+    let (cols, rows) = size.split_once('x')?;
+    Some((cols.trim().parse().ok()?, rows.trim().parse().ok()?))
+}
+
+/// Image `bytes` drawn within `cols` x `rows` cells with `backend` (one of
+/// the built-in ones), keeping its aspect ratio. Text and escapes to print.
+pub fn render(bytes: &[u8], backend: ImageBackend, cols: u32, rows: u32) -> Result<String> {
+    let format = image::guess_format(bytes).context("Unknown image format")?;
+    match backend {
+        ImageBackend::Kitty => {
+            let image = decode(bytes, format)?;
+            let cols = fitted_cols(image.width(), image.height(), cols, rows);
+            let png = if format == ImageFormat::Png {
+                bytes.to_vec()
+            } else {
+                let mut png = Vec::new();
+                image
+                    .write_to(&mut std::io::Cursor::new(&mut png), ImageFormat::Png)
+                    .context("Failed to encode PNG")?;
+                png
+            };
+            Ok(kitty(&png, cols))
+        }
+        ImageBackend::Iterm => Ok(iterm(bytes, cols, rows)),
+        ImageBackend::Sixels => Ok(sixel(&decode(bytes, format)?, cols, rows)),
+        _ => Ok(halfblocks(&decode(bytes, format)?, cols, rows)),
+    }
+}
+
+fn decode(bytes: &[u8], format: ImageFormat) -> Result<RgbaImage> {
+    let image = image::load_from_memory_with_format(bytes, format)
+        .with_context(|| format!("Failed to decode {:?} image", format))?;
+    Ok(image.to_rgba8())
+}
+
+/// Size of a character cell in pixels, as the terminal reports it.
+fn cell_size() -> (u32, u32) {
+    crossterm::terminal::window_size()
+        .ok()
+        .filter(|s| s.width > 0 && s.height > 0 && s.columns > 0 && s.rows > 0)
+        .map(|s| (u32::from(s.width / s.columns), u32::from(s.height / s.rows)))
+        .unwrap_or(CELL_SIZE)
+}
+
+/// Largest `width` x `height` with the same aspect ratio fitting in
+/// `max_width` x `max_height`.
+fn fit(width: u32, height: u32, max_width: u32, max_height: u32) -> (u32, u32) {
+    let scale = f64::min(
+        f64::from(max_width) / f64::from(width.max(1)),
+        f64::from(max_height) / f64::from(height.max(1)),
+    );
+    (
+        ((f64::from(width) * scale).round() as u32).clamp(1, max_width.max(1)),
+        ((f64::from(height) * scale).round() as u32).clamp(1, max_height.max(1)),
+    )
+}
+
+/// Columns an image takes at the largest size fitting `cols` x `rows`.
+fn fitted_cols(width: u32, height: u32, cols: u32, rows: u32) -> u32 {
+    let (cell_width, cell_height) = cell_size();
+    let (fitted, _) = fit(width, height, cols * cell_width, rows * cell_height);
+    ((f64::from(fitted) / f64::from(cell_width)).round() as u32).clamp(1, cols)
+}
+
+/// Kitty graphics: the PNG sent in chunks, shown `cols` wide (the height
+/// follows from the aspect ratio). Replies from the terminal are turned off.
+fn kitty(png: &[u8], cols: u32) -> String {
+    let data = general_purpose::STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+        if i == 0 {
+            let _ = write!(out, "\x1b_Ga=T,f=100,q=2,c={},m={};{}\x1b\\", cols, more, chunk);
+        } else {
+            let _ = write!(out, "\x1b_Gm={};{}\x1b\\", more, chunk);
+        }
+    }
+    out.push('\n');
+    out
+}
+
+/// iTerm2 inline image, which the terminal fits within `cols` x `rows`.
+fn iterm(bytes: &[u8], cols: u32, rows: u32) -> String {
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07\n",
+        bytes.len(),
+        cols,
+        rows,
+        general_purpose::STANDARD.encode(bytes)
+    )
+}
+
+/// The image scaled to fit `cols` x `rows` cells, two pixels per cell.
+fn halfblocks(image: &RgbaImage, cols: u32, rows: u32) -> String {
+    let (width, height) = fit(image.width(), image.height(), cols, rows * 2);
+    let image = image::imageops::resize(image, width, height, FilterType::Triangle);
+
+    let mut out = String::new();
+    for y in (0..height).step_by(2) {
+        let (mut fg, mut bg) = (None, None);
+        for x in 0..width {
+            let top = image.get_pixel(x, y).0;
+            let bottom = if y + 1 < height { image.get_pixel(x, y + 1).0 } else { [0; 4] };
+            // Upper half block in the top pixel's color, or the lower one
+            // when only the bottom pixel is drawn
+            let (symbol, ink, paper) = match (top[3] >= OPAQUE, bottom[3] >= OPAQUE) {
+                (true, true) => ('▀', Some(top), Some(bottom)),
+                (true, false) => ('▀', Some(top), None),
+                (false, true) => ('▄', Some(bottom), None),
+                (false, false) => (' ', None, None),
+            };
+            let ink = ink.map(|[r, g, b, _]| [r, g, b]);
+            let paper = paper.map(|[r, g, b, _]| [r, g, b]);
+            if ink.is_some() && ink != fg {
+                let [r, g, b] = ink.unwrap_or_default();
+                let _ = write!(out, "\x1b[38;2;{};{};{}m", r, g, b);
+                fg = ink;
+            }
+            if paper != bg {
+                match paper {
+                    Some([r, g, b]) => {
+                        let _ = write!(out, "\x1b[48;2;{};{};{}m", r, g, b);
+                    }
+                    None => out.push_str("\x1b[49m"),
+                }
+                bg = paper;
+            }
+            out.push(symbol);
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    out
+}
+
+/// Sixel graphics: the image scaled to fit `cols` x `rows` cells, with a
+/// palette of up to `SIXEL_COLORS` chosen for it. Transparent pixels are
+/// left unpainted, so the terminal background shows through.
+fn sixel(image: &RgbaImage, cols: u32, rows: u32) -> String {
+    let (cell_width, cell_height) = cell_size();
+    let (width, height) = fit(image.width(), image.height(), cols * cell_width, rows * cell_height);
+    let image = image::imageops::resize(image, width, height, FilterType::Triangle);
+    let palette = color_quant::NeuQuant::new(SIXEL_SAMPLING, SIXEL_COLORS, image.as_raw());
+    let colors: Vec<Option<usize>> = image
+        .pixels()
+        .map(|p| (p[3] >= OPAQUE).then(|| palette.index_of(&p.0)))
+        .collect();
+
+    // Pixel aspect 1:1, transparent background, then the palette in percent
+    let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", width, height);
+    let percent = |v: u8| u32::from(v) * 100 / 255;
+    for (i, c) in palette.color_map_rgba().chunks(4).enumerate() {
+        let _ = write!(out, "#{};2;{};{};{}", i, percent(c[0]), percent(c[1]), percent(c[2]));
+    }
+
+    // Bands six pixels high: each color's pixels in the band as one row of
+    // sixel characters, the rows drawn over each other ($), then the next
+    // band (-)
+    let (width, height) = (width as usize, height as usize);
+    for top in (0..height).step_by(6) {
+        let mut bands: BTreeMap<usize, Vec<u8>> = BTreeMap::new();
+        for dy in 0..(height - top).min(6) {
+            for x in 0..width {
+                if let Some(color) = colors[(top + dy) * width + x] {
+                    bands.entry(color).or_insert_with(|| vec![0; width])[x] |= 1 << dy;
+                }
+            }
+        }
+        for (i, (color, bits)) in bands.iter().enumerate() {
+            if i > 0 {
+                out.push('$');
+            }
+            let _ = write!(out, "#{}", color);
+            let end = bits.iter().rposition(|&b| b != 0).map_or(0, |last| last + 1);
+            let mut x = 0;
+            while x < end {
+                let run = bits[x..end].iter().take_while(|&&b| b == bits[x]).count();
+                let symbol = char::from(b'?' + bits[x]);
+                if run > 3 {
+                    let _ = write!(out, "!{}{}", run, symbol);
+                } else {
+                    out.extend(std::iter::repeat_n(symbol, run));
+                }
+                x += run;
+            }
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\\n");
+    out
+}
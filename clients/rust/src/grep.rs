@@ -0,0 +1,108 @@
+//! Pattern matching over a document's text chunks (`grep <slug> PATTERN`).
+//!
+//! Embeddings can't find exact patterns like "EPSG:\d+", so chunks are
+//! fetched in reading order and matched locally. Consecutive chunks on a
+//! page overlap; matches are keyed by their position on the page so text in
+//! the overlap is reported once.
+
+use anyhow::Result;
+use regex_lite::{Regex, RegexBuilder};
+use std::collections::HashSet;
+
+/// One match with surrounding text.
+pub struct Hit {
+    pub page: i32,
+    pub chunk_index: i32,
+    pub before: String,
+    pub matched: String,
+    pub after: String,
+}
+
+pub struct Matcher {
+    regex: Regex,
+    context: usize,
+    /// (page, character offset on the page) of matches already reported
+    seen: HashSet<(i32, usize)>,
+}
+
+impl Matcher {
+    /// `fixed` treats the pattern as a literal string.
+    pub fn new(pattern: &str, ignore_case: bool, fixed: bool, context: usize) -> Result<Self> {
+        let source = if fixed { regex_lite::escape(pattern) } else { pattern.to_string() };
+        let regex = RegexBuilder::new(&source)
+            .case_insensitive(ignore_case)
+            .build()
+            .map_err(|e| anyhow::anyhow!("Invalid pattern '{}': {}", pattern, e))?;
+        Ok(Self { regex, context, seen: HashSet::new() })
+    }
+
+    /// Start on another document, whose pages aren't those seen so far.
+    pub fn next_document(&mut self) {
+        self.seen.clear();
+    }
+
+    /// Matches in one chunk, skipping any already reported from an
+    /// overlapping chunk. `start_char` is the chunk's offset on its page.
+    pub fn scan(&mut self, page: i32, chunk_index: i32, start_char: Option<i32>, content: &str) -> Vec<Hit> {
+        let mut hits = Vec::new();
+        for m in self.regex.find_iter(content) {
+            if m.as_str().is_empty() {
+                continue;
+            }
+            if let Some(offset) = start_char {
+                let position = offset.max(0) as usize + content[..m.start()].chars().count();
+                if !self.seen.insert((page, position)) {
+                    continue;
+                }
+            }
+            hits.push(Hit {
+                page,
+                chunk_index,
+                before: tail(&content[..m.start()], self.context),
+                matched: one_line(m.as_str()),
+                after: head(&content[m.end()..], self.context),
+            });
+        }
+        hits
+    }
+}
+
+/// Last `n` characters of `text`, on one line.
+pub fn tail(text: &str, n: usize) -> String {
+    let text = one_line(text);
+    let count = text.chars().count();
+    if count <= n {
+        return text;
+    }
+    format!("…{}", text.chars().skip(count - n).collect::<String>())
+}
+
+/// First `n` characters of `text`, on one line.
+pub fn head(text: &str, n: usize) -> String {
+    let text = one_line(text);
+    if text.chars().count() <= n {
+        return text;
+    }
+    format!("{}…", text.chars().take(n).collect::<String>())
+}
+
+/// Collapse newlines and runs of whitespace, keeping edge spaces as one.
+pub fn one_line(text: &str) -> String {
+    let mut out = String::new();
+    let mut space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            space = true;
+        } else {
+            if space {
+                out.push(' ');
+                space = false;
+            }
+            out.push(c);
+        }
+    }
+    if space {
+        out.push(' ');
+    }
+    out
+}
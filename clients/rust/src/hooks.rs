@@ -0,0 +1,111 @@
+//! External commands run on results: the `on_show` hook from the config and
+//! `pipe N <command>` in chat.
+//!
+//! The result's image (or its text, for passages and elements without an
+//! image) is written to a temporary file, and placeholders in the command
+//! line are replaced with shell-quoted values: `{path}`, `{slug}`, `{page}`,
+//! `{type}`, `{label}`, `{title}`, `{id}`. A command without `{path}` gets
+//! the content on stdin instead, so filters like `wc -w` work as they are.
+//! Commands run through the shell, so pipes and redirects are allowed.
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// What a command receives.
+pub struct Payload {
+    pub bytes: Vec<u8>,
+    /// Temporary file name, without extension ("element-12")
+    pub name: String,
+    /// Extension of the temporary file ("png", "txt", ...)
+    pub extension: String,
+    /// Placeholder values other than `{path}`
+    pub vars: Vec<(&'static str, String)>,
+}
+
+/// Run `template` on `payload`, waiting for it to finish.
+pub fn run(template: &str, payload: &Payload) -> Result<()> {
+    let mut vars: Vec<(&str, String)> = payload.vars.clone();
+    let uses_path = template.contains("{path}");
+    if uses_path {
+        // Kept afterwards: the command may hand the file to a GUI app
+        let path = std::env::temp_dir().join(format!(
+            "osgeo-library-{}-{}.{}",
+            std::process::id(),
+            payload.name,
+            payload.extension
+        ));
+        std::fs::write(&path, &payload.bytes).context("Failed to write temp file")?;
+        vars.push(("path", path.to_string_lossy().into_owned()));
+    }
+    let line = expand(template, &vars);
+
+    std::io::stdout().flush().ok();
+    let mut child = shell(&line)
+        .stdin(if uses_path { Stdio::null() } else { Stdio::piped() })
+        .spawn()
+        .with_context(|| format!("Failed to run '{}'", line))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that stops reading early (e.g. `head`) isn't an error
+        if let Err(e) = stdin.write_all(&payload.bytes) {
+            if e.kind() != std::io::ErrorKind::BrokenPipe {
+                return Err(e).context("Failed to send content to the command");
+            }
+        }
+    }
+    let status = child.wait().context("Failed to wait for the command")?;
+    if !status.success() {
+        anyhow::bail!("'{}' failed ({})", template, status);
+    }
+    Ok(())
+}
+
+/// Replace `{name}` placeholders with quoted values in one pass, so values
+/// that contain braces are never expanded again. Unknown names stay as they are.
+fn expand(template: &str, vars: &[(&str, String)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after
+            .find('}')
+            .and_then(|end| vars.iter().find(|(name, _)| *name == &after[..end]).map(|(_, v)| (end, v)));
+        match value {
+            Some((end, value)) => {
+                out.push_str(&quote(value));
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(windows)]
+fn shell(line: &str) -> Command {
+    let mut command = Command::new("cmd");
+    command.args(["/C", line]);
+    command
+}
+
+#[cfg(not(windows))]
+fn shell(line: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.args(["-c", line]);
+    command
+}
+
+#[cfg(windows)]
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+#[cfg(not(windows))]
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
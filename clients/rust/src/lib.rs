@@ -0,0 +1,92 @@
+//! OSGeo Library client
+//!
+//! Search and question answering over the OSGeo Library REST API, as used by
+//! the `osgeo-library-cli` binary. The library part is the blocking
+//! [`OsgeoClient`] and its async twin [`AsyncOsgeoClient`], the request and
+//! response [`types`], and the plain-text rendering in [`format`]:
+//!
+//! ```no_run
+//! use osgeo_library::{types::SearchRequest, OsgeoClient};
+//!
+//! let client = OsgeoClient::new(osgeo_library::client::DEFAULT_SERVER_URL)?;
+//! let response = client.search(SearchRequest {
+//!     query: "map projections".to_string(),
+//!     limit: 5,
+//!     include_chunks: true,
+//!     include_elements: true,
+//!     ..Default::default()
+//! })?;
+//! for result in &response.results {
+//!     println!("{}", osgeo_library::format::source_summary(result));
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod async_client;
+pub mod client;
+pub mod format;
+pub mod types;
+
+pub use async_client::AsyncOsgeoClient;
+pub use client::{OsgeoClient, RequestPolicy};
+
+/// The command-line client; not a stable API.
+#[doc(hidden)]
+pub mod cli;
+
+mod audit;
+mod bugreport;
+mod builder;
+mod cancel;
+mod chatline;
+mod capabilities;
+mod cite;
+mod citations;
+mod clipboard;
+mod columns;
+mod completion;
+mod config;
+mod crash;
+mod digest;
+mod docdiff;
+mod editor;
+mod epub;
+mod evidence;
+mod failover;
+mod federation;
+mod gif;
+mod graphics;
+mod grep;
+mod hooks;
+mod httplog;
+mod live;
+mod lookup;
+mod mathtext;
+mod notes;
+mod paths;
+mod picker;
+mod plan;
+mod plugins;
+mod prefetch;
+mod preview;
+mod project;
+mod prompt;
+mod quantity;
+mod ranking;
+mod reader;
+mod recent;
+mod records;
+mod redact;
+mod report;
+mod resume;
+mod retry;
+mod shared;
+mod split;
+mod table;
+mod templates;
+mod term;
+mod textcache;
+mod transcript;
+mod tty;
+mod watch;
+mod xref;
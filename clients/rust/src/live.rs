@@ -0,0 +1,149 @@
+//! Live Markdown rendering of a streamed answer.
+//!
+//! Tokens arrive mid-word and mid-markup, so printing them as they come
+//! shows raw Markdown. Instead the unfinished last block is re-rendered in
+//! place (cursor moved back over it) as the text grows. Once a blank line
+//! outside a code fence closes a block, it is printed for good and scrolls
+//! away normally; only the last block is ever redrawn, so answers taller
+//! than the terminal render correctly too.
+
+use crossterm::{
+    cursor, queue,
+    terminal::{Clear, ClearType},
+};
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+use termimad::MadSkin;
+
+/// Minimum time between redraws within a line, to avoid flicker.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(50);
+
+pub struct LiveMarkdown {
+    skin: MadSkin,
+    width: usize,
+    height: usize,
+    /// Answer received so far
+    text: String,
+    /// Byte offset in `text` up to which blocks are printed for good
+    committed: usize,
+    /// Terminal lines taken by the last drawing of the unfinished block
+    tail_lines: usize,
+    last_draw: Option<Instant>,
+}
+
+impl LiveMarkdown {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            skin: MadSkin::default(),
+            width,
+            height,
+            text: String::new(),
+            committed: 0,
+            tail_lines: 0,
+            last_draw: None,
+        }
+    }
+
+    /// Append streamed text and update the display.
+    pub fn push(&mut self, chunk: &str) -> io::Result<()> {
+        self.text.push_str(chunk);
+        let finished = self.committed + finished_blocks(&self.text[self.committed..]);
+        if finished > self.committed {
+            self.clear_tail()?;
+            let (rendered, _) = self.render(self.committed, finished);
+            print!("{}", rendered);
+            self.committed = finished;
+        } else if !chunk.contains('\n')
+            && self.last_draw.is_some_and(|t| t.elapsed() < REDRAW_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.draw_tail()
+    }
+
+    /// Render what is left once the stream has ended.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.clear_tail()?;
+        let (rendered, _) = self.render(self.committed, self.text.len());
+        print!("{}", rendered);
+        io::stdout().flush()
+    }
+
+    fn draw_tail(&mut self) -> io::Result<()> {
+        let (rendered, lines) = self.render(self.committed, self.text.len());
+        // A block taller than the screen can't be redrawn in place; it shows
+        // up when it is finished
+        if lines + 1 >= self.height {
+            return Ok(());
+        }
+        self.clear_tail()?;
+        print!("{}", rendered);
+        self.tail_lines = lines;
+        self.last_draw = Some(Instant::now());
+        io::stdout().flush()
+    }
+
+    /// Move back over the unfinished block and erase it.
+    fn clear_tail(&mut self) -> io::Result<()> {
+        let mut out = io::stdout();
+        // MoveUp(0) would still move one line on most terminals
+        if self.tail_lines > 0 {
+            queue!(out, cursor::MoveUp(self.tail_lines as u16))?;
+        }
+        queue!(out, cursor::MoveToColumn(0), Clear(ClearType::FromCursorDown))?;
+        self.tail_lines = 0;
+        out.flush()
+    }
+
+    /// Rendered text for `text[start..end]` and the number of lines it takes.
+    fn render(&self, start: usize, end: usize) -> (String, usize) {
+        let source = &self.text[start..end];
+        if source.trim().is_empty() {
+            return (String::new(), 0);
+        }
+        let source = dash_bullets_to_stars(source);
+        let text = self.skin.text(&source, Some(self.width));
+        (text.to_string(), text.lines.len())
+    }
+}
+
+/// termimad only knows "* " list items; LLMs mostly write "- ".
+fn dash_bullets_to_stars(text: &str) -> String {
+    let mut in_fence = false;
+    let mut out = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        } else if !in_fence && indent <= 3 && trimmed.starts_with("- ") {
+            out.push_str(&line[..indent]);
+            out.push_str("* ");
+            out.push_str(&trimmed[2..]);
+            continue;
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+/// Length of the leading part of `text` made of finished blocks: up to the
+/// last blank line outside a code fence.
+fn finished_blocks(text: &str) -> usize {
+    let mut in_fence = false;
+    let mut end = 0;
+    let mut pos = 0;
+    for line in text.split_inclusive('\n') {
+        if !line.ends_with('\n') {
+            break;
+        }
+        pos += line.len();
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        } else if trimmed.is_empty() && !in_fence {
+            end = pos;
+        }
+    }
+    end
+}
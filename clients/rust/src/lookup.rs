@@ -0,0 +1,117 @@
+//! Finding an element by its printed label (`element "Table 3.2"`).
+//!
+//! When the label is known there is no need for a semantic search: the
+//! document's elements are listed and their labels compared with the one
+//! asked for. Numbers must agree exactly, so "Table 3.2" never finds
+//! "Table 3.3", while the words around them may be abbreviated ("Fig. 12")
+//! or misspelt within a small edit distance ("Tabel 3.2", "Fgure 12").
+//! Labels without a number, such as "Tissot indicatrix", are compared whole
+//! and with the caption after a colon.
+
+use crate::types::ElementListItem;
+use crate::xref;
+
+/// An element whose label is close to the one asked for.
+pub struct Match<'a> {
+    pub element: &'a ElementListItem,
+    /// Edits between the labels; 0 for a match as written
+    pub distance: usize,
+}
+
+/// Elements of `elements` labelled like `wanted`, closest first.
+pub fn find<'a>(wanted: &str, elements: &'a [ElementListItem]) -> Vec<Match<'a>> {
+    let reference = xref::parse(wanted);
+    let number = xref::label_number(wanted);
+    let wanted_words = words(wanted);
+    let wanted_text = normalize(wanted);
+
+    let mut found: Vec<Match> = elements
+        .iter()
+        .filter_map(|element| {
+            let label = element.label.as_deref()?;
+            let distance = if reference.as_ref().is_some_and(|r| r.matches(&element.element_type, label)) {
+                0
+            } else if let Some(number) = &number {
+                if !xref::label_number(label).is_some_and(|n| xref::same_number(&n, number)) {
+                    return None;
+                }
+                // "3.2" alone: any element numbered so
+                if wanted_words.is_empty() {
+                    0
+                } else {
+                    // "Chart 3" also finds a chart labelled "Figure 3"
+                    distance(&wanted_words, &kind_words(label, &element.element_type))
+                        .min(distance(&wanted_words, &element.element_type))
+                }
+            } else {
+                let (head, caption) = label.split_once(':').unwrap_or((label, ""));
+                [label, head, caption]
+                    .iter()
+                    .map(|part| distance(&wanted_text, &normalize(part)))
+                    .min()
+                    .unwrap_or(usize::MAX)
+            };
+            let compared = if number.is_some() { &wanted_words } else { &wanted_text };
+            (distance <= tolerance(compared)).then_some(Match { element, distance })
+        })
+        .collect();
+    found.sort_by_key(|m| (m.distance, m.element.page_number));
+    found
+}
+
+/// Edits allowed for a label of this length: two for short words, about
+/// one in three characters beyond that.
+fn tolerance(text: &str) -> usize {
+    (text.chars().count() / 3).max(2)
+}
+
+/// Lowercase, with runs of spaces and punctuation (but for the `.` and `-`
+/// of numbers) as one space.
+fn normalize(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric() && c != '.' && c != '-')
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// The words of a label's heading, without its number: "tabel" for
+/// "Tabel 3.2".
+fn words(label: &str) -> String {
+    let head = label.split(':').next().unwrap_or(label);
+    // Skipping the number whole, with its letter ("12a")
+    head.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty() && !w.chars().any(|c| c.is_ascii_digit()))
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Like `words`, with abbreviated kinds written out ("figure" for "Fig. 12")
+/// and the element type for bare numbers such as "(4-7)".
+fn kind_words(label: &str, element_type: &str) -> String {
+    if let Some(reference) = xref::parse(label.split(':').next().unwrap_or(label)) {
+        return reference.kind.to_string();
+    }
+    match words(label) {
+        words if words.is_empty() => element_type.to_string(),
+        words => words,
+    }
+}
+
+/// Levenshtein distance: single-character insertions, deletions and
+/// substitutions turning `a` into `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1).min(row[j] + 1).min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,366 @@
+//! LaTeX-to-Unicode conversion for equation previews.
+//!
+//! Equation elements carry their LaTeX source in the content field
+//! (e.g. "LaTeX: $\alpha^2 + \beta_i$"). This converts the common subset
+//! (Greek letters, operators, super/subscripts, fractions, roots) to Unicode
+//! so the text preview is readable without image rendering. Anything not
+//! recognized is passed through unchanged.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Opening and closing delimiters of math spans
+const DELIMITERS: [(&str, &str); 4] = [("$$", "$$"), ("$", "$"), ("\\(", "\\)"), ("\\[", "\\]")];
+
+/// Enable or disable conversion globally (`--no-mathtext`).
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Convert the math portions of `text` to Unicode.
+/// Math is taken from `$...$`, `$$...$$`, `\(...\)` and `\[...\]` spans;
+/// text without delimiters is treated as math entirely.
+pub fn to_unicode(text: &str) -> String {
+    if !DELIMITERS.iter().any(|(open, _)| text.contains(open)) {
+        return convert(text);
+    }
+
+    let mut out = String::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        // Earliest opening delimiter; longer ones win on ties ($$ before $)
+        let next = DELIMITERS
+            .iter()
+            .filter_map(|(open, close)| rest.find(open).map(|pos| (pos, *open, *close)))
+            .min_by_key(|(pos, open, _)| (*pos, usize::MAX - open.len()));
+
+        let Some((pos, open, close)) = next else {
+            out.push_str(rest);
+            break;
+        };
+
+        out.push_str(&rest[..pos]);
+        let body_start = pos + open.len();
+        match rest[body_start..].find(close) {
+            Some(end) => {
+                out.push_str(&convert(&rest[body_start..body_start + end]));
+                rest = &rest[body_start + end + close.len()..];
+            }
+            None => {
+                // Unterminated span: convert what's left
+                out.push_str(&convert(&rest[body_start..]));
+                break;
+            }
+        }
+    }
+    out
+}
+
+/// The LaTeX of `text` as it would be pasted into a document: the first
+/// math span without its delimiters, or for text without any, the text less
+/// a leading "LaTeX:".
+pub fn source(text: &str) -> String {
+    let span = DELIMITERS
+        .iter()
+        .filter_map(|(open, close)| text.find(open).map(|pos| (pos, *open, *close)))
+        .min_by_key(|(pos, open, _)| (*pos, usize::MAX - open.len()));
+    match span {
+        Some((pos, open, close)) => {
+            let body = &text[pos + open.len()..];
+            body[..body.find(close).unwrap_or(body.len())].trim().to_string()
+        }
+        None => {
+            let text = text.trim();
+            text.strip_prefix("LaTeX:").unwrap_or(text).trim().to_string()
+        }
+    }
+}
+
+/// Convert a LaTeX math expression (no delimiters) to Unicode.
+pub fn convert(latex: &str) -> String {
+    let chars: Vec<char> = latex.chars().collect();
+    let mut i = 0;
+    let out = parse_until(&chars, &mut i, None);
+    collapse_spaces(&out)
+}
+
+fn parse_until(chars: &[char], i: &mut usize, close: Option<char>) -> String {
+    let mut out = String::new();
+    while *i < chars.len() {
+        let c = chars[*i];
+        if Some(c) == close {
+            *i += 1;
+            return out;
+        }
+        match c {
+            '\\' => out.push_str(&parse_command(chars, i)),
+            '{' => {
+                *i += 1;
+                out.push_str(&parse_until(chars, i, Some('}')));
+            }
+            '^' | '_' => {
+                *i += 1;
+                let arg = parse_argument(chars, i);
+                out.push_str(&script(&arg, c == '^'));
+            }
+            '&' => {
+                out.push(' ');
+                *i += 1;
+            }
+            '~' => {
+                out.push(' ');
+                *i += 1;
+            }
+            _ => {
+                out.push(c);
+                *i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Parse a single argument: a braced group, a command, or one character.
+fn parse_argument(chars: &[char], i: &mut usize) -> String {
+    while *i < chars.len() && chars[*i] == ' ' {
+        *i += 1;
+    }
+    match chars.get(*i) {
+        Some('{') => {
+            *i += 1;
+            parse_until(chars, i, Some('}'))
+        }
+        Some('\\') => parse_command(chars, i),
+        Some(&c) => {
+            *i += 1;
+            c.to_string()
+        }
+        None => String::new(),
+    }
+}
+
+/// Skip an optional `[...]` argument (e.g. `\sqrt[3]{x}`), returning it.
+fn parse_optional(chars: &[char], i: &mut usize) -> Option<String> {
+    if chars.get(*i) != Some(&'[') {
+        return None;
+    }
+    *i += 1;
+    Some(parse_until(chars, i, Some(']')))
+}
+
+fn parse_command(chars: &[char], i: &mut usize) -> String {
+    // chars[*i] == '\\'
+    *i += 1;
+    let start = *i;
+    while *i < chars.len() && chars[*i].is_ascii_alphabetic() {
+        *i += 1;
+    }
+    if *i == start {
+        // Control symbol: \\, \,, \;, \{, \% ...
+        let Some(&c) = chars.get(*i) else {
+            return String::new();
+        };
+        *i += 1;
+        return match c {
+            '\\' => "; ".to_string(),
+            ',' | ';' | ':' | '!' | ' ' => " ".to_string(),
+            _ => c.to_string(),
+        };
+    }
+    let name: String = chars[start..*i].iter().collect();
+
+    match name.as_str() {
+        "frac" | "dfrac" | "tfrac" => {
+            let num = parse_argument(chars, i);
+            let den = parse_argument(chars, i);
+            format!("{}/{}", wrap(&num), wrap(&den))
+        }
+        "sqrt" => {
+            let degree = parse_optional(chars, i);
+            let arg = parse_argument(chars, i);
+            let root = match degree.as_deref() {
+                Some("3") => "∛",
+                Some("4") => "∜",
+                _ => "√",
+            };
+            format!("{}{}", root, wrap(&arg))
+        }
+        "text" | "textrm" | "textit" | "textbf" | "mathrm" | "mathit" | "mathbf"
+        | "mathsf" | "mathtt" | "operatorname" | "boldsymbol" | "mbox" => {
+            parse_argument(chars, i)
+        }
+        "mathcal" | "mathscr" => parse_argument(chars, i).chars().map(script_letter).collect(),
+        "mathbb" => parse_argument(chars, i).chars().map(double_struck).collect(),
+        "hat" | "widehat" => accent(&parse_argument(chars, i), '\u{0302}'),
+        "bar" | "overline" => accent(&parse_argument(chars, i), '\u{0304}'),
+        "tilde" | "widetilde" => accent(&parse_argument(chars, i), '\u{0303}'),
+        "dot" => accent(&parse_argument(chars, i), '\u{0307}'),
+        "vec" => accent(&parse_argument(chars, i), '\u{20D7}'),
+        "begin" | "end" => {
+            // Environment markers carry no content
+            parse_argument(chars, i);
+            String::new()
+        }
+        "left" | "right" | "big" | "Big" | "bigg" | "Bigg" | "displaystyle" => String::new(),
+        "quad" | "qquad" => "  ".to_string(),
+        _ if FUNCTIONS.contains(&name.as_str()) => {
+            // Upright function names; keep them apart from a following symbol
+            match chars.get(*i) {
+                Some(c) if c.is_alphanumeric() || *c == '\\' => format!("{} ", name),
+                _ => name,
+            }
+        }
+        _ => symbol(&name).map(str::to_string).unwrap_or_else(|| format!("\\{}", name)),
+    }
+}
+
+const FUNCTIONS: &[&str] = &[
+    "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan",
+    "sinh", "cosh", "tanh", "log", "ln", "exp", "lim", "max", "min", "arg",
+    "det", "argmin", "argmax",
+];
+
+fn symbol(name: &str) -> Option<&'static str> {
+    Some(match name {
+        // Greek lowercase
+        "alpha" => "α", "beta" => "β", "gamma" => "γ", "delta" => "δ",
+        "epsilon" => "ε", "varepsilon" => "ε", "zeta" => "ζ", "eta" => "η",
+        "theta" => "θ", "vartheta" => "ϑ", "iota" => "ι", "kappa" => "κ",
+        "lambda" => "λ", "mu" => "μ", "nu" => "ν", "xi" => "ξ", "pi" => "π",
+        "varpi" => "ϖ", "rho" => "ρ", "varrho" => "ϱ", "sigma" => "σ",
+        "varsigma" => "ς", "tau" => "τ", "upsilon" => "υ", "phi" => "φ",
+        "varphi" => "φ", "chi" => "χ", "psi" => "ψ", "omega" => "ω",
+        // Greek uppercase
+        "Gamma" => "Γ", "Delta" => "Δ", "Theta" => "Θ", "Lambda" => "Λ",
+        "Xi" => "Ξ", "Pi" => "Π", "Sigma" => "Σ", "Upsilon" => "Υ",
+        "Phi" => "Φ", "Psi" => "Ψ", "Omega" => "Ω",
+        // Operators and relations
+        "times" => "×", "cdot" => "·", "div" => "÷", "pm" => "±", "mp" => "∓",
+        "leq" | "le" => "≤", "geq" | "ge" => "≥", "neq" | "ne" => "≠",
+        "approx" => "≈", "equiv" => "≡", "sim" => "∼", "simeq" => "≃",
+        "propto" => "∝", "ll" => "≪", "gg" => "≫",
+        "infty" => "∞", "partial" => "∂", "nabla" => "∇",
+        "sum" => "∑", "prod" => "∏", "int" => "∫", "iint" => "∬", "oint" => "∮",
+        "in" => "∈", "notin" => "∉", "subset" => "⊂", "subseteq" => "⊆",
+        "cup" => "∪", "cap" => "∩", "emptyset" => "∅", "forall" => "∀",
+        "exists" => "∃", "neg" => "¬", "land" | "wedge" => "∧", "lor" | "vee" => "∨",
+        "rightarrow" | "to" => "→", "leftarrow" => "←", "Rightarrow" => "⇒",
+        "Leftarrow" => "⇐", "leftrightarrow" => "↔", "Leftrightarrow" => "⇔",
+        "mapsto" => "↦", "mid" => "|", "circ" => "∘", "degree" => "°",
+        "ldots" | "dots" | "cdots" => "…", "prime" => "′", "ast" => "∗",
+        "langle" => "⟨", "rangle" => "⟩", "lfloor" => "⌊", "rfloor" => "⌋",
+        "lceil" => "⌈", "rceil" => "⌉", "|" => "‖",
+        _ => return None,
+    })
+}
+
+/// Parenthesize multi-token fraction/root arguments for unambiguous reading.
+fn wrap(s: &str) -> String {
+    let s = s.trim();
+    if s.chars().count() <= 1 || s.chars().all(|c| c.is_alphanumeric() || c == '.') {
+        s.to_string()
+    } else {
+        format!("({})", s)
+    }
+}
+
+/// Render a super/subscript with Unicode script characters when every
+/// character has one; otherwise fall back to `^(...)` / `_(...)`.
+fn script(arg: &str, sup: bool) -> String {
+    let mapped: Option<String> = arg
+        .chars()
+        .map(|c| if sup { superscript(c) } else { subscript(c) })
+        .collect();
+    match mapped {
+        Some(m) if !m.is_empty() => m,
+        _ => {
+            let marker = if sup { '^' } else { '_' };
+            if arg.chars().count() == 1 {
+                format!("{}{}", marker, arg)
+            } else {
+                format!("{}({})", marker, arg)
+            }
+        }
+    }
+}
+
+fn superscript(c: char) -> Option<char> {
+    Some(match c {
+        '0' => '⁰', '1' => '¹', '2' => '²', '3' => '³', '4' => '⁴',
+        '5' => '⁵', '6' => '⁶', '7' => '⁷', '8' => '⁸', '9' => '⁹',
+        '+' => '⁺', '-' => '⁻', '=' => '⁼', '(' => '⁽', ')' => '⁾',
+        'n' => 'ⁿ', 'i' => 'ⁱ', 'T' => 'ᵀ', 'k' => 'ᵏ', 'j' => 'ʲ',
+        'x' => 'ˣ', 'a' => 'ᵃ', 'b' => 'ᵇ', 'c' => 'ᶜ', 'd' => 'ᵈ',
+        'e' => 'ᵉ', 'm' => 'ᵐ', 'p' => 'ᵖ', 't' => 'ᵗ', '*' => '*',
+        '′' => '′', '∗' => '∗',
+        _ => return None,
+    })
+}
+
+fn subscript(c: char) -> Option<char> {
+    Some(match c {
+        '0' => '₀', '1' => '₁', '2' => '₂', '3' => '₃', '4' => '₄',
+        '5' => '₅', '6' => '₆', '7' => '₇', '8' => '₈', '9' => '₉',
+        '+' => '₊', '-' => '₋', '=' => '₌', '(' => '₍', ')' => '₎',
+        'a' => 'ₐ', 'e' => 'ₑ', 'h' => 'ₕ', 'i' => 'ᵢ', 'j' => 'ⱼ',
+        'k' => 'ₖ', 'l' => 'ₗ', 'm' => 'ₘ', 'n' => 'ₙ', 'o' => 'ₒ',
+        'p' => 'ₚ', 'r' => 'ᵣ', 's' => 'ₛ', 't' => 'ₜ', 'u' => 'ᵤ',
+        'v' => 'ᵥ', 'x' => 'ₓ',
+        _ => return None,
+    })
+}
+
+fn script_letter(c: char) -> char {
+    match c {
+        'A' => '𝒜', 'B' => 'ℬ', 'C' => '𝒞', 'D' => '𝒟', 'E' => 'ℰ', 'F' => 'ℱ',
+        'G' => '𝒢', 'H' => 'ℋ', 'I' => 'ℐ', 'J' => '𝒥', 'K' => '𝒦', 'L' => 'ℒ',
+        'M' => 'ℳ', 'N' => '𝒩', 'O' => '𝒪', 'P' => '𝒫', 'Q' => '𝒬', 'R' => 'ℛ',
+        'S' => '𝒮', 'T' => '𝒯', 'U' => '𝒰', 'V' => '𝒱', 'W' => '𝒲', 'X' => '𝒳',
+        'Y' => '𝒴', 'Z' => '𝒵',
+        _ => c,
+    }
+}
+
+fn double_struck(c: char) -> char {
+    match c {
+        'C' => 'ℂ', 'N' => 'ℕ', 'P' => 'ℙ', 'Q' => 'ℚ', 'R' => 'ℝ', 'Z' => 'ℤ',
+        _ => c,
+    }
+}
+
+/// Apply a combining accent after the first character of `s`.
+fn accent(s: &str, mark: char) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => {
+            let mut out = String::new();
+            out.push(first);
+            out.push(mark);
+            out.extend(chars);
+            out
+        }
+        None => String::new(),
+    }
+}
+
+fn collapse_spaces(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut last_space = false;
+    for c in s.chars() {
+        if c.is_whitespace() {
+            if !last_space {
+                out.push(' ');
+            }
+            last_space = true;
+        } else {
+            out.push(c);
+            last_space = false;
+        }
+    }
+    out.trim().to_string()
+}
@@ -0,0 +1,195 @@
+//! Lecture-notes export (`export notes <slug> --chapter N`).
+//!
+//! Walks a page range and interleaves each page's summary with the figures,
+//! tables and equations found on it, producing a Markdown chapter that can
+//! be edited into teaching material. Images are saved next to the Markdown
+//! file and linked relatively, so the output directory is self-contained.
+
+use std::collections::HashSet;
+
+/// Title block of the exported chapter.
+pub struct Heading {
+    pub title: String,
+    pub document_title: String,
+    pub slug: String,
+    pub start_page: i32,
+    pub end_page: i32,
+}
+
+/// One page of the chapter with the elements kept for the notes.
+pub struct Page {
+    pub number: i32,
+    pub summary: Option<String>,
+    pub keywords: Vec<String>,
+    pub elements: Vec<Element>,
+}
+
+/// A figure, table or equation on a page.
+pub struct Element {
+    pub element_type: String,
+    pub label: Option<String>,
+    pub description: Option<String>,
+    pub latex: Option<String>,
+    /// Image path relative to the notes file, when one was downloaded
+    pub image: Option<String>,
+}
+
+/// Render the chapter as Markdown.
+pub fn render(heading: &Heading, pages: &[Page]) -> String {
+    let mut out = format!("# {}\n\n", heading.title);
+    out.push_str(&format!(
+        "*{}* (`{}`), {}\n",
+        heading.document_title,
+        heading.slug,
+        page_range(heading.start_page, heading.end_page)
+    ));
+
+    for page in pages {
+        out.push_str(&format!("\n## Page {}\n\n", page.number));
+        match page.summary.as_deref().map(str::trim) {
+            Some(summary) if !summary.is_empty() => out.push_str(&format!("{}\n", summary)),
+            _ => out.push_str("*No summary for this page.*\n"),
+        }
+        if !page.keywords.is_empty() {
+            out.push_str(&format!("\n**Keywords:** {}\n", page.keywords.join(", ")));
+        }
+        for element in &page.elements {
+            out.push('\n');
+            out.push_str(&render_element(element, page.number));
+        }
+    }
+    out
+}
+
+fn render_element(element: &Element, page: i32) -> String {
+    let name = element_name(element);
+    let caption = match element.description.as_deref().map(one_line) {
+        Some(desc) if !desc.is_empty() => format!("*{}* (p. {}): {}", name, page, desc),
+        _ => format!("*{}* (p. {})", name, page),
+    };
+
+    // Equations are written as LaTeX when the source is known
+    if element.element_type == "equation" {
+        if let Some(latex) = element.latex.as_deref().map(strip_math_delimiters) {
+            if !latex.is_empty() {
+                return format!("$$\n{}\n$$\n\n{}\n", latex, caption);
+            }
+        }
+    }
+
+    match &element.image {
+        Some(path) => format!("![{}]({})\n\n{}\n", name, path, caption),
+        None => format!("{}\n", caption),
+    }
+}
+
+/// "Figure 3-1", or the capitalized type when the element has no label.
+pub fn element_name(element: &Element) -> String {
+    let kind = capitalize(&element.element_type);
+    match element.label.as_deref().map(str::trim) {
+        Some(label) if !label.is_empty() => {
+            if label.to_lowercase().starts_with(&element.element_type) {
+                label.to_string()
+            } else {
+                format!("{} {}", kind, label)
+            }
+        }
+        _ => kind,
+    }
+}
+
+/// File name for an element image inside an e-book, e.g. "p045-figure-3-1.png".
+pub fn image_file_name(page: i32, element_type: &str, label: Option<&str>, id: i64, source: &str) -> String {
+    let ext = image_extension(source);
+    let label: String = label
+        .unwrap_or_default()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let label = label
+        .trim_start_matches(element_type)
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if label.is_empty() {
+        format!("p{:03}-{}-{}.{}", page, element_type, id, ext)
+    } else {
+        format!("p{:03}-{}-{}.{}", page, element_type, label, ext)
+    }
+}
+
+/// File name for a downloaded element image: `stem` (from the name
+/// template) with the extension of `source`, and "-2", "-3", ... added when
+/// `used` has it already, as for two figures on one page.
+pub fn unique_image_name(stem: &str, source: &str, used: &mut HashSet<String>) -> String {
+    let ext = image_extension(source);
+    let mut name = format!("{}.{}", stem, ext);
+    let mut n = 1;
+    while !used.insert(name.clone()) {
+        n += 1;
+        name = format!("{}-{}.{}", stem, n, ext);
+    }
+    name
+}
+
+/// Extension of an image path on the server ("png" when it has none).
+fn image_extension(source: &str) -> &str {
+    std::path::Path::new(source)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("png")
+}
+
+/// Parse a page range: "45-78" or a single page "45".
+pub fn parse_page_range(value: &str) -> Result<(i32, i32), String> {
+    let parse = |s: &str| {
+        s.trim()
+            .parse::<i32>()
+            .ok()
+            .filter(|n| *n >= 1)
+            .ok_or_else(|| format!("invalid page '{}'", s.trim()))
+    };
+    let (start, end) = match value.split_once('-') {
+        Some((start, end)) => (parse(start)?, parse(end)?),
+        None => {
+            let page = parse(value)?;
+            (page, page)
+        }
+    };
+    if end < start {
+        return Err(format!("range '{}' ends before it starts", value));
+    }
+    Ok((start, end))
+}
+
+fn page_range(start: i32, end: i32) -> String {
+    if start == end {
+        format!("page {}", start)
+    } else {
+        format!("pages {}–{}", start, end)
+    }
+}
+
+fn strip_math_delimiters(latex: &str) -> &str {
+    let latex = latex.trim();
+    for (open, close) in [("$$", "$$"), ("\\[", "\\]"), ("$", "$")] {
+        if let Some(inner) = latex.strip_prefix(open).and_then(|l| l.strip_suffix(close)) {
+            return inner.trim();
+        }
+    }
+    latex
+}
+
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
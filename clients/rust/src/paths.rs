@@ -0,0 +1,162 @@
+//! Where exports are written and what they are called.
+//!
+//! Each command that writes files used to settle this on its own: `export
+//! notes` made up a directory name, `export doc` needed a file name every
+//! time, and the rest wrote wherever they were run. Now they share one
+//! export directory, `--out-dir` or `[paths] exports` in the config, and
+//! one template for the names they choose themselves, `[paths] name`:
+//!
+//! | Placeholder | Expands to                                               |
+//! |-------------|----------------------------------------------------------|
+//! | `{slug}`    | document slug                                            |
+//! | `{page}`    | page or page range ("12", "45-78")                       |
+//! | `{type}`    | what is written: `notes`, `doc`, or an element type      |
+//! | `{label}`   | chapter ("chapter-3") or element label ("figure-3-1")    |
+//!
+//! A placeholder with nothing to expand to is left out along with the
+//! separator before it, so the default `{slug}_{page}_{type}` names a whole
+//! document export "usgs_snyder_doc". Paths given on the command line are
+//! used as given, relative ones inside the export directory.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Name template when the config has none
+pub const DEFAULT_NAME: &str = "{slug}_{page}_{type}";
+
+struct Settings {
+    dir: Option<PathBuf>,
+    name: String,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// Set the export directory (`None` for the current directory) and the name
+/// template. Once, at startup.
+pub fn init(dir: Option<PathBuf>, name: &str) {
+    let dir = dir.map(|d| match (d.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => d,
+    });
+    SETTINGS.set(Settings { dir, name: name.to_string() }).ok();
+}
+
+fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(|| Settings {
+        dir: None,
+        name: DEFAULT_NAME.to_string(),
+    })
+}
+
+/// What an export is, for naming it.
+#[derive(Default)]
+pub struct Export<'a> {
+    pub slug: Option<&'a str>,
+    pub page: Option<String>,
+    pub kind: &'a str,
+    pub label: Option<&'a str>,
+}
+
+/// `given` as it will be written: inside the export directory when it is
+/// relative and there is one.
+pub fn resolve(given: &Path) -> Result<PathBuf> {
+    match &settings().dir {
+        Some(dir) if given.is_relative() => Ok(export_dir(dir)?.join(given)),
+        _ => Ok(given.to_path_buf()),
+    }
+}
+
+/// Path for `export` named from the template, in the export directory,
+/// with `extension` (none for directories).
+pub fn default(export: &Export, extension: &str) -> Result<PathBuf> {
+    let mut name = name(export);
+    if !extension.is_empty() {
+        name = format!("{}.{}", name, extension);
+    }
+    match &settings().dir {
+        Some(dir) => Ok(export_dir(dir)?.join(name)),
+        None => Ok(PathBuf::from(name)),
+    }
+}
+
+/// File name for `export` from the template, without extension.
+pub fn name(export: &Export) -> String {
+    let vars = [
+        ("slug", export.slug.unwrap_or_default().to_string()),
+        ("page", export.page.clone().unwrap_or_default()),
+        ("type", export.kind.to_string()),
+        ("label", export.label.map(file_safe).unwrap_or_default()),
+    ];
+    let name = expand(&settings().name, &vars);
+    let name: String = name
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') { '-' } else { c })
+        .collect();
+    if name.trim().is_empty() {
+        export.kind.to_string()
+    } else {
+        name
+    }
+}
+
+/// The export directory, created if needed.
+fn export_dir(dir: &Path) -> Result<&Path> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    Ok(dir)
+}
+
+/// `template` with placeholders replaced. The text before a placeholder
+/// that expands to nothing is dropped, unless it starts the template.
+fn expand(template: &str, vars: &[(&str, String)]) -> String {
+    let mut out = String::new();
+    // Text since the last placeholder that expanded to something
+    let mut pending = String::new();
+    let mut rest = template;
+    let mut leading = true;
+    while let Some(open) = rest.find('{') {
+        let Some(close) = rest[open..].find('}').map(|c| open + c) else {
+            break;
+        };
+        pending.push_str(&rest[..open]);
+        match vars.iter().find(|(name, _)| *name == &rest[open + 1..close]) {
+            Some((_, value)) if value.is_empty() => {
+                let before = std::mem::take(&mut pending);
+                if leading {
+                    out.push_str(&before);
+                }
+            }
+            Some((_, value)) => {
+                if !out.is_empty() || leading {
+                    out.push_str(&pending);
+                }
+                pending.clear();
+                out.push_str(value);
+            }
+            // Unknown placeholders are kept as written
+            None => {
+                pending.push_str(&rest[open..=close]);
+                rest = &rest[close + 1..];
+                continue;
+            }
+        }
+        leading = false;
+        rest = &rest[close + 1..];
+    }
+    if !out.is_empty() || leading {
+        out.push_str(&pending);
+        out.push_str(rest);
+    }
+    out
+}
+
+/// `label` lowercased, with runs of anything but letters and digits turned
+/// into single dashes ("Figure 3.1: Tissot" becomes "figure-3-1-tissot").
+pub fn file_safe(label: &str) -> String {
+    label
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
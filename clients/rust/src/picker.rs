@@ -0,0 +1,236 @@
+//! Choosing a result by typing part of it (`pick` in chat, `search --pick`).
+//!
+//! Instead of reading the numbers off a long result list, the results are
+//! listed under a filter line: typing narrows them to those containing the
+//! typed letters in order ("tisind" finds "Tissot indicatrix"), words
+//! separated by spaces must all match, and the closest matches come first.
+//! Arrow keys move the selection, Enter takes it and Esc gives up. The list
+//! is drawn below the cursor rather than on the alternate screen, and
+//! erased when done, so what was printed before stays in view.
+
+use crate::tty;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, queue, terminal};
+use std::io::{IsTerminal, Write};
+
+/// Most items listed at once
+const MAX_ROWS: usize = 10;
+
+/// Let the user choose one of `items` (one line each); the index chosen, or
+/// `None` when they give up.
+pub fn pick(items: &[String]) -> Result<Option<usize>> {
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        anyhow::bail!("Picking a result needs a terminal");
+    }
+    let mut terminal = tty::Guard::default();
+    terminal.raw_mode().context("Failed to read keys from the terminal")?;
+
+    let mut picker = Picker {
+        items,
+        query: String::new(),
+        matches: (0..items.len()).collect(),
+        selected: 0,
+        top: 0,
+    };
+    let chosen = loop {
+        picker.draw()?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+        match action(key) {
+            Some(Action::Choose) if !picker.matches.is_empty() => break Some(picker.matches[picker.selected]),
+            Some(Action::Choose) => {}
+            Some(Action::Cancel) => break None,
+            Some(Action::Move(by)) => picker.move_by(by),
+            Some(Action::Type(c)) => {
+                picker.query.push(c);
+                picker.filter();
+            }
+            Some(Action::Erase) => {
+                picker.query.pop();
+                picker.filter();
+            }
+            Some(Action::Clear) => {
+                picker.query.clear();
+                picker.filter();
+            }
+            None => {}
+        }
+    };
+    let mut out = std::io::stdout();
+    clear(&mut out)?;
+    out.flush()?;
+    Ok(chosen)
+}
+
+enum Action {
+    Choose,
+    Cancel,
+    Move(isize),
+    Type(char),
+    Erase,
+    Clear,
+}
+
+fn action(key: KeyEvent) -> Option<Action> {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    Some(match key.code {
+        KeyCode::Enter => Action::Choose,
+        KeyCode::Esc => Action::Cancel,
+        KeyCode::Char('c' | 'd' | 'g') if ctrl => Action::Cancel,
+        KeyCode::Up => Action::Move(-1),
+        KeyCode::Char('p' | 'k') if ctrl => Action::Move(-1),
+        KeyCode::Down | KeyCode::Tab => Action::Move(1),
+        KeyCode::Char('n' | 'j') if ctrl => Action::Move(1),
+        KeyCode::PageUp => Action::Move(-(MAX_ROWS as isize)),
+        KeyCode::PageDown => Action::Move(MAX_ROWS as isize),
+        KeyCode::Backspace => Action::Erase,
+        KeyCode::Char('u') if ctrl => Action::Clear,
+        KeyCode::Char(c) if !ctrl => Action::Type(c),
+        _ => return None,
+    })
+}
+
+struct Picker<'a> {
+    items: &'a [String],
+    query: String,
+    /// Indices of the items matching `query`, best first
+    matches: Vec<usize>,
+    /// Position in `matches`
+    selected: usize,
+    /// First position in `matches` listed
+    top: usize,
+}
+
+impl Picker<'_> {
+    fn filter(&mut self) {
+        let terms: Vec<Vec<char>> = self
+            .query
+            .split_whitespace()
+            .map(|term| term.to_lowercase().chars().collect())
+            .collect();
+        let mut scored: Vec<(i64, usize)> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| {
+                let text = item.to_lowercase();
+                let total = terms.iter().map(|term| score(term, &text)).sum::<Option<i64>>()?;
+                Some((total, i))
+            })
+            .collect();
+        // Best first; equal scores in their listed order
+        scored.sort_by_key(|&(score, i)| (-score, i));
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+        self.top = 0;
+    }
+
+    fn move_by(&mut self, by: isize) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let last = self.matches.len() - 1;
+        self.selected = self.selected.saturating_add_signed(by).min(last);
+        if self.selected < self.top {
+            self.top = self.selected;
+        } else if self.selected >= self.top + self.rows() {
+            self.top = self.selected + 1 - self.rows();
+        }
+    }
+
+    /// Items listed at once, leaving room for the filter line
+    fn rows(&self) -> usize {
+        let height = size().1;
+        MAX_ROWS.min(height.saturating_sub(2)).max(1)
+    }
+
+    /// List the matches below the filter line, from the start of the filter
+    /// line, and put the cursor back at its end.
+    fn draw(&self) -> Result<()> {
+        let width = size().0;
+        let mut out = std::io::stdout();
+        clear(&mut out)?;
+
+        let count = format!("{}/{}", self.matches.len(), self.items.len());
+        let mut lines = vec![format!(
+            "{} {}  {}",
+            ">".cyan().bold(),
+            self.query,
+            format!("{} (type to filter, ↑↓ move, Enter picks, Esc cancels)", count).dimmed()
+        )];
+        for (position, &i) in self.matches.iter().enumerate().skip(self.top).take(self.rows()) {
+            let line = fit(&self.items[i], width.saturating_sub(3));
+            lines.push(if position == self.selected {
+                format!("{} {}", "▶".yellow(), line.bold())
+            } else {
+                format!("  {}", line)
+            });
+        }
+        if self.matches.is_empty() {
+            lines.push(format!("  {}", "No result matches.".dimmed()));
+        }
+        // Raw mode: no carriage return with the newline
+        write!(out, "{}", lines.join("\r\n"))?;
+        // Back to the end of the filter line, where typing goes
+        if lines.len() > 1 {
+            queue!(out, cursor::MoveUp(lines.len() as u16 - 1))?;
+        }
+        let typed = 2 + self.query.chars().count();
+        queue!(out, cursor::MoveToColumn(typed.min(width.saturating_sub(1)) as u16))?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Columns and rows of the terminal, assuming 80x24 where it can't tell.
+fn size() -> (usize, usize) {
+    match terminal::size() {
+        Ok((width, height)) if width > 0 && height > 0 => (width as usize, height as usize),
+        _ => (80, 24),
+    }
+}
+
+/// Erase the list, the cursor being on the filter line.
+fn clear(out: &mut impl Write) -> Result<()> {
+    queue!(out, cursor::MoveToColumn(0), terminal::Clear(terminal::ClearType::FromCursorDown))?;
+    Ok(())
+}
+
+/// How well `text` (lowercase) matches `term`: its characters in order,
+/// with runs and word starts counting more. `None` when they aren't all
+/// there.
+fn score(term: &[char], text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().collect();
+    let mut total = 0;
+    let mut from = 0;
+    let mut previous: Option<usize> = None;
+    for &wanted in term {
+        let at = from + text[from..].iter().position(|&c| c == wanted)?;
+        total += 1;
+        if previous.is_some_and(|before| before + 1 == at) {
+            total += 3;
+        }
+        if at == 0 || !text[at - 1].is_alphanumeric() {
+            total += 2;
+        }
+        previous = Some(at);
+        from = at + 1;
+    }
+    Some(total)
+}
+
+/// `line` cut to `width` characters.
+fn fit(line: &str, width: usize) -> String {
+    if line.chars().count() <= width {
+        return line.to_string();
+    }
+    let mut cut: String = line.chars().take(width.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
@@ -0,0 +1,151 @@
+//! What a bulk or destructive command would do (`--dry-run`).
+//!
+//! `ingest`, `export` and `audit duplicates --delete` upload, download or
+//! delete many things at once. With `--dry-run` they go through the same
+//! steps, reading what they need from the server, but record each upload,
+//! download, write and delete in a `Plan` instead of doing it. The plan is
+//! printed one step per line with its size where known, followed by the
+//! totals.
+
+use colored::Colorize;
+
+/// Kind of step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Upload a local file to the server
+    Upload,
+    /// Download from the server into a file
+    Download,
+    /// Download from the server into memory (e.g. images put in an e-book)
+    Fetch,
+    /// Write a file made here
+    Write,
+    /// Delete on the server
+    Delete,
+    /// Leave out, with the reason in the step's description
+    Skip,
+}
+
+const ACTIONS: [Action; 6] = [
+    Action::Upload,
+    Action::Download,
+    Action::Fetch,
+    Action::Write,
+    Action::Delete,
+    Action::Skip,
+];
+
+impl Action {
+    fn verb(self) -> &'static str {
+        match self {
+            Action::Upload => "upload",
+            Action::Download => "download",
+            Action::Fetch => "fetch",
+            Action::Write => "write",
+            Action::Delete => "delete",
+            Action::Skip => "skip",
+        }
+    }
+
+    /// What the step acts on, for the totals
+    fn noun(self) -> &'static str {
+        match self {
+            Action::Fetch => "image",
+            Action::Delete => "document",
+            _ => "file",
+        }
+    }
+}
+
+struct Step {
+    action: Action,
+    what: String,
+    bytes: Option<u64>,
+}
+
+/// Steps a command would take, in order.
+#[derive(Default)]
+pub struct Plan {
+    steps: Vec<Step>,
+}
+
+impl Plan {
+    /// Record a step on `what` (a path, slug or URL path, with any note),
+    /// of `bytes` when the size is known.
+    pub fn add(&mut self, action: Action, what: impl Into<String>, bytes: Option<u64>) {
+        self.steps.push(Step {
+            action,
+            what: what.into(),
+            bytes,
+        });
+    }
+
+    /// The steps, one per line, then a line of totals per kind of step.
+    pub fn render(&self) -> String {
+        let mut out = format!("{}\n\n", "Dry run: nothing was uploaded, written or deleted.".bold());
+        if self.steps.is_empty() {
+            out.push_str("Nothing to do.\n");
+            return out;
+        }
+        let verb_width = self.steps.iter().map(|s| s.action.verb().len()).max().unwrap_or(0);
+        // Sizes line up after the longest step that has one
+        let what_width = self
+            .steps
+            .iter()
+            .filter(|s| s.bytes.is_some())
+            .map(|s| s.what.chars().count())
+            .max()
+            .unwrap_or(0);
+        for step in &self.steps {
+            let size = step.bytes.map(format_size).unwrap_or_default();
+            let line = format!(
+                "  {:<verb_width$}  {:<what_width$}  {}",
+                step.action.verb(),
+                step.what,
+                size.dimmed()
+            );
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+
+        let mut totals = Vec::new();
+        for action in ACTIONS {
+            let steps: Vec<&Step> = self.steps.iter().filter(|s| s.action == action).collect();
+            if steps.is_empty() {
+                continue;
+            }
+            let noun = action.noun();
+            let mut total = format!(
+                "{} {} {}{}",
+                action.verb(),
+                steps.len(),
+                noun,
+                if steps.len() == 1 { "" } else { "s" }
+            );
+            let bytes: u64 = steps.iter().filter_map(|s| s.bytes).sum();
+            let unknown = steps.iter().filter(|s| s.bytes.is_none()).count();
+            match unknown {
+                0 => total.push_str(&format!(" ({})", format_size(bytes))),
+                // Deletes and skips have no size to speak of
+                n if n == steps.len() => {}
+                n => total.push_str(&format!(" (at least {}, {} of unknown size)", format_size(bytes), n)),
+            }
+            totals.push(total);
+        }
+        out.push_str(&format!("\nWould {}.\n", totals.join(", ")));
+        out
+    }
+}
+
+/// `bytes` for people: "812 B", "12.3 KB", "4.5 MB".
+pub fn format_size(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    let size = bytes as f64;
+    if size < KB {
+        format!("{} B", bytes)
+    } else if size < KB * KB {
+        format!("{:.1} KB", size / KB)
+    } else {
+        format!("{:.1} MB", size / (KB * KB))
+    }
+}
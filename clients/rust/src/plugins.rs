@@ -0,0 +1,241 @@
+//! Lua plugins that add chat commands.
+//!
+//! Every `*.lua` file in the `plugins` directory next to the config file
+//! (`~/.config/osgeo-library/plugins/`) is run when chat starts, in name
+//! order. A plugin registers commands with `osgeo.command`:
+//!
+//! ```lua
+//! osgeo.command("titles", "List the documents of the last sources", function(args, ctx)
+//!   local seen = {}
+//!   for _, s in ipairs(ctx.sources) do
+//!     if not seen[s.document_slug] then
+//!       seen[s.document_slug] = true
+//!       print(s.document_slug .. ": " .. s.document_title)
+//!     end
+//!   end
+//! end)
+//! ```
+//!
+//! The handler gets the rest of the command line and a context table with
+//! `sources` (the last results, as in `search --json`), `document` and
+//! `page`. While a command runs it can call the client:
+//!
+//! - `osgeo.search(query [, {limit = N, document = SLUG}])`: list of results
+//! - `osgeo.ask(question [, {limit = N, document = SLUG}])`: table with
+//!   `answer` and `sources`, as in `ask --json`
+//! - `osgeo.document(slug)`: document details
+//! - `osgeo.image(result)`: the result's image bytes, or nil
+//! - `osgeo.json(value)`: encode a table as JSON
+//!
+//! Plugins get the standard Lua libraries (`io`, `os`, ...), so they can write
+//! files and run programs; only install plugins you trust.
+
+use anyhow::{Context as _, Result};
+use mlua::{Function, Lua, LuaSerdeExt, RegistryKey, SerializeOptions, Table, Value};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// What plugins can ask of the client while a command runs.
+pub trait Api {
+    fn search(&self, query: &str, options: &Options) -> Result<serde_json::Value>;
+    fn ask(&self, question: &str, options: &Options) -> Result<serde_json::Value>;
+    fn document(&self, slug: &str) -> Result<serde_json::Value>;
+    /// Image of a result (as given to the plugin), `None` if it has none
+    fn image(&self, result: serde_json::Value) -> Result<Option<Vec<u8>>>;
+}
+
+/// Optional settings of `osgeo.search` and `osgeo.ask`.
+#[derive(Debug, Default)]
+pub struct Options {
+    pub limit: Option<i32>,
+    pub document: Option<String>,
+}
+
+/// A command registered by a plugin.
+pub struct Command {
+    pub name: String,
+    pub help: String,
+    /// File name of the plugin that registered it
+    pub plugin: String,
+    handler: RegistryKey,
+}
+
+pub struct Plugins {
+    lua: Lua,
+    commands: Rc<RefCell<Vec<Command>>>,
+}
+
+/// Directory plugins are loaded from.
+fn dir() -> Option<PathBuf> {
+    Some(crate::config::path()?.parent()?.join("plugins"))
+}
+
+impl Plugins {
+    /// Run every plugin. A plugin that fails is skipped (its error is
+    /// returned) and the others still load.
+    pub fn load() -> (Self, Vec<anyhow::Error>) {
+        let plugins = Self {
+            lua: Lua::new(),
+            commands: Rc::new(RefCell::new(Vec::new())),
+        };
+        let mut errors = Vec::new();
+        let Some(entries) = dir().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+            return (plugins, errors);
+        };
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|e| e == "lua"))
+            .collect();
+        files.sort();
+
+        if let Err(e) = plugins.install_api() {
+            errors.push(e);
+            return (plugins, errors);
+        }
+        for file in files {
+            if let Err(e) = plugins.run_file(&file) {
+                errors.push(e.context(file.display().to_string()));
+            }
+        }
+        (plugins, errors)
+    }
+
+    pub fn commands(&self) -> std::cell::Ref<'_, Vec<Command>> {
+        self.commands.borrow()
+    }
+
+    /// Whether a plugin registered the command `name`.
+    pub fn has(&self, name: &str) -> bool {
+        self.commands.borrow().iter().any(|c| c.name == name)
+    }
+
+    /// Run the command `name` with the rest of the command line.
+    pub fn run(&self, name: &str, args: &str, api: &dyn Api, context: &impl Serialize) -> Result<()> {
+        // Not borrowed while the handler runs, which may register commands
+        let handler: Function = {
+            let commands = self.commands.borrow();
+            let command = commands
+                .iter()
+                .find(|c| c.name == name)
+                .with_context(|| format!("No plugin command '{}'", name))?;
+            self.lua.registry_value(&command.handler).map_err(lua_error)?
+        };
+        let osgeo: Table = self.lua.globals().get("osgeo").map_err(lua_error)?;
+
+        // Client functions borrow `api`, so they only exist while the command runs
+        self.lua
+            .scope(|scope| {
+                osgeo.set(
+                    "search",
+                    scope.create_function(|lua, (query, options): (String, Option<Table>)| {
+                        let value = api.search(&query, &options_from(options)?).map_err(runtime)?;
+                        to_lua(lua, &value)
+                    })?,
+                )?;
+                osgeo.set(
+                    "ask",
+                    scope.create_function(|lua, (question, options): (String, Option<Table>)| {
+                        let value = api.ask(&question, &options_from(options)?).map_err(runtime)?;
+                        to_lua(lua, &value)
+                    })?,
+                )?;
+                osgeo.set(
+                    "document",
+                    scope.create_function(|lua, slug: String| {
+                        to_lua(lua, &api.document(&slug).map_err(runtime)?)
+                    })?,
+                )?;
+                osgeo.set(
+                    "image",
+                    scope.create_function(|lua, result: Value| {
+                        match api.image(lua.from_value(result)?).map_err(runtime)? {
+                            Some(bytes) => Ok(Value::String(lua.create_string(bytes)?)),
+                            None => Ok(Value::Nil),
+                        }
+                    })?,
+                )?;
+                handler.call::<_, ()>((args, to_lua(&self.lua, context)?))
+            })
+            .map_err(lua_error)
+    }
+
+    /// Set up the `osgeo` table available when plugins load.
+    fn install_api(&self) -> Result<()> {
+        let osgeo = self.lua.create_table().map_err(lua_error)?;
+        let commands = Rc::clone(&self.commands);
+        let register = self
+            .lua
+            .create_function(move |lua, (name, help, handler): (String, String, Function)| {
+                if name.is_empty() || name.contains(char::is_whitespace) || name != name.to_lowercase() {
+                    return Err(mlua::Error::RuntimeError(format!(
+                        "command name '{}' must be one lowercase word",
+                        name
+                    )));
+                }
+                if commands.borrow().iter().any(|c| c.name == name) {
+                    return Err(mlua::Error::RuntimeError(format!("command '{}' is already registered", name)));
+                }
+                let plugin: String = lua.named_registry_value("plugin")?;
+                commands.borrow_mut().push(Command {
+                    name,
+                    help,
+                    plugin,
+                    handler: lua.create_registry_value(handler)?,
+                });
+                Ok(())
+            })
+            .map_err(lua_error)?;
+        osgeo.set("command", register).map_err(lua_error)?;
+
+        let json = self
+            .lua
+            .create_function(|lua, value: Value| {
+                let value: serde_json::Value = lua.from_value(value)?;
+                lua.create_string(value.to_string())
+            })
+            .map_err(lua_error)?;
+        osgeo.set("json", json).map_err(lua_error)?;
+
+        self.lua.globals().set("osgeo", osgeo).map_err(lua_error)
+    }
+
+    fn run_file(&self, path: &Path) -> Result<()> {
+        let code = std::fs::read_to_string(path).context("Failed to read plugin")?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        self.lua.set_named_registry_value("plugin", name.as_str()).map_err(lua_error)?;
+        self.lua
+            .load(code)
+            .set_name(format!("@{}", name))
+            .exec()
+            .map_err(lua_error)
+    }
+}
+
+fn options_from(table: Option<Table>) -> mlua::Result<Options> {
+    let Some(table) = table else {
+        return Ok(Options::default());
+    };
+    Ok(Options {
+        limit: table.get("limit")?,
+        document: table.get("document")?,
+    })
+}
+
+/// Convert to a Lua value, with JSON nulls as nil.
+fn to_lua<'lua>(lua: &'lua Lua, value: &impl Serialize) -> mlua::Result<Value<'lua>> {
+    let options = SerializeOptions::new()
+        .serialize_none_to_null(false)
+        .serialize_unit_to_null(false);
+    lua.to_value_with(value, options)
+}
+
+fn runtime(e: anyhow::Error) -> mlua::Error {
+    mlua::Error::RuntimeError(format!("{:#}", e))
+}
+
+/// Lua errors carry their traceback in the message.
+fn lua_error(e: mlua::Error) -> anyhow::Error {
+    anyhow::anyhow!("{}", e)
+}
@@ -0,0 +1,113 @@
+//! Pages fetched ahead while reading in chat.
+//!
+//! Reading a document page by page is the usual way through it, and every
+//! `next` used to wait for a full download. Once a page is shown, the pages
+//! after and before it are fetched on background threads, so the next flip
+//! in either direction is usually instant. A page asked for while its fetch
+//! is still running waits for that fetch instead of starting another. Only
+//! the last few pages are kept; a failed background fetch is forgotten and
+//! the page is fetched again when asked for.
+
+use crate::client::OsgeoClient;
+use crate::types::PageResponse;
+use anyhow::Result;
+use std::sync::{Arc, Condvar, Mutex};
+
+/// Pages kept, counting those still loading
+const MAX_PAGES: usize = 6;
+
+/// Document slug, page number and requested width
+type Key = (String, i32, Option<u32>);
+/// Pages, oldest first; the condvar is notified whenever a fetch finishes
+type Slots = Arc<(Mutex<Vec<(Key, Slot)>>, Condvar)>;
+
+enum Slot {
+    Loading,
+    Ready(PageResponse),
+}
+
+#[derive(Default)]
+pub struct PageCache {
+    slots: Slots,
+}
+
+impl PageCache {
+    /// Page `number` of `slug`, from the cache when it was fetched ahead (the
+    /// flag is then true), otherwise fetched now.
+    pub fn get(&self, client: &OsgeoClient, slug: &str, number: i32, width: Option<u32>) -> Result<(PageResponse, bool)> {
+        let key = (slug.to_string(), number, width);
+        let (lock, finished) = &*self.slots;
+        let mut slots = lock.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            match slots.iter().find(|(k, _)| *k == key) {
+                Some((_, Slot::Ready(page))) => return Ok((page.clone(), true)),
+                Some((_, Slot::Loading)) => slots = finished.wait(slots).unwrap_or_else(|e| e.into_inner()),
+                None => break,
+            }
+        }
+        drop(slots);
+
+        let page = client.get_page(slug, number, width)?;
+        // Kept too, so going back to it needs no fetch
+        let mut slots = lock.lock().unwrap_or_else(|e| e.into_inner());
+        if !slots.iter().any(|(k, _)| *k == key) {
+            slots.push((key, Slot::Ready(page.clone())));
+            evict(&mut slots);
+        }
+        Ok((page, false))
+    }
+
+    /// Start fetching the pages around `number` (next first), skipping those
+    /// outside 1..=`total` and those already cached or loading.
+    pub fn prefetch_around(&self, client: &OsgeoClient, slug: &str, number: i32, total: i32, width: Option<u32>) {
+        // Without page images there is little to speed up
+        if !crate::preview::shows_images() {
+            return;
+        }
+        for neighbour in [number + 1, number - 1] {
+            if (1..=total).contains(&neighbour) {
+                self.prefetch(client, (slug.to_string(), neighbour, width));
+            }
+        }
+    }
+
+    fn prefetch(&self, client: &OsgeoClient, key: Key) {
+        {
+            let mut slots = self.slots.0.lock().unwrap_or_else(|e| e.into_inner());
+            if slots.iter().any(|(k, _)| *k == key) {
+                return;
+            }
+            slots.push((key.clone(), Slot::Loading));
+            evict(&mut slots);
+        }
+
+        // A clone, so background failures don't mark the server unreachable
+        let client = client.clone();
+        let slots = Arc::clone(&self.slots);
+        std::thread::spawn(move || {
+            let result = client.get_page(&key.0, key.1, key.2);
+            let (lock, finished) = &*slots;
+            let mut slots = lock.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(at) = slots.iter().position(|(k, _)| *k == key) {
+                match result {
+                    Ok(page) => slots[at].1 = Slot::Ready(page),
+                    Err(_) => {
+                        slots.remove(at);
+                    }
+                }
+            }
+            finished.notify_all();
+        });
+    }
+}
+
+/// Drop the oldest finished pages beyond `MAX_PAGES`; those still loading
+/// are left to finish.
+fn evict(slots: &mut Vec<(Key, Slot)>) {
+    while slots.len() > MAX_PAGES {
+        match slots.iter().position(|(_, slot)| matches!(slot, Slot::Ready(_))) {
+            Some(oldest) => slots.remove(oldest),
+            None => break,
+        };
+    }
+}
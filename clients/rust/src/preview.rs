@@ -0,0 +1,285 @@
+//! Image preparation before terminal preview.
+//!
+//! chafa maps each character cell to a block of pixels, so small crops
+//! (one-line equations, table headers) come out as a smudge. With
+//! `--upscale`, PNG images narrower than `SMALL_WIDTH` are enlarged 2x by
+//! pixel repetition first, which keeps strokes sharp instead of blurring
+//! them.
+//!
+//! Transparent pixels are filled with the terminal's background color (see
+//! `term::background`), so chafa doesn't have to guess it. On a dark
+//! terminal, black-on-white or black-on-transparent line art (equation
+//! renders) would show as a white block or vanish, so it is redrawn as light
+//! ink on the background instead.
+//!
+//! Animated GIFs and PNGs are reduced to their first frame, which chafa
+//! shows as a still (`--open` gets the original). Other formats, and images
+//! that can't be decoded, are shown as they are.
+
+use crate::config::ImageBackend;
+use crate::gif;
+use anyhow::{Context, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Images at least this wide are left alone by `--upscale`
+const SMALL_WIDTH: u32 = 800;
+/// Pixels of detail chafa draws per character cell with symbols
+const CELL_PIXELS: u32 = 8;
+/// Color of line art redrawn on a dark background
+const LIGHT_INK: [u8; 3] = [0xe6, 0xe6, 0xe6];
+
+static UPSCALE: AtomicBool = AtomicBool::new(false);
+static BACKEND: OnceLock<ImageBackend> = OnceLock::new();
+
+/// Enable upscaling of small images globally (`--upscale`).
+pub fn set_upscale(enabled: bool) {
+    UPSCALE.store(enabled, Ordering::Relaxed);
+}
+
+/// Choose how images are drawn (`image_backend` in the config).
+pub fn set_backend(backend: ImageBackend) {
+    BACKEND.set(backend).ok();
+}
+
+pub fn backend() -> ImageBackend {
+    BACKEND.get().copied().unwrap_or(ImageBackend::Auto)
+}
+
+/// Whether images are drawn in the terminal: not in plain output, nor with
+/// `image_backend = "none"`.
+pub fn shows_images() -> bool {
+    !crate::term::env().plain && backend() != ImageBackend::None
+}
+
+/// The image to hand to chafa for `bytes`.
+pub fn prepare(bytes: Vec<u8>) -> Vec<u8> {
+    let upscale = UPSCALE.load(Ordering::Relaxed);
+    if !bytes.starts_with(PNG_SIGNATURE) && !gif::is_gif(&bytes) {
+        return bytes;
+    }
+    let background = crate::term::background();
+    if !upscale && background.is_none() && frame_count(&bytes) == 1 {
+        return bytes;
+    }
+    match process(&bytes, upscale, background) {
+        Ok(Some(changed)) => changed,
+        Ok(None) => bytes,
+        Err(e) => {
+            crate::term::verbose_line(&format!("Image shown unchanged: {:#}", e));
+            bytes
+        }
+    }
+}
+
+/// `bytes` prepared for preview, or `None` if nothing needed changing.
+fn process(bytes: &[u8], upscale: bool, background: Option<[u8; 3]>) -> Result<Option<Vec<u8>>> {
+    let mut image = Image::decode(bytes)?;
+    let mut changed = frame_count(bytes) > 1;
+    if let Some(background) = background {
+        changed |= image.match_background(background);
+    }
+    if upscale && image.width < SMALL_WIDTH {
+        image.upscale();
+        changed = true;
+    }
+    if !changed {
+        return Ok(None);
+    }
+    image.encode().map(Some)
+}
+
+/// Decoded 8-bit samples, one to four per pixel.
+struct Image {
+    width: u32,
+    height: u32,
+    color: png::ColorType,
+    samples: Vec<u8>,
+}
+
+impl Image {
+    /// Decode a PNG, or the first frame of a GIF. For an animated PNG this is
+    /// its default image, which is the first frame or a still shown instead.
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        if gif::is_gif(bytes) {
+            let (width, height, samples) = gif::first_frame(bytes)?;
+            return Ok(Self {
+                width,
+                height,
+                color: png::ColorType::Rgba,
+                samples,
+            });
+        }
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().context("corrupt PNG")?;
+        let mut samples = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut samples).context("corrupt PNG")?;
+        samples.truncate(info.buffer_size());
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            color: info.color_type,
+            samples,
+        })
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, self.width, self.height);
+        encoder.set_color(self.color);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().context("Failed to encode PNG")?;
+        writer.write_image_data(&self.samples).context("Failed to encode PNG")?;
+        writer.finish().context("Failed to encode PNG")?;
+        Ok(out)
+    }
+
+    /// Pixels as RGBA.
+    fn rgba(&self) -> impl Iterator<Item = [u8; 4]> + '_ {
+        let color = self.color;
+        self.samples.chunks_exact(color.samples()).map(move |p| match color {
+            png::ColorType::Grayscale => [p[0], p[0], p[0], 255],
+            png::ColorType::GrayscaleAlpha => [p[0], p[0], p[0], p[1]],
+            png::ColorType::Rgba => [p[0], p[1], p[2], p[3]],
+            _ => [p[0], p[1], p[2], 255],
+        })
+    }
+
+    fn has_alpha(&self) -> bool {
+        matches!(self.color, png::ColorType::GrayscaleAlpha | png::ColorType::Rgba)
+    }
+
+    /// Fill transparency with `background`, and redraw line art as light ink
+    /// when the background is dark. Returns whether anything changed.
+    fn match_background(&mut self, background: [u8; 3]) -> bool {
+        let dark = luminance(background) < 128.0;
+        let line_art = dark && self.is_line_art();
+        if !line_art && !self.has_alpha() {
+            return false;
+        }
+
+        let pixels: Vec<[u8; 4]> = self.rgba().collect();
+        let mut samples = Vec::with_capacity(pixels.len() * 3);
+        for pixel in pixels {
+            let (color, coverage) = if line_art {
+                (LIGHT_INK, ink_coverage(pixel))
+            } else {
+                ([pixel[0], pixel[1], pixel[2]], f32::from(pixel[3]) / 255.0)
+            };
+            for (c, bg) in color.iter().zip(background) {
+                samples.push((f32::from(bg) + (f32::from(*c) - f32::from(bg)) * coverage).round() as u8);
+            }
+        }
+        self.color = png::ColorType::Rgb;
+        self.samples = samples;
+        true
+    }
+
+    /// Whether the image is dark ink on white or transparent paper: gray
+    /// throughout, mostly paper, and with few mid-tones (unlike a photo).
+    fn is_line_art(&self) -> bool {
+        let (mut total, mut ink, mut mid) = (0usize, 0f32, 0usize);
+        for pixel in self.rgba() {
+            let [r, g, b, a] = pixel;
+            if a > 0 && r.max(g).max(b) - r.min(g).min(b) > 24 {
+                return false;
+            }
+            let coverage = ink_coverage(pixel);
+            total += 1;
+            ink += coverage;
+            if (0.15..0.85).contains(&coverage) {
+                mid += 1;
+            }
+        }
+        total > 0 && ink / (total as f32) < 0.5 && mid * 10 < total
+    }
+
+    /// Enlarge 2x, repeating each pixel into a 2x2 block.
+    fn upscale(&mut self) {
+        let pixel = self.color.samples();
+        let row_size = self.width as usize * pixel;
+        let mut larger = Vec::with_capacity(self.samples.len() * 4);
+        for row in self.samples.chunks_exact(row_size) {
+            let start = larger.len();
+            for sample in row.chunks_exact(pixel) {
+                larger.extend_from_slice(sample);
+                larger.extend_from_slice(sample);
+            }
+            larger.extend_from_within(start..);
+        }
+        self.width *= 2;
+        self.height *= 2;
+        self.samples = larger;
+    }
+}
+
+/// Width to request page images at for terminal preview: the window width
+/// in pixels when the terminal reports it (Kitty, iTerm2 and sixel graphics
+/// show every pixel), otherwise what symbols resolve across the columns.
+/// Never below `SMALL_WIDTH`, so `--upscale` leaves pages alone. `None`
+/// (full resolution) when there is no terminal to size for.
+pub fn page_width() -> Option<u32> {
+    if !shows_images() {
+        return None;
+    }
+    let size = crossterm::terminal::window_size().ok()?;
+    let width = match size.width {
+        0 => u32::from(size.columns) * CELL_PIXELS,
+        pixels => u32::from(pixels),
+    };
+    (width > 0).then_some(width.max(SMALL_WIDTH))
+}
+
+/// Number of frames in an animated GIF or PNG; 1 for anything else.
+pub fn frame_count(bytes: &[u8]) -> usize {
+    if gif::is_gif(bytes) {
+        return gif::frame_count(bytes).unwrap_or(1).max(1);
+    }
+    if !bytes.starts_with(PNG_SIGNATURE) {
+        return 1;
+    }
+    // An animated PNG announces its frames in an acTL chunk before the image data
+    let mut pos = PNG_SIGNATURE.len();
+    while let Some(header) = bytes.get(pos..pos + 8) {
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        match &header[4..] {
+            b"acTL" => {
+                return bytes
+                    .get(pos + 8..pos + 12)
+                    .map_or(1, |n| u32::from_be_bytes([n[0], n[1], n[2], n[3]]).max(1) as usize);
+            }
+            b"IDAT" => break,
+            _ => pos += 12 + len,
+        }
+    }
+    1
+}
+
+/// File extension for image `bytes`, from their signature ("png" when unknown).
+pub fn extension(bytes: &[u8]) -> &'static str {
+    if gif::is_gif(bytes) {
+        "gif"
+    } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        "jpg"
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        "webp"
+    } else if bytes.starts_with(b"II*\0") || bytes.starts_with(b"MM\0*") {
+        "tiff"
+    } else {
+        "png"
+    }
+}
+
+/// How much ink a pixel of line art holds: dark and opaque is 1, white or
+/// transparent is 0.
+fn ink_coverage(pixel: [u8; 4]) -> f32 {
+    let [r, g, b, a] = pixel;
+    (1.0 - luminance([r, g, b]) / 255.0) * f32::from(a) / 255.0
+}
+
+fn luminance([r, g, b]: [u8; 3]) -> f32 {
+    0.2126 * f32::from(r) + 0.7152 * f32::from(g) + 0.0722 * f32::from(b)
+}
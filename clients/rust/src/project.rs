@@ -0,0 +1,264 @@
+//! Per-project log of what was searched, asked and cited (`--project`,
+//! `project report`).
+//!
+//! Grant-funded work has to document its sources. Run with `--project NAME`
+//! (or `OSGEO_PROJECT`), each search, question, `verify` and `similar`, on
+//! the command line or in chat, is added to that project's log with when it
+//! was made, on which server, and the results listed or sources used,
+//! marking those an answer cited. The log is one JSON line per entry in
+//! `~/.local/share/osgeo-library/projects/NAME.jsonl` (the platform's data
+//! directory elsewhere), kept until deleted, `--no-shared-state` or not:
+//! it is only written when asked for. `project report NAME` compiles it into
+//! a Markdown provenance report: the documents and pages cited, then every
+//! entry in order.
+
+use crate::citations::cited_numbers;
+use crate::types::SearchResult;
+use crate::OsgeoClient;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Project entries are recorded under, when one is given
+static PROJECT: OnceLock<String> = OnceLock::new();
+
+/// Record entries under `name` from now on.
+pub fn enable(name: &str) {
+    PROJECT.set(name.to_string()).ok();
+}
+
+/// `name` if it can name a project (and its log file).
+pub fn parse_name(name: &str) -> Result<String, String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(name.to_string())
+    } else {
+        Err("use letters, digits, '-', '_' and '.' (e.g. \"coastal-grant-2026\")".to_string())
+    }
+}
+
+/// Something searched, asked or checked, and what came back.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Entry {
+    /// "YYYY-MM-DDThh:mm:ssZ"
+    pub time: String,
+    pub server: String,
+    /// "search", "question", "verify" or "similar"
+    pub kind: String,
+    /// The query, question, statement or source
+    pub text: String,
+    /// The answer, or a verdict and its explanation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub answer: Option<String>,
+    pub sources: Vec<Source>,
+}
+
+/// A result listed or a source used.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Source {
+    /// "element:12", "chunk:34"
+    pub id: String,
+    pub document: String,
+    pub title: String,
+    pub page: i32,
+    /// "Figure 3", for elements
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Cited by the answer
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cited: bool,
+}
+
+impl Source {
+    fn new(result: &SearchResult, cited: bool) -> Self {
+        Self {
+            id: result.key(),
+            document: result.document_slug.clone(),
+            title: result.document_title.clone(),
+            page: result.page_number,
+            label: result.element_label.clone(),
+            cited,
+        }
+    }
+}
+
+/// Add a search (or `similar`, by `kind`) and its results to the project's
+/// log, when there is a project.
+pub fn record_results(client: &OsgeoClient, kind: &str, text: &str, results: &[SearchResult]) {
+    record(client, kind, text, None, results.iter().map(|r| Source::new(r, false)).collect());
+}
+
+/// Add a question (or `verify`, by `kind`) and its answer to the project's
+/// log, when there is a project; the sources `answer` cites are marked.
+pub fn record_answer(client: &OsgeoClient, kind: &str, text: &str, answer: &str, sources: &[SearchResult]) {
+    let cited = cited_numbers(answer);
+    let sources = sources
+        .iter()
+        .enumerate()
+        .map(|(i, s)| Source::new(s, cited.contains(&(i + 1))))
+        .collect();
+    record(client, kind, text, Some(answer.to_string()), sources);
+}
+
+fn record(client: &OsgeoClient, kind: &str, text: &str, answer: Option<String>, sources: Vec<Source>) {
+    let Some(name) = PROJECT.get() else {
+        return;
+    };
+    let entry = Entry {
+        time: crate::epub::utc_timestamp(),
+        server: client.base_url().to_string(),
+        kind: kind.to_string(),
+        text: text.to_string(),
+        answer,
+        sources,
+    };
+    let written = path(name).context("No data directory to keep projects in").and_then(|path| {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let line = serde_json::to_string(&entry).context("Failed to encode the entry")?;
+        crate::shared::append(&path, &format!("{}\n", line))
+    });
+    if let Err(e) = written {
+        crate::term::warning(&format!("not recorded in project {}: {:#}", name, e));
+    }
+}
+
+fn dir() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("osgeo-library").join("projects"))
+}
+
+fn path(name: &str) -> Option<PathBuf> {
+    Some(dir()?.join(format!("{}.jsonl", name)))
+}
+
+/// The entries of project `name`, oldest first.
+pub fn entries(name: &str) -> Result<Vec<Entry>> {
+    let path = path(name).context("No data directory to keep projects in")?;
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            anyhow::bail!("No project '{}' yet: run commands with --project {} to start one", name, name)
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).with_context(|| format!("{} line {} is damaged", path.display(), i + 1))
+        })
+        .collect()
+}
+
+/// Projects with an entry count and the time of the last one, by name.
+pub fn list() -> Vec<(String, usize, String)> {
+    let Some(files) = dir().and_then(|d| std::fs::read_dir(d).ok()) else {
+        return Vec::new();
+    };
+    let mut projects: Vec<(String, usize, String)> = files
+        .flatten()
+        .filter_map(|file| {
+            let name = file.file_name().to_string_lossy().strip_suffix(".jsonl")?.to_string();
+            let entries = entries(&name).ok()?;
+            let last = entries.last().map(|e| e.time.clone()).unwrap_or_default();
+            Some((name, entries.len(), last))
+        })
+        .collect();
+    projects.sort();
+    projects
+}
+
+/// Markdown provenance report of project `name`, with the answers in full
+/// when `answers` is set.
+pub fn report(name: &str, entries: &[Entry], answers: bool) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("# Sources of project {}\n\n", name));
+    let (Some(first), Some(last)) = (entries.first(), entries.last()) else {
+        out.push_str("Nothing recorded.\n");
+        return out;
+    };
+    let count = |kind: &str| entries.iter().filter(|e| e.kind == kind).count();
+    out.push_str(&format!("- Period: {} to {}\n", first.time, last.time));
+    out.push_str(&format!(
+        "- Searches: {}, questions: {}, statements checked: {}, similarity lookups: {}\n",
+        count("search"),
+        count("question"),
+        count("verify"),
+        count("similar")
+    ));
+    let mut servers: Vec<&str> = entries.iter().map(|e| e.server.as_str()).collect();
+    servers.sort();
+    servers.dedup();
+    out.push_str(&format!("- Library: {}\n", servers.join(", ")));
+    out.push_str(&format!("- Compiled {} by osgeo-library {}\n\n", crate::epub::utc_timestamp(), env!("CARGO_PKG_VERSION")));
+
+    out.push_str("## Cited sources\n\n");
+    // Title and cited pages by document
+    let mut cited: BTreeMap<&str, (&str, BTreeMap<i32, Vec<&str>>)> = BTreeMap::new();
+    for source in entries.iter().flat_map(|e| &e.sources).filter(|s| s.cited) {
+        let (_, pages) = cited.entry(&source.document).or_insert((&source.title, BTreeMap::new()));
+        let labels = pages.entry(source.page).or_default();
+        if let Some(label) = source.label.as_deref().filter(|l| !labels.contains(l)) {
+            labels.push(label);
+        }
+    }
+    if cited.is_empty() {
+        out.push_str("No answer cited a source.\n\n");
+    }
+    for (slug, (title, pages)) in &cited {
+        let pages: Vec<String> = pages
+            .iter()
+            .map(|(page, labels)| {
+                if labels.is_empty() {
+                    format!("p.{}", page)
+                } else {
+                    format!("p.{} ({})", page, labels.join(", "))
+                }
+            })
+            .collect();
+        out.push_str(&format!("- **{}** (`{}`): {}\n", title, slug, pages.join(", ")));
+    }
+    if !cited.is_empty() {
+        out.push('\n');
+    }
+
+    out.push_str("## Log\n");
+    for entry in entries {
+        out.push_str(&format!("\n### {} {}: {}\n\n", entry.time, entry.kind, entry.text.replace('\n', " ")));
+        if let Some(answer) = entry.answer.as_deref() {
+            if answers || entry.kind == "verify" {
+                for line in answer.trim().lines() {
+                    out.push_str(format!("> {}", line).trim_end());
+                    out.push('\n');
+                }
+                out.push('\n');
+            }
+        }
+        let listed: Vec<&Source> = match entry.answer {
+            Some(_) => entry.sources.iter().filter(|s| s.cited).collect(),
+            None => entry.sources.iter().collect(),
+        };
+        for source in &listed {
+            let label = source.label.as_deref().map(|l| format!(", {}", l)).unwrap_or_default();
+            out.push_str(&format!(
+                "- {} p.{}{} (`{}`)\n",
+                source.document, source.page, label, source.id
+            ));
+        }
+        match (entry.answer.is_some(), listed.is_empty()) {
+            (true, true) => out.push_str(&format!("- No source cited ({} used)\n", entry.sources.len())),
+            (true, false) if listed.len() < entry.sources.len() => out.push_str(&format!(
+                "- ({} more used as context, not cited)\n",
+                entry.sources.len() - listed.len()
+            )),
+            (false, true) => out.push_str("- No results\n"),
+            _ => {}
+        }
+    }
+    out
+}
@@ -0,0 +1,83 @@
+//! Chat prompt rendering.
+//!
+//! The `[prompt]` config section sets a format string that is expanded
+//! before every input line, so the prompt can show REPL state:
+//!
+//! | Placeholder | Expands to                                   |
+//! |-------------|----------------------------------------------|
+//! | `{doc}`     | scoped document slug (empty when none)       |
+//! | `{page}`    | page being viewed (empty when none)          |
+//! | `{status}`  | `online` or `offline` (last request result)  |
+//! | `{time}`    | local time as HH:MM                          |
+
+use crate::config::PromptConfig;
+use anyhow::Result;
+use colored::{Color, Colorize};
+
+/// REPL state the prompt can display.
+pub struct PromptState<'a> {
+    pub doc: Option<&'a str>,
+    pub page: Option<i32>,
+    pub online: bool,
+}
+
+pub struct Prompt {
+    format: String,
+    color: Option<Color>,
+}
+
+impl Prompt {
+    pub fn new(config: &PromptConfig, plain: bool) -> Result<Self> {
+        let color = match config.color.to_lowercase().as_str() {
+            "none" | "" => None,
+            name => Some(name.parse::<Color>().map_err(|_| {
+                anyhow::anyhow!("Unknown prompt color '{}' in config", config.color)
+            })?),
+        };
+        let default = if plain { "> " } else { "You: " };
+        Ok(Self {
+            format: config.format.clone().unwrap_or_else(|| default.to_string()),
+            color,
+        })
+    }
+
+    pub fn render(&self, state: &PromptState) -> String {
+        let text = self
+            .format
+            .replace("{doc}", state.doc.unwrap_or(""))
+            .replace("{page}", &state.page.map(|p| p.to_string()).unwrap_or_default())
+            .replace("{status}", if state.online { "online" } else { "offline" })
+            .replace("{time}", &local_time());
+
+        // Color the visible part only; trailing spaces separate it from input
+        let visible = text.trim_end();
+        let padding = &text[visible.len()..];
+        match self.color {
+            Some(color) => format!("{}{}", visible.color(color).bold(), padding),
+            None => text,
+        }
+    }
+}
+
+/// Current local time as HH:MM.
+#[cfg(unix)]
+fn local_time() -> String {
+    // SAFETY: localtime_r writes into the zeroed tm we own
+    let tm = unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&now, &mut tm);
+        tm
+    };
+    format!("{:02}:{:02}", tm.tm_hour, tm.tm_min)
+}
+
+/// Current time as HH:MM (UTC; no timezone lookup without libc).
+#[cfg(not(unix))]
+fn local_time() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format!("{:02}:{:02}", (secs / 3600) % 24, (secs / 60) % 60)
+}
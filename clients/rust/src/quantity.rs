@@ -0,0 +1,31 @@
+//! Detection of numeric values with units ("3.7 mm/yr", "1.5 °C", "2 km²").
+//!
+//! Used by `search --has-number` to keep only passages that state a
+//! quantity, for analysts hunting figures in long reports. Bare numbers
+//! (years, page and equation numbers) don't count; a unit or percent sign
+//! must follow.
+
+use regex_lite::Regex;
+use std::sync::OnceLock;
+
+/// Number (optionally a range or ± interval) followed by a unit.
+const PATTERN: &str = concat!(
+    r"\b\d+(?:[.,]\d+)*",
+    r"(?:\s?(?:-|–|to|±)\s?\d+(?:[.,]\d+)*)?",
+    r"\s?",
+    r"(?:°\s?[CF]|%|‰|",
+    r"(?:GtCO2|GtC|Gt|Mt|kt|ppm|ppb|hPa|mbar|km/h|m/s|W\s?m-2|W/m|mm|cm|km|µm|nm|mi|ft|ha|kg|K|m|g)",
+    r"(?:[²³]|[23]\b|\b)",
+    r"(?:\s?(?:/|per\s)\s?(?:yr|year|decade|day|s)\b|\s?yr-1\b|\s?yr⁻¹)?",
+    r")",
+);
+
+fn regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(PATTERN).expect("quantity pattern is valid"))
+}
+
+/// Quantities stated in `text`, in order.
+pub fn quantities(text: &str) -> Vec<&str> {
+    regex().find_iter(text).map(|m| m.as_str()).collect()
+}
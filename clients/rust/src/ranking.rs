@@ -0,0 +1,110 @@
+//! Client-side ranking preferences.
+//!
+//! Boosts are points added to a result's relevance score before search
+//! results are listed, so preferred documents or element types move up.
+//! They come from the `[ranking]` config section and `--boost-doc` /
+//! `--boost-type` flags; `--verbose` reports every adjustment.
+//!
+//! ```toml
+//! [ranking.documents]
+//! usgs_snyder = 10        # prefer the reference manual
+//!
+//! [ranking.types]
+//! equation = 5            # figure, table, equation, ...; "text" for chunks
+//!
+//! [[ranking.rules]]       # boost only when the query mentions a keyword
+//! keywords = ["formula", "equation"]
+//! element_type = "equation"
+//! boost = 15
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Ranking {
+    /// Points added by document slug
+    pub documents: HashMap<String, f64>,
+    /// Points added by element type ("text" for chunks)
+    pub types: HashMap<String, f64>,
+    /// Conditional boosts triggered by words in the query
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// Query words that trigger the rule (prefix match, case-insensitive)
+    pub keywords: Vec<String>,
+    /// Only boost this element type
+    pub element_type: Option<String>,
+    /// Only boost this document
+    pub document: Option<String>,
+    pub boost: f64,
+}
+
+/// One score adjustment and why it was applied.
+pub struct Boost {
+    pub reason: String,
+    pub points: f64,
+}
+
+impl Ranking {
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty() && self.types.is_empty() && self.rules.is_empty()
+    }
+
+    /// Boosts for a result from `document` of `kind` (element type or "text").
+    pub fn boosts(&self, query: &str, document: &str, kind: &str) -> Vec<Boost> {
+        let mut boosts = Vec::new();
+        if let Some(points) = self.documents.get(document) {
+            boosts.push(Boost { reason: format!("doc {}", document), points: *points });
+        }
+        if let Some(points) = self.types.get(kind) {
+            boosts.push(Boost { reason: format!("type {}", kind), points: *points });
+        }
+
+        let words: Vec<String> = query
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(String::from)
+            .collect();
+        for rule in &self.rules {
+            let Some(keyword) = rule.keywords.iter().find(|k| {
+                let k = k.to_lowercase();
+                words.iter().any(|w| w.starts_with(&k))
+            }) else {
+                continue;
+            };
+            if rule.element_type.as_deref().is_some_and(|t| t != kind)
+                || rule.document.as_deref().is_some_and(|d| d != document)
+            {
+                continue;
+            }
+            boosts.push(Boost { reason: format!("rule '{}'", keyword), points: rule.boost });
+        }
+        boosts
+    }
+}
+
+/// Parse a `NAME=POINTS` flag value such as `usgs_snyder=10`.
+pub fn parse_boost(value: &str) -> Result<(String, f64), String> {
+    let (name, points) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=POINTS, got '{}'", value))?;
+    let points = points
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| format!("invalid boost '{}' (expected a number)", points))?;
+    Ok((name.trim().to_string(), points))
+}
+
+/// `search --min-score`: a percentage from 0 to 100.
+pub fn parse_min_score(value: &str) -> Result<f64, String> {
+    match value.trim_end_matches('%').parse::<f64>() {
+        Ok(score) if (0.0..=100.0).contains(&score) => Ok(score),
+        _ => Err(format!("'{}' is not a score from 0 to 100", value)),
+    }
+}
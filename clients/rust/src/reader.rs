@@ -0,0 +1,94 @@
+//! Keys of reading mode (`read <slug>`, and `read` in chat).
+//!
+//! Paging through a document with `next` and `prev` means typing a command
+//! and Enter for every page. Reading mode shows one page at a time and
+//! turns the page on a single key: n, Space, → or PageDown for the next
+//! page, p, ← or PageUp for the previous one, Home and End for the first
+//! and last, g then a number and Enter to go to a page, and q or Esc to
+//! leave. Keys are read in raw mode only while waiting for one, so pages
+//! are drawn as they are everywhere else.
+
+use crate::tty;
+use anyhow::{Context, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use std::io::Write;
+
+/// Where to go from the page shown.
+pub enum Move {
+    Next,
+    Prev,
+    First,
+    Last,
+    Goto(i32),
+    Quit,
+}
+
+/// Wait for a key that moves, ignoring the others.
+pub fn next_move() -> Result<Move> {
+    let mut terminal = tty::Guard::default();
+    terminal.raw_mode().context("Failed to read keys from the terminal")?;
+    loop {
+        let key = read_key()?;
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let chosen = match key.code {
+            KeyCode::Char('c' | 'd') if ctrl => Some(Move::Quit),
+            KeyCode::Char('q') | KeyCode::Esc => Some(Move::Quit),
+            KeyCode::Char('n' | ' ' | 'j') | KeyCode::Right | KeyCode::Down | KeyCode::PageDown | KeyCode::Enter => {
+                Some(Move::Next)
+            }
+            KeyCode::Char('p' | 'k') | KeyCode::Left | KeyCode::Up | KeyCode::PageUp | KeyCode::Backspace => {
+                Some(Move::Prev)
+            }
+            KeyCode::Home => Some(Move::First),
+            KeyCode::End => Some(Move::Last),
+            KeyCode::Char('g') => read_number()?.map(Move::Goto),
+            _ => None,
+        };
+        if let Some(chosen) = chosen {
+            return Ok(chosen);
+        }
+    }
+}
+
+/// A page number typed after "g", ended with Enter; `None` when given up
+/// with Esc or left empty.
+fn read_number() -> Result<Option<i32>> {
+    let mut out = std::io::stdout();
+    let mut typed = String::new();
+    write!(out, "\r\x1b[2KGo to page: ")?;
+    out.flush()?;
+    loop {
+        let key = read_key()?;
+        match key.code {
+            KeyCode::Char(c) if c.is_ascii_digit() && typed.len() < 6 => {
+                typed.push(c);
+                write!(out, "{}", c)?;
+            }
+            KeyCode::Backspace if typed.pop().is_some() => write!(out, "\x08 \x08")?,
+            KeyCode::Enter => break,
+            KeyCode::Esc => {
+                typed.clear();
+                break;
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                typed.clear();
+                break;
+            }
+            _ => {}
+        }
+        out.flush()?;
+    }
+    write!(out, "\r\x1b[2K")?;
+    out.flush()?;
+    Ok(typed.parse().ok())
+}
+
+fn read_key() -> Result<KeyEvent> {
+    loop {
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Release {
+                return Ok(key);
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Recently viewed documents, pages and elements (`recent`, `recent N`).
+//!
+//! For finding again the figure looked at yesterday without remembering the
+//! search that found it. Each document opened with `doc`, page viewed and
+//! element shown is recorded in the user's cache directory, most recent
+//! first, up to `KEEP` of them; viewing one again moves it to the top. The
+//! list is kept per server, since slugs and element ids belong to one
+//! library. Nothing is recorded with `--no-shared-state`.
+
+use crate::types::SearchResult;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Views remembered per server
+const KEEP: usize = 50;
+
+/// Something viewed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Item {
+    Document { slug: String, title: String },
+    Page { slug: String, page: i32, title: String },
+    /// The search result it was shown from, enough to show it again
+    Element { result: Box<SearchResult> },
+}
+
+impl Item {
+    pub fn element(result: &SearchResult) -> Self {
+        Item::Element {
+            result: Box::new(result.clone()),
+        }
+    }
+
+    /// Whether `self` and `other` are views of the same thing
+    fn same(&self, other: &Item) -> bool {
+        match (self, other) {
+            (Item::Document { slug: a, .. }, Item::Document { slug: b, .. }) => a == b,
+            (Item::Page { slug: a, page: p, .. }, Item::Page { slug: b, page: q, .. }) => a == b && p == q,
+            (Item::Element { result: a }, Item::Element { result: b }) => {
+                a.document_slug == b.document_slug && a.id == b.id
+            }
+            _ => false,
+        }
+    }
+
+    /// One line for the list: "Table 3.2 (usgs_snyder p.80)".
+    pub fn describe(&self) -> String {
+        match self {
+            Item::Document { slug, title } => format!("{} ({})", title, slug),
+            Item::Page { slug, page, title } => format!("{} p.{} ({})", title, page, slug),
+            Item::Element { result } => {
+                let label = result
+                    .element_label
+                    .clone()
+                    .or_else(|| result.element_type.clone())
+                    .unwrap_or_else(|| "element".to_string());
+                format!("{} ({} p.{})", label, result.document_slug, result.page_number)
+            }
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Item::Document { .. } => "doc",
+            Item::Page { .. } => "page",
+            Item::Element { .. } => "element",
+        }
+    }
+}
+
+/// A view and when it happened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// Unix time of the view
+    pub viewed: u64,
+    #[serde(flatten)]
+    pub item: Item,
+}
+
+impl Entry {
+    /// How long ago: "5m", "3h", "2d".
+    pub fn age(&self) -> String {
+        let secs = now().saturating_sub(self.viewed);
+        match secs {
+            s if s < 60 => "now".to_string(),
+            s if s < 3600 => format!("{}m", s / 60),
+            s if s < 86_400 => format!("{}h", s / 3600),
+            s => format!("{}d", s / 86_400),
+        }
+    }
+}
+
+/// Views by server URL, most recent first
+type History = BTreeMap<String, Vec<Entry>>;
+
+/// Note a view of `item` on `server`.
+pub fn record(server: &str, item: Item) {
+    if !crate::shared::enabled() {
+        return;
+    }
+    let Some(path) = path() else {
+        return;
+    };
+    // A view that can't be recorded is only missing from the list
+    crate::shared::update(&path, |text| {
+        let mut history: History = serde_json::from_str(&text).unwrap_or_default();
+        let entries = history.entry(server.to_string()).or_default();
+        entries.retain(|e| !e.item.same(&item));
+        entries.insert(0, Entry { viewed: now(), item });
+        entries.truncate(KEEP);
+        Ok(serde_json::to_string_pretty(&history)?)
+    })
+    .ok();
+}
+
+/// Views on `server`, most recent first; empty when none are recorded.
+pub fn list(server: &str) -> Vec<Entry> {
+    path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|text| serde_json::from_str::<History>(&text).ok())
+        .and_then(|mut history| history.remove(server))
+        .unwrap_or_default()
+}
+
+fn path() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("osgeo-library").join("recent.json"))
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
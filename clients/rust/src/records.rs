@@ -0,0 +1,82 @@
+//! Search results written to files (`search --export`).
+//!
+//! A result becomes a flat `ResultRecord`, serialized with serde like the
+//! `--json` output: to CSV for spreadsheets, or to a JSON array when the
+//! file name ends in `.json`. Snippets are shared with `ask
+//! --citations-out`.
+
+use crate::redact;
+use crate::types::SearchResult;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+
+/// Characters of content kept in a snippet
+const SNIPPET_CHARS: usize = 300;
+
+/// One search result, as a spreadsheet row.
+#[derive(Serialize)]
+pub struct ResultRecord<'a> {
+    /// Position in the results, from 1
+    pub rank: usize,
+    pub id: i64,
+    pub score: f64,
+    /// "chunk" or "element"
+    pub source_type: &'a str,
+    /// Library of a federated search
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub library: Option<&'a str>,
+    pub document_slug: &'a str,
+    pub document_title: &'a str,
+    pub page: i32,
+    pub element_type: Option<&'a str>,
+    pub label: Option<&'a str>,
+    pub snippet: String,
+}
+
+impl<'a> ResultRecord<'a> {
+    pub fn new(rank: usize, result: &'a SearchResult) -> Self {
+        Self {
+            rank,
+            id: result.id,
+            score: result.score_pct,
+            source_type: &result.source_type,
+            library: result.library.as_deref(),
+            document_slug: &result.document_slug,
+            document_title: &result.document_title,
+            page: result.page_number,
+            element_type: result.element_type.as_deref(),
+            label: result.element_label.as_deref(),
+            snippet: snippet(&result.content),
+        }
+    }
+}
+
+/// `content` on one line, cut to `SNIPPET_CHARS` characters.
+pub fn snippet(content: &str) -> String {
+    let content = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut snippet: String = content.chars().take(SNIPPET_CHARS).collect();
+    if content.chars().count() > SNIPPET_CHARS {
+        snippet.push_str("...");
+    }
+    snippet
+}
+
+/// Write `records` to `path`: a JSON array for `.json`, CSV with a header
+/// row otherwise.
+pub fn write<T: Serialize>(path: &Path, records: &[T]) -> Result<()> {
+    let failed = || format!("Failed to write {}", path.display());
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if is_json {
+        let json = serde_json::to_string_pretty(records).context("Failed to encode JSON")?;
+        return std::fs::write(path, redact::text(&json) + "\n").with_context(failed);
+    }
+    let mut csv = csv::Writer::from_writer(Vec::new());
+    for record in records {
+        csv.serialize(record).with_context(failed)?;
+    }
+    let bytes = csv.into_inner().map_err(|e| e.into_error()).with_context(failed)?;
+    std::fs::write(path, redact::text(&String::from_utf8_lossy(&bytes))).with_context(failed)
+}
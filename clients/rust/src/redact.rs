@@ -0,0 +1,79 @@
+//! Output fit for sharing (`--redact`).
+//!
+//! Exported files and `--json` output carry details of the setup they came
+//! from: the server's URL (in a chat transcript's header and image links,
+//! or as the library of a federated result), paths on the server or on
+//! this machine, and the user names in those paths. With `--redact` these
+//! are taken out as the output is written, so a result set can go into a
+//! public issue or gist: the URLs and hosts of the servers in use (main
+//! server, mirrors, federated libraries) become `<server>`, credentials in
+//! any other URL `<redacted>`, the home directory `~`, user names in other
+//! home paths `<user>`, and a JSON value that is an absolute path on its
+//! own just its file name. Slugs, titles, pages and content are kept.
+
+use regex_lite::Regex;
+use std::sync::OnceLock;
+
+/// Server URLs in use, set when redacting
+static SERVERS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Redact output from now on, `servers` being the URLs of the servers in use.
+pub fn enable(servers: &[String]) {
+    let mut servers: Vec<String> = servers.iter().map(|s| s.trim_end_matches('/').to_string()).collect();
+    servers.retain(|s| !s.is_empty());
+    SERVERS.set(servers).ok();
+}
+
+/// `text` (JSON, CSV or Markdown) as it should be written: redacted with
+/// `--redact`, as it is otherwise.
+pub fn text(text: &str) -> String {
+    match SERVERS.get() {
+        Some(servers) => redact(text, servers),
+        None => text.to_string(),
+    }
+}
+
+fn redact(text: &str, servers: &[String]) -> String {
+    static CREDENTIALS: OnceLock<Regex> = OnceLock::new();
+    static HOME_PATH: OnceLock<Regex> = OnceLock::new();
+    static JSON_PATH: OnceLock<Regex> = OnceLock::new();
+
+    // Whole URLs first, then their hosts (a federated library's name)
+    let mut names: Vec<String> = servers.to_vec();
+    names.extend(servers.iter().flat_map(|url| hosts(url)));
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+    let mut out = text.to_string();
+    for name in &names {
+        out = out.replace(name.as_str(), "<server>");
+    }
+
+    let credentials = CREDENTIALS.get_or_init(|| Regex::new(r"([A-Za-z][A-Za-z0-9+.-]*://)[^/\s@]+@").unwrap());
+    out = credentials.replace_all(&out, "${1}<redacted>@").into_owned();
+
+    if let Some(home) = dirs::home_dir().map(|h| h.to_string_lossy().into_owned()) {
+        if home.len() > 1 {
+            // Backslashes of a Windows home are doubled in JSON
+            out = out.replace(&home.replace('\\', "\\\\"), "~").replace(&home, "~");
+        }
+    }
+    let home_path = HOME_PATH.get_or_init(|| Regex::new(r#"(/home/|/Users/|\\Users\\+)[^/\\\s"'()<>]+"#).unwrap());
+    out = home_path.replace_all(&out, "${1}<user>").into_owned();
+
+    let json_path = JSON_PATH.get_or_init(|| Regex::new(r#""(?:/|[A-Za-z]:\\\\)(?:[^"\s/\\]+(?:/|\\\\))+([^"\s/\\]+)""#).unwrap());
+    json_path.replace_all(&out, "\"${1}\"").into_owned()
+}
+
+/// "user:password@host:port" and "host:port" of `url`, as libraries are
+/// named after it.
+fn hosts(url: &str) -> Vec<String> {
+    let Some((_, rest)) = url.split_once("://") else {
+        return Vec::new();
+    };
+    let with_credentials = rest.split('/').next().unwrap_or(rest);
+    let host = with_credentials.rsplit_once('@').map_or(with_credentials, |(_, host)| host);
+    [with_credentials, host]
+        .into_iter()
+        .filter(|h| !h.is_empty())
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,633 @@
+//! PDF report of a chat session (`save --pdf FILE` in chat).
+//!
+//! Questions, answers, their sources and the images viewed with `show` are
+//! laid out on A4 pages in reading order, so a session can be archived or
+//! attached to project documentation. Text uses the standard Helvetica and
+//! Courier fonts (WinAnsi encoding), which every PDF reader provides, so no
+//! font data is embedded; characters outside that set print as '?'.
+//!
+//! `export pages` writes page images as a PDF with the same image handling
+//! ([`PageBook`]): one page per image and no text.
+
+use anyhow::{Context, Result};
+use miniz_oxide::deflate::compress_to_vec_zlib;
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use regex_lite::Regex;
+use std::path::Path;
+use std::sync::OnceLock;
+
+pub struct Report<'a> {
+    pub title: String,
+    /// Lines printed under the title (date, server, ...)
+    pub meta: Vec<String>,
+    pub entries: &'a [Entry],
+}
+
+pub enum Entry {
+    Exchange {
+        question: String,
+        answer: String,
+        /// One line per source, already numbered
+        sources: Vec<String>,
+    },
+    /// An element image viewed during the session, fetched when saving
+    Image { caption: String, url: String },
+}
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+const TEXT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+/// Tallest an image may be drawn, so one image doesn't take a whole page
+const MAX_IMAGE_HEIGHT: f32 = 320.0;
+
+const CATALOG_ID: Ref = Ref::new(1);
+const PAGE_TREE_ID: Ref = Ref::new(2);
+const INFO_ID: Ref = Ref::new(3);
+
+#[derive(Clone, Copy)]
+enum Font {
+    Regular,
+    Bold,
+    Mono,
+}
+
+impl Font {
+    const ALL: [Font; 3] = [Font::Regular, Font::Bold, Font::Mono];
+
+    fn id(self) -> Ref {
+        match self {
+            Font::Regular => Ref::new(4),
+            Font::Bold => Ref::new(5),
+            Font::Mono => Ref::new(6),
+        }
+    }
+
+    fn name(self) -> Name<'static> {
+        match self {
+            Font::Regular => Name(b"F1"),
+            Font::Bold => Name(b"F2"),
+            Font::Mono => Name(b"F3"),
+        }
+    }
+
+    fn base_font(self) -> Name<'static> {
+        match self {
+            Font::Regular => Name(b"Helvetica"),
+            Font::Bold => Name(b"Helvetica-Bold"),
+            Font::Mono => Name(b"Courier"),
+        }
+    }
+
+    /// Width of `text` in points at `size`.
+    fn width(self, text: &str, size: f32) -> f32 {
+        let units: u32 = encode(text).iter().map(|&b| self.glyph_width(b)).sum();
+        units as f32 * size / 1000.0
+    }
+
+    /// Glyph width in 1/1000 em (Adobe font metrics). Accented letters and
+    /// other non-ASCII glyphs use an average width.
+    fn glyph_width(self, byte: u8) -> u32 {
+        let table = match self {
+            Font::Mono => return 600,
+            Font::Regular => &HELVETICA,
+            Font::Bold => &HELVETICA_BOLD,
+        };
+        match byte {
+            32..=126 => u32::from(table[usize::from(byte - 32)]),
+            _ => 556,
+        }
+    }
+}
+
+#[rustfmt::skip]
+const HELVETICA: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+#[rustfmt::skip]
+const HELVETICA_BOLD: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611,
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556,
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611,
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+/// Write the report to `path`. `fetch_image` loads an image by URL; images
+/// that can't be loaded or decoded are noted in the report instead.
+pub fn write(path: &Path, report: &Report<'_>, fetch_image: impl Fn(&str) -> Result<Vec<u8>>) -> Result<()> {
+    let mut writer = Writer::new(&report.title);
+
+    writer.paragraph(&report.title, Font::Bold, 18.0, 0.0, 0.0);
+    for line in &report.meta {
+        writer.paragraph(line, Font::Regular, 9.0, 0.0, 0.4);
+    }
+    writer.gap(12.0);
+
+    let mut number = 0;
+    for entry in report.entries {
+        match entry {
+            Entry::Exchange { question, answer, sources } => {
+                number += 1;
+                writer.gap(10.0);
+                writer.rule();
+                writer.paragraph(&format!("Q{}. {}", number, question), Font::Bold, 12.0, 0.0, 0.0);
+                writer.gap(4.0);
+                writer.markdown(answer);
+                if !sources.is_empty() {
+                    writer.gap(6.0);
+                    writer.paragraph(&format!("Sources ({})", sources.len()), Font::Bold, 9.0, 0.0, 0.3);
+                    for source in sources {
+                        writer.paragraph(source, Font::Regular, 8.5, 8.0, 0.3);
+                    }
+                }
+            }
+            Entry::Image { caption, url } => {
+                writer.gap(8.0);
+                match fetch_image(url).and_then(|bytes| Raster::decode(&bytes)) {
+                    Ok(raster) => writer.image(&raster),
+                    Err(e) => writer.paragraph(&format!("[Image not included: {}]", e), Font::Regular, 9.0, 0.0, 0.4),
+                }
+                writer.paragraph(caption, Font::Regular, 8.5, 0.0, 0.3);
+            }
+        }
+    }
+
+    let bytes = writer.finish();
+    std::fs::write(path, bytes).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Lays text and images out top to bottom, starting new pages as needed.
+struct Writer {
+    pdf: Pdf,
+    title: String,
+    next_id: i32,
+    pages: Vec<Ref>,
+    content: Content,
+    /// Images drawn on the current page: (resource name, object id)
+    page_images: Vec<(String, Ref)>,
+    /// Top of the next line, from the bottom of the page
+    y: f32,
+}
+
+impl Writer {
+    fn new(title: &str) -> Self {
+        Self {
+            pdf: Pdf::new(),
+            title: title.to_string(),
+            next_id: 7,
+            pages: Vec::new(),
+            content: Content::new(),
+            page_images: Vec::new(),
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    fn alloc(&mut self) -> Ref {
+        let id = Ref::new(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    /// Make room for `height` points, moving to a new page if the current
+    /// one is full (unless nothing has been drawn on it yet).
+    fn reserve(&mut self, height: f32) {
+        if self.y - height < MARGIN && self.y < PAGE_HEIGHT - MARGIN {
+            self.end_page();
+        }
+    }
+
+    fn end_page(&mut self) {
+        // Footer: report title and page number
+        let number = format!("{}", self.pages.len() + 1);
+        let footer_y = MARGIN / 2.0;
+        let title = self.title.clone();
+        self.text_at(&title, Font::Regular, 8.0, MARGIN, footer_y, 0.5);
+        let x = PAGE_WIDTH - MARGIN - Font::Regular.width(&number, 8.0);
+        self.text_at(&number, Font::Regular, 8.0, x, footer_y, 0.5);
+
+        let page_id = self.alloc();
+        let content_id = self.alloc();
+        let content = std::mem::replace(&mut self.content, Content::new());
+        self.pdf.stream(content_id, &content.finish());
+
+        let mut page = self.pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT));
+        page.parent(PAGE_TREE_ID);
+        page.contents(content_id);
+        let mut resources = page.resources();
+        let mut fonts = resources.fonts();
+        for font in Font::ALL {
+            fonts.pair(font.name(), font.id());
+        }
+        fonts.finish();
+        let mut x_objects = resources.x_objects();
+        for (name, id) in &self.page_images {
+            x_objects.pair(Name(name.as_bytes()), *id);
+        }
+        x_objects.finish();
+        resources.finish();
+        page.finish();
+
+        self.pages.push(page_id);
+        self.page_images.clear();
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.end_page();
+        self.pdf.catalog(CATALOG_ID).pages(PAGE_TREE_ID);
+        self.pdf
+            .pages(PAGE_TREE_ID)
+            .kids(self.pages.iter().copied())
+            .count(self.pages.len() as i32);
+        for font in Font::ALL {
+            self.pdf
+                .type1_font(font.id())
+                .base_font(font.base_font())
+                .encoding_predefined(Name(b"WinAnsiEncoding"));
+        }
+        self.pdf
+            .document_info(INFO_ID)
+            .title(TextStr(&self.title))
+            .creator(TextStr(concat!("osgeo-library ", env!("CARGO_PKG_VERSION"))));
+        self.pdf.finish()
+    }
+
+    fn gap(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    /// A thin horizontal line across the text column.
+    fn rule(&mut self) {
+        self.reserve(30.0);
+        self.content
+            .save_state()
+            .set_stroke_gray(0.7)
+            .set_line_width(0.5)
+            .move_to(MARGIN, self.y)
+            .line_to(PAGE_WIDTH - MARGIN, self.y)
+            .stroke()
+            .restore_state();
+        self.y -= 14.0;
+    }
+
+    fn text_at(&mut self, text: &str, font: Font, size: f32, x: f32, y: f32, gray: f32) {
+        self.content
+            .begin_text()
+            .set_fill_gray(gray)
+            .set_font(font.name(), size)
+            .next_line(x, y)
+            .show(Str(&encode(text)))
+            .end_text();
+    }
+
+    /// Word-wrapped text, indented by `indent` points. `gray` is the text
+    /// color (0 is black).
+    fn paragraph(&mut self, text: &str, font: Font, size: f32, indent: f32, gray: f32) {
+        self.hanging(None, text, font, size, indent, gray);
+    }
+
+    /// Like `paragraph`, with `marker` (a bullet or number) printed in the
+    /// indent of the first line.
+    fn hanging(&mut self, marker: Option<&str>, text: &str, font: Font, size: f32, indent: f32, gray: f32) {
+        let leading = size * 1.35;
+        for (i, line) in wrap(text, font, size, TEXT_WIDTH - indent).iter().enumerate() {
+            self.reserve(leading);
+            self.y -= leading;
+            if let (0, Some(marker)) = (i, marker) {
+                let x = MARGIN + indent - Font::Regular.width(marker, size) - 4.0;
+                self.text_at(marker, Font::Regular, size, x, self.y, gray);
+            }
+            self.text_at(line, font, size, MARGIN + indent, self.y, gray);
+        }
+    }
+
+    /// An answer in Markdown: headings, lists and code blocks keep their
+    /// shape; inline emphasis and code marks are dropped.
+    fn markdown(&mut self, text: &str) {
+        let mut in_fence = false;
+        for line in text.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_fence = !in_fence;
+                self.gap(3.0);
+                continue;
+            }
+            if in_fence {
+                self.paragraph(line, Font::Mono, 8.5, 12.0, 0.2);
+            } else if trimmed.is_empty() {
+                self.gap(5.0);
+            } else if trimmed.starts_with('#') {
+                self.gap(3.0);
+                self.paragraph(&inline(trimmed.trim_start_matches('#').trim()), Font::Bold, 10.5, 0.0, 0.0);
+            } else if let Some(item) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) {
+                let depth = (line.len() - line.trim_start().len()) as f32 / 2.0;
+                self.hanging(Some("\u{2022}"), &inline(item), Font::Regular, 10.0, 14.0 + depth * 10.0, 0.0);
+            } else if let Some((marker, item)) = numbered_item(trimmed) {
+                self.hanging(Some(marker), &inline(item), Font::Regular, 10.0, 18.0, 0.0);
+            } else {
+                self.paragraph(&inline(trimmed), Font::Regular, 10.0, 0.0, 0.0);
+            }
+        }
+    }
+
+    /// Draw an image across at most the text column, keeping its aspect.
+    fn image(&mut self, raster: &Raster) {
+        // Pixels at 96 dpi, shrunk to fit the column and height limit
+        let (w, h) = (raster.width as f32 * 0.75, raster.height as f32 * 0.75);
+        let scale = (TEXT_WIDTH / w).min(MAX_IMAGE_HEIGHT / h).min(1.0);
+        let (w, h) = (w * scale, h * scale);
+
+        let image_id = self.alloc();
+        let mask_id = raster.mask.as_ref().map(|_| self.alloc());
+        raster.embed(&mut self.pdf, image_id, mask_id);
+
+        self.reserve(h + 4.0);
+        self.y -= h + 4.0;
+        let name = format!("Im{}", image_id.get());
+        self.content
+            .save_state()
+            .transform([w, 0.0, 0.0, h, MARGIN, self.y])
+            .x_object(Name(name.as_bytes()))
+            .restore_state();
+        self.page_images.push((name, image_id));
+    }
+}
+
+/// A PDF of page images (`export pages`): one page per image, as wide as A4
+/// and as tall as the image's proportions make it, with nothing else on it.
+pub struct PageBook {
+    pdf: Pdf,
+    title: String,
+    next_id: i32,
+    pages: Vec<Ref>,
+}
+
+impl PageBook {
+    pub fn new(title: &str) -> Self {
+        Self {
+            pdf: Pdf::new(),
+            title: title.to_string(),
+            next_id: 4,
+            pages: Vec::new(),
+        }
+    }
+
+    fn alloc(&mut self) -> Ref {
+        let id = Ref::new(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    /// Add a page showing `image`, a PNG or JPEG.
+    pub fn add(&mut self, image: &[u8]) -> Result<()> {
+        let raster = Raster::decode(image)?;
+        let height = PAGE_WIDTH * raster.height as f32 / raster.width.max(1) as f32;
+
+        let (page_id, content_id, image_id) = (self.alloc(), self.alloc(), self.alloc());
+        let mask_id = raster.mask.as_ref().map(|_| self.alloc());
+        raster.embed(&mut self.pdf, image_id, mask_id);
+
+        let mut content = Content::new();
+        content
+            .save_state()
+            .transform([PAGE_WIDTH, 0.0, 0.0, height, 0.0, 0.0])
+            .x_object(Name(b"Im1"))
+            .restore_state();
+        self.pdf.stream(content_id, &content.finish());
+
+        let mut page = self.pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, height));
+        page.parent(PAGE_TREE_ID);
+        page.contents(content_id);
+        page.resources().x_objects().pair(Name(b"Im1"), image_id);
+        page.finish();
+        self.pages.push(page_id);
+        Ok(())
+    }
+
+    /// Write the PDF to `path`.
+    pub fn write(mut self, path: &Path) -> Result<()> {
+        self.pdf.catalog(CATALOG_ID).pages(PAGE_TREE_ID);
+        self.pdf
+            .pages(PAGE_TREE_ID)
+            .kids(self.pages.iter().copied())
+            .count(self.pages.len() as i32);
+        self.pdf
+            .document_info(INFO_ID)
+            .title(TextStr(&self.title))
+            .creator(TextStr(concat!("osgeo-library ", env!("CARGO_PKG_VERSION"))));
+        std::fs::write(path, self.pdf.finish()).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Image samples ready to embed.
+struct Raster {
+    width: u32,
+    height: u32,
+    /// 1 (gray), 3 (RGB) or 4 (CMYK)
+    channels: u8,
+    filter: Filter,
+    data: Vec<u8>,
+    /// Compressed alpha channel, if the image has one
+    mask: Option<Vec<u8>>,
+}
+
+impl Raster {
+    /// Add the image to `pdf` as object `id`, its alpha channel (if any) as
+    /// `mask_id`.
+    fn embed(&self, pdf: &mut Pdf, id: Ref, mask_id: Option<Ref>) {
+        let mut image = pdf.image_xobject(id, &self.data);
+        image.filter(self.filter);
+        image.width(self.width as i32);
+        image.height(self.height as i32);
+        match self.channels {
+            1 => image.color_space().device_gray(),
+            4 => image.color_space().device_cmyk(),
+            _ => image.color_space().device_rgb(),
+        };
+        image.bits_per_component(8);
+        if let Some(mask_id) = mask_id {
+            image.s_mask(mask_id);
+        }
+        image.finish();
+        if let (Some(mask_id), Some(mask)) = (mask_id, &self.mask) {
+            let mut s_mask = pdf.image_xobject(mask_id, mask);
+            s_mask.filter(Filter::FlateDecode);
+            s_mask.width(self.width as i32);
+            s_mask.height(self.height as i32);
+            s_mask.color_space().device_gray();
+            s_mask.bits_per_component(8);
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.starts_with(&[0xFF, 0xD8]) {
+            return Self::jpeg(bytes);
+        }
+        if bytes.starts_with(b"\x89PNG") {
+            return Self::png(bytes);
+        }
+        anyhow::bail!("unsupported image format (only PNG and JPEG)")
+    }
+
+    /// JPEG data embeds as-is; only the frame header is read, for the size.
+    fn jpeg(bytes: &[u8]) -> Result<Self> {
+        let mut pos = 2;
+        while pos + 9 < bytes.len() {
+            if bytes[pos] != 0xFF {
+                anyhow::bail!("corrupt JPEG");
+            }
+            let marker = bytes[pos + 1];
+            let length = usize::from(u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]));
+            // Start-of-frame markers, except DHT (C4), JPG (C8) and DAC (CC)
+            if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+                return Ok(Self {
+                    height: u32::from(u16::from_be_bytes([bytes[pos + 5], bytes[pos + 6]])),
+                    width: u32::from(u16::from_be_bytes([bytes[pos + 7], bytes[pos + 8]])),
+                    channels: bytes[pos + 9],
+                    filter: Filter::DctDecode,
+                    data: bytes.to_vec(),
+                    mask: None,
+                });
+            }
+            pos += 2 + length;
+        }
+        anyhow::bail!("corrupt JPEG (no frame header)")
+    }
+
+    /// PNG samples are decoded and recompressed without the alpha channel,
+    /// which becomes a separate soft mask.
+    fn png(bytes: &[u8]) -> Result<Self> {
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().context("corrupt PNG")?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).context("corrupt PNG")?;
+        buf.truncate(info.buffer_size());
+
+        let (channels, has_alpha) = match info.color_type {
+            png::ColorType::Grayscale => (1, false),
+            png::ColorType::GrayscaleAlpha => (1, true),
+            png::ColorType::Rgba => (3, true),
+            _ => (3, false),
+        };
+        let (samples, mask) = if has_alpha {
+            let stride = usize::from(channels) + 1;
+            let mut samples = Vec::with_capacity(buf.len() / stride * usize::from(channels));
+            let mut alpha = Vec::with_capacity(buf.len() / stride);
+            for pixel in buf.chunks_exact(stride) {
+                samples.extend_from_slice(&pixel[..stride - 1]);
+                alpha.push(pixel[stride - 1]);
+            }
+            (samples, Some(compress_to_vec_zlib(&alpha, 6)))
+        } else {
+            (buf, None)
+        };
+
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            channels,
+            filter: Filter::FlateDecode,
+            data: compress_to_vec_zlib(&samples, 6),
+            mask,
+        })
+    }
+}
+
+/// "1. text" or "12) text" as (marker, text).
+fn numbered_item(line: &str) -> Option<(&str, &str)> {
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 || digits > 3 {
+        return None;
+    }
+    let rest = &line[digits..];
+    if (rest.starts_with(". ") || rest.starts_with(") ")) && rest.len() > 2 {
+        Some((&line[..digits + 1], rest[2..].trim_start()))
+    } else {
+        None
+    }
+}
+
+/// Drop inline Markdown marks that would print literally.
+fn inline(text: &str) -> String {
+    static EMPHASIS: OnceLock<Regex> = OnceLock::new();
+    let emphasis = EMPHASIS.get_or_init(|| Regex::new(r"\*(\S(?:[^*]*\S)?)\*").expect("emphasis pattern is valid"));
+    let text = text.replace("**", "").replace("__", "").replace('`', "");
+    emphasis.replace_all(&text, "$1").into_owned()
+}
+
+/// Break `text` into lines no wider than `width`, at spaces where possible.
+fn wrap(text: &str, font: Font, size: f32, width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+        if font.width(&candidate, size) <= width {
+            line = candidate;
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        // A word wider than the column is split wherever it overflows
+        for c in word.chars() {
+            line.push(c);
+            if font.width(&line, size) > width && line.chars().count() > 1 {
+                line.pop();
+                lines.push(std::mem::replace(&mut line, c.to_string()));
+            }
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Text in WinAnsi (Windows-1252), the encoding of the standard fonts.
+fn encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{A0}'..='\u{FF}' => c as u8,
+            '\t' => b' ',
+            '€' => 0x80,
+            '‚' => 0x82,
+            'ƒ' => 0x83,
+            '„' => 0x84,
+            '…' => 0x85,
+            '†' => 0x86,
+            '‡' => 0x87,
+            'ˆ' => 0x88,
+            '‰' => 0x89,
+            'Š' => 0x8A,
+            '‹' => 0x8B,
+            'Œ' => 0x8C,
+            'Ž' => 0x8E,
+            '\u{2018}' => 0x91,
+            '\u{2019}' => 0x92,
+            '\u{201C}' => 0x93,
+            '\u{201D}' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '˜' => 0x98,
+            '™' => 0x99,
+            'š' => 0x9A,
+            '›' => 0x9B,
+            'œ' => 0x9C,
+            'ž' => 0x9E,
+            'Ÿ' => 0x9F,
+            _ => b'?',
+        })
+        .collect()
+}
@@ -0,0 +1,88 @@
+//! Restartable exports (`export notes --resume`, and the same for `export
+//! doc`, `export elements` and `export pages`).
+//!
+//! Exports download one image per figure, table and equation (or per page),
+//! and over a slow or flaky link a large one can fail halfway. Each downloaded file is
+//! recorded in a manifest next to it as soon as it is written, with the
+//! server path it came from, its size and SHA-256. A run with `--resume`
+//! reuses every file the manifest lists that is still on disk unchanged and
+//! downloads only the rest; a run without it starts over.
+
+use crate::digest::sha256;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Manifest file name, in the directory the files are downloaded to
+const FILE_NAME: &str = ".export-manifest.json";
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    /// Image path on the server
+    source: String,
+    bytes: u64,
+    sha256: String,
+}
+
+/// Files downloaded into a directory so far.
+pub struct Manifest {
+    dir: PathBuf,
+    /// By path relative to `dir`
+    entries: BTreeMap<String, Entry>,
+}
+
+impl Manifest {
+    /// Manifest for downloads into `dir`: the one left by an earlier run
+    /// when `resume` is set, otherwise an empty one.
+    pub fn open(dir: &Path, resume: bool) -> Result<Self> {
+        let path = dir.join(FILE_NAME);
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(text) if resume => serde_json::from_str(&text)
+                .with_context(|| format!("Failed to read {}; delete it to start over", path.display()))?,
+            _ => BTreeMap::new(),
+        };
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            entries,
+        })
+    }
+
+    /// The contents of `name`, if an earlier run downloaded it from `source`
+    /// and it is still on disk with the size and SHA-256 it was written with.
+    pub fn completed(&self, name: &str, source: &str) -> Option<Vec<u8>> {
+        let entry = self.entries.get(name).filter(|e| e.source == source)?;
+        let path = self.dir.join(name);
+        // Check the size first, to skip reading files that were cut short
+        if std::fs::metadata(&path).ok()?.len() != entry.bytes {
+            return None;
+        }
+        let bytes = std::fs::read(&path).ok()?;
+        (sha256(&bytes) == entry.sha256).then_some(bytes)
+    }
+
+    /// Write `bytes`, downloaded from `source`, to `name` and record it.
+    pub fn save(&mut self, name: &str, source: &str, bytes: &[u8]) -> Result<()> {
+        write_whole(&self.dir.join(name), bytes)?;
+        self.entries.insert(
+            name.to_string(),
+            Entry {
+                source: source.to_string(),
+                bytes: bytes.len() as u64,
+                sha256: sha256(bytes),
+            },
+        );
+        let json = serde_json::to_string_pretty(&self.entries).context("Failed to encode export manifest")?;
+        write_whole(&self.dir.join(FILE_NAME), json.as_bytes())
+    }
+}
+
+/// Write `bytes` to a temporary file and rename it to `path`, so that an
+/// interrupted write leaves no partial file under the real name.
+fn write_whole(path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut temp = path.as_os_str().to_os_string();
+    temp.push(".part");
+    let temp = PathBuf::from(temp);
+    std::fs::write(&temp, bytes).with_context(|| format!("Failed to write {}", temp.display()))?;
+    std::fs::rename(&temp, path).with_context(|| format!("Failed to write {}", path.display()))
+}
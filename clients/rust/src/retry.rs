@@ -0,0 +1,66 @@
+//! Retrying requests after transient failures.
+//!
+//! A server restarting behind a proxy answers 502, 503 or 504 for a few
+//! seconds, and a busy one 429; a connection can drop or time out on the
+//! way. Requests that are safe to repeat (GET and HEAD, and search, which
+//! only reads) are sent again after such a failure, up to the policy's
+//! `retries`, waiting a little longer each time: about 0.5 s, then 1 s, 2 s
+//! and so on up to 8 s. Each wait is picked at random between half and all
+//! of that, so that clients which failed together don't retry in step.
+//! Questions, uploads and deletes are never repeated: the first may have
+//! been answered, at some cost, and the others done, before the failure.
+
+use reqwest::StatusCode;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Wait before the first retry, doubled for each one after it
+const FIRST_DELAY_MS: u64 = 500;
+
+/// Longest wait between two attempts
+const MAX_DELAY_MS: u64 = 8_000;
+
+/// Whether a response status is worth another try.
+pub fn transient_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Whether a failed request is worth another try: the server could not be
+/// reached or did not answer in time. Errors in building the request or
+/// following redirects would fail the same way again.
+pub fn transient_error(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout() || error.is_request()
+}
+
+/// Wait before retry number `retry` (from 1).
+pub fn delay(retry: u32) -> Duration {
+    let full = FIRST_DELAY_MS
+        .saturating_mul(1 << (retry.saturating_sub(1)).min(16))
+        .min(MAX_DELAY_MS);
+    Duration::from_millis(full / 2 + random() % (full / 2 + 1))
+}
+
+/// Report a retry in verbose mode.
+pub fn report(method: &str, url: &str, problem: &str, retry: u32, retries: u32, wait: Duration) {
+    crate::term::verbose_line(&format!(
+        "{} {} {}; retry {} of {} in {:.1}s",
+        method,
+        crate::httplog::redact_url(url),
+        problem,
+        retry,
+        retries,
+        wait.as_secs_f64()
+    ));
+}
+
+/// A random number, good enough for spreading retries out.
+fn random() -> u64 {
+    // Each RandomState is seeded differently
+    std::collections::hash_map::RandomState::new().build_hasher().finish()
+}
@@ -0,0 +1,90 @@
+//! Files shared by clients running at the same time.
+//!
+//! A chat session and scripted searches run side by side write the same
+//! request log (`httplog`) and crash log, and `config set` may run while
+//! another client does. Read, change and write back by two clients at once,
+//! one client's change was lost, and a reader could catch a file half
+//! written. Changes now go through `update` and `append`, which take an
+//! exclusive lock on a `.lock` file next to the shared one; `update` writes
+//! a new copy and renames it into place, so readers need no lock and see
+//! either the old file or the new one.
+//!
+//! `--no-shared-state` keeps a client out of the logs altogether: it records
+//! nothing there, for scripts whose requests shouldn't end up in a bug
+//! report or that run where the cache directory isn't writable.
+
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Keep this client out of the shared logs (`--no-shared-state`).
+pub fn set_enabled(on: bool) {
+    DISABLED.store(!on, Ordering::Relaxed);
+}
+
+/// Whether this client records requests and crashes in the shared logs.
+pub fn enabled() -> bool {
+    !DISABLED.load(Ordering::Relaxed)
+}
+
+/// Replace the contents of `path` with `edit` of them (empty when the file
+/// doesn't exist yet), while no other client changes it. Nothing is written
+/// when `edit` fails. A symlinked file is replaced where the link points.
+pub fn update(path: &Path, edit: impl FnOnce(String) -> Result<String>) -> Result<()> {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let _lock = lock(&path)?;
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let text = edit(text)?;
+
+    let temp = sibling(&path, &format!("tmp-{}", std::process::id()));
+    std::fs::write(&temp, text).with_context(|| format!("Failed to write {}", temp.display()))?;
+    std::fs::rename(&temp, &path).with_context(|| {
+        std::fs::remove_file(&temp).ok();
+        format!("Failed to write {}", path.display())
+    })
+}
+
+/// Add `text` to the end of `path` in one piece, so that lines written by
+/// clients at the same time don't interleave.
+pub fn append(path: &Path, text: &str) -> Result<()> {
+    let _lock = lock(path)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.write_all(text.as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Exclusive lock for changing `path`, held until the file is dropped.
+/// Creates the directory `path` goes in.
+fn lock(path: &Path) -> Result<File> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let lock_path = sibling(path, "lock");
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+    file.lock().with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+    Ok(file)
+}
+
+/// `path` with `.suffix` added ("requests.log.lock").
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", suffix));
+    path.with_file_name(name)
+}
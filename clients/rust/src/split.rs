@@ -0,0 +1,141 @@
+//! Split-pane chat layout.
+//!
+//! `chat --split` switches to the alternate screen and divides it into a
+//! viewer pane (top) and a conversation pane (bottom). The conversation pane
+//! is a terminal scroll region, so regular output and the readline prompt
+//! scroll inside it while the most recent image stays pinned in the viewer.
+
+use crossterm::{
+    cursor,
+    execute, queue,
+    style::Print,
+    terminal::{self, Clear, ClearType},
+};
+use std::io::Write;
+use std::sync::Mutex;
+
+static PANE: Mutex<Option<SplitScreen>> = Mutex::new(None);
+
+struct SplitScreen {
+    width: u16,
+    /// Rows reserved for the viewer (0-based rows 0..viewer_rows)
+    viewer_rows: u16,
+    height: u16,
+}
+
+impl SplitScreen {
+    fn conversation_top(&self) -> u16 {
+        // Viewer rows, then one separator row
+        self.viewer_rows + 1
+    }
+
+    fn draw_separator(&self, title: &str) -> std::io::Result<()> {
+        let mut out = std::io::stdout();
+        let label = if title.is_empty() {
+            String::new()
+        } else {
+            format!(" {} ", title)
+        };
+        let fill = (self.width as usize).saturating_sub(label.chars().count() + 2);
+        queue!(
+            out,
+            cursor::SavePosition,
+            cursor::MoveTo(0, self.viewer_rows),
+            Clear(ClearType::CurrentLine),
+            Print(format!("──{}{}", label, "─".repeat(fill))),
+            cursor::RestorePosition
+        )?;
+        out.flush()
+    }
+}
+
+/// Restores the normal screen when dropped, including on early return or panic.
+pub struct SplitGuard {
+    _terminal: crate::tty::Guard,
+}
+
+impl Drop for SplitGuard {
+    fn drop(&mut self) {
+        // Tolerate a poisoned lock: this also runs while unwinding from a panic
+        PANE.lock().unwrap_or_else(|e| e.into_inner()).take();
+    }
+}
+
+/// Enter split mode. The viewer takes roughly the top half of the screen.
+pub fn enter() -> std::io::Result<SplitGuard> {
+    let (width, height) = terminal::size()?;
+    if height < 16 {
+        return Err(std::io::Error::other(
+            "terminal too small for split mode (need at least 16 rows)",
+        ));
+    }
+    let pane = SplitScreen {
+        width,
+        viewer_rows: height / 2 - 1,
+        height,
+    };
+
+    let mut terminal = crate::tty::Guard::default();
+    terminal.alternate_screen()?;
+    let mut out = std::io::stdout();
+    execute!(out, Clear(ClearType::All))?;
+    // Scrolling stays within the conversation pane
+    terminal.scroll_region(pane.conversation_top(), pane.height - 1)?;
+    execute!(out, cursor::MoveTo(0, pane.conversation_top()))?;
+    pane.draw_separator("viewer")?;
+
+    *PANE.lock().unwrap() = Some(pane);
+    Ok(SplitGuard { _terminal: terminal })
+}
+
+/// Chafa size string ("WxH") that fits the viewer pane.
+pub fn viewer_size() -> Option<String> {
+    PANE.lock()
+        .unwrap()
+        .as_ref()
+        .map(|p| format!("{}x{}", p.width.saturating_sub(2), p.viewer_rows))
+}
+
+/// Rows in the conversation pane, when split mode is active.
+pub fn conversation_rows() -> Option<usize> {
+    PANE.lock()
+        .unwrap()
+        .as_ref()
+        .map(|p| (p.height - p.conversation_top()) as usize)
+}
+
+/// Draw pre-rendered image output (ANSI art lines) into the viewer pane.
+pub fn draw_viewer(rendered: &str, title: &str) -> std::io::Result<()> {
+    let guard = PANE.lock().unwrap();
+    let Some(pane) = guard.as_ref() else {
+        return Ok(());
+    };
+
+    let mut out = std::io::stdout();
+    queue!(out, cursor::SavePosition)?;
+    let mut lines = rendered.lines();
+    for row in 0..pane.viewer_rows {
+        queue!(out, cursor::MoveTo(0, row), Clear(ClearType::CurrentLine))?;
+        if let Some(line) = lines.next() {
+            queue!(out, Print(line), Print("\x1b[0m"))?;
+        }
+    }
+    queue!(out, cursor::RestorePosition)?;
+    out.flush()?;
+    pane.draw_separator(title)
+}
+
+/// Clear the conversation pane, leaving the viewer untouched.
+pub fn clear_conversation() -> std::io::Result<()> {
+    let guard = PANE.lock().unwrap();
+    let Some(pane) = guard.as_ref() else {
+        return Ok(());
+    };
+
+    let mut out = std::io::stdout();
+    for row in pane.conversation_top()..pane.height {
+        queue!(out, cursor::MoveTo(0, row), Clear(ClearType::CurrentLine))?;
+    }
+    queue!(out, cursor::MoveTo(0, pane.conversation_top()))?;
+    out.flush()
+}
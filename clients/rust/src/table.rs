@@ -0,0 +1,181 @@
+//! Text table rendering for table elements.
+//!
+//! Table elements sometimes carry extracted rows in their content field
+//! (Markdown pipes, tabs, CSV, or space-aligned columns). This finds the
+//! longest run of consistently delimited lines and renders it as an aligned
+//! Unicode box table. Content that doesn't look tabular returns `None` so
+//! callers can fall back to the raw text preview.
+
+/// A parsed table: any text preceding the rows, plus the rows themselves.
+/// The first row is treated as the header.
+pub struct Table {
+    pub caption: Option<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+#[derive(Clone, Copy)]
+enum Delimiter {
+    Pipe,
+    Tab,
+    Comma,
+    Spaces,
+}
+
+impl Delimiter {
+    fn split(self, line: &str) -> Vec<String> {
+        let cells: Vec<&str> = match self {
+            Delimiter::Pipe => {
+                let trimmed = line.trim().trim_start_matches('|').trim_end_matches('|');
+                trimmed.split('|').collect()
+            }
+            Delimiter::Tab => line.split('\t').collect(),
+            Delimiter::Comma => line.split(',').collect(),
+            Delimiter::Spaces => line.trim().split("  ").filter(|c| !c.trim().is_empty()).collect(),
+        };
+        cells.into_iter().map(|c| c.trim().to_string()).collect()
+    }
+
+    fn applies_to(self, line: &str) -> bool {
+        match self {
+            Delimiter::Pipe => line.contains('|'),
+            Delimiter::Tab => line.contains('\t'),
+            Delimiter::Comma => line.contains(','),
+            Delimiter::Spaces => line.trim().contains("  "),
+        }
+    }
+}
+
+/// Markdown separator rows like `|---|:--:|`.
+fn is_separator(line: &str) -> bool {
+    let t = line.trim();
+    !t.is_empty() && t.contains('-') && t.chars().all(|c| matches!(c, '|' | '-' | ':' | '+' | ' '))
+}
+
+/// Try to find a table in `content`. Requires at least two rows of two or
+/// more columns with a consistent column count.
+pub fn parse(content: &str) -> Option<Table> {
+    let lines: Vec<&str> = content
+        .lines()
+        .filter(|l| !l.trim().is_empty() && !is_separator(l))
+        .collect();
+
+    for delimiter in [Delimiter::Pipe, Delimiter::Tab, Delimiter::Comma, Delimiter::Spaces] {
+        // Longest run of consecutive lines with the same column count
+        let mut best: Option<(usize, usize)> = None; // (start, len)
+        let mut start = 0;
+        while start < lines.len() {
+            if !delimiter.applies_to(lines[start]) {
+                start += 1;
+                continue;
+            }
+            let cols = delimiter.split(lines[start]).len();
+            if cols < 2 {
+                start += 1;
+                continue;
+            }
+            let mut end = start + 1;
+            while end < lines.len()
+                && delimiter.applies_to(lines[end])
+                && delimiter.split(lines[end]).len() == cols
+            {
+                end += 1;
+            }
+            let len = end - start;
+            if len >= 2 && best.map(|(_, l)| len > l).unwrap_or(true) {
+                best = Some((start, len));
+            }
+            start = end;
+        }
+
+        // Comma-separated prose is common; demand more evidence for CSV
+        let min_rows = if matches!(delimiter, Delimiter::Comma) { 3 } else { 2 };
+        if let Some((start, len)) = best.filter(|(_, len)| *len >= min_rows) {
+            let caption = lines[..start].join(" ");
+            return Some(Table {
+                caption: (!caption.trim().is_empty()).then(|| caption.trim().to_string()),
+                rows: lines[start..start + len]
+                    .iter()
+                    .map(|l| delimiter.split(l))
+                    .collect(),
+            });
+        }
+    }
+    None
+}
+
+/// Render rows as a box table no wider than `max_width` columns, showing at
+/// most `max_rows` body rows. Wide columns are truncated with an ellipsis.
+pub fn render(table: &Table, max_width: usize, max_rows: usize) -> String {
+    let cols = table.rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    if cols == 0 {
+        return String::new();
+    }
+
+    let mut widths: Vec<usize> = (0..cols)
+        .map(|c| {
+            table
+                .rows
+                .iter()
+                .map(|r| r.get(c).map(|s| s.chars().count()).unwrap_or(0))
+                .max()
+                .unwrap_or(0)
+                .max(1)
+        })
+        .collect();
+
+    // Borders take 3 chars per column plus 1; shrink the widest column until it fits
+    let overhead = cols * 3 + 1;
+    let budget = max_width.saturating_sub(overhead).max(cols * 3);
+    while widths.iter().sum::<usize>() > budget {
+        let (idx, widest) = widths
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, w)| **w)
+            .map(|(i, w)| (i, *w))
+            .unwrap();
+        if widest <= 3 {
+            break;
+        }
+        widths[idx] -= 1;
+    }
+
+    let line = |left: &str, mid: &str, right: &str| {
+        let segments: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+        format!("{}{}{}", left, segments.join(mid), right)
+    };
+    let row = |cells: &[String]| {
+        let parts: Vec<String> = widths
+            .iter()
+            .enumerate()
+            .map(|(i, w)| format!(" {} ", fit(cells.get(i).map(String::as_str).unwrap_or(""), *w)))
+            .collect();
+        format!("│{}│", parts.join("│"))
+    };
+
+    let mut out = vec![line("┌", "┬", "┐")];
+    let mut rows = table.rows.iter();
+    if let Some(header) = rows.next() {
+        out.push(row(header));
+        out.push(line("├", "┼", "┤"));
+    }
+    let body: Vec<&Vec<String>> = rows.collect();
+    for r in body.iter().take(max_rows) {
+        out.push(row(r));
+    }
+    out.push(line("└", "┴", "┘"));
+    if body.len() > max_rows {
+        out.push(format!("({} more rows)", body.len() - max_rows));
+    }
+    out.join("\n")
+}
+
+/// Pad or truncate `s` to exactly `width` characters.
+fn fit(s: &str, width: usize) -> String {
+    let len = s.chars().count();
+    if len <= width {
+        format!("{}{}", s, " ".repeat(width - len))
+    } else {
+        let truncated: String = s.chars().take(width.saturating_sub(1)).collect();
+        format!("{}…", truncated)
+    }
+}
@@ -0,0 +1,60 @@
+//! Question templates for routine lookups (`ask --template NAME "topic"`).
+//!
+//! Each template wraps the topic in a fuller question so answers to common
+//! tasks come back in a consistent shape. `{topic}` marks where the topic
+//! goes. The `[templates]` config table adds templates or replaces built-in
+//! ones:
+//!
+//! ```toml
+//! [templates]
+//! epsg = "What is the EPSG code and parameter set for {topic}? Cite the document and page."
+//! ```
+
+use anyhow::Result;
+use std::collections::HashMap;
+
+const BUILTIN: &[(&str, &str)] = &[
+    (
+        "define",
+        "Define {topic}. Give the defining formula if there is one, explain its terms, \
+         and cite the document and page the definition comes from.",
+    ),
+    (
+        "compare",
+        "Compare {topic}: explain what each one is, how they differ, and when each \
+         should be used. Cite the document and page for each point.",
+    ),
+    (
+        "derive",
+        "Derive {topic} step by step, showing each equation and stating the assumptions \
+         made. Cite the document and page for every equation used.",
+    ),
+    (
+        "cite",
+        "Which documents and pages in the library discuss {topic}? List each with a \
+         one-line summary of what it says.",
+    ),
+];
+
+/// Expand template `name` around `topic`. Config templates take precedence.
+pub fn expand(name: &str, topic: &str, custom: &HashMap<String, String>) -> Result<String> {
+    let template = custom
+        .get(name)
+        .map(String::as_str)
+        .or_else(|| BUILTIN.iter().find(|(n, _)| *n == name).map(|(_, t)| *t))
+        .ok_or_else(|| anyhow::anyhow!("Unknown template '{}'. Available: {}", name, names(custom).join(", ")))?;
+
+    if topic.trim().is_empty() {
+        anyhow::bail!("Template '{}' needs a topic, e.g. ask --template {} \"mercator projection\"", name, name);
+    }
+    Ok(template.replace("{topic}", topic.trim()))
+}
+
+/// Names of all available templates, built-in first.
+fn names(custom: &HashMap<String, String>) -> Vec<String> {
+    let mut names: Vec<String> = BUILTIN.iter().map(|(n, _)| n.to_string()).collect();
+    let mut extra: Vec<&String> = custom.keys().filter(|k| !names.contains(k)).collect();
+    extra.sort();
+    names.extend(extra.into_iter().cloned());
+    names
+}
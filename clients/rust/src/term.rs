@@ -0,0 +1,305 @@
+//! Terminal environment detection.
+//!
+//! Decides once at startup how rich the output may be. When stdout is not a
+//! terminal, TERM is "dumb", or NO_COLOR is set, the client switches to a
+//! plain renderer: no ANSI colors, no inline images, no progress lines, and
+//! a short prompt. SSH sessions without display forwarding are detected so
+//! GUI-only features can be skipped up front. The background color and the
+//! graphics the terminal supports are asked from it only when an image is
+//! first shown.
+//!
+//! Warnings and hints go to stderr, so piping a command's output (`--json`
+//! into jq, a listing into grep) gets the results alone while the person at
+//! the terminal still sees them. `--no-hints` leaves hints out.
+
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+static ENV: OnceLock<TermEnv> = OnceLock::new();
+static BACKGROUND: OnceLock<Option<[u8; 3]>> = OnceLock::new();
+static GRAPHICS: OnceLock<Graphics> = OnceLock::new();
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+static HINTS: AtomicBool = AtomicBool::new(true);
+/// When the client started, and when the last startup step ended (--debug)
+static STARTUP: Mutex<Option<(Instant, Instant)>> = Mutex::new(None);
+
+#[derive(Debug)]
+pub struct TermEnv {
+    /// Plain output: no colors, images, progress lines or decorated prompts
+    pub plain: bool,
+    /// Running inside an SSH session
+    pub ssh: bool,
+    /// A graphical display is available for GUI viewers
+    pub gui: bool,
+}
+
+impl TermEnv {
+    fn detect(force_plain: bool) -> Self {
+        let dumb = std::env::var("TERM").map(|t| t == "dumb").unwrap_or(false);
+        let no_color = std::env::var_os("NO_COLOR").is_some();
+        let redirected = !std::io::stdout().is_terminal();
+
+        let ssh = std::env::var_os("SSH_CONNECTION").is_some()
+            || std::env::var_os("SSH_TTY").is_some();
+
+        Self {
+            plain: force_plain || dumb || no_color || redirected,
+            ssh,
+            gui: has_display(ssh),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn has_display(_ssh: bool) -> bool {
+    std::env::var_os("DISPLAY").is_some() || std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+#[cfg(target_os = "macos")]
+fn has_display(ssh: bool) -> bool {
+    !ssh || std::env::var_os("DISPLAY").is_some()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn has_display(_ssh: bool) -> bool {
+    true
+}
+
+/// Detect the environment and apply global settings (color override).
+/// Must be called once at startup, before any output.
+pub fn init(force_plain: bool) -> &'static TermEnv {
+    let env = ENV.get_or_init(|| TermEnv::detect(force_plain));
+    if env.plain {
+        colored::control::set_override(false);
+    }
+    env
+}
+
+/// Current terminal environment (detected lazily if `init` was not called).
+pub fn env() -> &'static TermEnv {
+    ENV.get_or_init(|| TermEnv::detect(false))
+}
+
+/// Terminal width in columns (80 when unknown, e.g. output redirected).
+pub fn width() -> usize {
+    terminal_size::terminal_size()
+        .map(|(w, _)| w.0 as usize)
+        .unwrap_or(80)
+}
+
+/// Terminal height in rows (24 when unknown).
+pub fn height() -> usize {
+    terminal_size::terminal_size()
+        .map(|(_, h)| h.0 as usize)
+        .unwrap_or(24)
+}
+
+/// Print a transient progress message ("Thinking...", "Loading page 3...").
+/// Suppressed in plain mode so redirected output contains only results.
+pub fn progress(msg: &str) {
+    if !env().plain {
+        println!("{}", colored::Colorize::dimmed(msg));
+    }
+}
+
+/// Like `progress`, but without a trailing newline so a status can follow.
+pub fn progress_inline(msg: &str) {
+    if !env().plain {
+        print!("{}", msg);
+        std::io::Write::flush(&mut std::io::stdout()).ok();
+    }
+}
+
+/// Finish a line started with `progress_inline`.
+pub fn progress_done(msg: &str) {
+    if !env().plain {
+        println!("{}", msg);
+    }
+}
+
+/// Enable --verbose reporting.
+pub fn set_verbose(on: bool) {
+    VERBOSE.store(on, Ordering::Relaxed);
+}
+
+pub fn verbose() -> bool {
+    VERBOSE.load(Ordering::Relaxed)
+}
+
+/// Print a --verbose detail line to stderr, keeping stdout for results.
+pub fn verbose_line(msg: &str) {
+    if verbose() {
+        eprintln!("{}", colored::Colorize::dimmed(msg));
+    }
+}
+
+/// Turn hints on or off (--no-hints).
+pub fn set_hints(on: bool) {
+    HINTS.store(on, Ordering::Relaxed);
+}
+
+/// Print a hint (what to run next, what was left out) to stderr, so stdout
+/// holds only results; nothing with --no-hints.
+pub fn hint(msg: &str) {
+    if HINTS.load(Ordering::Relaxed) {
+        eprintln!("{}", colored::Colorize::dimmed(msg));
+    }
+}
+
+/// Print "Warning: `msg`" to stderr. Unlike hints, warnings are kept with
+/// --no-hints: they say something about the results.
+pub fn warning(msg: &str) {
+    eprintln!("{}", colored::Colorize::yellow(format!("Warning: {}", msg).as_str()));
+}
+
+/// Time startup from `started` (--debug), reporting each `startup_step`.
+pub fn set_debug(started: Instant) {
+    *STARTUP.lock().unwrap_or_else(|e| e.into_inner()) = Some((started, started));
+}
+
+/// With --debug, report on stderr how long startup took to get through
+/// `step`, and since the last step.
+pub fn startup_step(step: &str) {
+    let mut startup = STARTUP.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((started, last)) = startup.as_mut() {
+        let now = Instant::now();
+        let ms = |since: Instant| now.duration_since(since).as_secs_f64() * 1000.0;
+        eprintln!(
+            "{}",
+            colored::Colorize::dimmed(format!("debug: {} at {:.1} ms (+{:.1} ms)", step, ms(*started), ms(*last)).as_str())
+        );
+        *last = now;
+    }
+}
+
+/// Terminal background color, asked once with an OSC 11 query and falling
+/// back to `COLORFGBG`. `None` in plain mode or when the terminal won't say.
+pub fn background() -> Option<[u8; 3]> {
+    *BACKGROUND.get_or_init(|| {
+        if env().plain {
+            return None;
+        }
+        let color = query_background().or_else(colorfgbg_background);
+        verbose_line(&format!("Terminal background: {:?}", color));
+        color
+    })
+}
+
+/// Graphics protocols the terminal answered for.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Graphics {
+    /// Kitty graphics protocol
+    pub kitty: bool,
+    /// Sixel graphics
+    pub sixel: bool,
+}
+
+/// Graphics the terminal supports, asked once: a Kitty graphics query
+/// (answered only by terminals that implement it) and the device attributes,
+/// which list sixel support as attribute 4. Nothing in plain mode.
+pub fn graphics() -> Graphics {
+    *GRAPHICS.get_or_init(|| {
+        if env().plain {
+            return Graphics::default();
+        }
+        let reply = String::from_utf8_lossy(&query(b"\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\\x1b[c")).to_string();
+        let graphics = Graphics {
+            kitty: reply.contains("_Gi=31;OK"),
+            sixel: device_attributes(&reply).contains(&4),
+        };
+        verbose_line(&format!("Terminal graphics: {:?}", graphics));
+        graphics
+    })
+}
+
+fn query_background() -> Option<[u8; 3]> {
+    parse_osc11(&String::from_utf8_lossy(&query(b"\x1b]11;?\x07\x1b[c")))
+}
+
+/// Longest wait for the terminal's reply
+#[cfg(unix)]
+const QUERY_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(300);
+
+/// Send `request`, which ends with a device attributes request (ESC [ c), to
+/// the terminal and read the replies. Nearly every terminal answers that
+/// one, so the wait ends even when the rest of the request isn't supported.
+#[cfg(unix)]
+fn query(request: &[u8]) -> Vec<u8> {
+    use std::io::{Read, Write};
+    use std::os::unix::io::AsRawFd;
+
+    let mut reply = Vec::new();
+    let Ok(mut tty) = std::fs::OpenOptions::new().read(true).write(true).open("/dev/tty") else {
+        return reply;
+    };
+    // Raw mode, so the reply isn't echoed or held back until Enter
+    let mut raw = crate::tty::Guard::default();
+    if raw.raw_mode().is_err() {
+        return reply;
+    }
+    if tty.write_all(request).and_then(|_| tty.flush()).is_ok() {
+        let deadline = std::time::Instant::now() + QUERY_TIMEOUT;
+        let mut buf = [0u8; 64];
+        while !has_device_attributes(&reply) {
+            let left = deadline.saturating_duration_since(std::time::Instant::now());
+            let mut fds = libc::pollfd { fd: tty.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+            if left.is_zero() || unsafe { libc::poll(&mut fds, 1, left.as_millis() as i32) } <= 0 {
+                break;
+            }
+            match tty.read(&mut buf) {
+                Ok(n) if n > 0 => reply.extend_from_slice(&buf[..n]),
+                _ => break,
+            }
+        }
+    }
+    reply
+}
+
+#[cfg(not(unix))]
+fn query(_request: &[u8]) -> Vec<u8> {
+    Vec::new()
+}
+
+/// Whether `reply` ends with the device attributes answer ("ESC [ ? ... c").
+#[cfg(unix)]
+fn has_device_attributes(reply: &[u8]) -> bool {
+    reply.windows(3).any(|w| w == b"\x1b[?") && reply.last() == Some(&b'c')
+}
+
+/// Attributes in a device attributes reply ("ESC [ ? 62 ; 4 ; 22 c").
+fn device_attributes(reply: &str) -> Vec<u32> {
+    let Some(start) = reply.rfind("\x1b[?") else {
+        return Vec::new();
+    };
+    let params = &reply[start + 3..];
+    params[..params.find('c').unwrap_or(params.len())]
+        .split(';')
+        .filter_map(|p| p.parse().ok())
+        .collect()
+}
+
+/// Color in an OSC 11 reply: "ESC ] 11 ; rgb:RRRR/GGGG/BBBB" ended by BEL or
+/// ESC \, with 1 to 4 hex digits per channel.
+fn parse_osc11(reply: &str) -> Option<[u8; 3]> {
+    let start = reply.find("]11;rgb:")? + "]11;rgb:".len();
+    let spec = reply[start..].split(['\x07', '\x1b']).next()?;
+    let mut channels = spec.split('/').map(|hex| {
+        if hex.is_empty() || hex.len() > 4 {
+            return None;
+        }
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        let max = (1u32 << (4 * hex.len())) - 1;
+        Some((value * 255 / max) as u8)
+    });
+    Some([channels.next()??, channels.next()??, channels.next()??])
+}
+
+/// Black or white from the background color index in `COLORFGBG`
+/// ("15;0" is light text on black), set by rxvt, Konsole and others.
+fn colorfgbg_background() -> Option<[u8; 3]> {
+    let value = std::env::var("COLORFGBG").ok()?;
+    let index: u8 = value.rsplit(';').next()?.parse().ok()?;
+    Some(if matches!(index, 7 | 9..=15) { [255; 3] } else { [0; 3] })
+}
@@ -0,0 +1,322 @@
+//! Documents' text kept on this machine (`cache text <slug>`).
+//!
+//! `grep` fetches every chunk of a document each time it runs, and nothing
+//! can be searched without the server. `cache text` fetches a document's
+//! chunks once, in batches with a pause between them so a large library
+//! can be cached without loading the server, and keeps their text (no
+//! images). From then on `grep` on that document reads the cached text,
+//! and `cache grep` searches every cached document without the server.
+//!
+//! A library of hundreds of documents is a lot of text, so each document is
+//! kept as one zstd-compressed blob, `<slug>.zst`, in a directory per server
+//! under `~/.cache/osgeo-library/text/` (the platform's cache directory
+//! elsewhere). The directory's `index.json` lists the documents with when
+//! they were fetched and their size before and after compression, so they
+//! can be listed and counted (`cache list`, `cache stats`) without opening
+//! a blob. The blob and its entry are written after every batch, so an
+//! interrupted run carries on where it stopped: each batch is compressed on
+//! its own as one more zstd frame (a line of JSON per chunk), so only the
+//! new text is compressed, and the blob is written beside the old one and
+//! renamed over it, so `grep` never reads one half-written.
+
+use crate::types::ChunkListItem;
+use crate::OsgeoClient;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Chunks asked for per request
+const BATCH: i32 = 200;
+
+/// zstd level of the blobs: text shrinks to about a fifth, still quickly
+const LEVEL: i32 = 9;
+
+/// Pause between requests unless `--delay` says otherwise
+pub const DEFAULT_DELAY_MS: u64 = 500;
+
+/// A document's text, or as much of it as was fetched.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Cached {
+    pub server: String,
+    pub slug: String,
+    /// "YYYY-MM-DDThh:mm:ssZ" of the last batch fetched
+    pub fetched: String,
+    /// Chunks the document has
+    pub total: i32,
+    /// In reading order
+    pub chunks: Vec<Chunk>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Chunk {
+    pub page: i32,
+    pub index: i32,
+    /// Offset of the chunk on its page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<i32>,
+    pub text: String,
+}
+
+impl From<ChunkListItem> for Chunk {
+    fn from(chunk: ChunkListItem) -> Self {
+        Self {
+            page: chunk.page_number,
+            index: chunk.chunk_index,
+            start: chunk.start_char,
+            text: chunk.content,
+        }
+    }
+}
+
+impl Cached {
+    /// Whether every chunk has been fetched.
+    pub fn complete(&self) -> bool {
+        self.chunks.len() as i32 >= self.total
+    }
+}
+
+/// A cached document, as the index lists it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// "YYYY-MM-DDThh:mm:ssZ" of the last batch fetched
+    pub fetched: String,
+    /// Chunks the document has
+    pub total: i32,
+    /// Chunks fetched
+    pub chunks: usize,
+    /// Size of the text uncompressed, in bytes
+    pub bytes: u64,
+    /// Size of the blob, in bytes
+    pub stored: u64,
+}
+
+impl Entry {
+    /// Whether every chunk has been fetched.
+    pub fn complete(&self) -> bool {
+        self.chunks as i32 >= self.total
+    }
+}
+
+/// A server's `index.json`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    server: String,
+    documents: BTreeMap<String, Entry>,
+}
+
+/// What is cached from one server (`cache stats`).
+pub struct Stats {
+    pub server: String,
+    pub documents: usize,
+    pub chunks: usize,
+    /// Size of the text uncompressed, in bytes
+    pub bytes: u64,
+    /// Size of the server's directory, index included, in bytes
+    pub on_disk: u64,
+}
+
+/// Cache the text of `slug` from `client`'s server, pausing `delay` between
+/// requests and calling `progress` with the chunks fetched and the total
+/// after each batch. Carries on from a partial cache; `refresh` starts over.
+/// The document's entry in the index, once fetched.
+pub fn fetch(
+    client: &OsgeoClient,
+    slug: &str,
+    delay: Duration,
+    refresh: bool,
+    mut progress: impl FnMut(usize, i32),
+) -> Result<Entry> {
+    let server = client.base_url().to_string();
+    path(&server, slug).with_context(|| format!("No place to cache the text of '{}'", slug))?;
+    let mut cached = match load(&server, slug) {
+        Some(cached) if !refresh && !cached.complete() => cached,
+        _ => Cached {
+            server,
+            slug: slug.to_string(),
+            fetched: String::new(),
+            total: 0,
+            chunks: Vec::new(),
+        },
+    };
+    // The chunks already cached, compressed again once per run
+    let (mut blob, mut bytes) = frame(&cached.chunks)?;
+    loop {
+        let batch = client.list_chunks(slug, BATCH, cached.chunks.len() as i32)?;
+        let fetched = batch.chunks.len();
+        let chunks: Vec<Chunk> = batch.chunks.into_iter().map(Chunk::from).collect();
+        let (compressed, size) = frame(&chunks)?;
+        blob.extend(compressed);
+        bytes += size;
+        cached.chunks.extend(chunks);
+        // A document that lost chunks since a partial fetch ends early
+        cached.total = if fetched == 0 { cached.chunks.len() as i32 } else { batch.total };
+        cached.fetched = crate::epub::utc_timestamp();
+        let entry = save(&cached, &blob, bytes)?;
+        progress(cached.chunks.len(), cached.total);
+        if fetched == 0 || cached.complete() {
+            return Ok(entry);
+        }
+        std::thread::sleep(delay);
+    }
+}
+
+/// `chunks` as a zstd frame of JSON lines, and their size uncompressed.
+/// Nothing for no chunks.
+fn frame(chunks: &[Chunk]) -> Result<(Vec<u8>, u64)> {
+    if chunks.is_empty() {
+        return Ok((Vec::new(), 0));
+    }
+    let mut text = Vec::new();
+    for chunk in chunks {
+        serde_json::to_writer(&mut text, chunk).context("Failed to encode cached text")?;
+        text.push(b'\n');
+    }
+    let compressed = zstd::encode_all(text.as_slice(), LEVEL).context("Failed to compress cached text")?;
+    Ok((compressed, text.len() as u64))
+}
+
+/// Replace the blob of `cached` with `blob` (`bytes` uncompressed), then
+/// its entry in the index.
+fn save(cached: &Cached, blob: &[u8], bytes: u64) -> Result<Entry> {
+    let path = path(&cached.server, &cached.slug).context("No cache directory to keep text in")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let temp = path.with_extension(format!("zst.tmp-{}", std::process::id()));
+    std::fs::write(&temp, blob).with_context(|| format!("Failed to write {}", temp.display()))?;
+    std::fs::rename(&temp, &path).with_context(|| {
+        std::fs::remove_file(&temp).ok();
+        format!("Failed to write {}", path.display())
+    })?;
+    let entry = Entry {
+        fetched: cached.fetched.clone(),
+        total: cached.total,
+        chunks: cached.chunks.len(),
+        bytes,
+        stored: blob.len() as u64,
+    };
+    update_index(&cached.server, |documents| {
+        documents.insert(cached.slug.clone(), entry.clone());
+    })?;
+    Ok(entry)
+}
+
+/// The cached text of `slug` from `server`, complete or not.
+pub fn load(server: &str, slug: &str) -> Option<Cached> {
+    let entry = entry(server, slug)?;
+    let blob = std::fs::read(path(server, slug)?).ok()?;
+    // Concatenated frames decode as one text
+    let text = zstd::decode_all(blob.as_slice()).ok()?;
+    let chunks = if text.starts_with(b"[") {
+        // Written whole as one JSON array by earlier versions
+        serde_json::from_slice(&text).ok()?
+    } else {
+        serde_json::Deserializer::from_slice(&text)
+            .into_iter::<Chunk>()
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?
+    };
+    Some(Cached {
+        server: server.to_string(),
+        slug: slug.to_string(),
+        fetched: entry.fetched,
+        total: entry.total,
+        chunks,
+    })
+}
+
+/// The index entry of `slug` from `server`, when it is cached.
+pub fn entry(server: &str, slug: &str) -> Option<Entry> {
+    read_index(server)?.documents.remove(slug)
+}
+
+/// Every document cached from `server`, by slug.
+pub fn list(server: &str) -> Vec<(String, Entry)> {
+    read_index(server).map(|index| index.documents.into_iter().collect()).unwrap_or_default()
+}
+
+/// Forget the cached text of `slug` from `server`; false when there was none.
+pub fn remove(server: &str, slug: &str) -> Result<bool> {
+    let path = path(server, slug).with_context(|| format!("No cached text of '{}'", slug))?;
+    let listed = read_index(server).is_some_and(|index| index.documents.contains_key(slug));
+    if listed {
+        update_index(server, |documents| {
+            documents.remove(slug);
+        })?;
+    }
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(listed),
+        Err(e) => Err(e).with_context(|| format!("Failed to remove {}", path.display())),
+    }
+}
+
+/// What is cached from each server, by URL.
+pub fn stats() -> Vec<Stats> {
+    let Some(dirs) = root().and_then(|d| std::fs::read_dir(d).ok()) else {
+        return Vec::new();
+    };
+    let mut stats: Vec<Stats> = dirs
+        .flatten()
+        .filter_map(|dir| {
+            let index: Index = serde_json::from_str(&std::fs::read_to_string(dir.path().join("index.json")).ok()?).ok()?;
+            let on_disk = std::fs::read_dir(dir.path())
+                .ok()?
+                .flatten()
+                .filter_map(|file| file.metadata().ok())
+                .map(|metadata| metadata.len())
+                .sum();
+            Some(Stats {
+                documents: index.documents.len(),
+                chunks: index.documents.values().map(|e| e.chunks).sum(),
+                bytes: index.documents.values().map(|e| e.bytes).sum(),
+                on_disk,
+                server: index.server,
+            })
+        })
+        .collect();
+    stats.sort_by(|a, b| a.server.cmp(&b.server));
+    stats
+}
+
+fn read_index(server: &str) -> Option<Index> {
+    let text = std::fs::read_to_string(server_dir(server)?.join("index.json")).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+/// Change the documents listed in `server`'s index.
+fn update_index(server: &str, change: impl FnOnce(&mut BTreeMap<String, Entry>)) -> Result<()> {
+    let path = server_dir(server).context("No cache directory to keep text in")?.join("index.json");
+    crate::shared::update(&path, |text| {
+        // An index that can't be read lists what is written from now on
+        let mut index: Index = serde_json::from_str(&text).unwrap_or_default();
+        index.server = server.to_string();
+        change(&mut index.documents);
+        Ok(serde_json::to_string_pretty(&index)?)
+    })
+}
+
+fn root() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("osgeo-library").join("text"))
+}
+
+/// Directory of `server`'s documents: its URL with anything but letters,
+/// digits, '-' and '.' made '_'.
+fn server_dir(server: &str) -> Option<PathBuf> {
+    let name: String = server
+        .trim_end_matches('/')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '.') { c } else { '_' })
+        .collect();
+    Some(root()?.join(name))
+}
+
+fn path(server: &str, slug: &str) -> Option<PathBuf> {
+    // Slugs come from the command line; keep them inside the directory
+    if slug.is_empty() || slug.contains(['/', '\\']) || slug.starts_with('.') {
+        return None;
+    }
+    Some(server_dir(server)?.join(format!("{}.zst", slug)))
+}
@@ -0,0 +1,55 @@
+//! Markdown transcript of a chat session (`save FILE.md` in chat, `chat
+//! --transcript FILE`).
+//!
+//! The same entries as the PDF report (`report`): each question with its
+//! answer and numbered sources ("[2] FIGURE 3-1 | usgs_snyder p.45"), and
+//! the images viewed with `show`, linked to the server. Plain Markdown, for
+//! reviewing a session later or pasting it into an issue or wiki page.
+
+use crate::report::{Entry, Report};
+
+/// `report` as a Markdown document.
+pub fn markdown(report: &Report) -> String {
+    let mut out = format!("# {}\n\n", report.title);
+    // Trailing double spaces keep the lines apart as hard breaks
+    out.push_str(&report.meta.join("  \n"));
+    out.push_str("\n\n");
+
+    let mut question = 0;
+    for entry in report.entries {
+        match entry {
+            Entry::Exchange {
+                question: text,
+                answer,
+                sources,
+            } => {
+                question += 1;
+                out.push_str(&format!("## Question {}\n\n", question));
+                for line in text.lines() {
+                    match line.trim_end() {
+                        "" => out.push_str(">\n"),
+                        line => out.push_str(&format!("> {}\n", line)),
+                    }
+                }
+                out.push_str(&format!("\n{}\n\n", answer.trim()));
+                if !sources.is_empty() {
+                    out.push_str("**Sources**\n\n");
+                    for source in sources {
+                        out.push_str(&format!("- {}\n", source));
+                    }
+                    out.push('\n');
+                }
+            }
+            Entry::Image { caption, url } => {
+                out.push_str(&format!("![{}]({})\n\n", escape(caption), url.replace(' ', "%20")));
+            }
+        }
+    }
+    out
+}
+
+/// `text` with brackets escaped, for image descriptions (the "[2]" of a
+/// caption would end the description early).
+fn escape(text: &str) -> String {
+    text.replace('[', "\\[").replace(']', "\\]")
+}
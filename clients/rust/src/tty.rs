@@ -0,0 +1,95 @@
+//! Terminal modes switched on by interactive features, and switched back.
+//!
+//! Raw mode, the alternate screen and scroll regions outlive the program if
+//! nothing undoes them, leaving a shell that doesn't echo or scrolls in half
+//! the window. Every feature that changes them does it through a `Guard`,
+//! which undoes what it switched on when dropped: on return, on error and
+//! while unwinding from a panic. What is on at any moment is also recorded
+//! here, so the panic hook (`crash`) and Ctrl-C (`cancel`), which end the
+//! program without dropping anything, can `restore` the terminal first.
+
+use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+const RAW_MODE: u8 = 1;
+const ALTERNATE_SCREEN: u8 = 1 << 1;
+const SCROLL_REGION: u8 = 1 << 2;
+
+/// Modes currently on
+static ACTIVE: AtomicU8 = AtomicU8::new(0);
+
+/// Undoes the modes switched on through it when dropped. Modes that were
+/// already on (by an enclosing guard) are left to that guard.
+#[derive(Default)]
+pub struct Guard(u8);
+
+impl Guard {
+    /// Raw mode: keys are read one at a time, without echo.
+    pub fn raw_mode(&mut self) -> std::io::Result<()> {
+        self.switch_on(RAW_MODE, crossterm::terminal::enable_raw_mode)
+    }
+
+    /// The alternate screen, which takes the whole window and gives back
+    /// the shell's scrollback when left.
+    pub fn alternate_screen(&mut self) -> std::io::Result<()> {
+        self.switch_on(ALTERNATE_SCREEN, || crossterm::execute!(std::io::stdout(), EnterAlternateScreen))
+    }
+
+    /// Restrict scrolling to rows `top` to `bottom` (0-based, inclusive).
+    pub fn scroll_region(&mut self, top: u16, bottom: u16) -> std::io::Result<()> {
+        // DECSTBM takes 1-based rows
+        let mut out = std::io::stdout();
+        write!(out, "\x1b[{};{}r", top + 1, bottom + 1)?;
+        out.flush()?;
+        if ACTIVE.fetch_or(SCROLL_REGION, Ordering::SeqCst) & SCROLL_REGION == 0 {
+            self.0 |= SCROLL_REGION;
+        }
+        Ok(())
+    }
+
+    fn switch_on(&mut self, mode: u8, on: impl FnOnce() -> std::io::Result<()>) -> std::io::Result<()> {
+        if ACTIVE.load(Ordering::SeqCst) & mode != 0 {
+            return Ok(());
+        }
+        on()?;
+        ACTIVE.fetch_or(mode, Ordering::SeqCst);
+        self.0 |= mode;
+        Ok(())
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        undo(self.0);
+    }
+}
+
+/// Switch off every mode that is on, show the cursor and reset colors.
+/// For ending the program without unwinding (a panic, Ctrl-C).
+pub fn restore() {
+    undo(u8::MAX);
+    let mut out = std::io::stdout();
+    if !out.is_terminal() {
+        return;
+    }
+    let _ = crossterm::execute!(out, crossterm::cursor::Show);
+    let _ = write!(out, "\x1b[0m");
+    let _ = out.flush();
+}
+
+/// Switch off those of `modes` that are still on, latest first.
+fn undo(modes: u8) {
+    let on = ACTIVE.fetch_and(!modes, Ordering::SeqCst) & modes;
+    let mut out = std::io::stdout();
+    if on & SCROLL_REGION != 0 {
+        let _ = write!(out, "\x1b[r");
+    }
+    if on & ALTERNATE_SCREEN != 0 {
+        let _ = crossterm::execute!(out, LeaveAlternateScreen);
+    }
+    let _ = out.flush();
+    if on & RAW_MODE != 0 {
+        let _ = crossterm::terminal::disable_raw_mode();
+    }
+}
@@ -0,0 +1,455 @@
+//! Request and response types of the OSGeo Library REST API.
+//!
+//! They mirror the server's schema (see docs/API.md); not every field is
+//! used by the command-line client. Requests are built with struct literals,
+//! so every field is public.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Body of `POST /search`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SearchRequest {
+    pub query: String,
+    pub limit: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_slug: Option<String>,
+    pub include_chunks: bool,
+    pub include_elements: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub element_type: Option<String>,
+    /// Match the query literally against element labels only
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub labels_only: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude_documents: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude_ids: Vec<String>,
+    /// Leave out results scoring below this (0-100%)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_score: Option<f64>,
+}
+
+/// Body of `POST /similar`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SimilarRequest {
+    /// "element:<id>", "chunk:<id>" or "<slug>:<page>"
+    pub source: String,
+    pub limit: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_slug: Option<String>,
+    pub include_chunks: bool,
+    pub include_elements: bool,
+}
+
+/// Body of `POST /chat`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChatRequest {
+    pub question: String,
+    pub limit: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_slug: Option<String>,
+    /// Several documents to search (overrides `document_slug`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub document_slugs: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Sources always included in the context (see `SearchResult::key`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pinned_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude_documents: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude_ids: Vec<String>,
+    /// Max context tokens; the server drops the lowest-ranked sources to fit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_budget: Option<i32>,
+    /// The user's own text (a draft, notes), answered alongside the sources
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_context: Option<String>,
+}
+
+/// A text chunk or an extracted element (figure, table, equation...).
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct SearchResult {
+    pub id: i64,
+    pub score_pct: f64,
+    pub content: String,
+    pub source_type: String,
+    pub document_slug: String,
+    pub document_title: String,
+    pub page_number: i32,
+    pub element_type: Option<String>,
+    pub element_label: Option<String>,
+    pub crop_path: Option<String>,
+    pub rendered_path: Option<String>,  // For equations: LaTeX-rendered image
+    pub image_width: Option<i32>,       // Image dimensions for proportional display
+    pub image_height: Option<i32>,
+    pub chunk_index: Option<i32>,
+    /// Score (0-100%) per retrieval method that found the result
+    #[serde(default)]
+    pub score_breakdown: std::collections::HashMap<String, f64>,
+    /// Library the result came from in a federated search (set by the
+    /// client, see `federation`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub library: Option<String>,
+}
+
+impl SearchResult {
+    /// Key identifying this result across requests ("element:12", "chunk:34").
+    pub fn key(&self) -> String {
+        format!("{}:{}", self.source_type, self.id)
+    }
+
+    /// Get the best image path for display.
+    /// For equations, prefer rendered_path (clean LaTeX) over crop_path (raw crop).
+    pub fn best_image_path(&self) -> Option<&str> {
+        // For equations, prefer rendered version if available
+        if self.element_type.as_deref() == Some("equation") {
+            if let Some(ref rendered) = self.rendered_path {
+                return Some(rendered.as_str());
+            }
+        }
+        // Fall back to crop_path for all other types or if rendered not available
+        self.crop_path.as_deref()
+    }
+    
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct SearchResponse {
+    pub query: String,
+    pub results: Vec<SearchResult>,
+    pub total: i32,
+    /// Results left out for scoring below the request's `min_score`
+    #[serde(default)]
+    pub below_min_score: usize,
+}
+
+impl SearchResponse {
+    /// Drop results scoring below `min_score`, as older servers don't, and
+    /// count them in `below_min_score`.
+    pub fn drop_below(&mut self, min_score: Option<f64>) {
+        let Some(min_score) = min_score else {
+            return;
+        };
+        let before = self.results.len();
+        self.results.retain(|r| r.score_pct >= min_score);
+        self.below_min_score += before - self.results.len();
+        self.total = self.results.len() as i32;
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ChatResponse {
+    /// Empty in the "meta" event of a streamed answer
+    #[serde(default)]
+    pub answer: String,
+    pub sources: Vec<SearchResult>,
+    pub query_used: String,
+    /// Estimated token use of the context (absent on older servers)
+    pub context: Option<ContextUsage>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ContextUsage {
+    pub context_tokens: i32,
+    pub prompt_tokens: i32,
+    pub context_window: i32,
+    pub budget: Option<i32>,
+    #[serde(default)]
+    pub dropped_sources: i32,
+    #[serde(default)]
+    pub truncated_sources: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyRequest {
+    pub statement: String,
+    pub limit: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_slug: Option<String>,
+}
+
+/// An answer found using one document, sent for synthesis.
+#[derive(Debug, Serialize)]
+pub struct DocumentAnswer {
+    pub document_slug: String,
+    pub document_title: String,
+    pub answer: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SynthesisRequest {
+    pub question: String,
+    pub answers: Vec<DocumentAnswer>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SynthesisResponse {
+    pub answer: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyResponse {
+    pub verdict: String,
+    pub explanation: String,
+    pub sources: Vec<SearchResult>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcronymSource {
+    pub document_slug: String,
+    pub document_title: String,
+    pub page_number: i32,
+    pub chunk_id: i64,
+    pub snippet: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcronymExpansion {
+    pub expansion: String,
+    pub count: i32,
+    pub documents: i32,
+    pub sources: Vec<AcronymSource>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcronymResponse {
+    pub acronym: String,
+    pub expansions: Vec<AcronymExpansion>,
+    pub chunks_scanned: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HealthResponse {
+    pub status: String,
+    pub embedding_server: bool,
+    pub llm_server: bool,
+    pub database: bool,
+    pub version: String,
+}
+
+/// Optional features of a server (`GET /capabilities`), e.g. "chat_stream",
+/// "ingest".
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CapabilitiesResponse {
+    pub version: String,
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct DocumentListItem {
+    pub slug: String,
+    pub title: String,
+    pub source_file: Option<String>,
+    pub total_pages: i32,
+    pub summary: Option<String>,
+    pub keywords: Option<Vec<String>>,
+    pub license: Option<String>,
+    /// MD5 of the document's text, only when asked for (see
+    /// `OsgeoClient::list_documents_with_checksums`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_checksum: Option<String>,
+    /// SHA-256 of the source PDF, for documents ingested since servers
+    /// started recording it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_sha256: Option<String>,
+}
+
+/// Filters of the document list (`GET /documents`), each matching part of
+/// the field, ignoring case; a document must pass all of those given.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DocumentFilter {
+    /// One of the document's keywords contains this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keyword: Option<String>,
+    /// The document's license contains this ("CC-BY" matches "CC-BY-4.0")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// The document's title or slug contains this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search: Option<String>,
+}
+
+impl DocumentFilter {
+    pub fn is_empty(&self) -> bool {
+        self.keyword.is_none() && self.license.is_none() && self.search.is_none()
+    }
+
+    /// Whether `doc` passes, as the server would decide.
+    pub fn matches(&self, doc: &DocumentListItem) -> bool {
+        let contains = |text: &str, part: &str| text.to_lowercase().contains(&part.to_lowercase());
+        let keyword = self.keyword.as_deref().is_none_or(|k| {
+            doc.keywords.iter().flatten().any(|keyword| contains(keyword, k))
+        });
+        let license = self
+            .license
+            .as_deref()
+            .is_none_or(|l| doc.license.as_deref().is_some_and(|license| contains(license, l)));
+        let search = self
+            .search
+            .as_deref()
+            .is_none_or(|s| contains(&doc.title, s) || contains(&doc.slug, s));
+        keyword && license && search
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct DocumentListResponse {
+    pub documents: Vec<DocumentListItem>,
+    pub page: i32,
+    pub page_size: i32,
+    pub total_pages: i32,
+    pub total_documents: i32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DocumentDetailResponse {
+    pub slug: String,
+    pub title: String,
+    pub source_file: Option<String>,
+    pub total_pages: i32,
+    pub summary: Option<String>,
+    pub keywords: Option<Vec<String>>,
+    pub license: Option<String>,
+    pub extraction_date: Option<String>,
+    pub element_counts: std::collections::HashMap<String, i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ElementListItem {
+    pub id: i64,
+    pub element_type: String,
+    pub label: Option<String>,
+    pub page_number: i32,
+    pub description: Option<String>,
+    pub crop_path: Option<String>,
+    pub rendered_path: Option<String>,
+    pub latex: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ElementListResponse {
+    pub document_slug: String,
+    pub elements: Vec<ElementListItem>,
+    pub total: i32,
+    pub limit: i32,
+    pub offset: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PageSummaryItem {
+    pub page_number: i32,
+    pub summary: Option<String>,
+    pub keywords: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PageSummaryListResponse {
+    pub document_slug: String,
+    pub pages: Vec<PageSummaryItem>,
+    pub total: i32,
+    pub limit: i32,
+    pub offset: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChunkListItem {
+    pub id: i64,
+    pub page_number: i32,
+    pub chunk_index: i32,
+    pub start_char: Option<i32>,
+    pub content: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChunkListResponse {
+    pub document_slug: String,
+    pub chunks: Vec<ChunkListItem>,
+    pub total: i32,
+    pub limit: i32,
+    pub offset: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DocumentDeleteResponse {
+    pub slug: String,
+    pub title: String,
+    pub source_file: Option<String>,
+}
+
+/// An uploaded PDF going through extraction, enrichment and ingestion on
+/// the server (`OsgeoClient::ingest_document`).
+#[derive(Debug, Deserialize)]
+pub struct IngestJob {
+    pub job_id: String,
+    pub slug: String,
+    pub source_file: String,
+    pub sha256: String,
+    /// queued, extracting, enriching, ingesting, done or failed
+    pub status: String,
+    #[serde(default)]
+    pub pages_done: i32,
+    #[serde(default)]
+    pub pages_total: i32,
+    pub error: Option<String>,
+}
+
+impl IngestJob {
+    /// Whether the job has stopped, successfully or not.
+    pub fn finished(&self) -> bool {
+        self.status == "done" || self.status == "failed"
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChapterItem {
+    pub number: i32,
+    pub title: Option<String>,
+    pub start_page: i32,
+    pub end_page: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChapterListResponse {
+    pub document_slug: String,
+    pub chapters: Vec<ChapterItem>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SectionItem {
+    /// "4" for a chapter, "4.2" or "4.2.1" for sections
+    pub number: String,
+    pub title: Option<String>,
+    /// 1 for chapters, 2 for 4.2, 3 for 4.2.1, ...
+    pub level: i32,
+    pub start_page: i32,
+    pub end_page: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SectionListResponse {
+    pub document_slug: String,
+    pub sections: Vec<SectionItem>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PageResponse {
+    pub document_slug: String,
+    pub document_title: String,
+    pub page_number: i32,
+    pub total_pages: i32,
+    /// Empty when the image was sent as raw bytes; use `image`
+    #[serde(default)]
+    pub image_base64: String,
+    pub image_width: i32,
+    pub image_height: i32,
+    pub mime_type: String,
+    pub has_annotated: bool,
+    pub summary: Option<String>,
+    pub keywords: Option<Vec<String>>,
+    /// Decoded image, however the server sent it (filled in by the client)
+    #[serde(skip)]
+    pub image: Vec<u8>,
+}
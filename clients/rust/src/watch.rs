@@ -0,0 +1,97 @@
+//! Looking up what is copied (`watch-clipboard`).
+//!
+//! While writing, copying a phrase from a PDF or a web page is enough to
+//! see where the library covers it: the clipboard is read every `POLL_MS`,
+//! and each new short text is searched for, with the top hits printed.
+//! Line breaks copied out of PDFs are joined into one line first. Longer
+//! texts (pasting a paragraph into an editor, say) are skipped, as are
+//! copies without a letter in them. What is on the clipboard when watching
+//! starts isn't looked up. Ctrl-C stops.
+
+use crate::format::format_result;
+use crate::types::SearchRequest;
+use crate::{clipboard, term, OsgeoClient};
+use anyhow::Result;
+use colored::Colorize;
+use std::time::Duration;
+
+/// How often the clipboard is read
+const POLL_MS: u64 = 500;
+
+/// What to search and how many hits to print.
+pub struct Options {
+    pub document: Option<String>,
+    pub limit: i32,
+    /// Longest copied text looked up, in characters
+    pub max_chars: usize,
+}
+
+/// Search for each short text copied, until Ctrl-C.
+pub fn run(client: &OsgeoClient, options: &Options) -> Result<()> {
+    let mut last = clipboard::text()?;
+    println!(
+        "{}",
+        format!(
+            "Watching the clipboard: copy a phrase (up to {} characters) to look it up{}. Ctrl-C stops.",
+            options.max_chars,
+            options.document.as_deref().map(|d| format!(" in {}", d)).unwrap_or_default()
+        )
+        .dimmed()
+    );
+    loop {
+        std::thread::sleep(Duration::from_millis(POLL_MS));
+        // Another program holding the clipboard: try again next time
+        let Ok(copied) = clipboard::text() else {
+            continue;
+        };
+        if copied == last {
+            continue;
+        }
+        last = copied;
+        let Some(text) = last.as_deref() else {
+            continue;
+        };
+        match query(text, options.max_chars) {
+            Ok(query) => look_up(client, options, &query),
+            Err(reason) => term::hint(&format!("Copied {}; not looked up.", reason)),
+        }
+    }
+}
+
+/// The copied text as one line to search for, or why it isn't one.
+fn query(text: &str, max_chars: usize) -> Result<String, String> {
+    let query = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let chars = query.chars().count();
+    if chars > max_chars {
+        return Err(format!("{} characters", chars));
+    }
+    if !query.chars().any(char::is_alphabetic) {
+        return Err("text without words".to_string());
+    }
+    Ok(query)
+}
+
+fn look_up(client: &OsgeoClient, options: &Options, query: &str) {
+    println!("\n{} {}", "Copied:".bold(), query);
+    let request = SearchRequest {
+        query: query.to_string(),
+        limit: options.limit,
+        document_slug: options.document.clone(),
+        include_chunks: true,
+        include_elements: true,
+        ..Default::default()
+    };
+    match client.search(request) {
+        Ok(response) if response.results.is_empty() => println!("No matches.\n"),
+        Ok(response) => {
+            for (i, result) in response.results.iter().enumerate() {
+                println!("{}", format_result(i + 1, result, false));
+            }
+            println!();
+        }
+        Err(e) => {
+            term::warning(&format!("search failed: {:#}", e));
+            println!();
+        }
+    }
+}
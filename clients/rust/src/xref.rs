@@ -0,0 +1,123 @@
+//! Cross-references inside a document ("see Figure 12", "Table 3.2",
+//! "Eq. (4-7)").
+//!
+//! Text chunks point at figures, tables and equations by label, and finding
+//! them meant a search by hand. References are detected here and matched
+//! against element labels, which are written the same way ("Figure 12",
+//! "Fig. 12: Tissot indicatrix"). Only the number has to agree: "Fig. 12"
+//! finds "Figure 12", but not "Figure 12.3" or "Figure 112".
+
+use regex_lite::Regex;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// Element kind word, then a number such as 12, 3.2, 4-7 or 12a
+/// (optionally in parentheses, as equation numbers often are).
+const PATTERN: &str = concat!(
+    r"(?i)\b(fig(?:ure)?s?|tab(?:le)?s?|eq(?:uation|n)?s?)\b\.?\s*",
+    r"\(?(\d+(?:[.\-–]\d+)*[a-z]?)\b\)?",
+);
+
+fn regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(PATTERN).expect("reference pattern is valid"))
+}
+
+/// A reference to an element by kind and number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// "figure", "table" or "equation"
+    pub kind: &'static str,
+    pub number: String,
+}
+
+impl Reference {
+    /// The reference written out, e.g. "Figure 12".
+    pub fn label(&self) -> String {
+        let mut kind = self.kind.to_string();
+        kind[..1].make_ascii_uppercase();
+        format!("{} {}", kind, self.number)
+    }
+
+    /// Whether an element of `element_type` labelled `label` is the one
+    /// referred to. Charts and diagrams are referred to as figures.
+    pub fn matches(&self, element_type: &str, label: &str) -> bool {
+        let kind_matches = match self.kind {
+            "figure" => matches!(element_type, "figure" | "chart" | "diagram"),
+            kind => element_type == kind,
+        };
+        kind_matches && label_number(label).is_some_and(|n| same_number(&n, &self.number))
+    }
+}
+
+/// The first reference in `text` ("xref Fig. 12" arguments).
+pub fn parse(text: &str) -> Option<Reference> {
+    find(text).into_iter().next()
+}
+
+/// References in `text`, in order of first mention.
+pub fn find(text: &str) -> Vec<Reference> {
+    let mut found: Vec<Reference> = Vec::new();
+    for (_, reference) in mentions(text) {
+        if !found.iter().any(|r| r.kind == reference.kind && same_number(&r.number, &reference.number)) {
+            found.push(reference);
+        }
+    }
+    found
+}
+
+/// Every reference in `text` with where it is written.
+fn mentions(text: &str) -> Vec<(Range<usize>, Reference)> {
+    regex()
+        .captures_iter(text)
+        .map(|caps| {
+            let word = caps[1].to_lowercase();
+            let kind = if word.starts_with("fig") {
+                "figure"
+            } else if word.starts_with("tab") {
+                "table"
+            } else {
+                "equation"
+            };
+            let reference = Reference {
+                kind,
+                number: caps[2].replace('–', "-"),
+            };
+            (caps.get(0).expect("match").range(), reference)
+        })
+        .collect()
+}
+
+/// Where an element labelled `label` is mentioned in `text`: as a reference
+/// of the same kind and number ("Fig. 12" for "Figure 12: Tissot
+/// indicatrix"), or for labels without one, the label itself (ignoring case
+/// and line breaks).
+pub fn find_label(text: &str, label: &str) -> Option<Range<usize>> {
+    if let Some(wanted) = parse(label) {
+        return mentions(text)
+            .into_iter()
+            .find(|(_, r)| r.kind == wanted.kind && same_number(&r.number, &wanted.number))
+            .map(|(range, _)| range);
+    }
+    let words: Vec<String> = label.split_whitespace().map(regex_lite::escape).collect();
+    if words.is_empty() {
+        return None;
+    }
+    let pattern = Regex::new(&format!("(?i){}", words.join(r"\s+"))).ok()?;
+    pattern.find(text).map(|m| m.range())
+}
+
+/// Number of an element label: that of its reference ("Figure 12: ...") or,
+/// for bare labels like "(4-7)", the first number in it.
+pub fn label_number(label: &str) -> Option<String> {
+    static NUMBER: OnceLock<Regex> = OnceLock::new();
+    if let Some(reference) = parse(label) {
+        return Some(reference.number);
+    }
+    let number = NUMBER.get_or_init(|| Regex::new(r"\d+(?:[.\-–]\d+)*[a-zA-Z]?\b").expect("number pattern is valid"));
+    number.find(label).map(|m| m.as_str().replace('–', "-"))
+}
+
+pub fn same_number(a: &str, b: &str) -> bool {
+    a.eq_ignore_ascii_case(b)
+}
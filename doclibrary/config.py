@@ -27,6 +27,7 @@ Environment variables:
     DOCLIBRARY_DB_HOST          - Database host
     DOCLIBRARY_DB_PORT          - Database port
     DOCLIBRARY_DB_USER          - Database user
+    DOCLIBRARY_ALLOW_DELETE     - Allow deleting documents through the API (1/true)
 """
 
 import os
@@ -58,6 +59,7 @@ class Config:
     llm_api_key: str = ""
     llm_temperature: float = 0.3
     llm_max_tokens: int = 1024
+    llm_context_window: int = 32768
 
     # Vision LLM (extraction)
     vision_llm_url: str = "http://localhost:8090/v1"
@@ -83,6 +85,12 @@ class Config:
     data_dir: str = "db/data"
     cache_dir: str = "/tmp/doclibrary_cache"  # For chat bridge image caching
 
+    # API server
+    allow_delete: bool = False  # DELETE /documents/{slug}; the API has no authentication
+    allow_ingest: bool = False  # POST /ingest (PDF upload); same caveat
+    max_upload_mb: int = 500  # POST /ingest refuses larger PDFs (413)
+    allow_download: bool = False  # GET /documents/{slug}/file (original PDFs)
+
     # Display (chafa terminal preview)
     chafa_size: str = "80x35"
     chafa_size_equation: str = "100x20"
@@ -137,6 +145,7 @@ def load_config() -> Config:
                 config.llm_api_key = llm.get("api_key", config.llm_api_key)
                 config.llm_temperature = llm.get("temperature", config.llm_temperature)
                 config.llm_max_tokens = llm.get("max_tokens", config.llm_max_tokens)
+                config.llm_context_window = llm.get("context_window", config.llm_context_window)
 
             # Vision LLM section
             if "vision_llm" in data:
@@ -181,6 +190,15 @@ def load_config() -> Config:
                 if "cache_dir" in paths:
                     config.cache_dir = paths["cache_dir"]
 
+            # Server section
+            if "server" in data:
+                config.allow_delete = bool(data["server"].get("allow_delete", config.allow_delete))
+                config.allow_ingest = bool(data["server"].get("allow_ingest", config.allow_ingest))
+                config.max_upload_mb = int(data["server"].get("max_upload_mb", config.max_upload_mb))
+                config.allow_download = bool(
+                    data["server"].get("allow_download", config.allow_download)
+                )
+
             # Display section
             if "display" in data:
                 display = data["display"]
@@ -214,13 +232,19 @@ def load_config() -> Config:
         "DOCLIBRARY_DB_USER": "db_user",
         "DOCLIBRARY_DB_PASSWORD": "db_password",
         "DOCLIBRARY_CHAFA_SIZE": "chafa_size",
+        "DOCLIBRARY_ALLOW_DELETE": "allow_delete",
+        "DOCLIBRARY_ALLOW_INGEST": "allow_ingest",
+        "DOCLIBRARY_MAX_UPLOAD_MB": "max_upload_mb",
+        "DOCLIBRARY_ALLOW_DOWNLOAD": "allow_download",
     }
 
     for env_var, attr in env_mappings.items():
         value = os.environ.get(env_var)
         if value is not None:
-            if attr == "embed_dimensions":
+            if attr in ("embed_dimensions", "max_upload_mb"):
                 value = int(value)
+            elif attr in ("allow_delete", "allow_ingest", "allow_download"):
+                value = value.strip().lower() in ("1", "true", "yes")
             setattr(config, attr, value)
             if config.config_source == "defaults":
                 config.config_source = "environment"
@@ -278,6 +302,13 @@ if __name__ == "__main__":
     print(f"  cache_dir: {config.cache_dir}")
     print()
 
+    print("[Server]")
+    print(f"  allow_delete: {config.allow_delete}")
+    print(f"  allow_ingest: {config.allow_ingest}")
+    print(f"  max_upload_mb: {config.max_upload_mb}")
+    print(f"  allow_download: {config.allow_download}")
+    print()
+
     print("[Display]")
     print(f"  chafa_size: {config.chafa_size}")
     print(f"  chafa_size_equation: {config.chafa_size_equation}")
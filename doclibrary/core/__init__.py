@@ -7,7 +7,7 @@ from .constants import (
     ELEMENT_TYPES,
     ELEMENT_TAG_MAP,
 )
-from .llm import LLMClient, query_llm, check_llm_health, strip_think_tags
+from .llm import LLMClient, query_llm, stream_llm, check_llm_health, strip_think_tags
 from .formatting import format_context_for_llm, get_source_tag, format_sources_list
 from .text import (
     extract_latex_from_description,
@@ -15,6 +15,7 @@ from .text import (
     normalize_whitespace,
     extract_keywords,
     truncate_text,
+    find_acronym_definitions,
 )
 from .image import create_annotated_image, crop_element, render_latex_to_image
 
@@ -28,6 +29,7 @@ __all__ = [
     # LLM
     "LLMClient",
     "query_llm",
+    "stream_llm",
     "check_llm_health",
     "strip_think_tags",
     # Formatting
@@ -40,6 +42,7 @@ __all__ = [
     "normalize_whitespace",
     "extract_keywords",
     "truncate_text",
+    "find_acronym_definitions",
     # Image
     "create_annotated_image",
     "crop_element",
@@ -12,6 +12,31 @@ Rules:
 Example: "The Mercator projection [1] preserves angles, making it useful for navigation [2]."
 """
 
+VERIFY_SYSTEM_PROMPT = """You are a careful fact-checker. Judge a statement using ONLY the provided context.
+
+Reply in exactly this format:
+VERDICT: SUPPORTED | CONTRADICTED | NOT_FOUND
+<1-2 paragraphs explaining the verdict, citing sources inline using [1], [2], [3] etc.>
+
+Rules:
+- SUPPORTED: the context clearly backs the statement
+- CONTRADICTED: the context clearly disagrees with the statement (explain how)
+- NOT_FOUND: the context does not settle it either way
+- Quote the decisive numbers or wording when there are any
+"""
+
+SYNTHESIS_SYSTEM_PROMPT = """You are a research assistant combining answers drawn from different documents.
+
+You receive a question and one answer per document, each found using only that document.
+
+Rules:
+- Merge them into one CONCISE answer (2-4 paragraphs)
+- Attribute every claim to its document(s) with the slug in brackets, e.g. [usgs_snyder]
+- Point out where documents disagree or use different conventions
+- Skip documents whose answer says they don't cover the question
+- Do NOT add information that is not in the answers
+"""
+
 # Element types recognized by the extraction pipeline
 ELEMENT_TYPES = frozenset(
     {
@@ -1,7 +1,8 @@
 """LLM client utilities for doclibrary."""
 
+import json
 import re
-from typing import Dict, List, Optional
+from typing import Dict, Iterator, List, Optional
 
 import requests
 
@@ -23,6 +24,63 @@ def strip_think_tags(text: str) -> str:
     return re.sub(r"<think>.*?</think>\s*", "", text, flags=re.DOTALL).strip()
 
 
+class ThinkFilter:
+    """Incremental counterpart of strip_think_tags for streamed text.
+
+    Feed chunks as they arrive; text inside <think>...</think> is dropped,
+    and a possible partial tag at the end of a chunk is held back until the
+    next one decides it.
+    """
+
+    OPEN = "<think>"
+    CLOSE = "</think>"
+
+    def __init__(self):
+        self.pending = ""
+        self.in_think = False
+        self.started = False  # Leading whitespace of the answer is dropped
+
+    def feed(self, chunk: str) -> str:
+        self.pending += chunk
+        out = []
+        while self.pending:
+            if self.in_think:
+                end = self.pending.find(self.CLOSE)
+                if end < 0:
+                    # Keep only what could be the start of the closing tag
+                    self.pending = self.pending[-(len(self.CLOSE) - 1) :]
+                    break
+                self.pending = self.pending[end + len(self.CLOSE) :].lstrip()
+                self.in_think = False
+                continue
+            start = self.pending.find(self.OPEN)
+            if start >= 0:
+                out.append(self.pending[:start])
+                self.pending = self.pending[start + len(self.OPEN) :]
+                self.in_think = True
+                continue
+            # Hold back a trailing "<", "<th"... that may begin a tag
+            hold = next(
+                (n for n in range(len(self.OPEN) - 1, 0, -1) if self.pending.endswith(self.OPEN[:n])),
+                0,
+            )
+            out.append(self.pending[: len(self.pending) - hold])
+            self.pending = self.pending[len(self.pending) - hold :]
+            break
+        return self._start("".join(out))
+
+    def flush(self) -> str:
+        rest = "" if self.in_think else self.pending
+        self.pending = ""
+        return self._start(rest)
+
+    def _start(self, text: str) -> str:
+        if not self.started:
+            text = text.lstrip()
+            self.started = bool(text)
+        return text
+
+
 class LLMClient:
     """OpenAI-compatible LLM client.
 
@@ -94,6 +152,57 @@ class LLMClient:
         # Remove thinking tags if present
         return strip_think_tags(content)
 
+    def chat_stream(
+        self,
+        messages: List[Dict[str, str]],
+        temperature: Optional[float] = None,
+        max_tokens: Optional[int] = None,
+    ) -> Iterator[str]:
+        """Send chat request to LLM and yield the answer as it is generated.
+
+        Uses the OpenAI-compatible server-sent events stream ("stream": true).
+        Thinking tags are removed as in chat().
+
+        Raises:
+            Exception: On API errors
+        """
+        headers = {"Content-Type": "application/json"}
+        if self.api_key:
+            headers["Authorization"] = f"Bearer {self.api_key}"
+            headers["HTTP-Referer"] = "https://github.com/ominiverdi/osgeo-library"
+
+        response = requests.post(
+            self.url,
+            json={
+                "model": self.model,
+                "messages": messages,
+                "temperature": temperature or self.temperature,
+                "max_tokens": max_tokens or self.max_tokens,
+                "stream": True,
+            },
+            headers=headers,
+            timeout=120,
+            stream=True,
+        )
+        response.raise_for_status()
+
+        think = ThinkFilter()
+        for line in response.iter_lines(decode_unicode=True):
+            if not line or not line.startswith("data:"):
+                continue
+            data = line[len("data:") :].strip()
+            if data == "[DONE]":
+                break
+            choices = json.loads(data).get("choices") or [{}]
+            delta = choices[0].get("delta", {}).get("content")
+            if delta:
+                text = think.feed(delta)
+                if text:
+                    yield text
+        rest = think.flush()
+        if rest:
+            yield rest
+
     def check_health(self) -> bool:
         """Check if LLM server is reachable.
 
@@ -186,3 +295,28 @@ def query_llm(
         return client.chat(messages)
     except Exception as e:
         return f"Error querying LLM: {e}"
+
+
+def stream_llm(
+    messages: List[Dict[str, str]],
+    url: str,
+    model: str,
+    api_key: str = "",
+    temperature: float = 0.3,
+    max_tokens: int = 1024,
+) -> Iterator[str]:
+    """Streaming variant of query_llm: yields the answer in pieces.
+
+    Errors are yielded as text, like query_llm returns them.
+    """
+    try:
+        client = LLMClient(
+            url=url,
+            model=model,
+            api_key=api_key,
+            temperature=temperature,
+            max_tokens=max_tokens,
+        )
+        yield from client.chat_stream(messages)
+    except Exception as e:
+        yield f"Error querying LLM: {e}"
@@ -1,7 +1,7 @@
 """Text processing utilities for doclibrary."""
 
 import re
-from typing import Optional
+from typing import List, Optional, Tuple
 
 
 def extract_latex_from_description(description: str) -> Optional[str]:
@@ -149,3 +149,70 @@ def truncate_text(text: str, max_length: int = 500, suffix: str = "...") -> str:
         truncated = truncated[:last_space]
 
     return truncated + suffix
+
+
+def _match_long_form(acronym: str, candidate: str) -> Optional[str]:
+    """Find the shortest tail of `candidate` that spells out `acronym`.
+
+    Follows Schwartz & Hearst (2003): acronym letters are matched right to
+    left inside the candidate, and the first letter must start a word.
+    """
+    letters = [c.lower() for c in acronym if c.isalnum()]
+    if not letters:
+        return None
+    text = candidate.lower()
+    pos = len(text) - 1
+    for i in range(len(letters) - 1, -1, -1):
+        letter = letters[i]
+        while pos >= 0:
+            at_word_start = pos == 0 or not text[pos - 1].isalnum()
+            if text[pos] == letter and (i > 0 or at_word_start):
+                break
+            pos -= 1
+        if pos < 0:
+            return None
+        pos -= 1
+    long_form = candidate[pos + 1 :].strip(" ,;:-")
+    # One-word matches like "Sam" for "SAM" are not expansions
+    if len(long_form.split()) < 2 and "-" not in long_form:
+        return None
+    return long_form
+
+
+def find_acronym_definitions(text: str, acronym: str) -> List[Tuple[str, int]]:
+    """Find definitions of `acronym` in text.
+
+    Recognizes "Long Form (ACR)" and "ACR (Long Form)". The long form must
+    spell out the acronym's letters in order, so unrelated parentheticals
+    are ignored.
+
+    Args:
+        text: Text to scan
+        acronym: Acronym to define, matched case-sensitively
+
+    Returns:
+        (expansion, character offset) pairs in order of appearance
+    """
+    found = []
+    escaped = re.escape(acronym)
+    max_words = min(len(acronym) + 5, len(acronym) * 2)
+
+    # Long Form (ACR)
+    for m in re.finditer(rf"\(\s*{escaped}s?\s*[),;]", text):
+        before = text[: m.start()].rstrip()
+        words = re.split(r"(?<=\s)", before)[-max_words:]
+        candidate = "".join(words)
+        # Don't reach back across a sentence or clause boundary
+        candidate = re.split(r"[.;:()\[\]]\s", candidate)[-1]
+        long_form = _match_long_form(acronym, candidate)
+        if long_form:
+            found.append((" ".join(long_form.split()), m.start()))
+
+    # ACR (Long Form)
+    for m in re.finditer(rf"(?<![\w-]){escaped}s?\s*\(([^()]{{3,120}})\)", text):
+        inner = re.split(r"[;,]|\be\.g\.|\bi\.e\.", m.group(1))[0].strip()
+        long_form = _match_long_form(acronym, inner)
+        if long_form and long_form.lower().startswith(inner.lower()[:1]):
+            found.append((" ".join(long_form.split()), m.start()))
+
+    return sorted(found, key=lambda item: item[1])
@@ -18,6 +18,7 @@ Usage:
     docs = list_available_documents()
 """
 
+import hashlib
 import json
 import re
 import time
@@ -243,6 +244,12 @@ def ingest_document(
 
     start_time = time.time()
 
+    # SHA-256 of the PDF, for spotting the same file uploaded again
+    metadata = doc_data.get("metadata", {})
+    source_path = doc_data.get("source_path")
+    if "sha256" not in metadata and source_path and Path(source_path).is_file():
+        metadata["sha256"] = hashlib.sha256(Path(source_path).read_bytes()).hexdigest()
+
     # Insert document with summary/keywords/license if available
     doc_id = insert_document(
         slug=doc_name,
@@ -250,7 +257,7 @@ def ingest_document(
         source_file=source_file,
         extraction_date=doc_data.get("extraction_date", ""),
         model=doc_data.get("model", "unknown"),
-        metadata=doc_data.get("metadata", {}),
+        metadata=metadata,
         summary=doc_data.get("summary"),
         keywords=doc_data.get("keywords"),
         license=doc_data.get("license"),
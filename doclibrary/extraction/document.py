@@ -25,7 +25,7 @@ import re
 import time
 from datetime import datetime
 from pathlib import Path
-from typing import Any, Dict, List, Optional, Tuple, Union
+from typing import Any, Callable, Dict, List, Optional, Tuple, Union
 
 import fitz  # PyMuPDF
 from openai import OpenAI
@@ -455,6 +455,7 @@ def extract_document(
     dpi: int = DEFAULT_DPI,
     skip_existing: bool = False,
     verbose: bool = True,
+    on_page: Optional[Callable[[int, int], None]] = None,
 ) -> Dict[str, Any]:
     """Extract elements from multiple PDF pages.
 
@@ -471,6 +472,7 @@ def extract_document(
         dpi: Resolution for rendering
         skip_existing: If True, skip pages that already have JSON files
         verbose: Print progress
+        on_page: Called with (pages done, pages to extract) after each page
 
     Returns:
         Dictionary with extraction summary
@@ -530,6 +532,8 @@ def extract_document(
 
         # Update document.json after each page (for progress tracking)
         _update_document_json(output_dir, pdf_path, total_pages, config.vision_llm_model)
+        if on_page:
+            on_page(i + 1, len(pages))
 
         # Delay between pages to prevent GPU overload/thermal issues
         if i < len(pages) - 1:
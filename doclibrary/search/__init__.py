@@ -12,9 +12,12 @@ from .service import (
     format_result,
     get_chunk_context,
     get_element_by_id,
+    get_results_by_keys,
     search,
     search_chunks,
+    search_element_labels,
     search_elements,
+    search_similar,
 )
 
 __all__ = [
@@ -22,8 +25,11 @@ __all__ = [
     "search",
     "search_elements",
     "search_chunks",
+    "search_element_labels",
+    "search_similar",
     "SearchResult",
     "get_element_by_id",
+    "get_results_by_keys",
     "get_chunk_context",
     "format_result",
     # Embeddings
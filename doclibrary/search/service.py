@@ -18,7 +18,7 @@ Usage:
     chunks = search_chunks("coordinate transformation")
 """
 
-from dataclasses import dataclass
+from dataclasses import dataclass, field
 from typing import Any, Dict, List, Optional
 
 from doclibrary.core.constants import STOPWORDS
@@ -68,6 +68,9 @@ class SearchResult:
     rendered_path: Optional[str] = None  # For equations: LaTeX-rendered image
     # Chunk-specific fields
     chunk_index: Optional[int] = None
+    # Best distance per retrieval method that found this result
+    # ("semantic", "keyword", with " (keywords)" for the keyword-extracted query)
+    score_details: Dict[str, float] = field(default_factory=dict)
 
 
 def search(
@@ -105,10 +108,13 @@ def search(
 
     # Use dict to keep best score for each result
     best_results: Dict[tuple, SearchResult] = {}
+    details: Dict[tuple, Dict[str, float]] = {}
 
-    def add_result(result: SearchResult):
-        """Add result, keeping the one with best score."""
+    def add_result(result: SearchResult, method: str):
+        """Add result, keeping the one with best score and each method's score."""
         key = (result.source_type, result.id)
+        method_scores = details.setdefault(key, {})
+        method_scores[method] = min(result.score, method_scores.get(method, result.score))
         if key not in best_results or result.score < best_results[key].score:
             best_results[key] = result
 
@@ -121,37 +127,40 @@ def search(
         embedding = get_embedding(q)
         if not embedding:
             continue
+        method = "semantic" if q == query else "semantic (keywords)"
 
         if include_chunks:
             for chunk in _search_chunks_by_vector(embedding, limit, document_slug):
-                add_result(chunk)
+                add_result(chunk, method)
 
         if include_elements:
             for elem in _search_elements_by_vector(embedding, limit, document_slug):
-                add_result(elem)
+                add_result(elem, method)
 
     # --- BM25 keyword search ---
     if hybrid:
         # Search with original query
         if include_chunks:
             for chunk in _search_chunks_by_bm25(query, limit, document_slug):
-                add_result(chunk)
+                add_result(chunk, "keyword")
 
         if include_elements:
             for elem in _search_elements_by_bm25(query, limit, document_slug):
-                add_result(elem)
+                add_result(elem, "keyword")
 
         # Also search with extracted keywords if different
         if keywords and keywords != query:
             if include_chunks:
                 for chunk in _search_chunks_by_bm25(keywords, limit, document_slug):
-                    add_result(chunk)
+                    add_result(chunk, "keyword (keywords)")
 
             if include_elements:
                 for elem in _search_elements_by_bm25(keywords, limit, document_slug):
-                    add_result(elem)
+                    add_result(elem, "keyword (keywords)")
 
     results = list(best_results.values())
+    for r in results:
+        r.score_details = details[(r.source_type, r.id)]
 
     # Sort by score (lower distance = better match)
     results.sort(key=lambda r: r.score)
@@ -194,7 +203,10 @@ def search_elements(
     if not embedding:
         raise RuntimeError("Failed to generate query embedding")
 
-    return _search_elements_by_vector(embedding, limit, document_slug, element_type)
+    results = _search_elements_by_vector(embedding, limit, document_slug, element_type)
+    for r in results:
+        r.score_details = {"semantic": r.score}
+    return results
 
 
 def _search_chunks_by_vector(
@@ -437,6 +449,94 @@ def _search_elements_by_bm25(
     ]
 
 
+def _like_escape(text: str) -> str:
+    """Escape LIKE wildcards so `text` matches literally ("50%" or "TM_zone")."""
+    return text.replace("\\", "\\\\").replace("%", "\\%").replace("_", "\\_")
+
+
+def search_element_labels(
+    query: str,
+    limit: int = 10,
+    document_slug: Optional[str] = None,
+    element_type: Optional[str] = None,
+) -> List[SearchResult]:
+    """
+    Find elements whose label contains every word of the query.
+
+    Literal matching on labels ("Tissot indicatrix", "Table 5") with no
+    embeddings involved. Exact labels rank first, then labels starting with
+    the query, then other matches; ties are ordered by document and page.
+
+    Args:
+        query: Words to look for in element labels (case-insensitive)
+        limit: Maximum number of results
+        document_slug: Filter to specific document
+        element_type: Filter to specific type ('figure', 'table', 'equation', etc.)
+    """
+    words = query.split()
+    if not words:
+        return []
+
+    clauses = ["e.label ILIKE %s" for _ in words]
+    params: list = [f"%{_like_escape(w)}%" for w in words]
+
+    if document_slug:
+        clauses.append("d.slug = %s")
+        params.append(document_slug)
+
+    if element_type:
+        clauses.append("e.element_type = %s")
+        params.append(element_type)
+
+    phrase = " ".join(words)
+    sql = f"""
+        SELECT
+            e.id,
+            e.element_type,
+            e.label,
+            e.description,
+            e.search_text,
+            e.crop_path,
+            e.rendered_path,
+            d.slug AS document_slug,
+            d.title AS document_title,
+            p.page_number,
+            CASE
+                WHEN lower(e.label) = lower(%s) THEN 0
+                WHEN e.label ILIKE %s THEN 1
+                ELSE 2
+            END AS match_rank
+        FROM elements e
+        JOIN documents d ON e.document_id = d.id
+        JOIN pages p ON e.page_id = p.id
+        WHERE {" AND ".join(clauses)}
+        ORDER BY match_rank, d.slug, p.page_number
+        LIMIT %s
+    """
+
+    rows = fetch_all(sql, tuple([phrase, f"{_like_escape(phrase)}%"] + params + [limit]))
+
+    # Report exact/prefix/partial matches as 100/80/60% (see _score_from_distance)
+    distances = {0: 0.7, 1: 0.76, 2: 0.82}
+    return [
+        SearchResult(
+            id=row["id"],
+            score=distances[row["match_rank"]],
+            score_details={"label": distances[row["match_rank"]]},
+            content=row["search_text"] or row["description"],
+            source_type="element",
+            document_slug=row["document_slug"],
+            document_title=row["document_title"],
+            page_number=row["page_number"],
+            element_type=row["element_type"],
+            element_label=row["label"],
+            crop_path=row["crop_path"],
+            rendered_path=row["rendered_path"],
+        )
+        for row in rows
+    ]
+
+
 def get_element_by_id(element_id: int) -> Optional[Dict[str, Any]]:
     """Get full element details by ID."""
     query = """
@@ -454,6 +554,163 @@ def get_element_by_id(element_id: int) -> Optional[Dict[str, Any]]:
     return fetch_one(query, (element_id,))
 
 
+def get_results_by_keys(keys: List[str]) -> List[SearchResult]:
+    """
+    Fetch specific chunks/elements as search results (e.g. pinned sources).
+
+    Args:
+        keys: Result keys of the form "element:<id>" or "chunk:<id>"
+
+    Returns:
+        SearchResult objects (score 0.0) in the given order; unknown keys are skipped
+    """
+    results = []
+    for key in keys:
+        source_type, _, raw_id = key.partition(":")
+        if not raw_id.isdigit():
+            continue
+
+        if source_type == "element":
+            row = fetch_one(
+                """
+                SELECT e.id, e.element_type, e.label, e.description, e.search_text,
+                       e.crop_path, e.rendered_path,
+                       d.slug AS document_slug, d.title AS document_title, p.page_number
+                FROM elements e
+                JOIN documents d ON e.document_id = d.id
+                JOIN pages p ON e.page_id = p.id
+                WHERE e.id = %s
+                """,
+                (int(raw_id),),
+            )
+            if row:
+                results.append(
+                    SearchResult(
+                        id=row["id"],
+                        score=0.0,
+                        content=row["search_text"] or row["description"],
+                        source_type="element",
+                        document_slug=row["document_slug"],
+                        document_title=row["document_title"],
+                        page_number=row["page_number"],
+                        element_type=row["element_type"],
+                        element_label=row["label"],
+                        crop_path=row["crop_path"],
+                        rendered_path=row["rendered_path"],
+                    )
+                )
+        elif source_type == "chunk":
+            row = fetch_one(
+                """
+                SELECT c.id, c.content, c.chunk_index,
+                       d.slug AS document_slug, d.title AS document_title, p.page_number
+                FROM chunks c
+                JOIN documents d ON c.document_id = d.id
+                JOIN pages p ON c.page_id = p.id
+                WHERE c.id = %s
+                """,
+                (int(raw_id),),
+            )
+            if row:
+                results.append(
+                    SearchResult(
+                        id=row["id"],
+                        score=0.0,
+                        content=row["content"],
+                        source_type="chunk",
+                        document_slug=row["document_slug"],
+                        document_title=row["document_title"],
+                        page_number=row["page_number"],
+                        chunk_index=row["chunk_index"],
+                    )
+                )
+
+    return results
+
+
+def search_similar(
+    source: str,
+    limit: int = 10,
+    document_slug: Optional[str] = None,
+    include_chunks: bool = True,
+    include_elements: bool = True,
+) -> List[SearchResult]:
+    """
+    Find the chunks and elements nearest to a source in embedding space.
+
+    Lets a user pivot from one good hit to related passages and figures
+    without phrasing a new query. The source's own page is left out: its
+    other chunks overlap the source and are already in view.
+
+    Args:
+        source: "element:<id>", "chunk:<id>", or "<slug>:<page>" for a page
+            (the average of its chunk embeddings)
+        limit: Maximum number of results
+        document_slug: Filter to specific document (optional)
+        include_chunks: Include text chunks
+        include_elements: Include elements (figures, tables, etc.)
+
+    Returns:
+        List of SearchResult objects sorted by similarity
+
+    Raises:
+        ValueError: If the source is malformed or has no embedding
+        LookupError: If the source doesn't exist
+    """
+    kind, _, ref = source.rpartition(":")
+    if kind in ("element", "chunk") and ref.isdigit():
+        table = "elements" if kind == "element" else "chunks"
+        row = fetch_one(
+            f"""
+            SELECT s.embedding::text AS embedding, d.slug, p.page_number
+            FROM {table} s
+            JOIN documents d ON s.document_id = d.id
+            JOIN pages p ON s.page_id = p.id
+            WHERE s.id = %s
+            """,
+            (int(ref),),
+        )
+    elif kind and ref.isdigit():
+        row = fetch_one(
+            """
+            SELECT AVG(c.embedding)::text AS embedding, d.slug, p.page_number
+            FROM pages p
+            JOIN documents d ON p.document_id = d.id
+            LEFT JOIN chunks c ON c.page_id = p.id
+            WHERE d.slug = %s AND p.page_number = %s
+            GROUP BY d.slug, p.page_number
+            """,
+            (kind, int(ref)),
+        )
+    else:
+        raise ValueError(f"Unknown source '{source}': use element:<id>, chunk:<id> or <slug>:<page>")
+
+    if not row:
+        raise LookupError(f"Source '{source}' not found")
+    if not row["embedding"]:
+        raise ValueError(f"Source '{source}' has no embedding")
+    embedding = [float(x) for x in row["embedding"].strip("[]").split(",")]
+    source_page = (row["slug"], row["page_number"])
+
+    # Over-fetch: results on the source's page are dropped below
+    fetch = limit * 2 + 10
+
+    results: List[SearchResult] = []
+    if include_chunks:
+        results.extend(_search_chunks_by_vector(embedding, fetch, document_slug))
+    if include_elements:
+        results.extend(_search_elements_by_vector(embedding, fetch, document_slug))
+    results = [
+        r
+        for r in results
+        if (r.document_slug, r.page_number) != source_page and r.score <= DISTANCE_THRESHOLD
+    ]
+    for r in results:
+        r.score_details = {"semantic": r.score}
+    results.sort(key=lambda r: r.score)
+    return results[:limit]
+
+
 def get_chunk_context(chunk_id: int, context_chunks: int = 2) -> List[Dict[str, Any]]:
     """Get a chunk and its surrounding context."""
     # First get the chunk to find its page and index
@@ -8,15 +8,30 @@ via a lightweight client (Rust CLI).
 
 Endpoints:
     GET  /health                          - Server status and service checks
+    GET  /capabilities                    - Optional features this server offers
     POST /search                          - Semantic search over documents
     POST /chat                            - Search + LLM-powered response
+    POST /chat/stream                     - Same, streaming the answer as NDJSON
+    POST /verify                          - Fact-check a statement against the library
+    POST /synthesize                      - Combine per-document answers into one
+    GET  /acronym/{acronym}               - Expansions of an acronym found in the text
     GET  /documents                       - List all documents (paginated)
     GET  /documents/{slug}                - Get document details with summary/keywords
+    DELETE /documents/{slug}              - Delete a document (needs allow_delete in config)
+    POST /ingest?filename=                - Upload a PDF to extract and ingest (needs allow_ingest)
+    GET  /ingest/{job_id}                 - Progress of an upload's extraction and ingestion
     GET  /documents/{slug}/elements       - List elements with optional filtering
+    GET  /documents/{slug}/chapters       - Chapter page ranges detected from headings
+    GET  /documents/{slug}/sections       - Chapter and numbered section page ranges
+    GET  /documents/{slug}/chunks         - Page through text chunks in reading order
+    GET  /documents/{slug}/pages          - Page summaries and keywords (no images)
+    GET  /documents/{slug}/file           - The original PDF, resumable with Range (needs
+                                            allow_download)
     POST /documents/search                - Search documents by title/slug/filename
-    GET  /page/{slug}/{page_number}       - Get page image (base64) with metadata
+    GET  /page/{slug}/{page_number}       - Get page image (base64) with metadata;
+                                            ?width= scales it down, ?bbox= crops it
     GET  /element/{element_id}            - Get element details
-    GET  /image/{slug}/{path}             - Serve element images
+    GET  /image/{slug}/{path}             - Serve element images (HEAD for just size and checksum)
 
 Usage:
     # Development
@@ -31,27 +46,38 @@ Configuration:
 """
 
 import base64
+import hashlib
+import io
+import json
+import re
+import threading
+import uuid
 from pathlib import Path
-from typing import List, Optional, Tuple
+from typing import Dict, List, Optional, Tuple
 
-from fastapi import FastAPI, HTTPException
+from fastapi import FastAPI, HTTPException, Request
 from fastapi.middleware.cors import CORSMiddleware
-from fastapi.responses import FileResponse
+from fastapi.responses import FileResponse, Response, StreamingResponse
 from PIL import Image
 from pydantic import BaseModel, Field
 
 from doclibrary.config import config
-from doclibrary.core.constants import SYSTEM_PROMPT
+from doclibrary.core.constants import SYNTHESIS_SYSTEM_PROMPT, SYSTEM_PROMPT, VERIFY_SYSTEM_PROMPT
 from doclibrary.core.formatting import format_context_for_llm
-from doclibrary.core.llm import check_llm_health, query_llm
-from doclibrary.db import fetch_all, fetch_one, get_document_by_slug
+from doclibrary.core.text import find_acronym_definitions
+from doclibrary.core.llm import check_llm_health, query_llm, stream_llm
+from doclibrary.db import delete_document, fetch_all, fetch_one, get_document_by_slug
+from doclibrary.db.chunking import estimate_tokens
 from doclibrary.search import (
     SearchResult,
     check_server as check_embed_server,
     get_element_by_id,
+    get_results_by_keys,
     search,
     search_chunks,
+    search_element_labels,
     search_elements,
+    search_similar,
 )
 from doclibrary.search.service import _score_from_distance
 
@@ -88,6 +114,30 @@ class SearchRequest(BaseModel):
     include_chunks: bool = Field(default=True, description="Include text chunks")
     include_elements: bool = Field(default=True, description="Include figures/tables/equations")
     element_type: Optional[str] = Field(default=None, description="Filter element type")
+    labels_only: bool = Field(
+        default=False, description="Match the query literally against element labels only"
+    )
+    exclude_documents: List[str] = Field(default_factory=list, description="Document slugs to leave out")
+    exclude_ids: List[str] = Field(
+        default_factory=list,
+        description='Results to leave out, as "element:<id>" or "chunk:<id>"',
+    )
+    min_score: Optional[float] = Field(
+        default=None, ge=0, le=100, description="Leave out results scoring below this (0-100%)"
+    )
+
+
+class SimilarRequest(BaseModel):
+    """Nearest-neighbor request parameters."""
+
+    source: str = Field(
+        ...,
+        description='Result to find neighbors of: "element:<id>", "chunk:<id>" or "<slug>:<page>"',
+    )
+    limit: int = Field(default=10, ge=1, le=50, description="Max results")
+    document_slug: Optional[str] = Field(default=None, description="Filter by document")
+    include_chunks: bool = Field(default=True, description="Include text chunks")
+    include_elements: bool = Field(default=True, description="Include figures/tables/equations")
 
 
 class SearchResultResponse(BaseModel):
@@ -109,6 +159,10 @@ class SearchResultResponse(BaseModel):
     image_height: Optional[int] = None
     # Chunk-specific
     chunk_index: Optional[int] = None
+    score_breakdown: Dict[str, float] = Field(
+        default_factory=dict,
+        description="Score (0-100%) from each retrieval method that found the result",
+    )
 
 
 class SearchResponse(BaseModel):
@@ -117,6 +171,7 @@ class SearchResponse(BaseModel):
     query: str
     results: List[SearchResultResponse]
     total: int
+    below_min_score: int = Field(default=0, description="Results left out for scoring below min_score")
 
 
 class ChatRequest(BaseModel):
@@ -125,7 +180,79 @@ class ChatRequest(BaseModel):
     question: str = Field(..., description="User question")
     limit: int = Field(default=8, ge=1, le=20, description="Max context results")
     document_slug: Optional[str] = Field(default=None, description="Filter by document")
+    document_slugs: List[str] = Field(
+        default_factory=list, description="Filter by several documents (overrides document_slug)"
+    )
+    model: Optional[str] = Field(default=None, description="LLM model override")
+    pinned_ids: List[str] = Field(
+        default_factory=list,
+        description='Sources always included in context, as "element:<id>" or "chunk:<id>"',
+    )
+    exclude_documents: List[str] = Field(default_factory=list, description="Document slugs to leave out")
+    exclude_ids: List[str] = Field(
+        default_factory=list,
+        description='Results to leave out, as "element:<id>" or "chunk:<id>"',
+    )
     conversation_id: Optional[str] = Field(default=None, description="For multi-turn (future)")
+    context_budget: Optional[int] = Field(
+        default=None,
+        ge=1,
+        description="Max context tokens; lowest-ranked sources are dropped to fit",
+    )
+    user_context: Optional[str] = Field(
+        default=None,
+        max_length=20000,
+        description="The user's own text (a draft, notes) to answer alongside the library sources",
+    )
+
+
+class VerifyRequest(BaseModel):
+    """Fact-check request parameters."""
+
+    statement: str = Field(..., description="Claim to check against the library")
+    limit: int = Field(default=8, ge=1, le=20, description="Max context results")
+    document_slug: Optional[str] = Field(default=None, description="Filter by document")
+
+
+class VerifyResponse(BaseModel):
+    """Fact-check verdict with explanation and sources."""
+
+    verdict: str  # 'supported', 'contradicted' or 'not_found'
+    explanation: str
+    sources: List[SearchResultResponse]
+
+
+class ContextUsage(BaseModel):
+    """Estimated token use of the context sent to the LLM."""
+
+    context_tokens: int  # Sources as formatted for the prompt
+    prompt_tokens: int  # System prompt, context and question
+    context_window: int  # Model window from config ([llm] context_window)
+    budget: Optional[int] = None
+    dropped_sources: int = 0  # Removed to fit the budget
+    truncated_sources: int = 0  # Content cut to the per-source preview length
+
+
+class DocumentAnswer(BaseModel):
+    """An answer found using a single document."""
+
+    document_slug: str
+    document_title: str = ""
+    answer: str
+
+
+class SynthesisRequest(BaseModel):
+    """Per-document answers to merge into one attributed answer."""
+
+    question: str = Field(..., description="Original question")
+    answers: List[DocumentAnswer] = Field(..., min_length=1, description="One answer per document")
+    model: Optional[str] = Field(default=None, description="LLM model override")
+
+
+class SynthesisResponse(BaseModel):
+    """Combined answer attributing claims to documents by slug."""
+
+    answer: str
 
 
 class ChatResponse(BaseModel):
@@ -134,6 +261,7 @@ class ChatResponse(BaseModel):
     answer: str
     sources: List[SearchResultResponse]
     query_used: str
+    context: Optional[ContextUsage] = None
 
 
 class HealthResponse(BaseModel):
@@ -146,6 +274,13 @@ class HealthResponse(BaseModel):
     version: str = "1.0.0"
 
 
+class CapabilitiesResponse(BaseModel):
+    """Optional features of this server, for clients to adapt to."""
+
+    version: str
+    features: List[str]
+
+
 class DocumentSearchRequest(BaseModel):
     """Document search request parameters."""
 
@@ -180,6 +315,12 @@ class DocumentListItem(BaseModel):
     summary: Optional[str] = None
     keywords: Optional[List[str]] = None
     license: Optional[str] = None
+    content_checksum: Optional[str] = Field(
+        default=None, description="MD5 of the page text in order, when asked for with checksums=true"
+    )
+    source_sha256: Optional[str] = Field(
+        default=None, description="SHA-256 of the source PDF, if known when it was ingested"
+    )
 
 
 class DocumentListResponse(BaseModel):
@@ -192,6 +333,30 @@ class DocumentListResponse(BaseModel):
     total_documents: int
 
 
+class DocumentDeleteResponse(BaseModel):
+    """Document removed by DELETE /documents/{slug}."""
+
+    slug: str
+    title: str
+    source_file: Optional[str] = None
+
+
+class IngestJob(BaseModel):
+    """An uploaded PDF on its way through extraction, enrichment and ingestion."""
+
+    job_id: str
+    slug: str
+    source_file: str
+    sha256: str
+    status: str = Field(
+        default="queued",
+        description="queued, extracting, enriching, ingesting, done or failed",
+    )
+    pages_done: int = 0
+    pages_total: int = 0
+    error: Optional[str] = None
+
+
 class DocumentDetailResponse(BaseModel):
     """Full document details response."""
 
@@ -207,7 +372,11 @@ class DocumentDetailResponse(BaseModel):
 
 
 class PageResponse(BaseModel):
-    """Page image and metadata response."""
+    """Page image and metadata response.
+
+    image_width and image_height are those of the returned image, which is
+    smaller than the stored page when it was scaled or cropped.
+    """
 
     document_slug: str
     document_title: str
@@ -232,6 +401,7 @@ class ElementListItem(BaseModel):
     description: Optional[str] = None
     crop_path: Optional[str] = None
     rendered_path: Optional[str] = None
+    latex: Optional[str] = None
 
 
 class ElementListResponse(BaseModel):
@@ -244,6 +414,104 @@ class ElementListResponse(BaseModel):
     offset: int
 
 
+class AcronymSource(BaseModel):
+    """Place where an acronym is defined."""
+
+    document_slug: str
+    document_title: str
+    page_number: int
+    chunk_id: int
+    snippet: str
+
+
+class AcronymExpansion(BaseModel):
+    """One expansion of an acronym and where it appears."""
+
+    expansion: str
+    count: int
+    documents: int
+    sources: List[AcronymSource]
+
+
+class AcronymResponse(BaseModel):
+    """Expansions found for an acronym, most frequent first."""
+
+    acronym: str
+    expansions: List[AcronymExpansion]
+    chunks_scanned: int
+
+
+class ChunkListItem(BaseModel):
+    """Single text chunk in list response."""
+
+    id: int
+    page_number: int
+    chunk_index: int
+    start_char: Optional[int] = None
+    content: str
+
+
+class ChunkListResponse(BaseModel):
+    """Paginated chunk list response."""
+
+    document_slug: str
+    chunks: List[ChunkListItem]
+    total: int
+    limit: int
+    offset: int
+
+
+class PageSummaryItem(BaseModel):
+    """Page summary without the page image."""
+
+    page_number: int
+    summary: Optional[str] = None
+    keywords: Optional[List[str]] = None
+
+
+class PageSummaryListResponse(BaseModel):
+    """Paginated page summary list response."""
+
+    document_slug: str
+    pages: List[PageSummaryItem]
+    total: int
+    limit: int
+    offset: int
+
+
+class ChapterItem(BaseModel):
+    """Chapter with its page range."""
+
+    number: int
+    title: Optional[str] = None
+    start_page: int
+    end_page: int
+
+
+class ChapterListResponse(BaseModel):
+    """Chapters detected in a document."""
+
+    document_slug: str
+    chapters: List[ChapterItem]
+
+
+class SectionItem(BaseModel):
+    """Chapter or numbered section with its page range."""
+
+    number: str = Field(description='"4" for a chapter, "4.2" or "4.2.1" for sections')
+    title: Optional[str] = None
+    level: int = Field(description="1 for chapters, 2 for 4.2, 3 for 4.2.1, ...")
+    start_page: int
+    end_page: int
+
+
+class SectionListResponse(BaseModel):
+    """Sections detected in a document, in page order."""
+
+    document_slug: str
+    sections: List[SectionItem]
+
+
 # -----------------------------------------------------------------------------
 # Helper functions
 # -----------------------------------------------------------------------------
@@ -265,6 +533,76 @@ def get_image_dimensions(
     return None, None
 
 
+def parse_bbox(bbox: str) -> Tuple[float, float, float, float]:
+    """Parse "x0,y0,x1,y1" given as fractions (0-1) of the page size."""
+    try:
+        x0, y0, x1, y1 = (float(v) for v in bbox.split(","))
+    except ValueError:
+        raise HTTPException(
+            status_code=400, detail=f"Invalid bbox '{bbox}', expected x0,y0,x1,y1"
+        ) from None
+    if not (0 <= x0 < x1 <= 1 and 0 <= y0 < y1 <= 1):
+        raise HTTPException(
+            status_code=400, detail=f"Invalid bbox '{bbox}', coordinates are fractions from 0 to 1"
+        )
+    return x0, y0, x1, y1
+
+
+def resize_page_image(
+    image_path: Path, width: Optional[int], bbox: Optional[str]
+) -> Tuple[bytes, int, int]:
+    """Crop a page image to bbox, then scale it down to at most width pixels.
+
+    Returns PNG bytes and the resulting size. Never scales up.
+    """
+    region = parse_bbox(bbox) if bbox else None
+    with Image.open(image_path) as img:
+        if region:
+            w, h = img.size
+            x0, y0, x1, y1 = region
+            img = img.crop((round(x0 * w), round(y0 * h), round(x1 * w), round(y1 * h)))
+        if width and img.width > width:
+            height = max(1, round(img.height * width / img.width))
+            img = img.resize((width, height), Image.LANCZOS)
+        buffer = io.BytesIO()
+        img.save(buffer, format="PNG", optimize=True)
+        return buffer.getvalue(), img.width, img.height
+
+
+def repr_digest(data: bytes) -> str:
+    """Repr-Digest header value (RFC 9530) for a response body.
+
+    Clients compare it with what they received and fetch again on a
+    mismatch, so images damaged in transfer are not kept or cached.
+    """
+    return "sha-256=:" + base64.b64encode(hashlib.sha256(data).digest()).decode("ascii") + ":"
+
+
+def prefers_image(accept: Optional[str]) -> bool:
+    """Whether an Accept header ranks an image type above JSON.
+
+    Clients that send e.g. "image/*, application/json;q=0.5" get page images
+    as raw bytes; anything else (no header, */*) gets the JSON response.
+    """
+    if not accept:
+        return False
+    image_q = json_q = 0.0
+    for part in accept.split(","):
+        media, *params = [p.strip() for p in part.split(";")]
+        q = 1.0
+        for param in params:
+            if param.startswith("q="):
+                try:
+                    q = float(param[2:])
+                except ValueError:
+                    q = 0.0
+        if media.startswith("image/"):
+            image_q = max(image_q, q)
+        elif media in ("application/json", "application/*"):
+            json_q = max(json_q, q)
+    return image_q > json_q
+
+
 def get_best_image_path(r: SearchResult) -> Optional[str]:
     """Get the best image path for display. Prefers rendered_path for equations."""
     if r.element_type == "equation" and r.rendered_path:
@@ -292,9 +630,173 @@ def result_to_response(r: SearchResult) -> SearchResultResponse:
         image_width=width,
         image_height=height,
         chunk_index=r.chunk_index,
+        score_breakdown={
+            method: round(_score_from_distance(distance), 1)
+            for method, distance in r.score_details.items()
+        },
     )
 
 
+def search_limit(limit: int, exclude_documents: List[str], exclude_ids: List[str]) -> int:
+    """Over-fetch when exclusions are set so filtering still leaves `limit` results."""
+    if exclude_documents or exclude_ids:
+        return min(limit * 2 + len(exclude_ids), 100)
+    return limit
+
+
+def apply_exclusions(
+    results: List[SearchResult], exclude_documents: List[str], exclude_ids: List[str], limit: int
+) -> List[SearchResult]:
+    """Drop excluded documents and results, keeping at most `limit`."""
+    excluded_docs = set(exclude_documents)
+    excluded_ids = set(exclude_ids)
+    kept = [
+        r
+        for r in results
+        if r.document_slug not in excluded_docs and f"{r.source_type}:{r.id}" not in excluded_ids
+    ]
+    return kept[:limit]
+
+
+def search_scoped(query: str, limit: int, slugs: List[str]) -> List[SearchResult]:
+    """Search within the given documents (all when empty), merging per-document
+    results by score when there are several."""
+    if len(slugs) <= 1:
+        return search(query, limit=limit, document_slug=slugs[0] if slugs else None)
+    results = [r for slug in slugs for r in search(query, limit=limit, document_slug=slug)]
+    results.sort(key=lambda r: r.score)
+    return results[:limit]
+
+
+def fit_context_budget(results: List[SearchResult], budget: int) -> Tuple[List[SearchResult], int]:
+    """Drop the lowest-ranked (last) results until the formatted context fits
+    `budget` tokens. At least one result is kept. Returns (kept, dropped count)."""
+    kept = list(results)
+    while len(kept) > 1 and estimate_tokens(format_context_for_llm(kept)) > budget:
+        kept.pop()
+    return kept, len(results) - len(kept)
+
+
+CHAPTER_HEADING = re.compile(r"^\s*chapter\s+(\d+|[ivxlc]+)\b[\s.:\u2013\u2014-]*(.*)$", re.IGNORECASE)
+ROMAN = {"i": 1, "v": 5, "x": 10, "l": 50, "c": 100}
+
+
+def _chapter_number(token: str) -> Optional[int]:
+    """Parse "3" or "III" into 3."""
+    if token.isdigit():
+        return int(token)
+    values = [ROMAN[c] for c in token.lower()]
+    total = 0
+    for i, v in enumerate(values):
+        total += -v if i + 1 < len(values) and v < values[i + 1] else v
+    return total or None
+
+
+def detect_chapters(pages: List[Tuple[int, str]], total_pages: int) -> List[ChapterItem]:
+    """Find chapter start pages from "Chapter N" headings.
+
+    Only the first few lines of each page are checked, so running text that
+    mentions a chapter does not count. Pages listing several chapters are
+    contents pages and are skipped. Consecutive pages with the same heading
+    (running headers) keep the first page as the start.
+    """
+    starts: dict = {}
+    previous: Optional[int] = None
+    for page_number, text in pages:
+        lines = [line for line in (text or "").splitlines() if line.strip()][:5]
+        found = []
+        for i, line in enumerate(lines):
+            match = CHAPTER_HEADING.match(line)
+            number = _chapter_number(match.group(1)) if match else None
+            if number is None:
+                continue
+            title = match.group(2).strip()
+            if not title and i + 1 < len(lines):
+                title = lines[i + 1].strip()
+            found.append((number, title[:200] or None))
+        if len({number for number, _ in found}) != 1:
+            continue
+        number, title = found[0]
+        if number != previous:
+            starts[number] = (page_number, title)
+        previous = number
+
+    ordered = sorted(starts.items(), key=lambda item: item[1][0])
+    chapters = []
+    for i, (number, (start, title)) in enumerate(ordered):
+        end = ordered[i + 1][1][0] - 1 if i + 1 < len(ordered) else total_pages
+        chapters.append(ChapterItem(number=number, title=title, start_page=start, end_page=end))
+    return chapters
+
+
+SECTION_HEADING = re.compile(r"^\s*(\d{1,2}(?:\.\d{1,2}){1,3})\.?\s+([A-Z][^\n]{1,118})$")
+DOT_LEADER = re.compile(r"\.{3,}")
+PAGE_REFERENCE = re.compile(r"\s\d+\s*$")
+# Largest step from one heading number to the next ("3.2" to "3.5" is fine,
+# "3.2" to "12.5" is a number in running text)
+MAX_SECTION_STEP = 3
+
+
+def _follows(previous: Optional[Tuple[int, ...]], number: Tuple[int, ...]) -> bool:
+    """Whether section `number` can come after `previous` in a document."""
+    if previous is None:
+        return True
+    for a, b in zip(previous, number):
+        if a != b:
+            return a < b <= a + MAX_SECTION_STEP
+    # Same prefix: a subsection ("4.2" then "4.2.1") comes after its parent
+    return len(number) > len(previous)
+
+
+def detect_sections(pages: List[Tuple[int, str]], total_pages: int) -> List[SectionItem]:
+    """Find numbered section headings ("4.2 Transverse Mercator") and merge
+    them with the chapters from detect_chapters.
+
+    Contents pages are skipped, and a heading is kept only when its number
+    follows the previous one kept, so numbers in running text and running
+    headers (the same heading on every page) drop out. A section ends where the next one at
+    the same or a higher level starts.
+    """
+    found = [
+        (chapter.start_page, (chapter.number,), chapter.title)
+        for chapter in detect_chapters(pages, total_pages)
+    ]
+    previous: Optional[Tuple[int, ...]] = None
+    for page_number, text in pages:
+        headings = []
+        for line in (text or "").splitlines():
+            match = SECTION_HEADING.match(line)
+            if match and not DOT_LEADER.search(match.group(2)):
+                headings.append((match.group(1), match.group(2).strip()))
+        # Several headings ending in a page number: a contents page
+        if sum(1 for _, title in headings if PAGE_REFERENCE.search(title)) >= 3:
+            continue
+        for digits, title in headings:
+            number = tuple(int(part) for part in digits.split("."))
+            if _follows(previous, number):
+                previous = number
+                found.append((page_number, number, title))
+
+    found.sort(key=lambda item: (item[0], item[1]))
+    sections = []
+    for i, (start, number, title) in enumerate(found):
+        end = total_pages
+        for next_start, next_number, _ in found[i + 1 :]:
+            if len(next_number) <= len(number):
+                end = max(start, next_start - 1)
+                break
+        sections.append(
+            SectionItem(
+                number=".".join(str(part) for part in number),
+                title=title,
+                level=len(number),
+                start_page=start,
+                end_page=end,
+            )
+        )
+    return sections
+
+
 def check_database() -> bool:
     """Check if database is accessible."""
     try:
@@ -326,43 +828,113 @@ async def health_check():
     )
 
 
+# Features every server of this version has; ingest and delete depend on the config
+FEATURES = [
+    "chat_stream",  # POST /chat/stream
+    "verify",  # POST /verify
+    "synthesize",  # POST /synthesize
+    "acronym",  # GET /acronym/{acronym}
+    "labels_search",  # labels_only in POST /search
+    "min_score",  # min_score in POST /search
+    "page_width",  # ?width= on GET /page
+    "page_bbox",  # ?bbox= on GET /page
+    "checksums",  # Repr-Digest on images and binary pages
+    "user_context",  # user_context in POST /chat
+    "similar",  # POST /similar
+    "docs_filters",  # keyword, license and search on GET /documents
+]
+
+
+@app.get("/capabilities", response_model=CapabilitiesResponse)
+async def capabilities():
+    """List the optional features this server offers.
+
+    Clients check these before using a feature, rather than failing at request
+    time on servers that are older or have uploads and deletes switched off.
+    """
+    features = list(FEATURES)
+    if config.allow_ingest:
+        features.append("ingest")
+    if config.allow_delete:
+        features.append("delete")
+    if config.allow_download:
+        features.append("download")
+    return CapabilitiesResponse(version=app.version, features=features)
+
+
 @app.post("/search", response_model=SearchResponse)
 async def search_endpoint(req: SearchRequest):
     """Semantic search over documents."""
-    if not check_embed_server():
+    if not req.labels_only and not check_embed_server():
         raise HTTPException(status_code=503, detail="Embedding server unavailable")
 
     try:
-        if req.element_type:
+        limit = search_limit(req.limit, req.exclude_documents, req.exclude_ids)
+        if req.labels_only:
+            results = search_element_labels(
+                req.query,
+                limit=limit,
+                document_slug=req.document_slug,
+                element_type=req.element_type,
+            )
+        elif req.element_type:
             results = search_elements(
                 req.query,
-                limit=req.limit,
+                limit=limit,
                 document_slug=req.document_slug,
                 element_type=req.element_type,
             )
         elif not req.include_chunks:
-            results = search_elements(req.query, limit=req.limit, document_slug=req.document_slug)
+            results = search_elements(req.query, limit=limit, document_slug=req.document_slug)
         elif not req.include_elements:
-            results = search_chunks(req.query, limit=req.limit, document_slug=req.document_slug)
+            results = search_chunks(req.query, limit=limit, document_slug=req.document_slug)
         else:
             results = search(
                 req.query,
-                limit=req.limit,
+                limit=limit,
                 document_slug=req.document_slug,
                 include_chunks=req.include_chunks,
                 include_elements=req.include_elements,
             )
+        results = apply_exclusions(results, req.exclude_documents, req.exclude_ids, req.limit)
+        responses = [result_to_response(r) for r in results]
+        kept = [r for r in responses if req.min_score is None or r.score_pct >= req.min_score]
 
         return SearchResponse(
             query=req.query,
-            results=[result_to_response(r) for r in results],
-            total=len(results),
+            results=kept,
+            total=len(kept),
+            below_min_score=len(responses) - len(kept),
         )
 
     except RuntimeError as e:
         raise HTTPException(status_code=500, detail=str(e))
 
 
+@app.post("/similar", response_model=SearchResponse)
+async def similar_endpoint(req: SimilarRequest):
+    """Chunks and elements nearest to a result, a page's average for "<slug>:<page>".
+
+    Uses the stored embeddings only, so the embedding server isn't needed.
+    Results on the source's own page are left out.
+    """
+    try:
+        results = search_similar(
+            req.source,
+            limit=req.limit,
+            document_slug=req.document_slug,
+            include_chunks=req.include_chunks,
+            include_elements=req.include_elements,
+        )
+    except LookupError as e:
+        raise HTTPException(status_code=404, detail=str(e))
+    except ValueError as e:
+        raise HTTPException(status_code=400, detail=str(e))
+
+    responses = [result_to_response(r) for r in results]
+    return SearchResponse(query=req.source, results=responses, total=len(responses))
+
+
 def extract_search_terms(question: str, document_info: Optional[dict] = None) -> str:
     """Use LLM to extract effective search terms from a natural language question.
 
@@ -429,71 +1001,317 @@ Search terms:"""
     return terms
 
 
-@app.post("/chat", response_model=ChatResponse)
-async def chat_endpoint(req: ChatRequest):
-    """Ask a question and get an LLM-powered answer with citations."""
+def prepare_chat(req: ChatRequest) -> Tuple[List[Dict[str, str]], ChatResponse]:
+    """Retrieve sources and build the LLM messages for a chat request.
+
+    Returns the messages and a response with everything but the answer.
+    """
+    scope = req.document_slugs or ([req.document_slug] if req.document_slug else [])
+
+    # Fetch document context if a single document is selected
+    document_info = None
+    if len(scope) == 1:
+        document_info = get_document_by_slug(scope[0])
+
+    # Pass 1: Extract search terms from natural language question
+    search_terms = extract_search_terms(req.question, document_info)
+
+    # Pass 2: Search with extracted terms (scoped to document if selected)
+    limit = search_limit(req.limit, req.exclude_documents, req.exclude_ids)
+    results = search_scoped(search_terms, limit, scope)
+    results = apply_exclusions(results, req.exclude_documents, req.exclude_ids, req.limit)
+
+    # Fallback: If no results and documents were selected, search all documents
+    used_fallback = False
+    if not results and scope:
+        results = search(search_terms, limit=limit, document_slug=None)
+        results = apply_exclusions(results, req.exclude_documents, req.exclude_ids, req.limit)
+        used_fallback = True
+
+    # Pinned sources go first, replacing any duplicate found by search
+    if req.pinned_ids:
+        pinned = get_results_by_keys(req.pinned_ids)
+        pinned_keys = {(r.source_type, r.id) for r in pinned}
+        results = pinned + [r for r in results if (r.source_type, r.id) not in pinned_keys]
+
+    # Pinned sources come first, so they are the last to be dropped
+    dropped = 0
+    if req.context_budget:
+        results, dropped = fit_context_budget(results, req.context_budget)
+
+    context = format_context_for_llm(results)
+
+    # Note in question if using fallback results
+    fallback_note = ""
+    if used_fallback and results:
+        scope_names = ", ".join(scope)
+        fallback_note = f"\n\nNote: No results were found in '{scope_names}', showing results from other documents."
+
+    # The user's text is shown apart from the sources, so it isn't cited as one
+    user_note = ""
+    if req.user_context and req.user_context.strip():
+        user_note = f"""The user's own text (not a library source; do not cite it with a tag, refer to it as "your text" and point out where the sources agree or disagree with it):
+
+{req.user_context.strip()}
+
+"""
+
+    augmented_question = f"""{user_note}Context (cite using the tags shown):
+
+{context}{fallback_note}
+
+Question: {req.question}
+
+IMPORTANT: Include citation tags like [1], [2], [3] in your answer to reference the sources above. Do NOT include a references/sources list at the end - just cite inline."""
+
+    messages = [
+        {"role": "system", "content": SYSTEM_PROMPT},
+        {"role": "user", "content": augmented_question},
+    ]
+
+    # Include fallback info in query_used
+    query_info = search_terms
+    if used_fallback:
+        query_info = f"{search_terms} (fallback: all docs)"
+
+    usage = ContextUsage(
+        context_tokens=estimate_tokens(context),
+        prompt_tokens=estimate_tokens(SYSTEM_PROMPT + augmented_question),
+        context_window=config.llm_context_window,
+        budget=req.context_budget,
+        dropped_sources=dropped,
+        truncated_sources=sum(1 for r in results if len(r.content or "") > 500),
+    )
+
+    response = ChatResponse(
+        answer="",
+        sources=[result_to_response(r) for r in results],
+        query_used=query_info,
+        context=usage,
+    )
+    return messages, response
+
+
+def check_chat_services():
+    """Raise 503 unless the embedding and LLM servers are up."""
     if not check_embed_server():
         raise HTTPException(status_code=503, detail="Embedding server unavailable")
 
     if not check_llm_health(config.llm_url):
         raise HTTPException(status_code=503, detail="LLM server unavailable")
 
+
+@app.post("/chat", response_model=ChatResponse)
+def chat_endpoint(req: ChatRequest):
+    """Ask a question and get an LLM-powered answer with citations.
+
+    A plain (sync) handler runs in FastAPI's threadpool, so concurrent
+    requests (one per document, see /synthesize) don't queue on the LLM call.
+    """
+    check_chat_services()
+
     try:
-        # Fetch document context if a document is selected
-        document_info = None
-        if req.document_slug:
-            document_info = get_document_by_slug(req.document_slug)
+        messages, response = prepare_chat(req)
 
-        # Pass 1: Extract search terms from natural language question
-        search_terms = extract_search_terms(req.question, document_info)
+        # Pass 3: Generate answer
+        response.answer = query_llm(messages, config.llm_url, req.model or config.llm_model, api_key=config.llm_api_key)
+        return response
 
-        # Pass 2: Search with extracted terms (scoped to document if selected)
-        results = search(search_terms, limit=req.limit, document_slug=req.document_slug)
+    except RuntimeError as e:
+        raise HTTPException(status_code=500, detail=str(e))
 
-        # Fallback: If no results and a document was selected, search all documents
-        used_fallback = False
-        if not results and req.document_slug:
-            results = search(search_terms, limit=req.limit, document_slug=None)
-            used_fallback = True
 
-        context = format_context_for_llm(results)
+@app.post("/chat/stream")
+def chat_stream_endpoint(req: ChatRequest):
+    """Like /chat, but stream the answer as it is generated.
 
-        # Note in question if using fallback results
-        fallback_note = ""
-        if used_fallback and results:
-            fallback_note = f"\n\nNote: No results were found in '{req.document_slug}', showing results from other documents."
+    The body is newline-delimited JSON: one {"type": "meta"} object with
+    sources, query_used and context, then {"type": "token", "text": ...}
+    objects, then {"type": "done"}. Retrieval errors are returned as HTTP
+    errors before streaming starts.
+    """
+    check_chat_services()
 
-        augmented_question = f"""Context (cite using the tags shown):
+    try:
+        messages, response = prepare_chat(req)
+    except RuntimeError as e:
+        raise HTTPException(status_code=500, detail=str(e))
 
-{context}{fallback_note}
+    def events():
+        meta = response.model_dump(exclude={"answer"})
+        yield json.dumps({"type": "meta", **meta}) + "\n"
+        model = req.model or config.llm_model
+        for text in stream_llm(messages, config.llm_url, model, api_key=config.llm_api_key):
+            yield json.dumps({"type": "token", "text": text}) + "\n"
+        yield json.dumps({"type": "done"}) + "\n"
 
-Question: {req.question}
+    return StreamingResponse(events(), media_type="application/x-ndjson")
 
-IMPORTANT: Include citation tags like [1], [2], [3] in your answer to reference the sources above. Do NOT include a references/sources list at the end - just cite inline."""
 
-        messages = [
-            {"role": "system", "content": SYSTEM_PROMPT},
-            {"role": "user", "content": augmented_question},
-        ]
+@app.post("/synthesize", response_model=SynthesisResponse)
+def synthesize_endpoint(req: SynthesisRequest):
+    """Merge answers obtained per document into one, attributing each claim."""
+    if not check_llm_health(config.llm_url):
+        raise HTTPException(status_code=503, detail="LLM server unavailable")
 
-        # Pass 3: Generate answer
-        answer = query_llm(messages, config.llm_url, config.llm_model, api_key=config.llm_api_key)
+    parts = []
+    for a in req.answers:
+        title = f" ({a.document_title})" if a.document_title else ""
+        parts.append(f"[{a.document_slug}]{title}\n{a.answer.strip()}")
+    answers = "\n\n".join(parts)
+
+    messages = [
+        {"role": "system", "content": SYNTHESIS_SYSTEM_PROMPT},
+        {"role": "user", "content": f"Answers by document:\n\n{answers}\n\nQuestion: {req.question}"},
+    ]
+    try:
+        answer = query_llm(messages, config.llm_url, req.model or config.llm_model, api_key=config.llm_api_key)
+    except RuntimeError as e:
+        raise HTTPException(status_code=500, detail=str(e))
+    return SynthesisResponse(answer=answer)
+
+
+def parse_verdict(text: str) -> Tuple[str, str]:
+    """Split an LLM fact-check reply into (verdict, explanation).
+
+    Expects a first line like "VERDICT: SUPPORTED", tolerating Markdown bold
+    and trailing punctuation ("**VERDICT:** Supported."); anything
+    unparseable is reported as not_found with the full reply as explanation.
+    """
+    first, _, rest = text.strip().partition("\n")
+    label = first.upper().replace("VERDICT:", "").strip(" *.!:;,")
+    label = label.replace(" ", "_").replace("-", "_")
+    if label in ("SUPPORTED", "CONTRADICTED", "NOT_FOUND"):
+        return label.lower(), rest.strip()
+    return "not_found", text.strip()
+
+
+@app.post("/verify", response_model=VerifyResponse)
+async def verify_endpoint(req: VerifyRequest):
+    """Check whether the library supports or contradicts a statement."""
+    if not check_embed_server():
+        raise HTTPException(status_code=503, detail="Embedding server unavailable")
 
-        # Include fallback info in query_used
-        query_info = search_terms
-        if used_fallback:
-            query_info = f"{search_terms} (fallback: all docs)"
+    if not check_llm_health(config.llm_url):
+        raise HTTPException(status_code=503, detail="LLM server unavailable")
+
+    try:
+        document_info = get_document_by_slug(req.document_slug) if req.document_slug else None
+        search_terms = extract_search_terms(req.statement, document_info)
+        results = search(search_terms, limit=req.limit, document_slug=req.document_slug)
 
-        return ChatResponse(
-            answer=answer,
+        if not results:
+            return VerifyResponse(
+                verdict="not_found",
+                explanation="No passages in the library relate to this statement.",
+                sources=[],
+            )
+
+        context = format_context_for_llm(results)
+        messages = [
+            {"role": "system", "content": VERIFY_SYSTEM_PROMPT},
+            {
+                "role": "user",
+                "content": f"Context (cite using the tags shown):\n\n{context}\n\nStatement: {req.statement}",
+            },
+        ]
+        reply = query_llm(messages, config.llm_url, config.llm_model, api_key=config.llm_api_key)
+        verdict, explanation = parse_verdict(reply)
+
+        return VerifyResponse(
+            verdict=verdict,
+            explanation=explanation,
             sources=[result_to_response(r) for r in results],
-            query_used=query_info,
         )
 
     except RuntimeError as e:
         raise HTTPException(status_code=500, detail=str(e))
 
 
+@app.get("/acronym/{acronym}", response_model=AcronymResponse)
+async def lookup_acronym(
+    acronym: str,
+    document_slug: Optional[str] = None,
+    max_sources: int = 3,
+):
+    """Find expansions of an acronym from definitions in the text.
+
+    Scans chunks containing "(ACR" or "ACR (" for definitions like
+    "Segment Anything Model (SAM)" or "SAM (Segment Anything Model)".
+    Expansions are grouped case-insensitively and ranked by how often
+    they occur; no LLM is involved.
+    """
+    acronym = acronym.strip()
+    if not re.fullmatch(r"[A-Za-z0-9&-]{2,15}", acronym):
+        raise HTTPException(status_code=400, detail="Acronym must be 2-15 letters or digits")
+
+    try:
+        # Cheap prefilter in SQL; the definition patterns are checked in Python
+        escaped = re.escape(acronym)
+        conditions = ["(c.content ~ %s OR c.content ~ %s)"]
+        params: list = [rf"\(\s*{escaped}", rf"{escaped}s?\s*\("]
+        if document_slug:
+            conditions.append("d.slug = %s")
+            params.append(document_slug)
+
+        rows = fetch_all(
+            f"""SELECT c.id, c.content, p.page_number, d.slug, d.title
+                FROM chunks c
+                JOIN pages p ON c.page_id = p.id
+                JOIN documents d ON c.document_id = d.id
+                WHERE {" AND ".join(conditions)}
+                ORDER BY d.slug, p.page_number, c.chunk_index
+                LIMIT 2000""",
+            tuple(params),
+        )
+
+        groups: dict = {}
+        for row in rows:
+            for expansion, offset in find_acronym_definitions(row["content"], acronym):
+                key = re.sub(r"[\s-]+", " ", expansion.lower())
+                group = groups.setdefault(
+                    key, {"forms": {}, "count": 0, "docs": set(), "sources": [], "seen": set()}
+                )
+                group["forms"][expansion] = group["forms"].get(expansion, 0) + 1
+                # Overlapping chunks repeat a definition; count each page once
+                place = (row["slug"], row["page_number"])
+                if place in group["seen"]:
+                    continue
+                group["seen"].add(place)
+                group["count"] += 1
+                group["docs"].add(row["slug"])
+                if len(group["sources"]) < max_sources:
+                    start = max(0, offset - 80)
+                    group["sources"].append(
+                        AcronymSource(
+                            document_slug=row["slug"],
+                            document_title=row["title"],
+                            page_number=row["page_number"],
+                            chunk_id=row["id"],
+                            snippet=" ".join(row["content"][start : offset + 80].split()),
+                        )
+                    )
+
+        expansions = [
+            AcronymExpansion(
+                # Most common spelling represents the group
+                expansion=max(g["forms"].items(), key=lambda item: item[1])[0],
+                count=g["count"],
+                documents=len(g["docs"]),
+                sources=g["sources"],
+            )
+            for g in groups.values()
+        ]
+        expansions.sort(key=lambda e: (-e.count, -e.documents, e.expansion.lower()))
+
+        return AcronymResponse(acronym=acronym, expansions=expansions, chunks_scanned=len(rows))
+
+    except HTTPException:
+        raise
+    except Exception as e:
+        raise HTTPException(status_code=500, detail=str(e))
+
+
 @app.get("/element/{element_id}")
 async def get_element(element_id: int):
     """Get full details for a specific element."""
@@ -506,9 +1324,11 @@ async def get_element(element_id: int):
     return result
 
 
-@app.get("/image/{document_slug}/{path:path}")
+@app.api_route("/image/{document_slug}/{path:path}", methods=["GET", "HEAD"])
 async def get_image(document_slug: str, path: str):
-    """Serve element images."""
+    """Serve element images, with their checksum in Repr-Digest. HEAD gives
+    the size (Content-Length) and checksum alone, for clients planning or
+    checking a download."""
     if ".." in path or path.startswith("/"):
         raise HTTPException(status_code=400, detail="Invalid path")
 
@@ -532,7 +1352,124 @@ async def get_image(document_slug: str, path: str):
         ".webp": "image/webp",
     }
 
-    return FileResponse(full_path, media_type=media_types.get(suffix, "image/png"))
+    return FileResponse(
+        full_path,
+        media_type=media_types.get(suffix, "image/png"),
+        headers={"Repr-Digest": repr_digest(full_path.read_bytes())},
+    )
+
+
+def source_pdf(slug: str, source_file: Optional[str]) -> Optional[Path]:
+    """Where the original PDF of a document is kept: in its data directory
+    when it was uploaded, otherwise where extraction read it from (the
+    source_path in its document.json)."""
+    doc_dir = Path(config.data_dir) / slug
+    candidates = []
+    if source_file:
+        candidates.append(doc_dir / Path(source_file).name)
+    try:
+        source_path = json.loads((doc_dir / "document.json").read_text()).get("source_path")
+    except (OSError, ValueError):
+        source_path = None
+    if source_path:
+        candidates.append(Path(source_path))
+    return next((p for p in candidates if p.suffix.lower() == ".pdf" and p.is_file()), None)
+
+
+def byte_range(header: Optional[str], size: int) -> Optional[Tuple[int, int]]:
+    """First and last byte asked for by a Range header ("bytes=100-",
+    "bytes=100-199" or "bytes=-500"), or None for the whole file, also when
+    the header is malformed, asks for several ranges or ends before it
+    starts. Raises ValueError for a range that starts past the end of the
+    file."""
+    if not header or not header.startswith("bytes=") or "," in header:
+        return None
+    first, _, last = header[len("bytes=") :].strip().partition("-")
+    try:
+        if not first:
+            start, end = max(size - int(last), 0), size - 1
+        else:
+            start = int(first)
+            if last and int(last) < start:
+                return None
+            end = min(int(last), size - 1) if last else size - 1
+    except ValueError:
+        return None
+    if start >= size:
+        raise ValueError(f"bytes {header[len('bytes='):]} of a {size}-byte file")
+    return start, end
+
+
+def read_range(path: Path, start: int, end: int, block: int = 1 << 16):
+    """Bytes start to end (inclusive) of a file, a block at a time."""
+    with open(path, "rb") as f:
+        f.seek(start)
+        left = end - start + 1
+        while left > 0:
+            data = f.read(min(block, left))
+            if not data:
+                break
+            left -= len(data)
+            yield data
+
+
+@app.api_route("/documents/{document_slug}/file", methods=["GET", "HEAD"])
+async def get_document_file(document_slug: str, request: Request):
+    """Serve a document's original PDF.
+
+    A Range header ("bytes=N-") resumes an interrupted download with 206
+    Partial Content. HEAD gives the size (Content-Length) and, when it was
+    recorded at upload or ingest, the checksum (Repr-Digest) alone. Disabled
+    unless `allow_download` is set in the [server] config, since the PDFs may
+    not be the library's to share.
+    """
+    if not config.allow_download:
+        raise HTTPException(
+            status_code=403,
+            detail="Downloading documents is disabled (set allow_download in the [server] config)",
+        )
+    doc = fetch_one(
+        "SELECT slug, source_file, metadata->>'sha256' as sha256 FROM documents WHERE slug = %s",
+        (document_slug,),
+    )
+    if not doc:
+        raise HTTPException(status_code=404, detail=f"Document '{document_slug}' not found")
+    path = source_pdf(doc["slug"], doc.get("source_file"))
+    if not path:
+        raise HTTPException(
+            status_code=404, detail=f"The original PDF of '{document_slug}' is not on the server"
+        )
+
+    size = path.stat().st_size
+    headers = {
+        "Accept-Ranges": "bytes",
+        "Content-Disposition": f'attachment; filename="{path.name}"',
+    }
+    if doc.get("sha256"):
+        digest = base64.b64encode(bytes.fromhex(doc["sha256"])).decode("ascii")
+        headers["Repr-Digest"] = f"sha-256=:{digest}:"
+    try:
+        requested = byte_range(request.headers.get("range"), size)
+    except ValueError as e:
+        raise HTTPException(
+            status_code=416,
+            detail=f"Range not satisfiable: {e}",
+            headers={"Content-Range": f"bytes */{size}"},
+        )
+    start, end = requested or (0, size - 1)
+    if requested:
+        headers["Content-Range"] = f"bytes {start}-{end}/{size}"
+    headers["Content-Length"] = str(end - start + 1)
+    status = 206 if requested else 200
+
+    if request.method == "HEAD":
+        return Response(status_code=status, headers=headers, media_type="application/pdf")
+    return StreamingResponse(
+        read_range(path, start, end),
+        status_code=status,
+        headers=headers,
+        media_type="application/pdf",
+    )
 
 
 @app.get("/documents", response_model=DocumentListResponse)
@@ -540,6 +1477,10 @@ async def list_documents(
     page: int = 1,
     page_size: int = 20,
     sort_by: str = "title",
+    checksums: bool = False,
+    keyword: Optional[str] = None,
+    license: Optional[str] = None,
+    search: Optional[str] = None,
 ):
     """List all documents with pagination.
 
@@ -547,6 +1488,11 @@ async def list_documents(
         page: Page number (1-indexed, default: 1)
         page_size: Results per page (default: 20, max: 100)
         sort_by: Sort field: 'title', 'date_added', or 'page_count'
+        checksums: Include a checksum of each document's text, which is the
+            same for the same PDF ingested twice under different names
+        keyword: Only documents with a keyword containing this (case-insensitive)
+        license: Only documents whose license contains this, e.g. "CC-BY"
+        search: Only documents whose title or slug contains this
     """
     try:
         # Validate inputs
@@ -561,22 +1507,44 @@ async def list_documents(
         }
         order_by = sort_map.get(sort_by, "d.title")
 
+        # Filters, each a substring match ignoring case
+        clauses = []
+        params: list = []
+        if keyword:
+            clauses.append("EXISTS (SELECT 1 FROM unnest(d.keywords) k WHERE k ILIKE %s)")
+            params.append(f"%{keyword}%")
+        if license:
+            clauses.append("d.license ILIKE %s")
+            params.append(f"%{license}%")
+        if search:
+            clauses.append("(d.title ILIKE %s OR d.slug ILIKE %s)")
+            params.extend([f"%{search}%", f"%{search}%"])
+        where = f"WHERE {' AND '.join(clauses)}" if clauses else ""
+
         # Get total count
-        total = fetch_one("SELECT COUNT(*) as count FROM documents")
+        total = fetch_one(f"SELECT COUNT(*) as count FROM documents d {where}", tuple(params))
         total_docs = total["count"] if total else 0
         total_pages = (total_docs + page_size - 1) // page_size if total_docs > 0 else 0
 
         # Get page of results with summaries and keywords
         offset = (page - 1) * page_size
+        # Reads every page's text, so only on request
+        checksum = (
+            "md5(string_agg(p.full_text, chr(12) ORDER BY p.page_number))"
+            if checksums
+            else "NULL"
+        )
         results = fetch_all(
             f"""SELECT d.slug, d.title, d.source_file, d.summary, d.keywords, d.license,
-                       COUNT(p.id) as page_count
+                       d.metadata->>'sha256' as source_sha256,
+                       COUNT(p.id) as page_count, {checksum} as content_checksum
                 FROM documents d
                 LEFT JOIN pages p ON p.document_id = d.id
+                {where}
                 GROUP BY d.id
                 ORDER BY {order_by}
                 LIMIT %s OFFSET %s""",
-            (page_size, offset),
+            (*params, page_size, offset),
         )
 
         return DocumentListResponse(
@@ -589,6 +1557,8 @@ async def list_documents(
                     summary=r["summary"],
                     keywords=r["keywords"],
                     license=r["license"],
+                    content_checksum=r["content_checksum"],
+                    source_sha256=r["source_sha256"],
                 )
                 for r in results
             ],
@@ -657,6 +1627,226 @@ async def get_document(document_slug: str):
         raise HTTPException(status_code=500, detail=str(e))
 
 
+@app.delete("/documents/{document_slug}", response_model=DocumentDeleteResponse)
+async def remove_document(document_slug: str):
+    """Delete a document with its pages, chunks and elements.
+
+    Disabled unless `allow_delete` is set in the [server] config, since the
+    API has no authentication. Extracted files under data_dir are kept.
+    """
+    if not config.allow_delete:
+        raise HTTPException(
+            status_code=403,
+            detail="Deleting documents is disabled (set allow_delete in the [server] config)",
+        )
+    try:
+        doc = get_document_by_slug(document_slug)
+        if not doc:
+            raise HTTPException(status_code=404, detail=f"Document not found: {document_slug}")
+        delete_document(doc["id"])
+        return DocumentDeleteResponse(
+            slug=doc["slug"], title=doc["title"], source_file=doc.get("source_file")
+        )
+
+    except HTTPException:
+        raise
+    except Exception as e:
+        raise HTTPException(status_code=500, detail=str(e))
+
+
+# Uploads by job ID, kept until the server restarts. Jobs run one at a time,
+# since extraction keeps the vision model busy.
+_ingest_jobs: Dict[str, IngestJob] = {}
+_ingest_lock = threading.Lock()
+
+
+def _slug_for_file(filename: str) -> str:
+    """Document slug for an uploaded file: its name without extension."""
+    slug = re.sub(r"[^a-z0-9]+", "_", Path(filename).stem.lower()).strip("_")
+    return slug or "document"
+
+
+async def _receive_upload(request: Request, path: Path, source_file: str) -> str:
+    """Write the request body to `path` a chunk at a time and return its
+    SHA-256. Raises 413 once it grows past `max_upload_mb` and 400 as soon as
+    it does not start like a PDF."""
+    max_bytes = config.max_upload_mb * 1024 * 1024
+    too_large = HTTPException(
+        status_code=413,
+        detail=f"{source_file} is larger than the {config.max_upload_mb} MB upload limit "
+        "(max_upload_mb in the [server] config)",
+    )
+    declared = request.headers.get("content-length", "")
+    if declared.isdigit() and int(declared) > max_bytes:
+        raise too_large
+
+    digest = hashlib.sha256()
+    head = b""
+    size = 0
+    with open(path, "wb") as f:
+        async for chunk in request.stream():
+            size += len(chunk)
+            if size > max_bytes:
+                raise too_large
+            if len(head) < 4:
+                head += chunk[: 4 - len(head)]
+                if len(head) == 4 and head != b"%PDF":
+                    break
+            digest.update(chunk)
+            f.write(chunk)
+    if head != b"%PDF":
+        raise HTTPException(status_code=400, detail=f"{source_file} is not a PDF")
+    return digest.hexdigest()
+
+
+def _run_ingest_job(job: IngestJob, pdf_path: Path, replace: bool) -> None:
+    """Extract, enrich and ingest an uploaded PDF, updating `job` as it goes."""
+    import fitz  # PyMuPDF
+
+    from doclibrary.db.ingest import ingest_document
+    from doclibrary.extraction import enrich_document, extract_document
+
+    with _ingest_lock:
+        try:
+            job.status = "extracting"
+            with fitz.open(str(pdf_path)) as doc:
+                job.pages_total = len(doc)
+
+            # A retried upload resumes where extraction stopped, a replaced
+            # one starts over
+            def on_page(done: int, total: int) -> None:
+                job.pages_done = job.pages_total - total + done
+
+            extract_document(
+                pdf_path=pdf_path,
+                output_dir=pdf_path.parent,
+                pages=list(range(1, job.pages_total + 1)),
+                skip_existing=not replace,
+                on_page=on_page,
+            )
+            job.pages_done = job.pages_total
+
+            job.status = "enriching"
+            if enrich_document(job.slug, data_dir=config.data_dir).get("error"):
+                raise RuntimeError("Enrichment failed (see the server log)")
+
+            job.status = "ingesting"
+            if not ingest_document(job.slug, delete_first=replace):
+                raise RuntimeError("Ingestion failed (see the server log)")
+            job.status = "done"
+
+        except Exception as e:
+            job.status = "failed"
+            job.error = str(e)
+
+
+@app.post("/ingest", response_model=IngestJob)
+async def upload_document(request: Request, filename: str, force: bool = False):
+    """Upload a PDF (the request body) to extract, enrich and ingest.
+
+    Processing runs in the background; poll GET /ingest/{job_id} for progress.
+    The body is written to disk as it arrives, up to `max_upload_mb` (413
+    past it). Refused with 409 if a document with the same file name, slug or
+    SHA-256 is already in the library, unless `force` is set, in which case a
+    document with the same file name is replaced. A different file whose name
+    gives the same slug ("Foo-Bar.pdf" and "foo_bar.pdf") is refused even
+    then, since replacing it would delete that document. Disabled unless `allow_ingest` is set
+    in the [server] config, since the API has no authentication.
+
+    Args:
+        filename: Name of the uploaded file, used as source_file and for the slug
+        force: Upload even if the document looks like a duplicate, replacing
+            one with the same file name
+    """
+    if not config.allow_ingest:
+        raise HTTPException(
+            status_code=403,
+            detail="Uploading documents is disabled (set allow_ingest in the [server] config)",
+        )
+    source_file = Path(filename).name
+    if not source_file.lower().endswith(".pdf"):
+        raise HTTPException(status_code=400, detail=f"Not a PDF file name: {filename}")
+    # Kept beside the documents until the duplicate checks pass, then moved
+    upload = Path(config.data_dir) / f".upload-{uuid.uuid4().hex[:12]}.pdf"
+    try:
+        upload.parent.mkdir(parents=True, exist_ok=True)
+        sha256 = await _receive_upload(request, upload, source_file)
+        return _start_ingest(upload, source_file, sha256, force)
+    except OSError as e:
+        raise HTTPException(status_code=500, detail=f"Could not store {source_file}: {e}")
+    finally:
+        upload.unlink(missing_ok=True)
+
+
+def _start_ingest(upload: Path, source_file: str, sha256: str, force: bool) -> IngestJob:
+    """Move an uploaded PDF to its document directory and start ingesting it,
+    unless it is a duplicate (409)."""
+    slug = _slug_for_file(source_file)
+
+    for job in _ingest_jobs.values():
+        if job.status not in ("done", "failed") and (job.slug == slug or job.sha256 == sha256):
+            raise HTTPException(
+                status_code=409, detail=f"{source_file} is already being ingested (job {job.job_id})"
+            )
+    if not force:
+        existing = fetch_one(
+            """SELECT slug, source_file, metadata->>'sha256' as sha256 FROM documents
+               WHERE source_file = %s OR slug = %s OR metadata->>'sha256' = %s
+               LIMIT 1""",
+            (source_file, slug, sha256),
+        )
+        if existing:
+            if existing["sha256"] == sha256:
+                reason = "same content"
+            elif existing["source_file"] == source_file:
+                reason = "same file name"
+            else:
+                reason = "same slug"
+            raise HTTPException(
+                status_code=409,
+                detail=f"{source_file} is already in the library as '{existing['slug']}' ({reason})",
+            )
+    else:
+        # Ingesting under the slug replaces whatever document has it
+        other = fetch_one(
+            "SELECT source_file FROM documents WHERE slug = %s AND source_file IS DISTINCT FROM %s",
+            (slug, source_file),
+        )
+        if other:
+            raise HTTPException(
+                status_code=409,
+                detail=f"{source_file} would replace '{slug}' ({other['source_file']}), "
+                "a different file with the same slug; rename the file to upload it",
+            )
+
+    doc_dir = Path(config.data_dir) / slug
+    doc_dir.mkdir(parents=True, exist_ok=True)
+    pdf_path = doc_dir / source_file
+    upload.replace(pdf_path)
+
+    # Kept in document.json for ingest, which stores it with the document
+    doc_json = doc_dir / "document.json"
+    doc_data = json.loads(doc_json.read_text()) if doc_json.exists() else {}
+    doc_data.setdefault("metadata", {})["sha256"] = sha256
+    doc_json.write_text(json.dumps(doc_data, indent=2, ensure_ascii=False))
+
+    job = IngestJob(job_id=uuid.uuid4().hex[:12], slug=slug, source_file=source_file, sha256=sha256)
+    _ingest_jobs[job.job_id] = job
+    threading.Thread(target=_run_ingest_job, args=(job, pdf_path, force), daemon=True).start()
+    return job
+
+
+@app.get("/ingest/{job_id}", response_model=IngestJob)
+async def get_ingest_job(job_id: str):
+    """Progress of an upload started with POST /ingest."""
+    job = _ingest_jobs.get(job_id)
+    if not job:
+        raise HTTPException(
+            status_code=404, detail=f"No ingest job '{job_id}' (jobs are forgotten on restart)"
+        )
+    return job
+
+
 @app.get("/documents/{document_slug}/elements", response_model=ElementListResponse)
 async def list_elements(
     document_slug: str,
@@ -723,7 +1913,7 @@ async def list_elements(
         params.extend([limit, offset])
         results = fetch_all(
             f"""SELECT e.id, e.element_type, e.label, p.page_number, 
-                       e.search_text as description, e.crop_path, e.rendered_path
+                       e.search_text as description, e.crop_path, e.rendered_path, e.latex
                 FROM elements e
                 JOIN pages p ON e.page_id = p.id
                 WHERE {where_clause}
@@ -743,6 +1933,7 @@ async def list_elements(
                     description=r["description"][:200] if r["description"] else None,
                     crop_path=r["crop_path"],
                     rendered_path=r["rendered_path"],
+                    latex=r["latex"],
                 )
                 for r in results
             ],
@@ -757,6 +1948,190 @@ async def list_elements(
         raise HTTPException(status_code=500, detail=str(e))
 
 
+@app.get("/documents/{document_slug}/chunks", response_model=ChunkListResponse)
+async def list_chunks(
+    document_slug: str,
+    page: Optional[int] = None,
+    limit: int = 100,
+    offset: int = 0,
+):
+    """List a document's text chunks in reading order.
+
+    Args:
+        document_slug: Document identifier
+        page: Filter to chunks on a specific page (1-indexed)
+        limit: Maximum results (default: 100, max: 500)
+        offset: Pagination offset (default: 0)
+
+    Returns:
+        Chunks with page number, index and character offset on the page.
+        Consecutive chunks overlap; use start_char to place text on the page.
+    """
+    try:
+        limit = min(max(1, limit), 500)
+        offset = max(0, offset)
+
+        doc = fetch_one("SELECT id FROM documents WHERE slug = %s", (document_slug,))
+        if not doc:
+            raise HTTPException(status_code=404, detail=f"Document '{document_slug}' not found")
+
+        conditions = ["c.document_id = %s"]
+        params: list = [doc["id"]]
+        if page is not None:
+            if page < 1:
+                raise HTTPException(status_code=400, detail="Page must be >= 1")
+            conditions.append("p.page_number = %s")
+            params.append(page)
+        where_clause = " AND ".join(conditions)
+
+        total_result = fetch_one(
+            f"""SELECT COUNT(*) as count
+                FROM chunks c
+                JOIN pages p ON c.page_id = p.id
+                WHERE {where_clause}""",
+            tuple(params),
+        )
+        total = total_result["count"] if total_result else 0
+
+        params.extend([limit, offset])
+        results = fetch_all(
+            f"""SELECT c.id, p.page_number, c.chunk_index, c.start_char, c.content
+                FROM chunks c
+                JOIN pages p ON c.page_id = p.id
+                WHERE {where_clause}
+                ORDER BY p.page_number, c.chunk_index
+                LIMIT %s OFFSET %s""",
+            tuple(params),
+        )
+
+        return ChunkListResponse(
+            document_slug=document_slug,
+            chunks=[ChunkListItem(**r) for r in results],
+            total=total,
+            limit=limit,
+            offset=offset,
+        )
+
+    except HTTPException:
+        raise
+    except Exception as e:
+        raise HTTPException(status_code=500, detail=str(e))
+
+
+@app.get("/documents/{document_slug}/pages", response_model=PageSummaryListResponse)
+async def list_page_summaries(
+    document_slug: str,
+    limit: int = 100,
+    offset: int = 0,
+):
+    """List page summaries and keywords in page order.
+
+    Lighter than /page/{slug}/{n} when only the text is needed (exports):
+    no page image is read or sent.
+
+    Args:
+        document_slug: Document identifier
+        limit: Maximum results (default: 100, max: 500)
+        offset: Pagination offset (default: 0)
+    """
+    try:
+        limit = min(max(1, limit), 500)
+        offset = max(0, offset)
+
+        doc = fetch_one("SELECT id FROM documents WHERE slug = %s", (document_slug,))
+        if not doc:
+            raise HTTPException(status_code=404, detail=f"Document '{document_slug}' not found")
+
+        total_result = fetch_one(
+            "SELECT COUNT(*) as count FROM pages WHERE document_id = %s", (doc["id"],)
+        )
+        total = total_result["count"] if total_result else 0
+
+        results = fetch_all(
+            """SELECT page_number, summary, keywords
+               FROM pages
+               WHERE document_id = %s
+               ORDER BY page_number
+               LIMIT %s OFFSET %s""",
+            (doc["id"], limit, offset),
+        )
+
+        return PageSummaryListResponse(
+            document_slug=document_slug,
+            pages=[PageSummaryItem(**r) for r in results],
+            total=total,
+            limit=limit,
+            offset=offset,
+        )
+
+    except HTTPException:
+        raise
+    except Exception as e:
+        raise HTTPException(status_code=500, detail=str(e))
+
+
+@app.get("/documents/{document_slug}/chapters", response_model=ChapterListResponse)
+async def list_chapters(document_slug: str):
+    """List chapters with their page ranges.
+
+    Chapters are detected from "Chapter N" headings at the top of pages;
+    documents without such headings return an empty list.
+    """
+    try:
+        doc = fetch_one("SELECT id FROM documents WHERE slug = %s", (document_slug,))
+        if not doc:
+            raise HTTPException(status_code=404, detail=f"Document '{document_slug}' not found")
+
+        pages = fetch_all(
+            """SELECT page_number, LEFT(full_text, 500) as head
+               FROM pages WHERE document_id = %s
+               ORDER BY page_number""",
+            (doc["id"],),
+        )
+        total_pages = pages[-1]["page_number"] if pages else 0
+
+        return ChapterListResponse(
+            document_slug=document_slug,
+            chapters=detect_chapters([(p["page_number"], p["head"]) for p in pages], total_pages),
+        )
+
+    except HTTPException:
+        raise
+    except Exception as e:
+        raise HTTPException(status_code=500, detail=str(e))
+
+
+@app.get("/documents/{document_slug}/sections", response_model=SectionListResponse)
+async def list_sections(document_slug: str):
+    """List chapters and numbered sections with their page ranges.
+
+    Sections are detected from numbered headings ("4.2 Title") in the page
+    text; documents without them return their chapters, or an empty list.
+    """
+    try:
+        doc = fetch_one("SELECT id FROM documents WHERE slug = %s", (document_slug,))
+        if not doc:
+            raise HTTPException(status_code=404, detail=f"Document '{document_slug}' not found")
+
+        pages = fetch_all(
+            """SELECT page_number, full_text
+               FROM pages WHERE document_id = %s
+               ORDER BY page_number""",
+            (doc["id"],),
+        )
+        total_pages = pages[-1]["page_number"] if pages else 0
+
+        return SectionListResponse(
+            document_slug=document_slug,
+            sections=detect_sections([(p["page_number"], p["full_text"]) for p in pages], total_pages),
+        )
+
+    except HTTPException:
+        raise
+    except Exception as e:
+        raise HTTPException(status_code=500, detail=str(e))
+
+
 @app.post("/documents/search", response_model=DocumentSearchResponse)
 async def search_documents(req: DocumentSearchRequest):
     """Search documents by title, slug, or source filename."""
@@ -800,8 +2175,26 @@ async def search_documents(req: DocumentSearchRequest):
 
 
 @app.get("/page/{document_slug}/{page_number}", response_model=PageResponse)
-async def get_page(document_slug: str, page_number: int):
-    """Get a page image with metadata."""
+async def get_page(
+    request: Request,
+    document_slug: str,
+    page_number: int,
+    width: Optional[int] = None,
+    bbox: Optional[str] = None,
+):
+    """Get a page image with metadata.
+
+    Args:
+        document_slug: Document identifier
+        page_number: Page number (1-indexed)
+        width: Scale the image down to at most this many pixels wide
+        bbox: Only this region, "x0,y0,x1,y1" as fractions (0-1) of the page
+
+    Without width and bbox the stored image is returned unchanged. When the
+    Accept header prefers an image, the image is sent as raw bytes (no base64
+    overhead) and the other fields as base64-encoded JSON in the
+    X-Page-Metadata header.
+    """
     try:
         doc = fetch_one("SELECT id, slug, title FROM documents WHERE slug = %s", (document_slug,))
         if not doc:
@@ -828,35 +2221,51 @@ async def get_page(document_slug: str, page_number: int):
                 status_code=404, detail=f"Image file not found: {page['image_path']}"
             )
 
-        with open(image_path, "rb") as f:
-            image_data = f.read()
-        image_base64 = base64.b64encode(image_data).decode("utf-8")
-
-        suffix = image_path.suffix.lower()
-        mime_types = {".png": "image/png", ".jpg": "image/jpeg", ".jpeg": "image/jpeg"}
-        mime_type = mime_types.get(suffix, "image/png")
-
-        width = page["width"]
-        height = page["height"]
-        if not width or not height:
-            with Image.open(image_path) as img:
-                width, height = img.size
+        if width is not None and width < 1:
+            raise HTTPException(status_code=400, detail="width must be at least 1")
 
+        if width or bbox:
+            image_data, image_width, image_height = resize_page_image(image_path, width, bbox)
+            mime_type = "image/png"
+        else:
+            with open(image_path, "rb") as f:
+                image_data = f.read()
+            suffix = image_path.suffix.lower()
+            mime_types = {".png": "image/png", ".jpg": "image/jpeg", ".jpeg": "image/jpeg"}
+            mime_type = mime_types.get(suffix, "image/png")
+
+            image_width = page["width"]
+            image_height = page["height"]
+            if not image_width or not image_height:
+                with Image.open(image_path) as img:
+                    image_width, image_height = img.size
         has_annotated = bool(page["annotated_image_path"])
 
-        return PageResponse(
+        response = PageResponse(
             document_slug=doc["slug"],
             document_title=doc["title"],
             page_number=page["page_number"],
             total_pages=total_pages,
-            image_base64=image_base64,
-            image_width=width,
-            image_height=height,
+            image_base64="",
+            image_width=image_width,
+            image_height=image_height,
             mime_type=mime_type,
             has_annotated=has_annotated,
             summary=page.get("summary"),
             keywords=page.get("keywords"),
         )
+        if prefers_image(request.headers.get("accept")):
+            metadata = response.model_dump_json(exclude={"image_base64"})
+            return Response(
+                content=image_data,
+                media_type=mime_type,
+                headers={
+                    "X-Page-Metadata": base64.b64encode(metadata.encode("utf-8")).decode("ascii"),
+                    "Repr-Digest": repr_digest(image_data),
+                },
+            )
+        response.image_base64 = base64.b64encode(image_data).decode("utf-8")
+        return response
 
     except HTTPException:
         raise
@@ -0,0 +1,170 @@
+"""Unit tests for helpers in doclibrary.servers.api."""
+
+import pytest
+from doclibrary.servers.api import byte_range, detect_chapters, detect_sections, parse_verdict
+
+
+class TestByteRange:
+    """Tests for byte_range function."""
+
+    def test_no_header(self):
+        """Should return None (whole file) without a Range header."""
+        assert byte_range(None, 1000) is None
+        assert byte_range("", 1000) is None
+
+    def test_open_ended_range(self):
+        """Should serve from the start byte to the end of the file."""
+        assert byte_range("bytes=100-", 1000) == (100, 999)
+
+    def test_closed_range(self):
+        """Should serve both ends inclusive, clamping the last byte to the file."""
+        assert byte_range("bytes=100-199", 1000) == (100, 199)
+        assert byte_range("bytes=900-5000", 1000) == (900, 999)
+
+    def test_suffix_range(self):
+        """Should serve the last N bytes, or the whole file when N is larger."""
+        assert byte_range("bytes=-500", 1000) == (500, 999)
+        assert byte_range("bytes=-5000", 1000) == (0, 999)
+
+    def test_empty_suffix_range(self):
+        """Should reject 'bytes=-0', which asks for no bytes at all."""
+        with pytest.raises(ValueError):
+            byte_range("bytes=-0", 1000)
+
+    def test_multiple_ranges(self):
+        """Should fall back to the whole file for several ranges."""
+        assert byte_range("bytes=0-99,200-299", 1000) is None
+
+    def test_malformed_header(self):
+        """Should fall back to the whole file for headers it cannot parse."""
+        assert byte_range("items=0-99", 1000) is None
+        assert byte_range("bytes=abc-", 1000) is None
+
+    def test_start_past_end_of_file(self):
+        """Should raise ValueError (answered with 416) past the end of the file."""
+        with pytest.raises(ValueError):
+            byte_range("bytes=1000-", 1000)
+        with pytest.raises(ValueError):
+            byte_range("bytes=5000-6000", 1000)
+
+    def test_start_after_end(self):
+        """Should ignore a range whose last byte comes before the first."""
+        assert byte_range("bytes=500-100", 1000) is None
+        assert byte_range("bytes=5000-100", 1000) is None
+
+    def test_zero_byte_file(self):
+        """Should serve a zero-byte file whole, and reject any range of it."""
+        assert byte_range(None, 0) is None
+        with pytest.raises(ValueError):
+            byte_range("bytes=0-", 0)
+        with pytest.raises(ValueError):
+            byte_range("bytes=-10", 0)
+
+
+class TestParseVerdict:
+    """Tests for parse_verdict function."""
+
+    def test_plain_verdict(self):
+        """Should split the verdict line from the explanation."""
+        assert parse_verdict("VERDICT: SUPPORTED\nOn page 12.") == ("supported", "On page 12.")
+        assert parse_verdict("VERDICT: CONTRADICTED\nNo.") == ("contradicted", "No.")
+
+    def test_trailing_punctuation(self):
+        """Should ignore punctuation after the verdict."""
+        assert parse_verdict("VERDICT: Supported.\nYes.") == ("supported", "Yes.")
+        assert parse_verdict("VERDICT: CONTRADICTED!\nNo.") == ("contradicted", "No.")
+
+    def test_markdown_bold(self):
+        """Should ignore Markdown bold around the label or the verdict."""
+        assert parse_verdict("**VERDICT:** SUPPORTED\nYes.") == ("supported", "Yes.")
+        assert parse_verdict("**VERDICT: SUPPORTED**\nYes.") == ("supported", "Yes.")
+
+    def test_not_found_spellings(self):
+        """Should accept 'not found' with a space, hyphen or underscore."""
+        assert parse_verdict("VERDICT: not found\nNothing.") == ("not_found", "Nothing.")
+        assert parse_verdict("VERDICT: NOT-FOUND\nNothing.") == ("not_found", "Nothing.")
+        assert parse_verdict("VERDICT: NOT_FOUND\nNothing.") == ("not_found", "Nothing.")
+
+    def test_unparseable_reply(self):
+        """Should report not_found with the whole reply as explanation."""
+        reply = "The library does not say.\nMaybe page 3."
+        assert parse_verdict(reply) == ("not_found", reply)
+
+
+class TestDetectChapters:
+    """Tests for detect_chapters function."""
+
+    def test_roman_numeral_heading(self):
+        """Should read 'Chapter IV' as chapter 4, titled by the next line."""
+        chapters = detect_chapters([(10, "Chapter IV\nThe Mercator Projection\nText.")], 20)
+        assert [(c.number, c.title, c.start_page, c.end_page) for c in chapters] == [
+            (4, "The Mercator Projection", 10, 20)
+        ]
+
+    def test_uppercase_heading_with_title(self):
+        """Should read 'CHAPTER 12: Conformal Maps' as chapter 12 with its title."""
+        chapters = detect_chapters([(5, "CHAPTER 12: Conformal Maps\nText.")], 9)
+        assert [(c.number, c.title) for c in chapters] == [(12, "Conformal Maps")]
+
+    def test_page_ranges(self):
+        """Should end each chapter on the page before the next one starts."""
+        pages = [(1, "Chapter 1 Intro"), (2, "Text."), (3, "Chapter 2 Methods"), (4, "Text.")]
+        chapters = detect_chapters(pages, 6)
+        assert [(c.number, c.start_page, c.end_page) for c in chapters] == [(1, 1, 2), (2, 3, 6)]
+
+    def test_ignores_mentions_in_running_text(self):
+        """Should not count chapters mentioned mid-sentence or below the first lines."""
+        pages = [
+            (3, "Projections\nAs shown in Chapter 3, the scale varies."),
+            (4, "One\nTwo\nThree\nFour\nFive\nChapter 5 describes the ellipsoid."),
+        ]
+        assert detect_chapters(pages, 10) == []
+
+    def test_skips_contents_pages(self):
+        """Should skip a page listing several chapters."""
+        pages = [(2, "Contents\nChapter 1 Intro\nChapter 2 Methods"), (7, "Chapter 1\nIntro")]
+        chapters = detect_chapters(pages, 10)
+        assert [(c.number, c.start_page) for c in chapters] == [(1, 7)]
+
+    def test_running_headers_keep_first_page(self):
+        """Should start a chapter on the first of several pages with its heading."""
+        pages = [(7, "Chapter 2 Methods\nText."), (8, "Chapter 2 Methods\nMore text.")]
+        chapters = detect_chapters(pages, 8)
+        assert [(c.number, c.start_page, c.end_page) for c in chapters] == [(2, 7, 8)]
+
+
+class TestDetectSections:
+    """Tests for detect_sections function."""
+
+    def test_sections_within_chapters(self):
+        """Should nest numbered sections under their chapter, ending at the next one."""
+        pages = [
+            (1, "Chapter 4\nProjections"),
+            (2, "4.1 Scope\nText."),
+            (3, "4.2 Transverse Mercator\nText."),
+            (5, "4.2.1 Ellipsoidal Form\nText."),
+            (6, "Chapter 5\nDatums"),
+        ]
+        sections = detect_sections(pages, 8)
+        assert [(s.number, s.level, s.start_page, s.end_page) for s in sections] == [
+            ("4", 1, 1, 5),
+            ("4.1", 2, 2, 2),
+            ("4.2", 2, 3, 5),
+            ("4.2.1", 3, 5, 5),
+            ("5", 1, 6, 8),
+        ]
+
+    def test_skips_contents_pages(self):
+        """Should skip a page of headings followed by page numbers."""
+        pages = [
+            (2, "1.1 Scope 3\n1.2 Terms 4\n1.3 Units 5"),
+            (4, "1.2 Terms\nText."),
+        ]
+        sections = detect_sections(pages, 6)
+        assert [(s.number, s.start_page) for s in sections] == [("1.2", 4)]
+
+    def test_ignores_numbers_out_of_sequence(self):
+        """Should drop headings whose number cannot follow the previous one."""
+        pages = [(2, "3.2 Scale Factor\nText."), (3, "25.4 Millimetres make an inch\nText.")]
+        sections = detect_sections(pages, 4)
+        assert [s.number for s in sections] == ["3.2"]
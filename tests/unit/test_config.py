@@ -59,6 +59,56 @@ class TestConfig:
         assert config.embed_dimensions == 512
         assert isinstance(config.embed_dimensions, int)
 
+    def test_allow_delete_as_bool(self, monkeypatch):
+        """Should read DOCLIBRARY_ALLOW_DELETE as a boolean."""
+        import importlib
+        import doclibrary.config
+
+        monkeypatch.setenv("DOCLIBRARY_ALLOW_DELETE", "true")
+        importlib.reload(doclibrary.config)
+        assert doclibrary.config.config.allow_delete is True
+
+        monkeypatch.setenv("DOCLIBRARY_ALLOW_DELETE", "0")
+        importlib.reload(doclibrary.config)
+        assert doclibrary.config.config.allow_delete is False
+
+    def test_allow_ingest_as_bool(self, monkeypatch):
+        """Should read DOCLIBRARY_ALLOW_INGEST as a boolean."""
+        import importlib
+        import doclibrary.config
+
+        monkeypatch.setenv("DOCLIBRARY_ALLOW_INGEST", "yes")
+        importlib.reload(doclibrary.config)
+        assert doclibrary.config.config.allow_ingest is True
+
+        monkeypatch.setenv("DOCLIBRARY_ALLOW_INGEST", "false")
+        importlib.reload(doclibrary.config)
+        assert doclibrary.config.config.allow_ingest is False
+
+    def test_max_upload_mb_as_int(self, monkeypatch):
+        """Should convert DOCLIBRARY_MAX_UPLOAD_MB to integer."""
+        monkeypatch.setenv("DOCLIBRARY_MAX_UPLOAD_MB", "50")
+
+        import importlib
+        import doclibrary.config
+
+        importlib.reload(doclibrary.config)
+
+        assert doclibrary.config.config.max_upload_mb == 50
+
+    def test_allow_download_as_bool(self, monkeypatch):
+        """Should read DOCLIBRARY_ALLOW_DOWNLOAD as a boolean."""
+        import importlib
+        import doclibrary.config
+
+        monkeypatch.setenv("DOCLIBRARY_ALLOW_DOWNLOAD", "1")
+        importlib.reload(doclibrary.config)
+        assert doclibrary.config.config.allow_download is True
+
+        monkeypatch.setenv("DOCLIBRARY_ALLOW_DOWNLOAD", "no")
+        importlib.reload(doclibrary.config)
+        assert doclibrary.config.config.allow_download is False
+
 
 class TestFindConfigFile:
     """Tests for find_config_file function."""
@@ -103,6 +153,7 @@ class TestLoadConfig:
 [llm]
 url = "http://test:8080/v1"
 model = "test-llm"
+context_window = 8192
 
 [database]
 name = "test_db"
@@ -122,6 +173,7 @@ name = "test_db"
 
         assert config.llm_url == "http://test:8080/v1"
         assert config.llm_model == "test-llm"
+        assert config.llm_context_window == 8192
         assert config.db_name == "test_db"
 
     def test_env_overrides_file(self, tmp_path, monkeypatch):
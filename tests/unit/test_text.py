@@ -6,6 +6,7 @@ from doclibrary.core.text import (
     clean_line_numbers,
     normalize_whitespace,
     extract_keywords,
+    find_acronym_definitions,
     truncate_text,
 )
 
@@ -187,3 +188,34 @@ class TestTruncateText:
         """Should handle empty or None input."""
         assert truncate_text("", max_length=100) == ""
         assert truncate_text(None, max_length=100) is None
+
+
+class TestFindAcronymDefinitions:
+    """Tests for find_acronym_definitions function."""
+
+    def test_long_form_before_acronym(self):
+        """Should find 'Long Form (ACR)' definitions."""
+        text = "We use the Segment Anything Model (SAM) for masks."
+        assert find_acronym_definitions(text, "SAM") == [("Segment Anything Model", 34)]
+
+    def test_acronym_before_long_form(self):
+        """Should find 'ACR (Long Form)' definitions."""
+        text = "Results from SAM (Segment Anything Model) are shown."
+        expansions = [e for e, _ in find_acronym_definitions(text, "SAM")]
+        assert expansions == ["Segment Anything Model"]
+
+    def test_ignores_unrelated_parenthetical(self):
+        """Should skip parentheticals that don't spell out the acronym."""
+        text = "UTM (see chapter 8) is widely used."
+        assert find_acronym_definitions(text, "UTM") == []
+
+    def test_does_not_cross_sentence_boundary(self):
+        """Should not take words from a previous sentence."""
+        text = "Mapping is hard. Transverse Mercator (TM) helps."
+        expansions = [e for e, _ in find_acronym_definitions(text, "TM")]
+        assert expansions == ["Transverse Mercator"]
+
+    def test_case_sensitive_acronym(self):
+        """Should match the acronym case-sensitively."""
+        text = "the Segment Anything Model (sam) tool"
+        assert find_acronym_definitions(text, "SAM") == []